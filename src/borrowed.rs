@@ -0,0 +1,256 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::iter::Peekable;
+
+use crate::lexer::{error_at, Lexer, Token, TokenKind};
+use crate::parser::{
+    count_control_characters, intern_key, number_value, unescape, DuplicateKeys, JsonMap, JsonValue, ParseError, ParseErrorKind, ParserOptions
+};
+
+
+/// JSON value borrowing from the parsed text: strings without escape
+/// sequences are slices of the input instead of fresh allocations.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq)]
+pub enum JsonValueRef<'a> {
+    STRING(Cow<'a, str>),
+    NUMBER(f64),
+    /// Number kept as written, see `ParserOptions::preserve_number_literals`.
+    RAWNUMBER(&'a str),
+    /// Members in document order, repeated keys included.
+    OBJECT(Vec<(Cow<'a, str>, JsonValueRef<'a>)>),
+    ARRAY(Vec<JsonValueRef<'a>>),
+    TRUE,
+    FALSE,
+    NULL
+}
+
+impl JsonValueRef<'_> {
+    /// Copies the value into an owned `JsonValue`; of repeated keys the
+    /// last one wins.
+    pub fn into_owned(self) -> JsonValue {
+        match self {
+            JsonValueRef::STRING(line) => JsonValue::STRING(line.into_owned()),
+            JsonValueRef::NUMBER(number) => JsonValue::NUMBER(number),
+            JsonValueRef::RAWNUMBER(literal) => JsonValue::RAWNUMBER(literal.to_string()),
+            JsonValueRef::OBJECT(members) => JsonValue::OBJECT(members.into_iter()
+                .map(|(key, value)| (intern_key(&key), value.into_owned()))
+                .collect::<JsonMap<_>>()),
+            JsonValueRef::ARRAY(items) => JsonValue::ARRAY(items.into_iter().map(JsonValueRef::into_owned).collect()),
+            JsonValueRef::TRUE => JsonValue::TRUE,
+            JsonValueRef::FALSE => JsonValue::FALSE,
+            JsonValueRef::NULL => JsonValue::NULL
+        }
+    }
+}
+
+/// Members of an object together with the key of the member being read
+/// and the distinct keys, tracked only for `DuplicateKeys::Error` and
+/// `Limits::max_keys`.
+enum Frame<'a> {
+    Array(Vec<JsonValueRef<'a>>),
+    Object(Vec<(Cow<'a, str>, JsonValueRef<'a>)>, Cow<'a, str>, HashSet<Cow<'a, str>>)
+}
+
+/// Builds `JsonValueRef` trees from the tokens of `Lexer`.
+struct TreeParser<'a, 'o> {
+    source: &'a str,
+    options: &'o ParserOptions,
+    tokens: Peekable<Lexer<'a>>,
+    /// Values read so far, containers included.
+    nodes: usize
+}
+
+impl<'a> TreeParser<'a, '_> {
+    fn skip_trivia(&mut self) {
+        let allow_comments = self.options.allow_comments;
+        while matches!(self.tokens.peek(), Some(Ok(token)) if token.kind == TokenKind::Whitespace
+                || (token.kind == TokenKind::Comment && allow_comments)) {
+            self.tokens.next();
+        }
+    }
+
+    /// Next token that is neither whitespace nor an allowed comment.
+    fn next(&mut self) -> Result<Option<Token>, ParseError> {
+        self.skip_trivia();
+        self.tokens.next().transpose()
+    }
+
+    fn peek_kind(&mut self) -> Option<TokenKind> {
+        self.skip_trivia();
+        match self.tokens.peek() {
+            Some(Ok(token)) => Some(token.kind),
+            _ => None
+        }
+    }
+
+    fn unexpected(&self, token: Option<Token>, expected: &[&str]) -> ParseError {
+        let expected: Vec<String> = expected.iter().map(|token| token.to_string()).collect();
+        let (span, kind, msg) = match token {
+            Some(token) => (token.span.clone(), ParseErrorKind::UnexpectedChar,
+                format!("unexpected token {:?}", token.text(self.source))),
+            None => (self.source.len()..self.source.len(), ParseErrorKind::UnexpectedEof,
+                "unexpected end of input".to_string())
+        };
+        let msg = match expected.as_slice() {
+            [] => msg,
+            [single] => format!("{}, expected {}", msg, single),
+            [init @ .., last] => format!("{}, expected {} or {}", msg, init.join(", "), last)
+        };
+        ParseError { expected, ..error_at(self.source, span, kind, msg) }
+    }
+
+    fn string(&self, token: &Token) -> Result<Cow<'a, str>, ParseError> {
+        let text = token.text(self.source);
+        let body = &text[1..text.len() - 1];
+        if let Some(max_length) = self.options.limits.max_string_length.filter(|max| body.chars().count() > *max) {
+            return Err(error_at(self.source, token.span.clone(), ParseErrorKind::StringTooLong,
+                format!("string too long, limit is {} characters", max_length)));
+        }
+        let mut controls = body.char_indices().filter(|(_, symbol)| *symbol < ' ');
+        if self.options.allow_control_characters {
+            count_control_characters(controls.count());
+        } else if let Some((at, symbol)) = controls.next() {
+            let start = token.span.start + 1 + at;
+            return Err(error_at(self.source, start..start + 1, ParseErrorKind::UnexpectedChar,
+                format!("unescaped control character {:?} in string", symbol)));
+        }
+        if ! body.contains('\\') {
+            return Ok(Cow::Borrowed(body));
+        }
+        let chars: Vec<char> = body.chars().collect();
+        unescape(&chars, self.options.allow_lone_surrogates)
+            .map(Cow::Owned)
+            .map_err(|reason| error_at(self.source, token.span.clone(), ParseErrorKind::InvalidEscape, reason))
+    }
+
+    /// Reads the key of the next member of an object with the distinct
+    /// `keys` so far.
+    fn key(&mut self, keys: &mut HashSet<Cow<'a, str>>) -> Result<Cow<'a, str>, ParseError> {
+        let (key, span) = match self.next()? {
+            Some(token) if token.kind == TokenKind::String => (self.string(&token)?, token.span),
+            other => return Err(self.unexpected(other, &["'\"'"]))
+        };
+        match self.next()? {
+            Some(Token { kind: TokenKind::Colon, .. }) => {},
+            other => return Err(self.unexpected(other, &["':'"]))
+        }
+        let max_keys = self.options.limits.max_keys;
+        if self.options.duplicate_keys != DuplicateKeys::Error && max_keys.is_none() {
+            return Ok(key);
+        }
+        if keys.contains(&key) {
+            if self.options.duplicate_keys == DuplicateKeys::Error {
+                return Err(error_at(self.source, span, ParseErrorKind::DuplicateKey, format!("duplicate key \'{}\'", key)));
+            }
+        } else if let Some(max_keys) = max_keys.filter(|max| keys.len() >= *max) {
+            return Err(error_at(self.source, span, ParseErrorKind::TooManyKeys,
+                format!("too many keys in object, limit is {}", max_keys)));
+        } else {
+            keys.insert(key.clone());
+        }
+        Ok(key)
+    }
+
+    /// Reads a scalar or an empty container, or opens a container on the
+    /// stack and returns `None`.
+    fn member(&mut self, frames: &mut Vec<Frame<'a>>) -> Result<Option<JsonValueRef<'a>>, ParseError> {
+        let token = self.next()?;
+        let Some(token) = token else {
+            return Err(self.unexpected(None, &["value"]));
+        };
+        self.nodes += 1;
+        if let Some(max_nodes) = self.options.limits.max_nodes.filter(|max| self.nodes > *max) {
+            return Err(error_at(self.source, token.span, ParseErrorKind::TooManyNodes,
+                format!("too many values, limit is {}", max_nodes)));
+        }
+        let value = match token.kind {
+            TokenKind::String => JsonValueRef::STRING(self.string(&token)?),
+            TokenKind::Number if self.options.preserve_number_literals => JsonValueRef::RAWNUMBER(token.text(self.source)),
+            TokenKind::Number => JsonValueRef::NUMBER(number_value(token.text(self.source)).unwrap_or_default()),
+            TokenKind::True => JsonValueRef::TRUE,
+            TokenKind::False => JsonValueRef::FALSE,
+            TokenKind::Null => JsonValueRef::NULL,
+            TokenKind::LBracket | TokenKind::LBrace => {
+                if frames.len() == self.options.max_depth {
+                    return Err(error_at(self.source, token.span, ParseErrorKind::DepthExceeded,
+                        format!("nesting too deep, limit is {} levels", self.options.max_depth)));
+                }
+                let is_array = token.kind == TokenKind::LBracket;
+                let closing = if is_array { TokenKind::RBracket } else { TokenKind::RBrace };
+                if self.peek_kind() == Some(closing) {
+                    self.tokens.next();
+                    return Ok(Some(if is_array { JsonValueRef::ARRAY(vec![]) } else { JsonValueRef::OBJECT(vec![]) }));
+                }
+                frames.push(if is_array {
+                    Frame::Array(vec![])
+                } else {
+                    let mut keys = HashSet::new();
+                    Frame::Object(vec![], self.key(&mut keys)?, keys)
+                });
+                return Ok(None);
+            },
+            _ => return Err(self.unexpected(Some(token), &["value"]))
+        };
+        Ok(Some(value))
+    }
+
+    fn parse(&mut self) -> Result<JsonValueRef<'a>, ParseError> {
+        let mut frames: Vec<Frame<'a>> = vec![];
+        loop {
+            let Some(mut value) = self.member(&mut frames)? else {
+                continue;
+            };
+            loop {
+                match frames.last_mut() {
+                    None => return match self.next()? {
+                        None => Ok(value),
+                        other => Err(self.unexpected(other, &[]))
+                    },
+                    Some(Frame::Array(items)) => {
+                        items.push(value);
+                        match self.next()? {
+                            Some(Token { kind: TokenKind::Comma, .. }) => break,
+                            Some(Token { kind: TokenKind::RBracket, .. }) => {},
+                            other => return Err(self.unexpected(other, &["','", "']'"]))
+                        }
+                    },
+                    Some(Frame::Object(members, key, keys)) => {
+                        members.push((std::mem::take(key), value));
+                        match self.next()? {
+                            Some(Token { kind: TokenKind::Comma, .. }) => {
+                                *key = self.key(keys)?;
+                                break;
+                            },
+                            Some(Token { kind: TokenKind::RBrace, .. }) => {},
+                            other => return Err(self.unexpected(other, &["','", "'}'"]))
+                        }
+                    }
+                }
+                value = match frames.pop() {
+                    Some(Frame::Array(items)) => JsonValueRef::ARRAY(items),
+                    Some(Frame::Object(members, ..)) => JsonValueRef::OBJECT(members),
+                    None => JsonValueRef::NULL
+                };
+            }
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Parses `content` into a tree borrowing unescaped strings from it.
+    /// Only strict JSON (with comments, if allowed) is accepted, the other
+    /// lenient extensions and `raw_depth` fail with
+    /// `ParseErrorKind::Unsupported`. Repeated
+    /// keys are all kept unless `DuplicateKeys::Error` rejects them.
+    pub fn parse_borrowed<'a>(&self, content: &'a str) -> Result<JsonValueRef<'a>, ParseError> {
+        if let Some(error) = self.unsupported_error("borrowing parser", true) {
+            return Err(error);
+        }
+        TreeParser { source: content, options: self, tokens: Lexer::new(content).peekable(), nodes: 0 }.parse()
+    }
+}
+
+pub fn parse_borrowed(content: &str) -> Result<JsonValueRef<'_>, ParseError> {
+    ParserOptions::default().parse_borrowed(content)
+}
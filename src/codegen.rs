@@ -0,0 +1,434 @@
+use std::collections::HashSet;
+
+use crate::parser::JsonValue;
+use crate::sniffer::{member_path, JsonStatsReport, ReportDetails};
+
+
+/// Type used where the values have no single Rust type.
+const ANY_TYPE: &str = "serde_json::Value";
+
+/// Protobuf well-known types used where the values have no single type.
+const PROTO_ANY_TYPE: &str = "google.protobuf.Value";
+const PROTO_LIST_TYPE: &str = "google.protobuf.ListValue";
+
+const RUST_KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static", "struct",
+    "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "final", "try", "yield"
+];
+
+/// Words of `key`, split at non-alphanumeric characters and before the
+/// upper case letters of camelCase.
+fn words(key: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    let mut previous = None;
+    for symbol in key.chars() {
+        if ! symbol.is_alphanumeric() {
+            previous = None;
+            continue;
+        }
+        let is_boundary = previous.is_none()
+            || (symbol.is_uppercase() && previous.is_some_and(|previous: char| previous.is_lowercase() || previous.is_numeric()));
+        match words.last_mut() {
+            Some(word) if ! is_boundary => word.push(symbol),
+            _ => words.push(symbol.to_string())
+        }
+        previous = Some(symbol);
+    }
+    words
+}
+
+/// `snake_case` field name for `key`, raw if it is a keyword.
+fn field_name(key: &str) -> String {
+    let name = words(key).iter().map(|word| word.to_lowercase()).collect::<Vec<_>>().join("_");
+    match name.as_str() {
+        "" => "field".to_string(),
+        "self" | "super" | "crate" => format!("{}_", name),
+        _ if name.starts_with(|symbol: char| symbol.is_numeric()) => format!("_{}", name),
+        _ if RUST_KEYWORDS.contains(&name.as_str()) => format!("r#{}", name),
+        _ => name
+    }
+}
+
+/// `PascalCase` type name for `key`.
+fn type_name(key: &str) -> String {
+    let name: String = words(key).iter().map(|word| {
+        let mut symbols = word.chars();
+        symbols.next().map_or(String::new(), |first| first.to_uppercase().chain(symbols.flat_map(char::to_lowercase)).collect())
+    }).collect();
+    match name.as_str() {
+        "" => "Value".to_string(),
+        _ if name.starts_with(|symbol: char| symbol.is_numeric()) => format!("T{}", name),
+        _ => name
+    }
+}
+
+/// `name`, or `name` with the lowest number from 2 appended not yet in
+/// `names`; the result is added to them.
+fn unique_name(names: &mut HashSet<String>, name: String, separator: &str) -> String {
+    let mut unique = name.clone();
+    let mut suffix = 1;
+    while ! names.insert(unique.clone()) {
+        suffix += 1;
+        unique = format!("{}{}{}", name, separator, suffix);
+    }
+    unique
+}
+
+/// Kinds of values of the given types, `null` left out and booleans named
+/// `bool`, with the arrays and objects described by `containers`.
+fn value_kinds<'a>(type_names: &[&'a str], containers: &[&'a JsonStatsReport]) -> Vec<&'a str> {
+    let mut kinds: Vec<&str> = type_names.iter()
+        .filter(|type_name| **type_name != "null")
+        .map(|type_name| if matches!(*type_name, "true" | "false") { "bool" } else { type_name })
+        .chain(containers.iter().map(|container| container.kind()))
+        .collect();
+    kinds.sort();
+    kinds.dedup();
+    kinds
+}
+
+/// Key of the objects of a report.
+struct Field<'a> {
+    key: &'a str,
+    /// Types of the primitive values under the key.
+    types: Vec<&'a str>,
+    container: Option<&'a JsonStatsReport>,
+    is_nonobligatory: bool
+}
+
+impl Field<'_> {
+    fn is_nullable(&self) -> bool {
+        self.types.contains(&"null")
+    }
+}
+
+/// Keys of the objects of `report`, primitive keys first; none for arrays.
+fn object_fields(report: &JsonStatsReport) -> Vec<Field<'_>> {
+    let ReportDetails::Object { nonobligatory, primitive_keys, members, .. } = &report.details else {
+        return vec![];
+    };
+    let mut fields: Vec<Field> = primitive_keys.iter()
+        .map(|(key, types)| Field {
+            key,
+            types: types.iter().map(String::as_str).collect(),
+            container: None,
+            is_nonobligatory: false
+        })
+        .collect();
+    for (key, stats) in members {
+        match fields.iter_mut().find(|field| field.key == key) {
+            Some(field) => field.container = Some(stats),
+            None => fields.push(Field { key, types: vec![], container: Some(stats), is_nonobligatory: false })
+        }
+    }
+    for field in &mut fields {
+        field.is_nonobligatory = nonobligatory.iter().any(|other| other == field.key);
+    }
+    fields
+}
+
+/// Types and containers of the items of the arrays of `report`.
+fn items_of(report: &JsonStatsReport) -> (Vec<&str>, Vec<&JsonStatsReport>) {
+    let containers = match &report.details {
+        ReportDetails::Array { inner_arrays, inner_objects } => inner_arrays.iter().chain(inner_objects).map(AsRef::as_ref).collect(),
+        ReportDetails::Object { .. } => vec![]
+    };
+    (report.types.iter().map(String::as_str).collect(), containers)
+}
+
+/// Writes the structs of a report, the outermost first.
+struct RustWriter {
+    structs: Vec<String>,
+    names: HashSet<String>
+}
+
+impl RustWriter {
+    /// Rust type of values of the given types, arrays and objects among
+    /// them described by `containers`; `Option` if `null` is one of them.
+    fn value_type(&mut self, type_names: &[&str], containers: &[&JsonStatsReport], name: &str) -> String {
+        let kinds = value_kinds(type_names, containers);
+        let rust_type = match (kinds.as_slice(), containers) {
+            (["string"], _) => "String".to_string(),
+            (["number"], _) => "f64".to_string(),
+            (["bool"], _) => "bool".to_string(),
+            (["object"], [container]) => self.object_struct(container, name),
+            (["array"], [container]) => format!("Vec<{}>", self.items_type(container, name)),
+            _ => ANY_TYPE.to_string()
+        };
+        if type_names.contains(&"null") {
+            return format!("Option<{}>", rust_type);
+        }
+        rust_type
+    }
+
+    fn items_type(&mut self, report: &JsonStatsReport, name: &str) -> String {
+        let (types, containers) = items_of(report);
+        self.value_type(&types, &containers, &format!("{}Item", name))
+    }
+
+    /// Declares a struct for the objects of `report`, returning its name.
+    fn object_struct(&mut self, report: &JsonStatsReport, name: &str) -> String {
+        let name = unique_name(&mut self.names, type_name(name), "");
+        let index = self.structs.len();
+        self.structs.push(String::new());
+
+        let mut fields = vec![];
+        let mut field_names = HashSet::new();
+        for field in object_fields(report) {
+            let mut name = unique_name(&mut field_names, field_name(field.key), "_");
+            if name.starts_with("r#") && name.contains('_') {
+                // keywords have no underscores, a numbered one needs no `r#`
+                name = name.split_off(2);
+            }
+            let mut rust_type = self.value_type(&field.types, field.container.as_slice(), field.key);
+            if field.is_nonobligatory && ! rust_type.starts_with("Option<") {
+                rust_type = format!("Option<{}>", rust_type);
+            }
+            let mut declaration = String::new();
+            if name.trim_start_matches("r#") != field.key {
+                declaration.push_str(&format!("    #[serde(rename = {:?})]\n", field.key));
+            }
+            declaration.push_str(&format!("    pub {}: {},\n", name, rust_type));
+            fields.push(declaration);
+        }
+        self.structs[index] = format!("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\npub struct {} {{\n{}}}\n",
+            name, fields.concat());
+        name
+    }
+}
+
+/// Name of a Protobuf field or Avro field for `key`, `snake_case` for the
+/// former.
+fn identifier(key: &str, is_snake_case: bool) -> String {
+    let name: String = if is_snake_case {
+        words(key).iter().map(|word| word.to_lowercase()).collect::<Vec<_>>().join("_")
+    } else {
+        key.chars().map(|symbol| if symbol.is_ascii_alphanumeric() { symbol } else { '_' }).collect()
+    };
+    match name.as_str() {
+        "" => "field".to_string(),
+        _ if name.starts_with(|symbol: char| symbol.is_ascii_digit()) => format!("_{}", name),
+        _ => name
+    }
+}
+
+/// Writes the messages of a report, the outermost first, noting the values
+/// Protobuf cannot hold as they are.
+struct ProtoWriter {
+    messages: Vec<String>,
+    names: HashSet<String>,
+    warnings: Vec<String>,
+    uses_struct: bool
+}
+
+impl ProtoWriter {
+    /// Protobuf type of values of the given types at `path`, `repeated`
+    /// for arrays; `null`s are left to the caller.
+    fn value_type(&mut self, type_names: &[&str], containers: &[&JsonStatsReport], name: &str, path: &str) -> String {
+        let kinds = value_kinds(type_names, containers);
+        match (kinds.as_slice(), containers) {
+            (["string"], _) => "string".to_string(),
+            (["number"], _) => "double".to_string(),
+            (["bool"], _) => "bool".to_string(),
+            (["object"], [container]) => self.message(container, name),
+            (["array"], [container]) => format!("repeated {}", self.items_type(container, name)),
+            ([], _) => {
+                self.warnings.push(format!("{}: no values but null, written as {}", path, PROTO_ANY_TYPE));
+                self.any_type(PROTO_ANY_TYPE)
+            },
+            _ => {
+                self.warnings.push(format!("{}: mixed types {}, written as {}", path, kinds.join(", "), PROTO_ANY_TYPE));
+                self.any_type(PROTO_ANY_TYPE)
+            }
+        }
+    }
+
+    fn any_type(&mut self, type_name: &str) -> String {
+        self.uses_struct = true;
+        type_name.to_string()
+    }
+
+    fn items_type(&mut self, report: &JsonStatsReport, name: &str) -> String {
+        let path = format!("{}[*]", report.path);
+        let (types, containers) = items_of(report);
+        if types.contains(&"null") {
+            self.warnings.push(format!("{}: null items cannot be repeated, they are dropped", path));
+        }
+        match self.value_type(&types, &containers, &format!("{}Item", name), &path) {
+            nested if nested.starts_with("repeated ") => {
+                self.warnings.push(format!("{}: nested arrays written as {}", path, PROTO_LIST_TYPE));
+                self.any_type(PROTO_LIST_TYPE)
+            },
+            item_type => item_type
+        }
+    }
+
+    /// Declares a message for the objects of `report`, returning its name.
+    fn message(&mut self, report: &JsonStatsReport, name: &str) -> String {
+        let name = unique_name(&mut self.names, type_name(name), "");
+        let index = self.messages.len();
+        self.messages.push(String::new());
+
+        let mut fields = vec![];
+        let mut field_names = HashSet::new();
+        for (number, field) in object_fields(report).into_iter().enumerate() {
+            let path = member_path(&report.path, field.key);
+            let field_name = unique_name(&mut field_names, identifier(field.key, true), "_");
+            let mut proto_type = self.value_type(&field.types, field.container.as_slice(), field.key, &path);
+            if proto_type.starts_with("repeated ") {
+                if field.is_nullable() {
+                    self.warnings.push(format!("{}: null arrays read as empty ones", path));
+                }
+            } else if (field.is_nullable() || field.is_nonobligatory) && proto_type != PROTO_ANY_TYPE {
+                proto_type = format!("optional {}", proto_type);
+            }
+            let options = if field_name != field.key { format!(" [json_name = {:?}]", field.key) } else { String::new() };
+            fields.push(format!("  {} {} = {}{};\n", proto_type, field_name, number + 1, options));
+        }
+        self.messages[index] = format!("message {} {{\n{}}}\n", name, fields.concat());
+        name
+    }
+}
+
+/// Builds the records of a report in place, noting the values Avro cannot
+/// hold as they are.
+struct AvroWriter {
+    names: HashSet<String>,
+    warnings: Vec<String>
+}
+
+impl AvroWriter {
+    /// Avro schema of values of the given types at `path`, a union if
+    /// there are several of them.
+    fn value_schema(&mut self, type_names: &[&str], containers: &[&JsonStatsReport], name: &str, path: &str) -> JsonValue {
+        let mut branches: Vec<JsonValue> = vec![];
+        if type_names.contains(&"null") {
+            branches.push("null".into());
+        }
+        for kind in value_kinds(type_names, containers) {
+            branches.push(match kind {
+                "string" => "string".into(),
+                "number" => "double".into(),
+                "bool" => "boolean".into(),
+                _ => match containers.iter().find(|container| container.kind() == kind) {
+                    Some(container) if kind == "object" => self.record(container, name),
+                    Some(container) => self.array(container, name),
+                    None => continue
+                }
+            });
+        }
+        match branches.len() {
+            0 => {
+                self.warnings.push(format!("{}: no values, written as null", path));
+                "null".into()
+            },
+            1 => branches.remove(0),
+            _ => JsonValue::from(branches)
+        }
+    }
+
+    fn array(&mut self, report: &JsonStatsReport, name: &str) -> JsonValue {
+        let (types, containers) = items_of(report);
+        let items = self.value_schema(&types, &containers, &format!("{}Item", name), &format!("{}[*]", report.path));
+        json!({"type": "array", "items": items})
+    }
+
+    /// Record for the objects of `report`; keys that are no Avro names are
+    /// renamed.
+    fn record(&mut self, report: &JsonStatsReport, name: &str) -> JsonValue {
+        let name = unique_name(&mut self.names, type_name(name), "");
+        let mut fields = vec![];
+        let mut field_names = HashSet::new();
+        for field in object_fields(report) {
+            let path = member_path(&report.path, field.key);
+            let field_name = unique_name(&mut field_names, identifier(field.key, false), "_");
+            if field_name != field.key {
+                self.warnings.push(format!("{}: key is no Avro name, renamed to {}", path, field_name));
+            }
+            let mut schema = self.value_schema(&field.types, field.container.as_slice(), field.key, &path);
+            let mut avro_field = json!({"name": field_name});
+            if field.is_nonobligatory && ! field.is_nullable() {
+                schema = match schema {
+                    JsonValue::ARRAY(mut branches) => {
+                        branches.insert(0, "null".into());
+                        JsonValue::ARRAY(branches)
+                    },
+                    JsonValue::STRING(null) if null == "null" => JsonValue::STRING(null),
+                    single => JsonValue::from(vec![JsonValue::from("null"), single])
+                };
+            }
+            let is_optional = field.is_nonobligatory || field.is_nullable();
+            avro_field.insert("type", schema);
+            if is_optional {
+                avro_field.insert("default", JsonValue::NULL);
+            }
+            fields.push(avro_field);
+        }
+        json!({"type": "record", "name": name, "fields": fields})
+    }
+}
+
+impl JsonStatsReport {
+    /// Rust declarations with serde derives matching the collected
+    /// documents, `name` being the type of a whole document. Keys missing
+    /// from some objects or holding `null` become `Option`s.
+    pub fn to_rust(&self, name: &str) -> String {
+        let mut writer = RustWriter { structs: vec![], names: HashSet::new() };
+        let root = match self.details {
+            ReportDetails::Object { .. } => {
+                writer.object_struct(self, name);
+                None
+            },
+            ReportDetails::Array { .. } => {
+                let root = unique_name(&mut writer.names, type_name(name), "");
+                Some(format!("pub type {} = Vec<{}>;\n", root, writer.items_type(self, &root)))
+            }
+        };
+        let mut code = "use serde::{Deserialize, Serialize};\n\n".to_string();
+        code.extend(root);
+        for declaration in writer.structs {
+            if ! code.ends_with("\n\n") {
+                code.push('\n');
+            }
+            code.push_str(&declaration);
+        }
+        code
+    }
+
+    /// Protobuf (proto3) messages matching the collected documents, `name`
+    /// being the message of a whole document, along with warnings on the
+    /// values that do not map cleanly. Numbers become `double`s and values
+    /// of mixed types `google.protobuf.Value`s.
+    pub fn to_protobuf(&self, name: &str) -> (String, Vec<String>) {
+        let mut writer = ProtoWriter { messages: vec![], names: HashSet::new(), warnings: vec![], uses_struct: false };
+        if let ReportDetails::Array { .. } = self.details {
+            let root = unique_name(&mut writer.names, type_name(name), "");
+            writer.warnings.push(format!("top-level array wrapped into the items of message {}", root));
+            writer.messages.push(String::new());
+            let items_type = writer.items_type(self, &root);
+            writer.messages[0] = format!("message {} {{\n  repeated {} items = 1;\n}}\n", root, items_type);
+        } else {
+            writer.message(self, name);
+        }
+        let mut code = "syntax = \"proto3\";\n\n".to_string();
+        if writer.uses_struct {
+            code.push_str("import \"google/protobuf/struct.proto\";\n\n");
+        }
+        code.push_str(&writer.messages.join("\n"));
+        (code, writer.warnings)
+    }
+
+    /// Avro schema matching the collected documents, records named after
+    /// `name`, along with warnings on the values that do not map cleanly.
+    /// Numbers become `double`s, keys missing from some objects nullable
+    /// with a `null` default.
+    pub fn to_avro(&self, name: &str) -> (JsonValue, Vec<String>) {
+        let mut writer = AvroWriter { names: HashSet::new(), warnings: vec![] };
+        let schema = match self.details {
+            ReportDetails::Object { .. } => writer.record(self, name),
+            ReportDetails::Array { .. } => writer.array(self, name)
+        };
+        (schema, writer.warnings)
+    }
+}
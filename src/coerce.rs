@@ -0,0 +1,67 @@
+use crate::lexer::number_length;
+use crate::parser::{number_value, JsonValue};
+
+
+/// Calls `convert` on every value that is not a container and counts the
+/// values it changed.
+fn convert_leaves(value: &mut JsonValue, convert: &mut impl FnMut(&mut JsonValue) -> bool) -> usize {
+    match value {
+        JsonValue::OBJECT(object) => object.values_mut().map(|member| convert_leaves(member, convert)).sum(),
+        JsonValue::ARRAY(array) => array.iter_mut().map(|item| convert_leaves(item, convert)).sum(),
+        leaf => usize::from(convert(leaf))
+    }
+}
+
+impl JsonValue {
+    /// Turns strings holding a JSON number, surrounding whitespace aside,
+    /// into numbers; returns how many were converted.
+    pub fn coerce_number_strings(&mut self) -> usize {
+        convert_leaves(self, &mut |value| {
+            let JsonValue::STRING(line) = value else {
+                return false;
+            };
+            let literal = line.trim();
+            let number = number_value(literal).filter(|_| number_length(literal) == Some(literal.len()));
+            if let Some(number) = number {
+                *value = JsonValue::NUMBER(number);
+            }
+            number.is_some()
+        })
+    }
+
+    /// Turns the strings `true` and `false`, in any case and surrounding
+    /// whitespace aside, into booleans; returns how many were converted.
+    pub fn coerce_bool_strings(&mut self) -> usize {
+        convert_leaves(self, &mut |value| {
+            let JsonValue::STRING(line) = value else {
+                return false;
+            };
+            let flag = match line.trim() {
+                literal if literal.eq_ignore_ascii_case("true") => true,
+                literal if literal.eq_ignore_ascii_case("false") => false,
+                _ => return false
+            };
+            *value = JsonValue::from(flag);
+            true
+        })
+    }
+
+    /// Replaces `null`s with the values at the same place of `defaults`,
+    /// whose only array item, if any, is the default of every item; returns
+    /// how many were replaced.
+    pub fn nulls_to_defaults(&mut self, defaults: &JsonValue) -> usize {
+        match (self, defaults) {
+            (value @ JsonValue::NULL, default) if ! default.is_null() => {
+                *value = default.clone();
+                1
+            },
+            (JsonValue::OBJECT(object), JsonValue::OBJECT(defaults)) => object.iter_mut()
+                .filter_map(|(key, member)| defaults.get(key).map(|default| member.nulls_to_defaults(default)))
+                .sum(),
+            (JsonValue::ARRAY(array), JsonValue::ARRAY(defaults)) if defaults.len() == 1 => array.iter_mut()
+                .map(|item| item.nulls_to_defaults(&defaults[0]))
+                .sum(),
+            _ => 0
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+
+use crate::parser::{ParseError, ParserOptions};
+
+
+/// Concrete syntax tree node: a value together with the whitespace around
+/// it, so that printing the tree reproduces the source byte for byte.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxNode {
+    /// Whitespace before the value.
+    pub leading: String,
+    pub kind: SyntaxKind,
+    /// Whitespace after the value up to the next separator or closing bracket.
+    pub trailing: String
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntaxKind {
+    /// String, number, `true`, `false` or `null` exactly as written.
+    Literal(String),
+    /// Array items in order; `inner` keeps the whitespace of an empty array.
+    Array { items: Vec<SyntaxNode>, inner: String },
+    /// Object members in document order; `inner` keeps the whitespace of an
+    /// empty object.
+    Object { members: Vec<SyntaxMember>, inner: String }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxMember {
+    /// Whitespace before the key.
+    pub leading: String,
+    /// Key with its quotes and escapes as written.
+    pub key: String,
+    /// Whitespace between the key and the colon.
+    pub before_colon: String,
+    pub value: SyntaxNode
+}
+
+struct Scanner<'a> {
+    source: &'a str,
+    position: usize
+}
+
+impl<'a> Scanner<'a> {
+    fn peek(&self) -> char {
+        self.source[self.position..].chars().next().unwrap_or('\0')
+    }
+
+    fn bump(&mut self) {
+        self.position += self.peek().len_utf8();
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.position;
+        while self.position < self.source.len() && predicate(self.peek()) {
+            self.bump();
+        }
+        &self.source[start..self.position]
+    }
+
+    /// Whitespace and, in JSONC documents, comments before the next token.
+    fn whitespace(&mut self) -> String {
+        let start = self.position;
+        loop {
+            self.take_while(|symbol| matches!(symbol, ' ' | '\n' | '\r' | '\t'));
+            let rest = &self.source[self.position..];
+            if rest.starts_with("//") {
+                self.take_while(|symbol| symbol != '\n');
+            } else if rest.starts_with("/*") {
+                self.position += rest.find("*/").map_or(rest.len(), |end| end + 2);
+            } else {
+                break;
+            }
+        }
+        self.source[start..self.position].to_string()
+    }
+
+    fn string(&mut self) -> String {
+        let start = self.position;
+        let quote = self.peek();
+        self.bump();
+        while self.position < self.source.len() && self.peek() != quote {
+            if self.peek() == '\\' {
+                self.bump();
+            }
+            self.bump();
+        }
+        self.bump();
+        self.source[start..self.position].to_string()
+    }
+
+    fn node(&mut self) -> SyntaxNode {
+        let leading = self.whitespace();
+        let kind = match self.peek() {
+            '[' => {
+                self.bump();
+                let mut items = vec![];
+                let mut inner = String::new();
+                loop {
+                    let item = self.node();
+                    if matches!(&item.kind, SyntaxKind::Literal(literal) if literal.is_empty()) {
+                        inner = item.leading;
+                        self.bump();
+                        break;
+                    }
+                    items.push(item);
+                    let separator = self.peek();
+                    self.bump();
+                    if separator != ',' {
+                        break;
+                    }
+                }
+                SyntaxKind::Array { items, inner }
+            },
+            '{' => {
+                self.bump();
+                let mut members = vec![];
+                let mut inner = String::new();
+                loop {
+                    let leading = self.whitespace();
+                    if self.peek() == '}' {
+                        inner = leading;
+                        self.bump();
+                        break;
+                    }
+                    let key = if matches!(self.peek(), '"' | '\'') {
+                        self.string()
+                    } else {
+                        self.take_while(|symbol| symbol.is_alphanumeric() || symbol == '_' || symbol == '$').to_string()
+                    };
+                    let before_colon = self.whitespace();
+                    self.bump();
+                    members.push(SyntaxMember { leading, key, before_colon, value: self.node() });
+                    let separator = self.peek();
+                    self.bump();
+                    if separator != ',' {
+                        break;
+                    }
+                }
+                SyntaxKind::Object { members, inner }
+            },
+            '"' | '\'' => SyntaxKind::Literal(self.string()),
+            _ => SyntaxKind::Literal(self.take_while(
+                |symbol| ! matches!(symbol, ' ' | '\n' | '\r' | '\t' | ',' | ']' | '}')).to_string())
+        };
+        SyntaxNode { leading, kind, trailing: self.whitespace() }
+    }
+}
+
+impl ParserOptions {
+    /// Parses `content` into a concrete syntax tree that keeps whitespace,
+    /// key order and raw literals. The input is validated first, so the
+    /// tree is only built for well-formed documents. Trailing commas of
+    /// JSON5 documents are not kept.
+    pub fn parse_lossless(&self, content: &str) -> Result<SyntaxNode, ParseError> {
+        self.parse_str(content)?;
+        Ok(Scanner { source: content, position: 0 }.node())
+    }
+}
+
+pub fn parse_lossless(content: &str) -> Result<SyntaxNode, ParseError> {
+    ParserOptions::default().parse_lossless(content)
+}
+
+impl fmt::Display for SyntaxMember {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}:{}", self.leading, self.key, self.before_colon, self.value)
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.leading)?;
+        match &self.kind {
+            SyntaxKind::Literal(literal) => f.write_str(literal)?,
+            SyntaxKind::Array { items, inner } => {
+                f.write_str("[")?;
+                f.write_str(inner)?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 { f.write_str(",")?; }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")?;
+            },
+            SyntaxKind::Object { members, inner } => {
+                f.write_str("{")?;
+                f.write_str(inner)?;
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 { f.write_str(",")?; }
+                    write!(f, "{}", member)?;
+                }
+                f.write_str("}")?;
+            }
+        }
+        f.write_str(&self.trailing)
+    }
+}
@@ -0,0 +1,188 @@
+use std::error::Error;
+use std::fmt;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, Deserializer, EnumAccess, IntoDeserializer, VariantAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::parser::{number_value, JsonValue, MAX_EXACT_INTEGER};
+
+
+/// Value that does not fit the type it is deserialized into.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeserializeError {
+    pub msg: String
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self { msg: msg.to_string() }
+    }
+}
+
+/// Builds a `T` out of the value, e.g. a struct deriving `Deserialize`.
+pub fn from_value<T: DeserializeOwned>(value: JsonValue) -> Result<T, DeserializeError> {
+    T::deserialize(value)
+}
+
+fn visit_number<'de, V: Visitor<'de>>(number: f64, visitor: V) -> Result<V::Value, DeserializeError> {
+    if number.fract() != 0f64 || number.abs() > MAX_EXACT_INTEGER {
+        return visitor.visit_f64(number);
+    }
+    if number < 0f64 {
+        return visitor.visit_i64(number as i64);
+    }
+    visitor.visit_u64(number as u64)
+}
+
+impl<'de> IntoDeserializer<'de, DeserializeError> for JsonValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Integral numbers are passed on as integers, so that they fit integer
+/// fields; number literals are passed on as integers when they fit into
+/// `i64` or `u64`.
+impl<'de> Deserializer<'de> for JsonValue {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self {
+            JsonValue::STRING(line) => visitor.visit_string(line),
+            JsonValue::NUMBER(number) => visit_number(number, visitor),
+            JsonValue::RAWNUMBER(literal) => match (literal.parse::<u64>(), literal.parse::<i64>()) {
+                (Ok(integer), _) => visitor.visit_u64(integer),
+                (_, Ok(integer)) => visitor.visit_i64(integer),
+                _ => visitor.visit_f64(number_value(&literal).unwrap_or(f64::NAN))
+            },
+            JsonValue::OBJECT(object) => {
+                let mut map = MapDeserializer::new(object.into_iter().map(|(key, value)| (key.to_string(), value)));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            },
+            JsonValue::ARRAY(array) => {
+                let mut seq = SeqDeserializer::new(array.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            },
+            JsonValue::TRUE => visitor.visit_bool(true),
+            JsonValue::FALSE => visitor.visit_bool(false),
+            JsonValue::NULL => visitor.visit_unit(),
+            JsonValue::RAW(..) => Err(de::Error::custom("unresolved raw value"))
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self {
+            JsonValue::NULL => visitor.visit_none(),
+            value => visitor.visit_some(value)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, DeserializeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are strings, the others objects with a single member
+    /// named after the variant.
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V)
+            -> Result<V::Value, DeserializeError> {
+        match self {
+            JsonValue::STRING(variant) => visitor.visit_enum(Enum { variant, value: None }),
+            JsonValue::OBJECT(object) => {
+                let mut members = object.into_iter();
+                match (members.next(), members.next()) {
+                    (Some((variant, value)), None) => visitor.visit_enum(Enum { variant: variant.to_string(), value: Some(value) }),
+                    _ => Err(de::Error::invalid_value(de::Unexpected::Map, &"object with a single member"))
+                }
+            },
+            other => Err(de::Error::invalid_type(unexpected(&other), &"string or object with a single member"))
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+fn unexpected(value: &JsonValue) -> de::Unexpected<'_> {
+    match value {
+        JsonValue::STRING(line) => de::Unexpected::Str(line),
+        JsonValue::NUMBER(number) => de::Unexpected::Float(*number),
+        JsonValue::RAWNUMBER(literal) => de::Unexpected::Other(literal),
+        JsonValue::OBJECT(_) => de::Unexpected::Map,
+        JsonValue::ARRAY(_) => de::Unexpected::Seq,
+        JsonValue::TRUE => de::Unexpected::Bool(true),
+        JsonValue::FALSE => de::Unexpected::Bool(false),
+        JsonValue::NULL => de::Unexpected::Unit,
+        JsonValue::RAW(..) => de::Unexpected::Other("raw value")
+    }
+}
+
+/// Variant of an enum with its content, if any.
+struct Enum {
+    variant: String,
+    value: Option<JsonValue>
+}
+
+impl<'de> EnumAccess<'de> for Enum {
+    type Error = DeserializeError;
+    type Variant = Self;
+
+    fn variant_seed<S: de::DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self), DeserializeError> {
+        let variant = seed.deserialize(self.variant.as_str().into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Enum {
+    type Error = DeserializeError;
+
+    fn unit_variant(self) -> Result<(), DeserializeError> {
+        match self.value {
+            None | Some(JsonValue::NULL) => Ok(()),
+            Some(other) => Err(de::Error::invalid_type(unexpected(&other), &"unit variant"))
+        }
+    }
+
+    fn newtype_variant_seed<S: de::DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, DeserializeError> {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"newtype variant"))
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.value {
+            Some(value @ JsonValue::ARRAY(_)) => value.deserialize_any(visitor),
+            Some(other) => Err(de::Error::invalid_type(unexpected(&other), &"tuple variant")),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"tuple variant"))
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.value {
+            Some(value @ JsonValue::OBJECT(_)) => value.deserialize_any(visitor),
+            Some(other) => Err(de::Error::invalid_type(unexpected(&other), &"struct variant")),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"struct variant"))
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use std::fmt;
+use std::str::FromStr;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+
+/// Kind of data a string holds beyond being text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum StringFormat {
+    /// ISO 8601 or RFC 2822 date, or Unix time in seconds or milliseconds.
+    Date,
+    /// UUID of versions 1 to 7 in the `8-4-4-4-12` hexadecimal form.
+    Uuid,
+    /// Address of `local@domain.tld`.
+    Email,
+    /// Absolute URL of `scheme://authority...`.
+    Url,
+    /// IPv4 address in dotted decimal.
+    Ipv4,
+    /// IPv6 address, possibly compressed.
+    Ipv6,
+    /// Hexadecimal digits of a single case, at least one a letter, of the
+    /// given length.
+    Hex(usize),
+    /// Long base64 of the standard or URL-safe alphabet, possibly a
+    /// `data:` URL, mixing cases and digits; likely binary data embedded in
+    /// the document.
+    Base64
+}
+
+/// Shortest strings taken for hexadecimal ones.
+const MIN_HEX_LENGTH: usize = 8;
+
+/// Shortest strings taken for base64 ones, so that words and identifiers
+/// are not.
+const MIN_BASE64_LENGTH: usize = 64;
+
+impl StringFormat {
+    /// What hexadecimal strings of the length usually are.
+    fn hex_kind(length: usize) -> Option<&'static str> {
+        match length {
+            24 => Some("objectid"),
+            32 => Some("md5"),
+            40 => Some("sha1"),
+            64 => Some("sha256"),
+            128 => Some("sha512"),
+            _ => None
+        }
+    }
+
+    /// Whether strings of the format may identify people, which privacy
+    /// reviews look for.
+    pub fn is_personal(&self) -> bool {
+        matches!(self, StringFormat::Email | StringFormat::Ipv4 | StringFormat::Ipv6)
+    }
+}
+
+/// `date`, `uuid`, `email`, `url`, `ipv4`, `ipv6`, `base64` or `hex-N` followed by what such strings usually are,
+/// e.g. `hex-40 / sha1-like`.
+impl fmt::Display for StringFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StringFormat::Date => f.write_str("date"),
+            StringFormat::Uuid => f.write_str("uuid"),
+            StringFormat::Email => f.write_str("email"),
+            StringFormat::Url => f.write_str("url"),
+            StringFormat::Ipv4 => f.write_str("ipv4"),
+            StringFormat::Ipv6 => f.write_str("ipv6"),
+            StringFormat::Base64 => f.write_str("base64"),
+            StringFormat::Hex(length) => match StringFormat::hex_kind(*length) {
+                Some(kind) => write!(f, "hex-{} / {}-like", length, kind),
+                None => write!(f, "hex-{}", length)
+            }
+        }
+    }
+}
+
+/// Reads a format as `Display` writes it, what it is usually aside.
+impl FromStr for StringFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.split(" / ").next().unwrap_or(name);
+        Ok(match name {
+            "date" => StringFormat::Date,
+            "uuid" => StringFormat::Uuid,
+            "email" => StringFormat::Email,
+            "url" => StringFormat::Url,
+            "ipv4" => StringFormat::Ipv4,
+            "ipv6" => StringFormat::Ipv6,
+            "base64" => StringFormat::Base64,
+            _ => match name.strip_prefix("hex-").and_then(|length| length.parse().ok()) {
+                Some(length) => StringFormat::Hex(length),
+                None => return Err(format!("unknown string format \'{}\'", name))
+            }
+        })
+    }
+}
+
+/// Format of `line`, the first one recognized.
+pub fn detect(line: &str) -> Option<StringFormat> {
+    if parse_date(line).is_some() {
+        Some(StringFormat::Date)
+    } else if is_uuid(line) {
+        Some(StringFormat::Uuid)
+    } else if line.parse::<Ipv4Addr>().is_ok() {
+        Some(StringFormat::Ipv4)
+    } else if line.parse::<Ipv6Addr>().is_ok() {
+        Some(StringFormat::Ipv6)
+    } else if is_email(line) {
+        Some(StringFormat::Email)
+    } else if is_url(line) {
+        Some(StringFormat::Url)
+    } else if is_hex(line) {
+        Some(StringFormat::Hex(line.len()))
+    } else if base64_size(line).is_some() {
+        Some(StringFormat::Base64)
+    } else {
+        None
+    }
+}
+
+/// Whether `line` is a UUID of versions 1 to 7 with the RFC 4122 variant.
+pub fn is_uuid(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    bytes.len() == 36
+        && bytes.iter().enumerate().all(|(index, byte)| match index {
+            8 | 13 | 18 | 23 => *byte == b'-',
+            _ => byte.is_ascii_hexdigit()
+        })
+        && (b'1'..=b'7').contains(&bytes[14])
+        && matches!(bytes[19], b'8' | b'9' | b'a' | b'b' | b'A' | b'B')
+}
+
+/// Whether `line` is made of hexadecimal digits, see `StringFormat::Hex`.
+pub fn is_hex(line: &str) -> bool {
+    line.len() >= MIN_HEX_LENGTH
+        && line.bytes().all(|byte| byte.is_ascii_hexdigit())
+        && line.bytes().any(|byte| byte.is_ascii_alphabetic())
+        && ! (line.bytes().any(|byte| byte.is_ascii_lowercase()) && line.bytes().any(|byte| byte.is_ascii_uppercase()))
+}
+
+/// Whether `line` is a domain name of at least two labels, the last of
+/// letters only.
+fn is_domain(line: &str) -> bool {
+    let labels: Vec<&str> = line.split('.').collect();
+    labels.len() >= 2
+        && labels.iter().all(|label| {
+            (1..=63).contains(&label.len())
+                && label.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+                && ! label.starts_with('-') && ! label.ends_with('-')
+        })
+        && labels.last().is_some_and(|tld| tld.len() >= 2 && tld.bytes().all(|byte| byte.is_ascii_alphabetic()))
+}
+
+/// Whether `line` is an email address with a dot-atom local part and a
+/// domain name, as most addresses in use are.
+pub fn is_email(line: &str) -> bool {
+    let Some((local, domain)) = line.rsplit_once('@') else {
+        return false;
+    };
+    ! local.is_empty() && local.len() <= 64
+        && local.split('.').all(|atom| {
+            ! atom.is_empty() && atom.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+/=?^_`{|}~-".contains(&byte))
+        })
+        && is_domain(domain)
+}
+
+/// Whether `line` is an absolute URL with an authority: a scheme, `://`
+/// and a host, without whitespace.
+pub fn is_url(line: &str) -> bool {
+    let Some((scheme, rest)) = line.split_once("://") else {
+        return false;
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    scheme.bytes().next().is_some_and(|byte| byte.is_ascii_alphabetic())
+        && scheme.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"+.-".contains(&byte))
+        && ! host.is_empty() && ! host.starts_with(':')
+        && ! line.chars().any(char::is_whitespace)
+}
+
+/// Number of bytes `line` decodes to if it is base64, see
+/// `StringFormat::Base64`.
+pub fn base64_size(line: &str) -> Option<usize> {
+    let payload = match line.strip_prefix("data:") {
+        Some(url) => url.split_once(";base64,")?.1,
+        None => line
+    };
+    let data = payload.trim_end_matches('=');
+    let padding = payload.len() - data.len();
+    let is_standard = data.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'+' || byte == b'/');
+    let is_url_safe = data.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+    // encoded data mixes cases and digits, unlike long words
+    let is_mixed = data.bytes().any(|byte| byte.is_ascii_uppercase()) && data.bytes().any(|byte| byte.is_ascii_lowercase())
+        && data.bytes().any(|byte| ! byte.is_ascii_alphabetic());
+    if payload.len() < MIN_BASE64_LENGTH || padding > 2 || ! (is_standard || is_url_safe) || ! is_mixed
+        || (padding > 0 && payload.len() % 4 != 0) || data.len() % 4 == 1 {
+        return None;
+    }
+    Some(data.len() * 3 / 4)
+}
+
+/// Days from 1970-01-01 to the given date of the proleptic Gregorian
+/// calendar (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = i64::from((153 * ((month + 9) % 12) + 2) / 5 + day - 1);
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+/// Seconds since the Unix epoch of a valid date and time of day.
+fn timestamp(year: i64, month: u32, day: u32, (hours, minutes, seconds): (u32, u32, f64)) -> Option<f64> {
+    if ! (1..=12).contains(&month) || day == 0 || day > days_in_month(year, month)
+        || hours > 23 || minutes > 59 || ! (0.0..61.0).contains(&seconds) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) as f64 * 86_400.0 + f64::from(hours * 3600 + minutes * 60) + seconds)
+}
+
+fn is_digits(text: &str) -> bool {
+    text.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Value of `text` if it is made of `digits` ASCII digits.
+fn number(text: &str, digits: usize) -> Option<u32> {
+    if text.len() != digits || ! is_digits(text) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// `SS` with an optional fraction after `.` or `,`.
+fn parse_seconds(text: &str) -> Option<f64> {
+    let whole = f64::from(number(text.get(..2)?, 2)?);
+    match &text[2..] {
+        "" => Some(whole),
+        rest => {
+            let fraction = rest.strip_prefix(['.', ','])?;
+            if fraction.is_empty() || ! is_digits(fraction) {
+                return None;
+            }
+            Some(whole + format!("0.{}", fraction).parse::<f64>().ok()?)
+        }
+    }
+}
+
+/// `HH:MM` or `HH:MM:SS`, the seconds possibly fractional.
+fn parse_time(text: &str) -> Option<(u32, u32, f64)> {
+    let (hours, rest) = text.split_once(':')?;
+    let (minutes, seconds) = match rest.split_once(':') {
+        Some((minutes, seconds)) => (minutes, parse_seconds(seconds)?),
+        None => (rest, 0.0)
+    };
+    Some((number(hours, 2)?, number(minutes, 2)?, seconds))
+}
+
+/// Seconds east of UTC of `+HH:MM`, `+HHMM` or `+HH`.
+fn parse_offset(text: &str) -> Option<i64> {
+    let sign = match text.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None
+    };
+    let digits = text[1..].replace(':', "");
+    let (hours, minutes) = match digits.len() {
+        2 => (number(&digits, 2)?, 0),
+        4 => (number(&digits[..2], 2)?, number(&digits[2..], 2)?),
+        _ => return None
+    };
+    (hours < 24 && minutes < 60).then_some(sign * i64::from(hours * 3600 + minutes * 60))
+}
+
+/// `YYYY-MM-DD`, optionally followed by `T` or a space, a time and `Z` or an
+/// offset; times without either are taken as UTC.
+fn parse_iso(line: &str) -> Option<f64> {
+    let date = line.get(..10)?;
+    let bytes = date.as_bytes();
+    if bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let (year, month, day) = (number(&date[..4], 4)?, number(&date[5..7], 2)?, number(&date[8..], 2)?);
+    let rest = &line[10..];
+    if rest.is_empty() {
+        return timestamp(i64::from(year), month, day, (0, 0, 0.0));
+    }
+    let rest = rest.strip_prefix(['T', 't', ' '])?;
+    let (time, offset) = match rest.find(['Z', 'z', '+', '-']) {
+        Some(index) if rest[index..].eq_ignore_ascii_case("z") => (&rest[..index], 0),
+        Some(index) => (&rest[..index], parse_offset(&rest[index..])?),
+        None => (rest, 0)
+    };
+    Some(timestamp(i64::from(year), month, day, parse_time(time)?)? - offset as f64)
+}
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// `[Mon, ]DD Mon YYYY HH:MM[:SS] zone`, the zone an offset or one of the
+/// names RFC 2822 keeps from RFC 822.
+fn parse_rfc2822(line: &str) -> Option<f64> {
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.first()?.ends_with(',') {
+        let weekday = tokens.remove(0).trim_end_matches(',');
+        if ! DAYS.iter().any(|name| weekday.eq_ignore_ascii_case(name)) {
+            return None;
+        }
+    }
+    let [day, month, year, time, zone] = tokens.as_slice() else {
+        return None;
+    };
+    let day = number(day, day.len().clamp(1, 2))?;
+    let month = MONTHS.iter().position(|name| month.eq_ignore_ascii_case(name))? as u32 + 1;
+    let year = match year.len() {
+        4 => number(year, 4)?,
+        2 => number(year, 2)? + if number(year, 2)? < 50 { 2000 } else { 1900 },
+        _ => return None
+    };
+    let offset = match zone.to_ascii_uppercase().as_str() {
+        "GMT" | "UT" | "UTC" | "Z" => 0,
+        "EDT" => -4 * 3600,
+        "EST" | "CDT" => -5 * 3600,
+        "CST" | "MDT" => -6 * 3600,
+        "MST" | "PDT" => -7 * 3600,
+        "PST" => -8 * 3600,
+        _ => parse_offset(zone)?
+    };
+    Some(timestamp(i64::from(year), month, day, parse_time(time)?)? - offset as f64)
+}
+
+/// Unix time as a string: 10 digits of seconds or 13 of milliseconds,
+/// either with an optional fraction.
+fn parse_epoch(line: &str) -> Option<f64> {
+    let (whole, fraction) = line.split_once('.').unwrap_or((line, "0"));
+    if ! is_digits(whole) || fraction.is_empty() || ! is_digits(fraction) {
+        return None;
+    }
+    let value: f64 = line.parse().ok()?;
+    match whole.len() {
+        10 => Some(value),
+        13 => Some(value / 1000.0),
+        _ => None
+    }
+}
+
+/// Seconds since the Unix epoch of a date in one of the formats of
+/// `StringFormat::Date`.
+pub fn parse_date(line: &str) -> Option<f64> {
+    parse_iso(line).or_else(|| parse_rfc2822(line)).or_else(|| parse_epoch(line))
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ` for seconds since the Unix epoch, fractions of a
+/// second dropped.
+pub fn format_timestamp(seconds: f64) -> String {
+    let seconds = seconds.floor() as i64;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time = seconds.rem_euclid(86_400);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
@@ -0,0 +1,229 @@
+use std::io::BufRead;
+use std::ops::Range;
+
+use crate::parser::{intern_key, unsupported_option, JsonEvent, JsonMap, JsonValue, ParseError, ParserOptions};
+
+
+/// Handle of a value stored in a `JsonDocument`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// View of a single value of a `JsonDocument`; containers report their
+/// number of members, see `JsonDocument::items` and `JsonDocument::members`.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeRef<'d> {
+    STRING(&'d str),
+    NUMBER(f64),
+    RAWNUMBER(&'d str),
+    OBJECT(usize),
+    ARRAY(usize),
+    TRUE,
+    FALSE,
+    NULL
+}
+
+/// Value stored in the arena; strings are ranges of the shared text buffer
+/// and container members are ranges of the shared entries.
+enum Node {
+    String(Range<usize>),
+    Number(f64),
+    RawNumber(Range<usize>),
+    Object(Range<usize>),
+    Array(Range<usize>),
+    True,
+    False,
+    Null
+}
+
+/// Member of a container; array items have an empty key.
+struct Entry {
+    key: Range<usize>,
+    value: NodeId
+}
+
+/// Arena-backed JSON document. Values, strings and container members live
+/// in three flat buffers instead of separately allocated nodes, so dropping
+/// a document is cheap and `clear` lets one document be reused for parsing
+/// many inputs without reallocating.
+#[derive(Default)]
+pub struct JsonDocument {
+    nodes: Vec<Node>,
+    text: String,
+    entries: Vec<Entry>,
+    /// Members of the containers being parsed, moved to `entries` once the
+    /// container is closed so that every container's members are adjacent.
+    scratch: Vec<Entry>,
+    root: Option<NodeId>
+}
+
+impl JsonDocument {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes all values, keeping the allocated memory.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.text.clear();
+        self.entries.clear();
+        self.scratch.clear();
+        self.root = None;
+    }
+
+    pub fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    /// Number of values stored in the document.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn value(&self, id: NodeId) -> NodeRef<'_> {
+        match &self.nodes[id.0] {
+            Node::String(range) => NodeRef::STRING(&self.text[range.clone()]),
+            Node::Number(number) => NodeRef::NUMBER(*number),
+            Node::RawNumber(range) => NodeRef::RAWNUMBER(&self.text[range.clone()]),
+            Node::Object(range) => NodeRef::OBJECT(range.len()),
+            Node::Array(range) => NodeRef::ARRAY(range.len()),
+            Node::True => NodeRef::TRUE,
+            Node::False => NodeRef::FALSE,
+            Node::Null => NodeRef::NULL
+        }
+    }
+
+    fn children(&self, id: NodeId) -> &[Entry] {
+        match &self.nodes[id.0] {
+            Node::Object(range) | Node::Array(range) => &self.entries[range.clone()],
+            _ => &[]
+        }
+    }
+
+    /// Items of the array `id`; empty for other values.
+    pub fn items(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.children(id).iter().map(|entry| entry.value)
+    }
+
+    /// Members of the object `id` in document order, repeated keys
+    /// included; empty for other values.
+    pub fn members(&self, id: NodeId) -> impl Iterator<Item = (&str, NodeId)> + '_ {
+        self.children(id).iter().map(|entry| (&self.text[entry.key.clone()], entry.value))
+    }
+
+    /// Value of the last member of the object `id` named `key`.
+    pub fn get(&self, id: NodeId, key: &str) -> Option<NodeId> {
+        self.members(id).filter(|(name, _)| *name == key).last().map(|(_, value)| value)
+    }
+
+    /// Copies the value `id` out of the arena.
+    pub fn to_value(&self, id: NodeId) -> JsonValue {
+        match self.value(id) {
+            NodeRef::STRING(line) => JsonValue::STRING(line.to_string()),
+            NodeRef::NUMBER(number) => JsonValue::NUMBER(number),
+            NodeRef::RAWNUMBER(literal) => JsonValue::RAWNUMBER(literal.to_string()),
+            NodeRef::OBJECT(_) => JsonValue::OBJECT(self.members(id)
+                .map(|(key, value)| (intern_key(key), self.to_value(value)))
+                .collect::<JsonMap<_>>()),
+            NodeRef::ARRAY(_) => JsonValue::ARRAY(self.items(id).map(|item| self.to_value(item)).collect()),
+            NodeRef::TRUE => JsonValue::TRUE,
+            NodeRef::FALSE => JsonValue::FALSE,
+            NodeRef::NULL => JsonValue::NULL
+        }
+    }
+
+    fn push_text(&mut self, text: &str) -> Range<usize> {
+        let start = self.text.len();
+        self.text.push_str(text);
+        start..self.text.len()
+    }
+
+    fn push_node(&mut self, node: Node) -> NodeId {
+        self.nodes.push(node);
+        NodeId(self.nodes.len() - 1)
+    }
+}
+
+/// Adds parsing events to a document.
+struct DocumentBuilder<'d> {
+    document: &'d mut JsonDocument,
+    /// Open containers with their own key and the start of their members
+    /// in `scratch`.
+    open: Vec<(NodeId, Range<usize>, usize)>,
+    key: Range<usize>
+}
+
+impl DocumentBuilder<'_> {
+    fn attach(&mut self, key: Range<usize>, value: NodeId) {
+        if self.open.is_empty() {
+            self.document.root = Some(value);
+            return;
+        }
+        self.document.scratch.push(Entry { key, value });
+    }
+
+    fn event(&mut self, event: JsonEvent<'_>) {
+        let node = match event {
+            JsonEvent::StartObject | JsonEvent::StartArray => {
+                let node = if event == JsonEvent::StartObject { Node::Object(0..0) } else { Node::Array(0..0) };
+                let id = self.document.push_node(node);
+                let key = std::mem::replace(&mut self.key, 0..0);
+                self.open.push((id, key, self.document.scratch.len()));
+                return;
+            },
+            JsonEvent::EndObject | JsonEvent::EndArray => {
+                let Some((id, key, start)) = self.open.pop() else {
+                    return;
+                };
+                let document = &mut *self.document;
+                let range = document.entries.len()..document.entries.len() + document.scratch.len() - start;
+                document.entries.extend(document.scratch.drain(start..));
+                match &mut document.nodes[id.0] {
+                    Node::Object(members) | Node::Array(members) => *members = range,
+                    _ => {}
+                }
+                self.attach(key, id);
+                return;
+            },
+            JsonEvent::Key(key) => {
+                self.key = self.document.push_text(key);
+                return;
+            },
+            JsonEvent::String(line) => Node::String(self.document.push_text(line)),
+            JsonEvent::Number(number) => Node::Number(number),
+            JsonEvent::RawNumber(literal) => Node::RawNumber(self.document.push_text(literal)),
+            JsonEvent::Bool(true) => Node::True,
+            JsonEvent::Bool(false) => Node::False,
+            JsonEvent::Null => Node::Null
+        };
+        let id = self.document.push_node(node);
+        let key = std::mem::replace(&mut self.key, 0..0);
+        self.attach(key, id);
+    }
+}
+
+impl ParserOptions {
+    /// Parses the input into `document`, replacing its previous content.
+    /// On error the document is left empty. `raw_depth` is not supported.
+    pub fn parse_document(&self, reader: impl BufRead + 'static, document: &mut JsonDocument) -> Result<(), ParseError> {
+        document.clear();
+        if self.raw_depth.is_some() {
+            return Err(unsupported_option("raw_depth", "document parser"));
+        }
+        let mut builder = DocumentBuilder { document, open: vec![], key: 0..0 };
+        let result = self.parse_events(reader, |event| builder.event(event));
+        if result.is_err() {
+            document.clear();
+        }
+        result
+    }
+}
+
+pub fn parse_document(reader: impl BufRead + 'static, document: &mut JsonDocument) -> Result<(), ParseError> {
+    ParserOptions::default().parse_document(reader, document)
+}
+
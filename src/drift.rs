@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+
+use crate::parser::{JsonMap, JsonValue};
+use crate::sniffer::member_path;
+
+
+/// Difference at one path between the schemas inferred by two stats
+/// reports. Paths are those of `JsonStatsReport::rows`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SchemaChange {
+    Added { path: String, types: Vec<String> },
+    Removed { path: String, types: Vec<String> },
+    Retyped { path: String, before: Vec<String>, after: Vec<String> }
+}
+
+impl SchemaChange {
+    pub fn path(&self) -> &str {
+        match self {
+            SchemaChange::Added { path, .. } | SchemaChange::Removed { path, .. } | SchemaChange::Retyped { path, .. } => path
+        }
+    }
+}
+
+/// `+ path: types` for added paths, `-` for removed ones and
+/// `~ path: before -> after` for retyped ones, types separated by `|`.
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaChange::Added { path, types } => write!(f, "+ {}: {}", path, types.join("|")),
+            SchemaChange::Removed { path, types } => write!(f, "- {}: {}", path, types.join("|")),
+            SchemaChange::Retyped { path, before, after } => write!(f, "~ {}: {} -> {}", path, before.join("|"), after.join("|"))
+        }
+    }
+}
+
+/// Report that is not a stats report in JSON.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DriftError {
+    pub msg: String
+}
+
+impl fmt::Display for DriftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed stats report: {}", self.msg)
+    }
+}
+
+impl Error for DriftError {}
+
+fn drift_error(msg: impl Into<String>) -> DriftError {
+    DriftError { msg: msg.into() }
+}
+
+/// Names of `types`, `true` and `false` being booleans alike so that the
+/// values seen do not count as drift.
+fn type_names(types: Option<&JsonValue>) -> Result<Vec<String>, DriftError> {
+    let name = |name: &str| match name {
+        "true" | "false" => "boolean".to_string(),
+        other => other.to_string()
+    };
+    types.and_then(JsonValue::as_array)
+        .and_then(|types| types.iter().map(|type_name| type_name.as_str().map(name)).collect())
+        .ok_or_else(|| drift_error("types are not a list of names"))
+}
+
+fn members<'a>(report: &'a JsonValue, name: &str) -> Result<&'a JsonMap<JsonValue>, DriftError> {
+    report.get(name).and_then(JsonValue::as_object).ok_or_else(|| drift_error(format!("\'{}\' is not an object", name)))
+}
+
+fn collect_paths(report: &JsonValue, paths: &mut BTreeMap<String, BTreeSet<String>>) -> Result<(), DriftError> {
+    let path = report.get("path").and_then(JsonValue::as_str).ok_or_else(|| drift_error("missing \'path\'"))?;
+    match report.get("type").and_then(JsonValue::as_str) {
+        Some("array") => {
+            paths.entry(format!("{}[*]", path)).or_default().extend(type_names(report.get("types"))?);
+            for inner in ["inner_arrays", "inner_objects"] {
+                if let Some(inner) = report.get(inner).filter(|inner| ! inner.is_null()) {
+                    collect_paths(inner, paths)?;
+                }
+            }
+        },
+        Some("object") => {
+            for (key, types) in members(report, "primitive_keys")? {
+                paths.entry(member_path(path, key)).or_default().extend(type_names(Some(types))?);
+            }
+            for (key, member) in members(report, "members")? {
+                let kind = member.get("type").and_then(JsonValue::as_str).unwrap_or_default();
+                paths.entry(member_path(path, key)).or_default().insert(kind.to_string());
+                collect_paths(member, paths)?;
+            }
+        },
+        _ => return Err(drift_error(format!("unknown type at \'{}\'", path)))
+    }
+    Ok(())
+}
+
+/// Paths of a report written as JSON by `--output json` and the sorted
+/// types of their values; none for the `null` report of no documents.
+pub fn schema_paths(report: &JsonValue) -> Result<BTreeMap<String, Vec<String>>, DriftError> {
+    let mut paths = BTreeMap::new();
+    if ! report.is_null() {
+        collect_paths(report, &mut paths)?;
+    }
+    Ok(paths.into_iter().map(|(path, types)| (path, types.into_iter().collect())).collect())
+}
+
+/// Changes of the schema from the `baseline` report to `report`, both as
+/// JSON, sorted by path.
+pub fn schema_drift(baseline: &JsonValue, report: &JsonValue) -> Result<Vec<SchemaChange>, DriftError> {
+    let before = schema_paths(baseline)?;
+    let mut after = schema_paths(report)?;
+    let mut changes = vec![];
+    for (path, types) in before {
+        match after.remove(&path) {
+            None => changes.push(SchemaChange::Removed { path, types }),
+            Some(current) if current != types => changes.push(SchemaChange::Retyped { path, before: types, after: current }),
+            Some(_) => {}
+        }
+    }
+    changes.extend(after.into_iter().map(|(path, types)| SchemaChange::Added { path, types }));
+    changes.sort_by(|left, right| left.path().cmp(right.path()));
+    Ok(changes)
+}
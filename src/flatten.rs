@@ -0,0 +1,161 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::jsonpath::members;
+use crate::parser::{self, unescape, JsonMap, JsonValue};
+use crate::serializer::write_string;
+
+
+/// Name of the whole document in flattened paths.
+pub const ROOT: &str = "json";
+
+/// Flattened path that cannot be parsed or that conflicts with another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlattenError {
+    pub path: String,
+    pub msg: String
+}
+
+impl fmt::Display for FlattenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in path \'{}\'", self.msg, self.path)
+    }
+}
+
+impl Error for FlattenError {}
+
+enum Step {
+    Key(String),
+    Index(usize)
+}
+
+/// Whether `key` can follow a dot in a path, like a JavaScript identifier.
+fn is_identifier(key: &str) -> bool {
+    let mut symbols = key.chars();
+    symbols.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_' || first == '$')
+        && symbols.all(|symbol| symbol.is_ascii_alphanumeric() || symbol == '_' || symbol == '$')
+}
+
+fn member_path(path: &str, key: &str) -> String {
+    if is_identifier(key) {
+        return format!("{}.{}", path, key);
+    }
+    let mut member = format!("{}[", path);
+    let _ = write_string(&mut member, key);
+    member.push(']');
+    member
+}
+
+fn flatten_into(value: &JsonValue, path: String, entries: &mut Vec<(String, JsonValue)>) {
+    match value {
+        JsonValue::OBJECT(object) if ! object.is_empty() => for (key, member) in members(object) {
+            flatten_into(member, member_path(&path, key), entries);
+        },
+        JsonValue::ARRAY(array) if ! array.is_empty() => for (index, item) in array.iter().enumerate() {
+            flatten_into(item, format!("{}[{}]", path, index), entries);
+        },
+        _ => entries.push((path, value.clone()))
+    }
+}
+
+impl JsonValue {
+    /// Leaves of the value with gron-style paths, e.g. `json.users[0].name`
+    /// or `json["first name"]`. Empty objects and arrays are leaves too, so
+    /// that the document can be rebuilt from the entries.
+    pub fn flatten(&self) -> Vec<(String, JsonValue)> {
+        let mut entries = vec![];
+        flatten_into(self, ROOT.to_string(), &mut entries);
+        entries
+    }
+}
+
+/// Steps of the path at the start of `text` and the number of bytes it
+/// takes.
+fn parse_path(text: &str) -> Result<(Vec<Step>, usize), String> {
+    let Some(mut rest) = text.strip_prefix(ROOT) else {
+        return Err(format!("missing leading '{}'", ROOT));
+    };
+    let mut steps = vec![];
+    loop {
+        if let Some(after) = rest.strip_prefix('.') {
+            let length = after.find(|symbol: char| ! (symbol.is_ascii_alphanumeric() || symbol == '_' || symbol == '$'))
+                .unwrap_or(after.len());
+            if ! is_identifier(&after[..length]) {
+                return Err("invalid key after '.'".to_string());
+            }
+            steps.push(Step::Key(after[..length].to_string()));
+            rest = &after[length..];
+        } else if let Some(after) = rest.strip_prefix("[\"") {
+            let mut is_escaped = false;
+            let end = after.char_indices().find(|&(_, symbol)| {
+                let is_end = symbol == '"' && ! is_escaped;
+                is_escaped = symbol == '\\' && ! is_escaped;
+                is_end
+            }).map(|(at, _)| at).ok_or("unterminated key")?;
+            steps.push(Step::Key(unescape(&after[..end].chars().collect::<Vec<_>>(), false)?));
+            rest = after[end + 1..].strip_prefix(']').ok_or("missing ']' after key")?;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let length = after.find(|symbol: char| ! symbol.is_ascii_digit()).unwrap_or(after.len());
+            steps.push(Step::Index(after[..length].parse().map_err(|_| "invalid array index")?));
+            rest = after[length..].strip_prefix(']').ok_or("missing ']' after index")?;
+        } else {
+            return Ok((steps, text.len() - rest.len()));
+        }
+    }
+}
+
+fn assign(root: &mut JsonValue, steps: &[Step], value: JsonValue) -> Result<(), String> {
+    let mut target = root;
+    for step in steps {
+        if target.is_null() {
+            *target = match step {
+                Step::Key(_) => JsonValue::OBJECT(JsonMap::new()),
+                Step::Index(_) => JsonValue::ARRAY(vec![])
+            };
+        }
+        target = match (step, target) {
+            (Step::Key(key), JsonValue::OBJECT(object)) => object.entry(key.as_str().into())
+                .or_insert(JsonValue::NULL),
+            (Step::Index(index), JsonValue::ARRAY(array)) => {
+                if array.len() <= *index {
+                    array.resize_with(index + 1, || JsonValue::NULL);
+                }
+                &mut array[*index]
+            },
+            (Step::Key(key), _) => return Err(format!("key '{}' of a value that is not an object", key)),
+            (Step::Index(index), _) => return Err(format!("index {} of a value that is not an array", index))
+        };
+    }
+    *target = value;
+    Ok(())
+}
+
+/// Rebuilds a value from entries produced by `JsonValue::flatten`. Later
+/// entries win, and items missing from arrays are filled with `null`s.
+pub fn unflatten<P: AsRef<str>>(entries: impl IntoIterator<Item = (P, JsonValue)>) -> Result<JsonValue, FlattenError> {
+    let mut root = JsonValue::NULL;
+    for (path, value) in entries {
+        let path = path.as_ref();
+        let fail = |msg: String| FlattenError { path: path.to_string(), msg };
+        let (steps, length) = parse_path(path).map_err(fail)?;
+        if length < path.len() {
+            return Err(fail(format!("unexpected '{}'", &path[length..])));
+        }
+        assign(&mut root, &steps, value).map_err(fail)?;
+    }
+    Ok(root)
+}
+
+/// Reads a `path = value` line as written by the `flatten` subcommand; a
+/// trailing `;` as in gron output is accepted.
+pub fn parse_line(line: &str) -> Result<(String, JsonValue), FlattenError> {
+    let fail = |path: &str, msg: String| FlattenError { path: path.to_string(), msg };
+    let (_, length) = parse_path(line).map_err(|msg| fail(line, msg))?;
+    let path = &line[..length];
+    let Some(value) = line[length..].trim_start().strip_prefix('=') else {
+        return Err(fail(path, "missing '='".to_string()));
+    };
+    let value = value.trim().trim_end_matches(';');
+    let value = parser::parse_str(value).map_err(|error| fail(path, error.msg))?;
+    Ok((path.to_string(), value))
+}
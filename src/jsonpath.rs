@@ -0,0 +1,455 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+
+use crate::lexer::number_length;
+use crate::parser::{number_value, unescape, JsonMap, JsonValue};
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonPathError {
+    /// Byte offset of the error in the expression.
+    pub offset: usize,
+    pub msg: String
+}
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.msg, self.offset)
+    }
+}
+
+impl Error for JsonPathError {}
+
+/// Value selected by a `JsonPath` together with its normalized path, e.g.
+/// `$['store']['book'][0]`.
+#[derive(Debug, PartialEq)]
+pub struct Match<'a> {
+    pub path: String,
+    pub value: &'a JsonValue
+}
+
+/// Step of a path inside a filter, which selects at most one value.
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Name(String),
+    Index(i64)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    /// Path relative to the value being filtered, `@`.
+    Current(Vec<Step>),
+    /// Path relative to the queried value, `$`.
+    Root(Vec<Step>),
+    Literal(JsonValue)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Filter {
+    Or(Box<Filter>, Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    /// Holds if the operand selects a value.
+    Exists(Operand),
+    Compare(Operand, Comparison, Operand)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Selector {
+    Name(String),
+    /// Array item, counted from the end if negative.
+    Index(i64),
+    Wildcard,
+    Filter(Filter)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Segment {
+    selectors: Vec<Selector>,
+    /// `..` segment applied to the value and all of its descendants.
+    is_descendant: bool
+}
+
+/// Compiled JSONPath expression: `$` followed by `.name`, `.*`, `..name`,
+/// `..*` and bracketed selectors: names (`['a']`), indices (`[0]`, `[-1]`),
+/// wildcards (`[*]`), unions of them (`[0,2]`) and filters such as
+/// `[?(@.price < 10 && @.isbn)]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>
+}
+
+/// Recursive descent parser of JSONPath expressions.
+struct PathParser<'p> {
+    source: &'p str,
+    position: usize
+}
+
+impl PathParser<'_> {
+    fn error(&self, msg: impl Into<String>) -> JsonPathError {
+        JsonPathError { offset: self.position, msg: msg.into() }
+    }
+
+    fn rest(&self) -> &str {
+        &self.source[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_spaces(&mut self) {
+        self.position = self.source.len() - self.rest().trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), JsonPathError> {
+        self.skip_spaces();
+        if self.eat(token) {
+            return Ok(());
+        }
+        Err(self.error(format!("expected '{}'", token)))
+    }
+
+    fn name(&mut self) -> Result<String, JsonPathError> {
+        let length = self.rest()
+            .find(|symbol: char| ! (symbol.is_alphanumeric() || symbol == '_' || symbol == '-' || symbol == '$'))
+            .unwrap_or(self.rest().len());
+        if length == 0 {
+            return Err(self.error("expected a member name"));
+        }
+        let name = self.rest()[..length].to_string();
+        self.position += length;
+        Ok(name)
+    }
+
+    fn string(&mut self) -> Result<String, JsonPathError> {
+        let start = self.position;
+        let quote = self.peek().unwrap_or('\0');
+        self.position += 1;
+        let mut raw = vec![];
+        let mut chars = self.rest().char_indices();
+        while let Some((at, symbol)) = chars.next() {
+            match symbol {
+                '\\' => match chars.next() {
+                    Some((_, '\'')) => raw.push('\''),
+                    Some((_, escaped)) => raw.extend(['\\', escaped]),
+                    None => break
+                },
+                _ if symbol == quote => {
+                    self.position += at + 1;
+                    return unescape(&raw, false)
+                        .map_err(|reason| JsonPathError { offset: start, msg: reason });
+                },
+                _ => raw.push(symbol)
+            }
+        }
+        self.position = start;
+        Err(self.error("unterminated string"))
+    }
+
+    fn index(&mut self) -> Result<i64, JsonPathError> {
+        let length = usize::from(self.rest().starts_with('-'))
+            + self.rest().trim_start_matches('-').find(|symbol: char| ! symbol.is_ascii_digit())
+                .unwrap_or(self.rest().trim_start_matches('-').len());
+        let index = self.rest()[..length].parse().map_err(|_| self.error("invalid index"))?;
+        self.position += length;
+        Ok(index)
+    }
+
+    /// Names and indices following `@` or `$` inside a filter.
+    fn steps(&mut self) -> Result<Vec<Step>, JsonPathError> {
+        let mut steps = vec![];
+        loop {
+            if self.eat(".") {
+                steps.push(Step::Name(self.name()?));
+            } else if self.eat("[") {
+                self.skip_spaces();
+                steps.push(match self.peek() {
+                    Some('\'' | '"') => Step::Name(self.string()?),
+                    _ => Step::Index(self.index()?)
+                });
+                self.expect("]")?;
+            } else {
+                return Ok(steps);
+            }
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, JsonPathError> {
+        self.skip_spaces();
+        if self.eat("@") {
+            return self.steps().map(Operand::Current);
+        }
+        if self.eat("$") {
+            return self.steps().map(Operand::Root);
+        }
+        for (word, value) in [("true", JsonValue::TRUE), ("false", JsonValue::FALSE), ("null", JsonValue::NULL)] {
+            if self.eat(word) {
+                return Ok(Operand::Literal(value));
+            }
+        }
+        match self.peek() {
+            Some('\'' | '"') => self.string().map(|line| Operand::Literal(JsonValue::STRING(line))),
+            _ => {
+                let length = number_length(self.rest()).ok_or_else(|| self.error("expected an operand"))?;
+                let number = number_value(&self.rest()[..length]).unwrap_or_default();
+                self.position += length;
+                Ok(Operand::Literal(JsonValue::NUMBER(number)))
+            }
+        }
+    }
+
+    fn comparison(&mut self) -> Option<Comparison> {
+        self.skip_spaces();
+        let comparisons = [
+            ("==", Comparison::Equal), ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual), (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less), (">", Comparison::Greater)
+        ];
+        comparisons.into_iter().find(|(token, _)| self.eat(token)).map(|(_, comparison)| comparison)
+    }
+
+    fn primary(&mut self) -> Result<Filter, JsonPathError> {
+        self.skip_spaces();
+        if self.eat("!") {
+            return self.primary().map(|filter| Filter::Not(Box::new(filter)));
+        }
+        if self.eat("(") {
+            let filter = self.filter()?;
+            self.expect(")")?;
+            return Ok(filter);
+        }
+        let left = self.operand()?;
+        Ok(match self.comparison() {
+            Some(comparison) => Filter::Compare(left, comparison, self.operand()?),
+            None => Filter::Exists(left)
+        })
+    }
+
+    fn conjunction(&mut self) -> Result<Filter, JsonPathError> {
+        let mut filter = self.primary()?;
+        self.skip_spaces();
+        while self.eat("&&") {
+            filter = Filter::And(Box::new(filter), Box::new(self.primary()?));
+            self.skip_spaces();
+        }
+        Ok(filter)
+    }
+
+    fn filter(&mut self) -> Result<Filter, JsonPathError> {
+        let mut filter = self.conjunction()?;
+        while self.eat("||") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.conjunction()?));
+        }
+        Ok(filter)
+    }
+
+    fn selector(&mut self) -> Result<Selector, JsonPathError> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('*') => {
+                self.position += 1;
+                Ok(Selector::Wildcard)
+            },
+            Some('?') => {
+                self.position += 1;
+                self.filter().map(Selector::Filter)
+            },
+            Some('\'' | '"') => self.string().map(Selector::Name),
+            _ => self.index().map(Selector::Index)
+        }
+    }
+
+    fn brackets(&mut self) -> Result<Vec<Selector>, JsonPathError> {
+        let mut selectors = vec![self.selector()?];
+        loop {
+            self.skip_spaces();
+            if self.eat("]") {
+                return Ok(selectors);
+            }
+            self.expect(",")?;
+            selectors.push(self.selector()?);
+        }
+    }
+
+    fn path(&mut self) -> Result<JsonPath, JsonPathError> {
+        self.skip_spaces();
+        if ! self.eat("$") {
+            return Err(self.error("expected '$'"));
+        }
+        let mut segments = vec![];
+        loop {
+            let is_descendant = self.eat("..");
+            let selectors = if self.eat("[") {
+                self.brackets()?
+            } else if is_descendant || self.eat(".") {
+                if self.eat("*") { vec![Selector::Wildcard] } else { vec![Selector::Name(self.name()?)] }
+            } else if self.rest().trim().is_empty() {
+                return Ok(JsonPath { segments });
+            } else {
+                return Err(self.error("expected '.', '..' or '['"));
+            };
+            segments.push(Segment { selectors, is_descendant });
+        }
+    }
+}
+
+/// Members of an object in a stable order: the document order with the
+/// `preserve_order` feature and sorted by key otherwise.
+pub(crate) fn members(object: &JsonMap<JsonValue>) -> Vec<(&str, &JsonValue)> {
+    #[allow(unused_mut)]
+    let mut members: Vec<_> = object.iter().map(|(key, value)| (key.as_ref(), value)).collect();
+    #[cfg(not(feature = "preserve_order"))]
+    members.sort_by_key(|(key, _)| *key);
+    members
+}
+
+fn name_path(path: &str, name: &str) -> String {
+    format!("{}['{}']", path, name.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn resolve_index(index: i64, length: usize) -> Option<usize> {
+    let index = if index < 0 { length as i64 + index } else { index };
+    usize::try_from(index).ok().filter(|index| *index < length)
+}
+
+fn resolve<'a>(value: &'a JsonValue, steps: &[Step]) -> Option<&'a JsonValue> {
+    steps.iter().try_fold(value, |value, step| match step {
+        Step::Name(name) => value.get(name),
+        Step::Index(index) => value.as_array()
+            .and_then(|array| resolve_index(*index, array.len()))
+            .and_then(|index| value.get_index(index))
+    })
+}
+
+fn compare(left: &JsonValue, right: &JsonValue) -> Option<Ordering> {
+    match (left.as_f64(), right.as_f64(), left.as_str(), right.as_str()) {
+        (Some(left), Some(right), _, _) => left.partial_cmp(&right),
+        (_, _, Some(left), Some(right)) => Some(left.cmp(right)),
+        _ if left == right => Some(Ordering::Equal),
+        _ => None
+    }
+}
+
+impl Filter {
+    fn operand<'a>(operand: &'a Operand, current: &'a JsonValue, root: &'a JsonValue) -> Option<&'a JsonValue> {
+        match operand {
+            Operand::Current(steps) => resolve(current, steps),
+            Operand::Root(steps) => resolve(root, steps),
+            Operand::Literal(value) => Some(value)
+        }
+    }
+
+    fn holds(&self, current: &JsonValue, root: &JsonValue) -> bool {
+        match self {
+            Filter::Or(left, right) => left.holds(current, root) || right.holds(current, root),
+            Filter::And(left, right) => left.holds(current, root) && right.holds(current, root),
+            Filter::Not(filter) => ! filter.holds(current, root),
+            Filter::Exists(operand) => Self::operand(operand, current, root).is_some(),
+            Filter::Compare(left, comparison, right) => {
+                let (Some(left), Some(right)) = (Self::operand(left, current, root), Self::operand(right, current, root)) else {
+                    return *comparison == Comparison::NotEqual;
+                };
+                let ordering = compare(left, right);
+                match comparison {
+                    Comparison::Equal => ordering == Some(Ordering::Equal),
+                    Comparison::NotEqual => ordering != Some(Ordering::Equal),
+                    Comparison::Less => ordering == Some(Ordering::Less),
+                    Comparison::LessOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    Comparison::Greater => ordering == Some(Ordering::Greater),
+                    Comparison::GreaterOrEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                }
+            }
+        }
+    }
+}
+
+/// Items of an array or members of an object.
+fn children<'a>(path: &str, value: &'a JsonValue) -> Vec<Match<'a>> {
+    match value {
+        JsonValue::ARRAY(array) => array.iter().enumerate()
+            .map(|(index, item)| Match { path: format!("{}[{}]", path, index), value: item })
+            .collect(),
+        JsonValue::OBJECT(object) => members(object).into_iter()
+            .map(|(key, member)| Match { path: name_path(path, key), value: member })
+            .collect(),
+        _ => vec![]
+    }
+}
+
+impl Selector {
+    fn apply<'a>(&self, path: &str, value: &'a JsonValue, root: &JsonValue, matches: &mut Vec<Match<'a>>) {
+        match self {
+            Selector::Name(name) => if let Some(member) = value.get(name) {
+                matches.push(Match { path: name_path(path, name), value: member });
+            },
+            Selector::Index(index) => if let Some(index) = value.as_array().and_then(|array| resolve_index(*index, array.len())) {
+                matches.push(Match { path: format!("{}[{}]", path, index), value: &value[index] });
+            },
+            Selector::Wildcard => matches.extend(children(path, value)),
+            Selector::Filter(filter) => matches.extend(children(path, value).into_iter()
+                .filter(|child| filter.holds(child.value, root)))
+        }
+    }
+}
+
+/// The value and all of its descendants in document order.
+fn descendants<'a>(node: Match<'a>, nodes: &mut Vec<Match<'a>>) {
+    let inner = children(&node.path, node.value);
+    nodes.push(node);
+    for child in inner {
+        descendants(child, nodes);
+    }
+}
+
+impl JsonPath {
+    pub fn parse(expression: &str) -> Result<Self, JsonPathError> {
+        PathParser { source: expression, position: 0 }.path()
+    }
+
+    /// Values of `root` matched by the path, in document order.
+    pub fn select<'a>(&self, root: &'a JsonValue) -> Vec<Match<'a>> {
+        let mut nodes = vec![Match { path: "$".to_string(), value: root }];
+        for segment in &self.segments {
+            if segment.is_descendant {
+                let mut expanded = vec![];
+                for node in nodes {
+                    descendants(node, &mut expanded);
+                }
+                nodes = expanded;
+            }
+            let mut matches = vec![];
+            for node in &nodes {
+                for selector in &segment.selectors {
+                    selector.apply(&node.path, node.value, root, &mut matches);
+                }
+            }
+            nodes = matches;
+        }
+        nodes
+    }
+}
+
+/// Values of `root` matched by the JSONPath `expression`.
+pub fn query<'a>(root: &'a JsonValue, expression: &str) -> Result<Vec<Match<'a>>, JsonPathError> {
+    JsonPath::parse(expression).map(|path| path.select(root))
+}
@@ -0,0 +1,209 @@
+use std::ops::Range;
+
+use crate::parser::{ParseError, ParseErrorKind};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Colon,
+    Comma,
+    String,
+    Number,
+    True,
+    False,
+    Null,
+    Whitespace,
+    /// `//` line or `/* */` block comment; only valid JSON with
+    /// `ParserOptions::allow_comments`.
+    Comment
+}
+
+/// Token together with the byte range it occupies in the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>
+}
+
+impl Token {
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.span.clone()]
+    }
+}
+
+/// Splits JSON text into tokens, whitespace and comments included, so that
+/// the spans of all tokens cover the source. After an invalid token the
+/// lexer reports an error and continues behind it.
+pub struct Lexer<'a> {
+    source: &'a str,
+    position: usize
+}
+
+fn is_number_char(symbol: char) -> bool {
+    symbol.is_ascii_digit() || matches!(symbol, '-' | '+' | '.' | 'e' | 'E')
+}
+
+fn count_digits(bytes: &[u8], from: usize) -> usize {
+    bytes[from.min(bytes.len())..].iter().take_while(|byte| byte.is_ascii_digit()).count()
+}
+
+/// Length of the number at the beginning of `rest` per the RFC 8259
+/// grammar, or `None` if it is malformed.
+pub(crate) fn number_length(rest: &str) -> Option<usize> {
+    let bytes = rest.as_bytes();
+    let mut end = usize::from(bytes.first() == Some(&b'-'));
+    end += match bytes.get(end) {
+        Some(b'0') => 1,
+        Some(b'1'..=b'9') => count_digits(bytes, end),
+        _ => return None
+    };
+    if bytes.get(end) == Some(&b'.') {
+        let digits = count_digits(bytes, end + 1);
+        if digits == 0 {
+            return None;
+        }
+        end += 1 + digits;
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        end += 1;
+        if matches!(bytes.get(end), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        let digits = count_digits(bytes, end);
+        if digits == 0 {
+            return None;
+        }
+        end += digits;
+    }
+    Some(end)
+}
+
+/// Error for `span` of `source`, with the row and column computed from it.
+pub(crate) fn error_at(source: &str, span: Range<usize>, kind: ParseErrorKind, msg: String) -> ParseError {
+    let before = &source[..span.start];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    ParseError {
+        kind,
+        row: before.matches('\n').count(),
+        col: before[line_start..].chars().count(),
+        offset: span.start,
+        span,
+        expected: vec![],
+        msg
+    }
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self { source, position: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn error(&self, span: Range<usize>, kind: ParseErrorKind, msg: String) -> ParseError {
+        error_at(self.source, span, kind, msg)
+    }
+
+    /// Length of the string literal at the current position, or the error
+    /// together with the number of bytes to skip: the whole literal, so
+    /// lexing resumes after its closing quote.
+    fn string(&self) -> Result<usize, (ParseError, usize)> {
+        let mut chars = self.rest().char_indices().skip(1).peekable();
+        let mut failure = None;
+        while let Some((at, symbol)) = chars.next() {
+            match symbol {
+                '"' => return match failure {
+                    Some(error) => Err((error, at + 1)),
+                    None => Ok(at + 1)
+                },
+                '\\' => {
+                    let escape = chars.next().map(|(_, escaped)| escaped);
+                    let is_valid = match escape {
+                        Some('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => true,
+                        Some('u') => (0..4).all(|_| chars.next_if(|(_, digit)| digit.is_ascii_hexdigit()).is_some()),
+                        _ => false
+                    };
+                    if ! is_valid && failure.is_none() {
+                        let start = self.position + at;
+                        let end = chars.peek().map_or(self.source.len(), |(next, _)| self.position + next);
+                        failure = Some(self.error(start..end, ParseErrorKind::InvalidEscape,
+                            "invalid escape sequence".to_string()));
+                    }
+                },
+                _ => {}
+            }
+        }
+        let error = self.error(self.position..self.source.len(), ParseErrorKind::UnexpectedEof,
+            "unterminated string".to_string());
+        Err((failure.unwrap_or(error), self.rest().len()))
+    }
+
+    fn token(&self) -> Result<(TokenKind, usize), (ParseError, usize)> {
+        let rest = self.rest();
+        let symbol = rest.chars().next().unwrap_or('\0');
+        let punctuation = match symbol {
+            '{' => Some(TokenKind::LBrace),
+            '}' => Some(TokenKind::RBrace),
+            '[' => Some(TokenKind::LBracket),
+            ']' => Some(TokenKind::RBracket),
+            ':' => Some(TokenKind::Colon),
+            ',' => Some(TokenKind::Comma),
+            _ => None
+        };
+        if let Some(kind) = punctuation {
+            return Ok((kind, 1));
+        }
+        for (word, kind) in [("true", TokenKind::True), ("false", TokenKind::False), ("null", TokenKind::Null)] {
+            if rest.starts_with(word) {
+                return Ok((kind, word.len()));
+            }
+        }
+        match symbol {
+            ' ' | '\n' | '\r' | '\t' => Ok((TokenKind::Whitespace,
+                rest.find(|symbol| ! matches!(symbol, ' ' | '\n' | '\r' | '\t')).unwrap_or(rest.len()))),
+            '"' => self.string().map(|length| (TokenKind::String, length)),
+            '/' if rest.starts_with("//") => Ok((TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))),
+            '/' if rest.starts_with("/*") => match rest.find("*/") {
+                Some(end) => Ok((TokenKind::Comment, end + 2)),
+                None => Err((self.error(self.position..self.source.len(), ParseErrorKind::UnexpectedEof,
+                    "unterminated block comment".to_string()), rest.len()))
+            },
+            _ if is_number_char(symbol) => number_length(rest)
+                .map(|length| (TokenKind::Number, length))
+                .ok_or_else(|| {
+                    let length = rest.find(|symbol| ! is_number_char(symbol)).unwrap_or(rest.len());
+                    (self.error(self.position..self.position + length, ParseErrorKind::UnexpectedChar,
+                        format!("malformed number {:?}", &rest[..length])), length)
+                }),
+            _ => Err((self.error(self.position..self.position + symbol.len_utf8(), ParseErrorKind::UnexpectedChar,
+                format!("unexpected symbol {:?}", symbol)), symbol.len_utf8()))
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.source.len() {
+            return None;
+        }
+        let start = self.position;
+        Some(match self.token() {
+            Ok((kind, length)) => {
+                self.position += length;
+                Ok(Token { kind, span: start..self.position })
+            },
+            Err((error, skipped)) => {
+                self.position += skipped;
+                Err(error)
+            }
+        })
+    }
+}
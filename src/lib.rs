@@ -1,10 +1,56 @@
+#[macro_use]
+mod macros;
+
+pub mod borrowed;
+pub mod codegen;
+pub mod coerce;
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod detect;
+pub mod document;
+pub mod drift;
+pub mod flatten;
+pub mod jsonpath;
+pub mod lexer;
 pub mod parser;
+pub mod patch;
+pub mod pointer;
+pub mod push;
+mod scan;
+pub mod schema;
+pub mod select;
+#[cfg(feature = "serde")]
+mod serde_impl;
+pub mod serializer;
+pub mod sketch;
 pub mod sniffer;
+pub mod value;
+pub mod visitor;
+pub mod walk;
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::io::{BufReader, Cursor};
+    use std::borrow::Cow;
+    use crate::borrowed::*;
+    use crate::cst::*;
+    use crate::detect::*;
+    use crate::document::*;
+    use crate::drift::*;
+    use crate::flatten::*;
+    use crate::jsonpath::*;
+    use crate::lexer::*;
     use crate::parser::*;
+    use crate::patch::*;
+    use crate::pointer::*;
+    use crate::push::*;
+    use crate::select::*;
+    use crate::serializer::*;
+    use crate::sketch::*;
+    use crate::sniffer::*;
+    use crate::value::*;
+    use crate::visitor::*;
 
     #[test]
     fn check_true_constant() {
@@ -39,13 +85,15 @@ mod tests {
     #[test]
     fn check_strings() {
         let cases = [
-            "some long value string SLDFJNSDLFN",
-            "\\/multi\\b line\\n stri ng000 111\\r with\\t control seqE\\\\UNces",
-            "\\u2764\\ubBbB\\u27af\\u2Ef4\\u2cD4\\u2AA4",
-            "                                        "
+            ("some long value string SLDFJNSDLFN", "some long value string SLDFJNSDLFN"),
+            ("\\/multi\\b line\\n stri ng000 111\\r with\\t control seqE\\\\UNces",
+                "/multi\u{8} line\n stri ng000 111\r with\t control seqE\\UNces"),
+            ("\\u2764\\ubBbB\\u27af\\u2Ef4\\u2cD4\\u2AA4",
+                "\u{2764}\u{bbbb}\u{27af}\u{2ef4}\u{2cd4}\u{2aa4}"),
+            ("                                        ", "                                        ")
         ];
-        for expected in cases {
-            let stream = format!("\"{}\"", expected);
+        for (raw, expected) in cases {
+            let stream = format!("\"{}\"", raw);
             prepare_environment(stream);
             let result = accept_string(JsonValue::NULL);
             assert!(result.is_ok());
@@ -55,6 +103,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn check_surrogate_pairs() {
+        let result = parse_str("\"\\uD83D\\uDE00 \\ud83d\\udc4d\"");
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::STRING("😀 👍".to_string()));
+        }
+
+        for stream in ["\"\\uD83D\"", "\"\\uDE00\\uD83D\"", "\"\\uD83Dx\""] {
+            let result = parse_str(stream);
+            assert!(result.is_err());
+            if let Err(error) = result {
+                assert_eq!(error.kind, ParseErrorKind::InvalidEscape);
+            }
+        }
+
+        let options = ParserOptions { allow_lone_surrogates: true, ..ParserOptions::default() };
+        let result = options.parse_str("\"\\uD83Dx\"");
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::STRING("\u{FFFD}x".to_string()));
+        }
+    }
+
     #[test]
     fn check_numbers() {
         let cases = [
@@ -134,16 +206,2193 @@ mod tests {
         let result = accept_object(JsonValue::NULL);
         assert!(result.is_ok());
         if let Ok(jval) = result {
-            assert_eq!(jval, JsonValue::OBJECT(HashMap::from([
-                ("3.18".to_string(), Box::new(JsonValue::OBJECT(HashMap::from([
-                    ("3.18.1".to_string(), Box::new(JsonValue::OBJECT(HashMap::from([
-                        ("CVE-2014-8559".to_string(), Box::new(JsonValue::OBJECT(HashMap::from([
-                            ("cmt_msg".to_string(), Box::new(JsonValue::STRING("crypto: prefix module autoloading with \\\"crypto-\\\"".to_string()))),
-                            ("cmt_id".to_string(), Box::new(JsonValue::STRING("679829c2e50332832c2e85b12ec851a423ad9892".to_string())))
-                        ]))))
-                    ]))))
-                ]))))
+            assert_eq!(jval, JsonValue::OBJECT(JsonMap::from([
+                ("3.18".into(), JsonValue::OBJECT(JsonMap::from([
+                    ("3.18.1".into(), JsonValue::OBJECT(JsonMap::from([
+                        ("CVE-2014-8559".into(), JsonValue::OBJECT(JsonMap::from([
+                            ("cmt_msg".into(), JsonValue::STRING("crypto: prefix module autoloading with \"crypto-\"".to_string())),
+                            ("cmt_id".into(), JsonValue::STRING("679829c2e50332832c2e85b12ec851a423ad9892".to_string()))
+                        ])))
+                    ])))
+                ])))
+            ])));
+        }
+    }
+
+    #[test]
+    fn check_parse_str() {
+        let result = parse_str(" { \"name\": \"значение\", \"list\": [true, null] } ");
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::OBJECT(JsonMap::from([
+                ("name".into(), JsonValue::STRING("значение".to_string())),
+                ("list".into(), JsonValue::ARRAY(vec![JsonValue::TRUE, JsonValue::NULL]))
+            ])));
+        }
+
+        let result = parse_str("[1, 2] 3");
+        assert!(result.is_err());
+        if let Err(error) = result {
+            assert_eq!((error.row, error.col), (0, 7));
+        }
+    }
+
+    #[test]
+    fn check_parse_reader() {
+        let result = parse_reader("[1, \"two\"]".as_bytes());
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::ARRAY(vec![
+                JsonValue::NUMBER(1f64),
+                JsonValue::STRING("two".to_string())]));
+        }
+    }
+
+    #[test]
+    fn check_parse_buffered() {
+        let stream = "[\"ключ\", \"❤\", 12.5e1]".as_bytes();
+        let result = parse_buffered(BufReader::with_capacity(3, stream));
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::ARRAY(vec![
+                JsonValue::STRING("ключ".to_string()),
+                JsonValue::STRING("❤".to_string()),
+                JsonValue::NUMBER(125f64)]));
+        }
+
+        let result = parse_buffered(BufReader::with_capacity(3, &b"[\"\xff\"]"[..]));
+        assert!(result.is_err());
+        if let Err(error) = result {
+            assert_eq!(error.kind, ParseErrorKind::Io);
+            assert!(error.msg.starts_with("unable to read input"));
+        }
+    }
+
+    #[test]
+    fn check_mixed_values() {
+        let cases = [
+            ("[]", JsonValue::ARRAY(vec![])),
+            ("{ }", JsonValue::OBJECT(JsonMap::new())),
+            ("[true, \"a\", 1, null, [], {}]", JsonValue::ARRAY(vec![
+                JsonValue::TRUE,
+                JsonValue::STRING("a".to_string()),
+                JsonValue::NUMBER(1f64),
+                JsonValue::NULL,
+                JsonValue::ARRAY(vec![]),
+                JsonValue::OBJECT(JsonMap::new())]))
+        ];
+        for (stream, expected) in cases {
+            let result = parse_str(stream);
+            assert!(result.is_ok());
+            if let Ok(jval) = result {
+                assert_eq!(jval, expected);
+            }
+        }
+        for stream in ["[1,]", "[1 2]", "{\"a\" 1}", "{\"a\": 1,}", "[", "{"] {
+            assert!(parse_str(stream).is_err());
+        }
+    }
+
+    #[test]
+    fn check_nesting_depth() {
+        let allowed = format!("{}{}", "[".repeat(DEFAULT_MAX_DEPTH), "]".repeat(DEFAULT_MAX_DEPTH));
+        assert!(parse_str(&allowed).is_ok());
+
+        let result = parse_str(&"[{\"a\":".repeat(100_000));
+        assert!(result.is_err());
+        if let Err(error) = result {
+            assert_eq!(error.kind, ParseErrorKind::DepthExceeded);
+        }
+
+        let options = ParserOptions { max_depth: 2, ..ParserOptions::default() };
+        assert!(options.parse_str("[{\"a\": 1}]").is_ok());
+        let result = options.parse_str("[{\"a\": [1]}]");
+        assert!(result.is_err());
+        if let Err(error) = result {
+            assert_eq!(error.kind, ParseErrorKind::DepthExceeded);
+            assert_eq!((error.row, error.col), (0, 7));
+        }
+    }
+
+    #[test]
+    fn check_duplicate_keys() {
+        let stream = "{\"a\": 1, \"b\": 2, \"a\": 3}";
+        let cases = [
+            (DuplicateKeys::KeepFirst, 1f64),
+            (DuplicateKeys::KeepLast, 3f64),
+            (DuplicateKeys::Warn, 3f64)
+        ];
+        for (policy, expected) in cases {
+            let options = ParserOptions { duplicate_keys: policy, ..ParserOptions::default() };
+            let result = options.parse_str(stream);
+            assert!(result.is_ok());
+            if let Ok(JsonValue::OBJECT(obj)) = result {
+                assert_eq!(obj.get("a"), Some(&JsonValue::NUMBER(expected)));
+            }
+            let warnings = take_warnings();
+            assert_eq!(warnings.len(), if policy == DuplicateKeys::Warn { 1 } else { 0 });
+        }
+
+        let options = ParserOptions { duplicate_keys: DuplicateKeys::Error, ..ParserOptions::default() };
+        let result = options.parse_str(stream);
+        assert!(result.is_err());
+        if let Err(error) = result {
+            assert_eq!(error.kind, ParseErrorKind::DuplicateKey);
+            assert_eq!((error.row, error.col), (0, 17));
+        }
+    }
+
+    #[test]
+    fn check_error_kinds() {
+        let cases = [
+            ("[1, 2", ParseErrorKind::UnexpectedEof,
+                "unexpected end of input, expected digit, '.', 'e', 'E', ',' or ']' at (0, 5)"),
+            ("[1;\n 2]", ParseErrorKind::UnexpectedChar,
+                "unexpected symbol ';', expected digit, '.', 'e', 'E', ',' or ']' at (0, 2)"),
+            ("\"\\uDE00\"", ParseErrorKind::InvalidEscape, "unpaired surrogate \\ude00 at (0, 0)")
+        ];
+        for (stream, kind, rendered) in cases {
+            let result = parse_str(stream);
+            assert!(result.is_err());
+            if let Err(error) = result {
+                assert_eq!(error.kind, kind);
+                assert_eq!(error.to_string(), rendered);
+            }
+        }
+    }
+
+    #[test]
+    fn check_error_spans() {
+        let cases = [
+            ("[\"ключ\", ×]", 13, 13..15),
+            ("{\"a\": 1,\n \"a\": 2}", 10, 10..13),
+            ("[\"ok\", \"\\uD800\"]", 7, 7..15),
+            ("[1, 2", 5, 5..5)
+        ];
+        let options = ParserOptions { duplicate_keys: DuplicateKeys::Error, ..ParserOptions::default() };
+        for (stream, offset, span) in cases {
+            let result = options.parse_str(stream);
+            assert!(result.is_err());
+            if let Err(error) = result {
+                assert_eq!(error.offset, offset);
+                assert_eq!(error.span, span);
+            }
+        }
+    }
+
+    #[test]
+    fn check_error_recovery() {
+        let cases = [
+            ("{\"a\": [1, 2]}", vec![]),
+            ("[1, x, 3, [tru], {\"a\" 1, \"b\": nul}]", vec![
+                (ParseErrorKind::UnexpectedChar, 4),
+                (ParseErrorKind::UnexpectedChar, 14),
+                (ParseErrorKind::UnexpectedChar, 22),
+                (ParseErrorKind::UnexpectedChar, 33)
+            ]),
+            ("[\"x, y\" 1, [2", vec![
+                (ParseErrorKind::UnexpectedChar, 8),
+                (ParseErrorKind::UnexpectedEof, 13)
+            ]),
+            ("nope", vec![(ParseErrorKind::UnexpectedChar, 1)]),
+            ("[1] ]", vec![(ParseErrorKind::UnexpectedChar, 4)])
+        ];
+        for (stream, expected) in cases {
+            let errors = ParserOptions::default().diagnose_str(stream);
+            let actual: Vec<_> = errors.iter().map(|error| (error.kind, error.offset)).collect();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn check_control_character_recovery() {
+        for (stream, offset) in [("[\"a\tb\", 1]", 3), ("{\"k\u{1}\\\"x\": 1, \"b\": \"y\"}", 3), ("\"a\nb\"", 2)] {
+            let errors = ParserOptions::default().diagnose_str(stream);
+            let actual: Vec<_> = errors.iter().map(|error| (error.kind, error.offset, error.msg.as_str())).collect();
+            assert_eq!(actual.len(), 1, "{:?}", actual);
+            assert_eq!((actual[0].0, actual[0].1), (ParseErrorKind::UnexpectedChar, offset));
+            assert!(actual[0].2.starts_with("unescaped control character"));
+        }
+    }
+
+    #[test]
+    fn check_expected_tokens() {
+        let cases = [
+            ("[true false]", vec!["','", "']'"]),
+            ("[1,]", vec!["value"]),
+            ("{\"a\": tru}", vec!["'e'"]),
+            ("{\"a\" 1}", vec!["':'"]),
+            ("{1: 2}", vec!["'}'", "'\"'"]),
+            ("\"\\u12x4\"", vec!["hex digit"])
+        ];
+        for (stream, expected) in cases {
+            let result = parse_str(stream);
+            assert!(result.is_err());
+            if let Err(error) = result {
+                assert_eq!(error.expected, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn check_error_render() {
+        let source = "{\n\t\"a\": [1, 2],\n\t\"b\": tru\n}";
+        let result = parse_str(source);
+        assert!(result.is_err());
+        if let Err(error) = result {
+            assert_eq!(error.render(source), "  |
+3 | \t\"b\": tru
+  | \t        ^
+");
+        }
+    }
+
+    #[test]
+    fn check_number_literals() {
+        let options = ParserOptions { preserve_number_literals: true, ..ParserOptions::default() };
+        let result = options.parse_str("[0.1000000000000000055, -12E+3, 18446744073709551617]");
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::ARRAY(vec![
+                JsonValue::RAWNUMBER("0.1000000000000000055".to_string()),
+                JsonValue::RAWNUMBER("-12E+3".to_string()),
+                JsonValue::RAWNUMBER("18446744073709551617".to_string())]));
+        }
+    }
+
+    #[test]
+    fn check_lossless_roundtrip() {
+        let source = " {\n  \"z\" : [ 1.50 , [ ], {} ,\"a\\u0062\"],\r\n\t\"a\":{ \"k\":null } ,\"e\": -0E+2\n}\n";
+        let result = parse_lossless(source);
+        assert!(result.is_ok());
+        if let Ok(node) = result {
+            assert_eq!(node.to_string(), source);
+            if let SyntaxKind::Object { members, .. } = &node.kind {
+                let keys: Vec<_> = members.iter().map(|member| member.key.as_str()).collect();
+                assert_eq!(keys, ["\"z\"", "\"a\"", "\"e\""]);
+            }
+        }
+        assert!(parse_lossless("[1,]").is_err());
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn check_key_order() {
+        let result = parse_str("{\"z\": 1, \"a\": {\"y\": 2, \"b\": 3}, \"m\": 4}");
+        assert!(result.is_ok());
+        if let Ok(JsonValue::OBJECT(obj)) = result {
+            let keys: Vec<_> = obj.keys().map(AsRef::as_ref).collect();
+            assert_eq!(keys, ["z", "a", "m"]);
+            if let Some(JsonValue::OBJECT(inner)) = obj.get("a") {
+                let keys: Vec<_> = inner.keys().map(AsRef::as_ref).collect();
+                assert_eq!(keys, ["y", "b"]);
+            }
+        }
+    }
+
+    #[test]
+    fn check_comments() {
+        let source = "// settings\n{\n  \"a\": 1, /* inline */ \"b\": [2 // two\n]\n} /* end */";
+        let options = ParserOptions { allow_comments: true, ..ParserOptions::default() };
+        let result = options.parse_str(source);
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::OBJECT(JsonMap::from([
+                ("a".into(), JsonValue::NUMBER(1f64)),
+                ("b".into(), JsonValue::ARRAY(vec![JsonValue::NUMBER(2f64)]))
+            ])));
+        }
+        let result = options.parse_lossless(source);
+        assert!(result.is_ok());
+        if let Ok(node) = result {
+            assert_eq!(node.to_string(), source);
+        }
+
+        assert!(parse_str(source).is_err());
+        for (stream, kind) in [("[1] /* open", ParseErrorKind::UnexpectedEof), ("[1 /x]", ParseErrorKind::UnexpectedChar)] {
+            let result = options.parse_str(stream);
+            assert!(result.is_err());
+            if let Err(error) = result {
+                assert_eq!(error.kind, kind);
+            }
+        }
+    }
+
+    #[test]
+    fn check_json5() {
+        let source = "// config\n{unquoted: 'single \\' quote', \"list\": [0x1F, +1, .5, 5., -Infinity,],}";
+        let options = ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() };
+        let result = options.parse_str(source);
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::OBJECT(JsonMap::from([
+                ("unquoted".into(), JsonValue::STRING("single ' quote".to_string())),
+                ("list".into(), JsonValue::ARRAY(vec![
+                    JsonValue::NUMBER(31f64),
+                    JsonValue::NUMBER(1f64),
+                    JsonValue::NUMBER(0.5f64),
+                    JsonValue::NUMBER(5f64),
+                    JsonValue::NUMBER(f64::NEG_INFINITY)
+                ]))
+            ])));
+        }
+        assert!(matches!(options.parse_str("NaN"), Ok(JsonValue::NUMBER(number)) if number.is_nan()));
+
+        for stream in ["{a: 1}", "['a']", "[1,]", "0x1F", "+1", ".5", "5.", "Infinity"] {
+            assert!(parse_str(stream).is_err());
+        }
+    }
+
+    #[test]
+    fn check_trailing_commas() {
+        let options = ParserOptions { allow_trailing_commas: true, ..ParserOptions::default() };
+        let result = options.parse_str("{\"a\": [1, 2, ], }");
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::OBJECT(JsonMap::from([
+                ("a".into(), JsonValue::ARRAY(vec![JsonValue::NUMBER(1f64), JsonValue::NUMBER(2f64)]))
+            ])));
+        }
+        for stream in ["[,]", "[1,,]", "{,}", "{'a': 1,}"] {
+            assert!(options.parse_str(stream).is_err());
+        }
+        assert!(parse_str("[1, 2,]").is_err());
+    }
+
+    #[test]
+    fn check_nan_literals() {
+        let options = ParserOptions { allow_nan: true, ..ParserOptions::default() };
+        let result = options.parse_str("[Infinity, -Infinity, 1.5]");
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::ARRAY(vec![
+                JsonValue::NUMBER(f64::INFINITY),
+                JsonValue::NUMBER(f64::NEG_INFINITY),
+                JsonValue::NUMBER(1.5f64)
+            ]));
+        }
+        assert!(matches!(options.parse_str("NaN"), Ok(JsonValue::NUMBER(number)) if number.is_nan()));
+        for stream in ["+Infinity", "nan", "Inf"] {
+            assert!(options.parse_str(stream).is_err());
+        }
+        assert!(parse_str("NaN").is_err());
+    }
+
+    #[test]
+    fn check_single_quotes() {
+        let options = ParserOptions { allow_single_quotes: true, ..ParserOptions::default() };
+        let result = options.parse_str("{'key': 'it\\'s', \"other\": \"\'\"}");
+        assert!(result.is_ok());
+        if let Ok(jval) = result {
+            assert_eq!(jval, JsonValue::OBJECT(JsonMap::from([
+                ("key".into(), JsonValue::STRING("it's".to_string())),
+                ("other".into(), JsonValue::STRING("'".to_string()))
             ])));
         }
+        let warnings = take_warnings();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!((warnings[1].row, warnings[1].col), (0, 8));
+        assert_eq!(warnings[1].msg, "single-quoted string");
+
+        assert!(parse_str("'value'").is_err());
+        assert!(options.parse_str("{key: 1}").is_err());
+    }
+
+    #[test]
+    fn check_ndjson() {
+        let source = "{\"a\": 1}\n\n[true]\r\n{\"a\": }\n\"last\"";
+        let values: Vec<_> = parse_ndjson(BufReader::new(Cursor::new(source))).collect();
+        assert_eq!(values.len(), 4);
+        assert!(matches!(&values[0], Ok(JsonValue::OBJECT(object)) if object.len() == 1));
+        assert!(matches!(&values[1], Ok(JsonValue::ARRAY(array)) if array == &vec![JsonValue::TRUE]));
+        assert!(matches!(&values[3], Ok(JsonValue::STRING(line)) if line == "last"));
+        assert!(matches!(&values[2], Err(error) if (error.row, error.col, error.offset) == (3, 6, 24)));
+    }
+
+    #[test]
+    fn check_json_seq() {
+        let source = "\u{1e}{\"a\": 1}\n\u{1e}\u{1e}[1,\n 2]\n\u{1e}tru\n";
+        let values: Vec<_> = parse_json_seq(BufReader::new(Cursor::new(source))).collect();
+        assert_eq!(values.len(), 3);
+        assert!(matches!(&values[0], Ok(JsonValue::OBJECT(object)) if object.len() == 1));
+        assert!(matches!(&values[1], Ok(JsonValue::ARRAY(array)) if array.len() == 2));
+        assert!(matches!(&values[2], Err(error) if (error.row, error.col, error.offset) == (3, 4, 24)));
+    }
+
+    #[test]
+    fn check_encodings() {
+        let text = "{\"k\": [\"\u{1F600}\u{e9}\", 1]}";
+        let utf16le: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let utf16be: Vec<u8> = [0xFE, 0xFF].into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes)).collect();
+        let utf32le: Vec<u8> = [0xFF, 0xFE, 0, 0].into_iter()
+            .chain(text.chars().flat_map(|symbol| (symbol as u32).to_le_bytes())).collect();
+        let utf32be: Vec<u8> = text.chars().flat_map(|symbol| (symbol as u32).to_be_bytes()).collect();
+        let utf8: Vec<u8> = [0xEF, 0xBB, 0xBF].into_iter().chain(text.bytes()).collect();
+        assert!(parse_str(text).is_ok());
+        for bytes in [utf16le, utf16be, utf32le, utf32be, utf8] {
+            let result = parse_buffered(BufReader::with_capacity(3, Cursor::new(bytes)));
+            assert_eq!(result.ok(), parse_str(text).ok());
+        }
+
+        let result = parse_reader(Cursor::new(vec![0x31, 0x00, 0x00, 0xD8]));
+        assert!(matches!(result, Err(error) if error.kind == ParseErrorKind::Io));
+
+        let broken = "[1,\n 2 3]";
+        let bytes: Vec<u8> = [0xFF, 0xFE].into_iter().chain(broken.encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        let source = decode_buffered(Cursor::new(bytes.clone()));
+        assert_eq!(source, broken);
+        let errors = ParserOptions::default().diagnose_buffered(Cursor::new(bytes));
+        assert_eq!(errors.first().map(|error| error.render(&source)), ParserOptions::default().diagnose_str(broken).first().map(|error| error.render(broken)));
+        assert!(errors[0].render(&source).contains("2 |  2 3]\n"));
+        assert_eq!(decode_buffered(Cursor::new(b"\xEF\xBB\xBF[1]".to_vec())), "[1]");
+    }
+
+    #[test]
+    fn check_events() {
+        let mut events = vec![];
+        let result = parse_events(Cursor::new("{\"a\": [1, \"x\", {}], \"b\": null}"), |event| {
+            events.push(match event {
+                JsonEvent::Key(key) => format!("key {}", key),
+                JsonEvent::String(line) => format!("string {}", line),
+                other => format!("{:?}", other)
+            });
+        });
+        assert!(result.is_ok());
+        assert_eq!(events, vec![
+            "StartObject", "key a", "StartArray", "Number(1.0)", "string x", "StartObject",
+            "EndObject", "EndArray", "key b", "Null", "EndObject"
+        ]);
+
+        let mut count = 0;
+        let result = parse_events(Cursor::new("[true, false, ]"), |_| count += 1);
+        assert!(result.is_err());
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn check_lexer() {
+        let source = "{\"a\": [1.5e3, true]} // end";
+        let tokens: Vec<_> = Lexer::new(source).filter_map(Result::ok)
+            .map(|token| (token.kind, token.text(source).to_string()))
+            .collect();
+        assert_eq!(tokens, vec![
+            (TokenKind::LBrace, "{".to_string()),
+            (TokenKind::String, "\"a\"".to_string()),
+            (TokenKind::Colon, ":".to_string()),
+            (TokenKind::Whitespace, " ".to_string()),
+            (TokenKind::LBracket, "[".to_string()),
+            (TokenKind::Number, "1.5e3".to_string()),
+            (TokenKind::Comma, ",".to_string()),
+            (TokenKind::Whitespace, " ".to_string()),
+            (TokenKind::True, "true".to_string()),
+            (TokenKind::RBracket, "]".to_string()),
+            (TokenKind::RBrace, "}".to_string()),
+            (TokenKind::Whitespace, " ".to_string()),
+            (TokenKind::Comment, "// end".to_string())
+        ]);
+
+        let results: Vec<_> = Lexer::new("[1., \"\\x\", @]").collect();
+        let errors: Vec<_> = results.iter().filter_map(|result| result.as_ref().err())
+            .map(|error| (error.kind, error.span.clone()))
+            .collect();
+        assert_eq!(errors, vec![
+            (ParseErrorKind::UnexpectedChar, 1..3),
+            (ParseErrorKind::InvalidEscape, 6..8),
+            (ParseErrorKind::UnexpectedChar, 11..12)
+        ]);
+        assert_eq!(results.last().map(|result| result.as_ref().map(|token| token.kind).ok()), Some(Some(TokenKind::RBracket)));
+    }
+
+    #[test]
+    fn check_push_parser() {
+        let source = "{\"name\": \"\\u00e9t\u{e9}\", \"list\": [12.5e1, true, null, []]}".as_bytes();
+        for size in [1, 2, 5, source.len()] {
+            let mut parser = PushParser::default();
+            for chunk in source.chunks(size) {
+                assert!(parser.feed(chunk).is_ok());
+            }
+            assert_eq!(parser.finish().ok(), parse_str(std::str::from_utf8(source).unwrap_or("")).ok());
+        }
+
+        let mut events = vec![];
+        let mut parser = PushParser::default();
+        assert!(parser.feed_events(b"[1", |event| events.push(format!("{:?}", event))).is_ok());
+        assert_eq!(events, vec!["StartArray"]);
+        assert!(parser.feed_events(b"0, tr", |event| events.push(format!("{:?}", event))).is_ok());
+        assert!(parser.feed_events(b"ue]", |event| events.push(format!("{:?}", event))).is_ok());
+        assert!(parser.finish_events(|_| {}).is_ok());
+        assert_eq!(events, vec!["StartArray", "Number(10.0)", "Bool(true)", "EndArray"]);
+
+        let mut parser = PushParser::default();
+        let result = parser.feed(b"{\"a\" 1}");
+        assert!(matches!(result, Err(error) if error.offset == 5 && error.expected == vec!["':'"]));
+        let mut parser = PushParser::default();
+        assert!(parser.feed(b"[1, 2").is_ok());
+        assert!(matches!(parser.finish(), Err(error) if error.kind == ParseErrorKind::UnexpectedEof));
+
+        let mut lenient = ParserOptions::default();
+        lenient.set_mode(Mode::Permissive);
+        let mut parser = PushParser::new(lenient);
+        let result = parser.feed(b"[1, NaN]");
+        assert!(matches!(result, Err(error) if error.kind == ParseErrorKind::Unsupported
+            && error.msg == "allow_comments is not supported by the push parser"));
+        let parser = PushParser::new(ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() });
+        assert!(matches!(parser.finish(), Err(error) if error.kind == ParseErrorKind::Unsupported));
+        let parser = PushParser::new(ParserOptions { raw_depth: Some(1), ..ParserOptions::default() });
+        assert!(matches!(parser.finish(), Err(error) if error.msg == "raw_depth is not supported by the push parser"));
+    }
+
+    #[test]
+    fn check_borrowed_values() {
+        let source = "{\"plain\": \"text\", \"escaped\": \"a\\nb\", \"list\": [1, [], {}, null]}";
+        let result = parse_borrowed(source);
+        assert!(result.is_ok());
+        if let Ok(JsonValueRef::OBJECT(members)) = &result {
+            assert!(matches!(&members[0], (Cow::Borrowed("plain"), JsonValueRef::STRING(Cow::Borrowed("text")))));
+            assert!(matches!(&members[1], (_, JsonValueRef::STRING(Cow::Owned(line))) if line == "a\nb"));
+        }
+        assert_eq!(result.ok().map(JsonValueRef::into_owned), parse_str(source).ok());
+
+        for stream in ["[1,]", "{\"a\" 1}", "[1] 2", "[", "\"\\x\""] {
+            assert!(parse_borrowed(stream).is_err());
+        }
+        let options = ParserOptions { max_depth: 2, ..ParserOptions::default() };
+        assert!(matches!(options.parse_borrowed("[[[]]]"), Err(error) if error.kind == ParseErrorKind::DepthExceeded));
+
+        let outcome = |result: Result<JsonValue, ParseError>| result.map_err(|error| (error.kind, error.offset));
+        let strict = ParserOptions { duplicate_keys: DuplicateKeys::Error, ..ParserOptions::default() };
+        let comments = ParserOptions { allow_comments: true, ..ParserOptions::default() };
+        for (options, stream) in [
+            (ParserOptions::default(), "{\"a\": 1, \"b\": [2], \"a\": 3}"),
+            (strict.clone(), "{\"a\": 1, \"b\": {\"a\": 2}}"),
+            (strict, "{\"a\": 1, \"b\": 2, \"a\": 3}"),
+            (comments.clone(), "[1, /* two */ 2] // end"),
+            (ParserOptions::default(), "[1, /* two */ 2]")
+        ] {
+            assert_eq!(outcome(options.parse_borrowed(stream).map(JsonValueRef::into_owned)), outcome(options.parse_str(stream)), "{}", stream);
+        }
+        for options in [
+            ParserOptions { allow_trailing_commas: true, ..comments.clone() },
+            ParserOptions { allow_nan: true, ..ParserOptions::default() },
+            ParserOptions { allow_single_quotes: true, ..ParserOptions::default() },
+            ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() },
+            ParserOptions { raw_depth: Some(1), ..ParserOptions::default() }
+        ] {
+            assert!(matches!(options.parse_borrowed("[1]"), Err(error) if error.kind == ParseErrorKind::Unsupported));
+        }
+    }
+
+    #[test]
+    fn check_control_characters_everywhere() {
+        let source = "[\"a\tb\", {\"c\nd\": \"\u{1}\"}]";
+        let outcome = |result: Result<JsonValue, ParseError>| result.map_err(|error| (error.kind, error.offset, error.msg));
+        let pushed = |options: &ParserOptions| {
+            let mut parser = PushParser::new(options.clone());
+            parser.feed(source.as_bytes()).and_then(|_| parser.finish())
+        };
+        let strict = ParserOptions::default();
+        let expected = Err((ParseErrorKind::UnexpectedChar, 3, "unescaped control character '\\t' in string".to_string()));
+        assert_eq!(outcome(strict.parse_str(source)), expected);
+        assert_eq!(outcome(pushed(&strict)), expected);
+        assert_eq!(outcome(strict.parse_borrowed(source).map(JsonValueRef::into_owned)), expected);
+
+        let lenient = ParserOptions { allow_control_characters: true, ..ParserOptions::default() };
+        let parsed = lenient.parse_str(source);
+        assert!(parsed.is_ok());
+        assert_eq!(take_control_characters(), 3);
+        assert_eq!(outcome(pushed(&lenient)), outcome(parsed.clone()));
+        assert_eq!(take_control_characters(), 3);
+        assert_eq!(outcome(lenient.parse_borrowed(source).map(JsonValueRef::into_owned)), outcome(parsed));
+        assert_eq!(take_control_characters(), 3);
+    }
+
+    #[test]
+    fn check_document() {
+        let source = "{\"name\": \"doc\", \"list\": [1, [true], {\"a\": null}], \"empty\": {}}";
+        let mut document = JsonDocument::new();
+        assert!(parse_document(Cursor::new(source), &mut document).is_ok());
+        let root = document.root();
+        assert!(root.is_some());
+        if let Some(root) = root {
+            assert_eq!(document.value(root), NodeRef::OBJECT(3));
+            let name = document.get(root, "name").map(|id| document.value(id));
+            assert_eq!(name, Some(NodeRef::STRING("doc")));
+            let list: Vec<_> = document.get(root, "list").into_iter()
+                .flat_map(|id| document.items(id))
+                .map(|id| document.value(id))
+                .collect();
+            assert_eq!(list, vec![NodeRef::NUMBER(1f64), NodeRef::ARRAY(1), NodeRef::OBJECT(1)]);
+            assert_eq!(document.to_value(root), parse_str(source).unwrap_or(JsonValue::NULL));
+        }
+        assert_eq!(document.len(), 9);
+
+        assert!(parse_document(Cursor::new("[1, 2"), &mut document).is_err());
+        assert!(document.is_empty() && document.root().is_none());
+        assert!(parse_document(Cursor::new("7"), &mut document).is_ok());
+        assert_eq!(document.root().map(|id| document.value(id)), Some(NodeRef::NUMBER(7f64)));
+        let options = ParserOptions { raw_depth: Some(1), ..ParserOptions::default() };
+        let result = options.parse_document(Cursor::new("[[1]]"), &mut document);
+        assert!(matches!(result, Err(error) if error.kind == ParseErrorKind::Unsupported));
+        assert!(document.is_empty());
+    }
+
+    #[test]
+    fn check_bulk_scanning() {
+        let padding = " \t\r\n".repeat(40);
+        let text = "x".repeat(100) + "\u{e9}\\\"" + &"y".repeat(50);
+        let source = format!("{0}[{0}\"{1}\"{0}, \"\u{1F600}\"{0}]{0}", padding, text);
+        for capacity in [1, 7, 64, source.len()] {
+            let result = parse_buffered(BufReader::with_capacity(capacity, Cursor::new(source.clone())));
+            assert!(matches!(&result, Ok(JsonValue::ARRAY(array)) if array.len() == 2
+                && array[0] == JsonValue::STRING(format!("{}\u{e9}\"{}", "x".repeat(100), "y".repeat(50)))));
+        }
+
+        let source = format!("{}\n  [\"{}\", @]", " ".repeat(100), "z".repeat(70));
+        let result = parse_buffered(BufReader::with_capacity(16, Cursor::new(source)));
+        assert!(matches!(result, Err(error) if (error.row, error.col, error.offset) == (1, 77, 178)));
+    }
+
+    #[test]
+    fn check_limits() {
+        let limits = Limits { max_string_length: Some(5), max_keys: Some(2), max_nodes: Some(6) };
+        let options = ParserOptions { limits, ..ParserOptions::default() };
+        assert!(options.parse_str("{\"abcde\": [\"12345\", 1], \"b\": null, \"b\": {}}").is_ok());
+
+        let result = options.parse_str("[\"a\", \"a\\\"cdef\"]");
+        assert!(matches!(result, Err(error) if error.kind == ParseErrorKind::StringTooLong && error.span == (6..14)));
+        let result = options.parse_str("{\"a\": 1, \"b\": 2, \"c\": 3}");
+        assert!(matches!(result, Err(error) if error.kind == ParseErrorKind::TooManyKeys && error.span == (17..20)));
+        let result = options.parse_str("[1, [2, 3], [4, 5]]");
+        assert!(matches!(result, Err(error) if error.kind == ParseErrorKind::TooManyNodes && error.offset == 13));
+
+        let pushed = |source: &str| {
+            let mut parser = PushParser::new(options.clone());
+            parser.feed(source.as_bytes()).and_then(|_| parser.finish())
+        };
+        for (source, failure) in [
+            ("{\"abcde\": [\"12345\", 1], \"b\": null, \"b\": {}}", None),
+            ("[\"a\", \"a\\\"cdef\"]", Some((ParseErrorKind::StringTooLong, 6))),
+            ("{\"a\": 1, \"b\": 2, \"c\": 3}", Some((ParseErrorKind::TooManyKeys, 17))),
+            ("{\"abcdef\": 1}", Some((ParseErrorKind::StringTooLong, 1))),
+            ("[1, [2, 3], [4, 5]]", Some((ParseErrorKind::TooManyNodes, 13)))
+        ] {
+            let outcome = |result: Result<JsonValue, ParseError>| result.err().map(|error| (error.kind, error.offset));
+            assert_eq!(outcome(pushed(source)), failure, "{}", source);
+            assert_eq!(outcome(options.parse_borrowed(source).map(JsonValueRef::into_owned)), failure, "{}", source);
+        }
+    }
+
+    #[test]
+    fn check_modes() {
+        let source = "{'list': [1, NaN, ], /* note */ \"b\": null}";
+        let mut options = ParserOptions::default();
+        assert_eq!(options.mode(), Mode::Strict);
+        assert!(options.parse_str(source).is_err());
+
+        options.set_mode(Mode::Permissive);
+        assert_eq!(options.mode(), Mode::Permissive);
+        assert!(matches!(options.parse_str(source), Ok(JsonValue::OBJECT(object)) if object.len() == 2));
+
+        options.set_mode(Mode::Strict);
+        assert_eq!(options.mode(), Mode::Strict);
+        assert!(options.parse_str(source).is_err());
+        let options = ParserOptions { allow_nan: true, ..ParserOptions::default() };
+        assert_eq!(options.mode().to_string(), "permissive");
+    }
+
+    #[test]
+    fn check_control_characters() {
+        let source = "[\"tab\there\", \"line\nbreak\\n\"]";
+        let result = parse_str(source);
+        assert!(matches!(result, Err(error) if error.kind == ParseErrorKind::UnexpectedChar
+            && error.offset == 5 && error.msg.contains("control character")));
+        assert!(parse_str("[\"escaped\\tand\\u0001\"]").is_ok());
+        assert_eq!(take_control_characters(), 0);
+
+        let options = ParserOptions { allow_control_characters: true, ..ParserOptions::default() };
+        let result = options.parse_str(source);
+        assert!(matches!(&result, Ok(JsonValue::ARRAY(array)) if array[1] == JsonValue::STRING("line\nbreak\n".to_string())));
+        assert_eq!(take_control_characters(), 2);
+        assert_eq!(options.mode(), Mode::Permissive);
+    }
+
+    #[test]
+    fn check_large_integers() {
+        let result = parse_str("[9007199254740992, 9007199254740993, -123456789012345678901234567890, 1e300]");
+        assert!(result.is_ok());
+        let warnings: Vec<_> = take_warnings().into_iter().map(|warning| (warning.col, warning.msg)).collect();
+        assert_eq!(warnings, vec![
+            (19, "integer 9007199254740993 exceeds 2^53 and may lose precision".to_string()),
+            (37, "integer -123456789012345678901234567890 exceeds 2^53 and may lose precision".to_string())
+        ]);
+
+        let options = ParserOptions { preserve_number_literals: true, ..ParserOptions::default() };
+        assert!(options.parse_str("9007199254740993").is_ok());
+        assert!(take_warnings().is_empty());
+    }
+
+    #[test]
+    fn check_accessors() {
+        let result = parse_str("{\"name\": \"x\", \"list\": [1.5, true, null], \"nested\": {}}");
+        assert!(result.is_ok());
+        let json = result.unwrap_or(JsonValue::NULL);
+        assert!(json.is_object() && ! json.is_array());
+        assert_eq!(json.get("name").and_then(JsonValue::as_str), Some("x"));
+        assert_eq!(json.get("list").and_then(|list| list.get_index(0)).and_then(JsonValue::as_f64), Some(1.5));
+        assert_eq!(json.get("list").and_then(|list| list.get_index(1)).and_then(JsonValue::as_bool), Some(true));
+        assert!(json.get("list").and_then(|list| list.get_index(2)).is_some_and(JsonValue::is_null));
+        assert!(json.get("list").and_then(|list| list.get_index(3)).is_none());
+        assert_eq!(json.get("list").and_then(JsonValue::as_array).map(Vec::len), Some(3));
+        assert!(json.get("nested").and_then(JsonValue::as_object).is_some_and(|object| object.is_empty()));
+        assert!(json.get("missing").is_none() && json.get_index(0).is_none() && json.as_str().is_none());
+        assert_eq!(JsonValue::RAWNUMBER("0.25".to_string()).as_f64(), Some(0.25));
+    }
+
+    #[test]
+    fn check_index() {
+        let json = parse_str("{\"users\": [{\"name\": \"ann\"}, {\"name\": null}]}").unwrap_or(JsonValue::NULL);
+        assert_eq!(json["users"][0]["name"], JsonValue::STRING("ann".to_string()));
+        assert_eq!(json["users"][1]["name"], JsonValue::NULL);
+        assert_eq!(json["users"][2]["name"], JsonValue::NULL);
+        assert_eq!(json["missing"][0], JsonValue::NULL);
+        assert_eq!(json[0], JsonValue::NULL);
+    }
+
+    #[test]
+    fn check_jsonpath() {
+        let json = parse_str("{\"store\": {\"book\": [\
+            {\"author\": \"Rees\", \"price\": 8.95, \"isbn\": \"0-553\"},\
+            {\"author\": \"Waugh\", \"price\": 12.99},\
+            {\"author\": \"Tolkien\", \"price\": 22.99, \"isbn\": \"0-395\"}],\
+            \"bicycle\": {\"price\": 19.95}}}").unwrap_or(JsonValue::NULL);
+        let selected = |expression: &str| query(&json, expression)
+            .map(|matches| matches.into_iter().map(|found| found.path).collect::<Vec<_>>());
+
+        assert_eq!(selected("$.store.book[*].author"), Ok(vec![
+            "$['store']['book'][0]['author']".to_string(),
+            "$['store']['book'][1]['author']".to_string(),
+            "$['store']['book'][2]['author']".to_string()
+        ]));
+        let authors = query(&json, "$..book[?(@.price < 20 && @.isbn)].author");
+        assert!(matches!(authors.as_deref(), Ok([found]) if found.value == &JsonValue::STRING("Rees".to_string())));
+        assert_eq!(selected("$.store['book'][-1, 0].price"), Ok(vec![
+            "$['store']['book'][2]['price']".to_string(),
+            "$['store']['book'][0]['price']".to_string()
+        ]));
+        assert_eq!(selected("$..price").map(|paths| paths.len()), Ok(4));
+        assert_eq!(selected("$.store.book[?(@.author == 'Waugh' || !@.isbn)]"), Ok(vec!["$['store']['book'][1]".to_string()]));
+        assert_eq!(selected("$.store.missing[0]"), Ok(vec![]));
+
+        assert!(matches!(JsonPath::parse("$.store[?(@.price <)]"), Err(error) if error.offset == 19));
+        assert!(JsonPath::parse("store.book").is_err());
+        assert!(JsonPath::parse("$['book").is_err());
+    }
+
+    #[test]
+    fn check_mutation() {
+        let mut json = parse_str("{\"users\": [{\"name\": \"ann\"}], \"a/b\": {\"~\": 1}}").unwrap_or(JsonValue::NULL);
+        assert_eq!(json.pointer("/a~1b/~0"), Some(&JsonValue::NUMBER(1f64)));
+        assert_eq!(json.pointer("/users/0/name").and_then(JsonValue::as_str), Some("ann"));
+        assert!(json.pointer("/users/01").is_none() && json.pointer("users").is_none());
+
+        assert_eq!(json.insert("count", JsonValue::NUMBER(1f64)), None);
+        assert_eq!(json.insert("count", JsonValue::NUMBER(2f64)), Some(JsonValue::NUMBER(1f64)));
+        assert_eq!(json.remove("a/b").map(|removed| removed.is_object()), Some(true));
+        assert_eq!(json.remove("a/b"), None);
+
+        let previous = json.set_by_pointer("/users/0/name", JsonValue::STRING("bob".to_string()));
+        assert_eq!(previous, Ok(Some(JsonValue::STRING("ann".to_string()))));
+        assert_eq!(json.set_by_pointer("/users/-", JsonValue::NULL), Ok(None));
+        assert_eq!(json.set_by_pointer("/users/2", JsonValue::TRUE), Ok(None));
+        assert_eq!(json["users"].as_array().map(Vec::len), Some(3));
+        assert!(matches!(json.set_by_pointer("/users/9", JsonValue::NULL), Err(error) if error.msg.contains("index")));
+        assert!(json.set_by_pointer("/missing/key", JsonValue::NULL).is_err());
+        assert!(json.set_by_pointer("/users/0/name/x", JsonValue::NULL).is_err());
+        assert!(matches!(json.set_by_pointer("/bad~2", JsonValue::NULL), Err(PointerError { .. })));
+
+        let mut list = JsonValue::NULL;
+        list.push(JsonValue::FALSE);
+        assert_eq!(list.as_array().map(Vec::len), Some(1));
+        assert_eq!(list.set_by_pointer("", JsonValue::NULL).map(|previous| previous.is_some_and(|value| value.is_array())), Ok(true));
+        assert!(list.is_null());
+    }
+
+    #[test]
+    fn check_json_macro() {
+        let name = "ann";
+        let built = json!({
+            "name": name,
+            "tags": ["admin", null, -1, 2.5, true],
+            "nested": {"empty": [], "object": {}},
+            "sum": 30 + 1,
+        });
+        assert_eq!(built, JsonValue::object([
+            ("name", JsonValue::string("ann")),
+            ("tags", JsonValue::array([
+                JsonValue::string("admin"), JsonValue::NULL, JsonValue::number(-1f64), JsonValue::number(2.5), JsonValue::TRUE
+            ])),
+            ("nested", JsonValue::object([
+                ("empty", JsonValue::array([])),
+                ("object", JsonValue::object(Vec::<(String, JsonValue)>::new()))
+            ])),
+            ("sum", JsonValue::number(31f64))
+        ]));
+        assert_eq!(Ok(built), parse_str("{\"name\": \"ann\", \"tags\": [\"admin\", null, -1, 2.5, true],\
+            \"nested\": {\"empty\": [], \"object\": {}}, \"sum\": 31}").map_err(|error| error.msg));
+        assert_eq!(json!([[1, 2], {"a": false},]), JsonValue::array([
+            JsonValue::array([JsonValue::from(1), JsonValue::from(2u8)]),
+            JsonValue::object([("a", JsonValue::from(false))])
+        ]));
+    }
+
+    #[test]
+    fn check_round_trip() {
+        let sources = [
+            "null", "true", "-0", "[]", "{}", "0.1", "1e300", "-2.5e-8", "12345678901234",
+            "\"quote \\\" backslash \\\\ slash \\/ controls \\b\\f\\n\\r\\t\\u0001\"",
+            "\"\u{e9}\\u00e9 \\ud83d\\ude00 \u{1F600}\"",
+            "{\"a\": [1, 2.5, {\"b\": null}], \"c\": {\"d\": [[], {}]}, \"\\n\": false}",
+        ];
+        for source in sources {
+            let json = parse_str(source).map_err(|error| error.msg);
+            let text = json.as_ref().map(JsonValue::to_string).unwrap_or_default();
+            assert_eq!(parse_str(&text).map_err(|error| error.msg), json, "{}", text);
+        }
+        assert_eq!(json!({"list": [1, "two\n", null]}).to_string(), "{\"list\": [1, \"two\\n\", null]}");
+        assert_eq!(JsonValue::NUMBER(f64::NAN).to_string(), "null");
+        assert_eq!(JsonValue::RAWNUMBER("0x1F".to_string()).to_string(), "31");
+        assert_eq!(JsonValue::RAWNUMBER("1.50".to_string()).to_string(), "1.50");
+        assert_eq!(JsonValue::STRING("\u{1f}".to_string()).to_string(), "\"\\u001f\"");
+    }
+
+    #[test]
+    fn check_formatter() {
+        let json = json!({"b": [1, [], {}], "a": {"c": null}});
+        let formatter = Formatter { sort_keys: true, trailing_newline: true, ..Formatter::default() };
+        assert_eq!(formatter.format(&json), "{\n  \"a\": {\n    \"c\": null\n  },\n  \"b\": [\n    1,\n    [],\n    {}\n  ]\n}\n");
+        let formatter = Formatter { indent: Indent::Tabs, sort_keys: true, trailing_newline: false };
+        assert_eq!(formatter.format(&json["b"]), "[\n\t1,\n\t[],\n\t{}\n]");
+        let formatter = Formatter { indent: Indent::Spaces(0), ..Formatter::default() };
+        assert_eq!(formatter.format(&json["a"]), "{\n\"c\": null\n}");
+        assert_eq!(Formatter::default().format(&JsonValue::STRING("x".to_string())), "\"x\"");
+        assert_eq!(parse_str(&Formatter::default().format(&json)).ok(), Some(json));
+    }
+
+    #[test]
+    fn check_minifier() {
+        let source = "{\n  \"list\": [1000000, 0.0001, 1.5, -0],\n  \"text\": \"a b\"\n}\n";
+        let json = parse_str(source).unwrap_or(JsonValue::NULL);
+        assert_eq!(json["list"].to_compact_string(), "[1000000,0.0001,1.5,-0]");
+        assert_eq!(json!({"text": "a b"}).to_compact_string(), "{\"text\":\"a b\"}");
+
+        let minifier = Minifier { shortest_numbers: true };
+        assert_eq!(minifier.minify(&json["list"]), "[1e6,1e-4,1.5,-0]");
+        let (text, report) = minifier.minify_with_report(&json, source.len());
+        assert_eq!(parse_str(&text).ok(), Some(json));
+        assert_eq!(report.compact_bytes, text.len());
+        assert_eq!(report.saved_bytes(), source.len() - text.len());
+        assert!(report.saved_percent() > 30f64);
+    }
+
+    #[test]
+    fn check_canonical() {
+        let json = parse_str("{\"\u{20ac}\": 1, \"\\r\": 2, \"1\": 3, \"\u{1F600}\": 4, \"\u{fb33}\": 5, \"a\": [\"\u{e9}\\u0007\\/\"]}");
+        assert_eq!(json.ok().map(|json| json.to_canonical_string()), Some(Ok(
+            "{\"\\r\":2,\"1\":3,\"a\":[\"\u{e9}\\u0007/\"],\"\u{20ac}\":1,\"\u{1F600}\":4,\"\u{fb33}\":5}".to_string())));
+
+        let numbers = [
+            (0f64, "0"), (-0f64, "0"), (1f64, "1"), (-1.5, "-1.5"), (1e21, "1e+21"), (1e20, "100000000000000000000"),
+            (123456789012345680000f64, "123456789012345680000"), (1e-6, "0.000001"), (1e-7, "1e-7"),
+            (4.5e-7, "4.5e-7"), (0.1, "0.1"), (333333333.3333333, "333333333.3333333"),
+            (f64::MAX, "1.7976931348623157e+308"), (5e-324, "5e-324")
+        ];
+        for (number, expected) in numbers {
+            assert_eq!(JsonValue::NUMBER(number).to_canonical_string(), Ok(expected.to_string()));
+        }
+        assert_eq!(JsonValue::RAWNUMBER("1.50E2".to_string()).to_canonical_string(), Ok("150".to_string()));
+        assert!(json!([1, f64::INFINITY]).to_canonical_string().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_serde() {
+        let json = json!({"name": "ann", "list": [1, 2.5, true, null], "nested": {"empty": []}});
+        let value = serde_json::to_value(&json).map_err(|error| error.to_string());
+        assert_eq!(value, Ok(serde_json::json!({"name": "ann", "list": [1, 2.5, true, null], "nested": {"empty": []}})));
+        let back = value.and_then(|value| serde_json::from_value::<JsonValue>(value).map_err(|error| error.to_string()));
+        assert_eq!(back, Ok(json));
+
+        let literal = JsonValue::RAWNUMBER("18446744073709551615".to_string());
+        assert_eq!(serde_json::to_string(&literal).ok(), Some("18446744073709551615".to_string()));
+        let parsed: Result<Vec<JsonValue>, _> = serde_json::from_str("[\"x\", {\"k\": -3}]");
+        assert_eq!(parsed.ok(), Some(vec![json!("x"), json!({"k": -3})]));
+    }
+
+    #[test]
+    fn check_conversions() {
+        assert_eq!(JsonValue::from(vec![1, 2]), json!([1, 2]));
+        assert_eq!(JsonValue::from(vec![JsonValue::NULL]), json!([null]));
+        assert_eq!(JsonValue::from(std::collections::HashMap::from([("a", vec!["x"])])), json!({"a": ["x"]}));
+        assert_eq!(JsonValue::from(Some("x")), json!("x"));
+        assert_eq!(JsonValue::from(None::<bool>), JsonValue::NULL);
+
+        assert_eq!(String::try_from(json!("text")), Ok("text".to_string()));
+        assert_eq!(f64::try_from(json!(2.5)), Ok(2.5));
+        assert_eq!(bool::try_from(json!(false)), Ok(false));
+        assert_eq!(i64::try_from(json!(-7)), Ok(-7));
+        assert_eq!(u64::try_from(JsonValue::RAWNUMBER("18446744073709551615".to_string())), Ok(u64::MAX));
+        assert_eq!(Vec::<JsonValue>::try_from(json!([true])), Ok(vec![JsonValue::TRUE]));
+        assert_eq!(i64::try_from(json!(1.5)), Err(TypeError { expected: "integer", found: "number" }));
+        assert_eq!(u64::try_from(json!(-1)), Err(TypeError { expected: "integer", found: "number" }));
+        assert_eq!(String::try_from(json!(null)).map_err(|error| error.to_string()), Err("expected string, found null".to_string()));
+    }
+
+    #[test]
+    fn check_value_hashing() {
+        use std::collections::HashSet;
+
+        let json = json!({"a": [1, {"b": null}], "c": "x"});
+        assert_eq!(json.clone(), json);
+        assert_eq!(JsonValue::NUMBER(f64::NAN), JsonValue::NUMBER(-f64::NAN));
+        assert_eq!(JsonValue::NUMBER(0f64), JsonValue::NUMBER(-0f64));
+        assert_ne!(JsonValue::NUMBER(1f64), JsonValue::RAWNUMBER("1".to_string()));
+
+        let mut reordered = json!({"c": "x"});
+        reordered.insert("a", json!([1, {"b": null}]));
+        let values = HashSet::from([json.clone(), reordered, json!(f64::NAN), json!(f64::NAN), json!(0), json!(-0.0)]);
+        assert_eq!(values.len(), 3);
+        assert!(values.contains(&json));
+    }
+
+    #[test]
+    fn check_merge() {
+        let mut json = json!({"title": "Goodbye!", "author": {"givenName": "John", "familyName": "Doe"}, "tags": ["example", "sample"]});
+        json.merge_patch(json!({"title": "Hello!", "phoneNumber": "+01-123-456-7890", "author": {"familyName": null}, "tags": ["example"]}));
+        assert_eq!(json, json!({"title": "Hello!", "author": {"givenName": "John"}, "tags": ["example"], "phoneNumber": "+01-123-456-7890"}));
+
+        let mut json = json!([1]);
+        json.merge_patch(json!({"a": {"b": null, "c": 1}}));
+        assert_eq!(json, json!({"a": {"c": 1}}));
+
+        let fragments = vec![json!({"server": {"port": 80, "host": "a"}}), json!({"server": {"port": 8080}, "debug": null})];
+        let merged = fragments.into_iter().fold(JsonValue::NULL, merge);
+        assert_eq!(merged, json!({"server": {"port": 8080, "host": "a"}, "debug": null}));
+    }
+
+    #[test]
+    fn check_patch() {
+        let mut json = json!({"a": {"b": [1, 2, 3]}, "c": "x"});
+        let ops = parse_patch(&json!([
+            {"op": "add", "path": "/a/b/1", "value": 9},
+            {"op": "remove", "path": "/a/b/3"},
+            {"op": "replace", "path": "/c", "value": "y"},
+            {"op": "move", "from": "/c", "path": "/d"},
+            {"op": "copy", "from": "/a/b", "path": "/e~1f"},
+            {"op": "test", "path": "/d", "value": "y"}
+        ])).unwrap();
+        json.apply_patch(&ops).unwrap();
+        assert_eq!(json, json!({"a": {"b": [1, 9, 2]}, "d": "y", "e/f": [1, 9, 2]}));
+
+        let failing = parse_patch(&json!([{"op": "remove", "path": "/d"}, {"op": "test", "path": "/a", "value": 1}])).unwrap();
+        assert_eq!(json.apply_patch(&failing).map_err(|error| error.index), Err(1));
+        assert!(json.get("d").is_some());
+        assert_eq!(parse_patch(&json!([{"op": "swap"}])).map_err(|error| error.msg), Err("unknown operation 'swap'".to_string()));
+        assert!(json.apply_patch(&[PatchOp::Move { from: "/a".to_string(), path: "/a/b/0".to_string() }]).is_err());
+
+        let from = json!({"a": [1, 2, 3, 4], "b": {"c": 1}, "d": true});
+        let to = json!({"a": [1, 3, 4], "b": {"c": 2, "e~": null}});
+        let ops = diff(&from, &to);
+        assert_eq!(ops, vec![
+            PatchOp::Remove { path: "/d".to_string() },
+            PatchOp::Remove { path: "/a/1".to_string() },
+            PatchOp::Replace { path: "/b/c".to_string(), value: json!(2) },
+            PatchOp::Add { path: "/b/e~0".to_string(), value: JsonValue::NULL }
+        ]);
+        let mut patched = from.clone();
+        patched.apply_patch(&parse_patch(&patch_to_json(&ops)).unwrap()).unwrap();
+        assert_eq!(patched, to);
+        assert!(diff(&to, &to).is_empty());
+    }
+
+    #[test]
+    fn check_walk() {
+        let json = json!({"b": [true, {"c/d": null}], "a": 1});
+        let visited: Vec<_> = json.walk().map(|(pointer, value)| (pointer.to_string(), value.clone())).collect();
+        #[cfg(not(feature = "preserve_order"))]
+        let expected = vec![
+            ("", json.clone()), ("/a", json!(1)), ("/b", json["b"].clone()), ("/b/0", json!(true)),
+            ("/b/1", json!({"c/d": null})), ("/b/1/c~1d", JsonValue::NULL)
+        ];
+        #[cfg(feature = "preserve_order")]
+        let expected = vec![
+            ("", json.clone()), ("/b", json["b"].clone()), ("/b/0", json!(true)),
+            ("/b/1", json!({"c/d": null})), ("/b/1/c~1d", JsonValue::NULL), ("/a", json!(1))
+        ];
+        assert_eq!(visited, expected.into_iter().map(|(pointer, value)| (pointer.to_string(), value)).collect::<Vec<_>>());
+
+        for (pointer, value) in json.walk() {
+            assert_eq!(json.pointer(&pointer.to_string()), Some(value));
+            assert_eq!(JsonPointer::parse(&pointer.to_string()), Ok(pointer));
+        }
+        let deepest = json.walk().map(|(pointer, _)| pointer.depth()).max();
+        assert_eq!(deepest, Some(3));
+    }
+
+    #[test]
+    fn check_visitor() {
+        #[derive(Default)]
+        struct Collector {
+            events: Vec<String>,
+            numbers: f64
+        }
+
+        impl JsonVisitor for Collector {
+            fn visit_object(&mut self, pointer: &JsonPointer, _object: &JsonMap<JsonValue>) -> bool {
+                self.events.push(format!("object {}", pointer));
+                pointer.depth() == 0
+            }
+
+            fn leave_object(&mut self, pointer: &JsonPointer) {
+                self.events.push(format!("leave {}", pointer));
+            }
+
+            fn visit_array(&mut self, pointer: &JsonPointer, array: &[JsonValue]) -> bool {
+                self.events.push(format!("array {} of {}", pointer, array.len()));
+                true
+            }
+
+            fn visit_number(&mut self, _pointer: &JsonPointer, number: f64) {
+                self.numbers += number;
+            }
+
+            fn visit_string(&mut self, pointer: &JsonPointer, line: &str) {
+                self.events.push(format!("string {} {}", pointer, line));
+            }
+        }
+
+        let json = json!({"a": [1, "x", {"b": 2}, [3]]});
+        let mut collector = Collector::default();
+        visit(&json, &mut collector);
+        assert_eq!(collector.events, vec!["object ", "array /a of 4", "string /a/1 x", "object /a/2", "array /a/3 of 1", "leave "]);
+        assert_eq!(collector.numbers, 4f64);
+
+        let raw = ParserOptions { preserve_number_literals: true, ..ParserOptions::default() }.parse_str("[1.5, 2]").unwrap();
+        let mut collector = Collector::default();
+        visit(&raw, &mut collector);
+        assert_eq!(collector.numbers, 3.5f64);
+    }
+
+    #[test]
+    #[cfg(feature = "preserve_order")]
+    fn check_sort_keys() {
+        let mut json = json!({"b": [{"z": 1, "y": {"d": null, "c": true}}], "a": "x"});
+        let same = json!({"a": "x", "b": [{"y": {"c": true, "d": null}, "z": 1}]});
+        assert_ne!(json.to_string(), same.to_string());
+        json.sort_keys_recursively();
+        assert_eq!(json.to_string(), same.to_string());
+        assert_eq!(json.to_string(), r#"{"a": "x", "b": [{"y": {"c": true, "d": null}, "z": 1}]}"#);
+    }
+
+    #[test]
+    fn check_flatten() {
+        let json = json!({"users": [{"name": "bob", "tags": []}], "first name": "a\"b", "$id": 1, "9": {}});
+        let lines: Vec<_> = json.flatten().into_iter().map(|(path, value)| format!("{} = {}", path, value)).collect();
+        #[cfg(not(feature = "preserve_order"))]
+        assert_eq!(lines, vec![
+            "json.$id = 1", "json[\"9\"] = {}", "json[\"first name\"] = \"a\\\"b\"",
+            "json.users[0].name = \"bob\"", "json.users[0].tags = []"
+        ]);
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(lines, vec![
+            "json.users[0].name = \"bob\"", "json.users[0].tags = []", "json[\"first name\"] = \"a\\\"b\"",
+            "json.$id = 1", "json[\"9\"] = {}"
+        ]);
+        assert_eq!(json!("x").flatten(), vec![("json".to_string(), json!("x"))]);
+    }
+
+    #[test]
+    fn check_unflatten() {
+        let json = json!({"users": [{"name": "bob", "tags": []}, null], "first name": "a\"b", "$id": 1, "9": {"x]": [true]}});
+        assert_eq!(unflatten(json.flatten()), Ok(json.clone()));
+        let lines: Vec<_> = json.flatten().into_iter().map(|(path, value)| format!("{} = {}", path, value)).collect();
+        let entries = lines.iter().map(|line| parse_line(line)).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(unflatten(entries), Ok(json));
+
+        assert_eq!(parse_line("json.a[1].b = 2;"), Ok(("json.a[1].b".to_string(), json!(2))));
+        assert_eq!(unflatten([("json.a[1].b", json!(2))]), Ok(json!({"a": [null, {"b": 2}]})));
+        assert_eq!(unflatten([("json", json!(1))]), Ok(json!(1)));
+        assert_eq!(unflatten([("json.a", json!(1)), ("json.a.b", json!(2))]).map_err(|error| error.msg),
+            Err("key 'b' of a value that is not an object".to_string()));
+        assert_eq!(unflatten([("json.a b", json!(1))]).map_err(|error| error.msg), Err("unexpected ' b'".to_string()));
+        assert_eq!(parse_line("data.a = 1").map_err(|error| error.msg), Err("missing leading 'json'".to_string()));
+        assert!(parse_line("json.a = {").is_err());
+    }
+
+    #[test]
+    fn check_key_interning() {
+        use std::sync::Arc;
+
+        let key = |value: &JsonValue| value.as_object().and_then(|object| object.keys().next().cloned()).unwrap();
+        let json = parse_str(r#"[{"id": 1}, {"id": 2}, {"name": "x"}]"#).unwrap();
+        assert!(Arc::ptr_eq(&key(&json[0]), &key(&json[1])));
+        assert!(! Arc::ptr_eq(&key(&json[0]), &key(&json[2])));
+
+        let records: Vec<_> = parse_ndjson(Cursor::new("{\"id\": 1}\n{\"id\": 2}\n")).collect::<Result<_, _>>().unwrap();
+        assert!(Arc::ptr_eq(&key(&records[0]), &key(&records[1])));
+        assert_eq!(&*key(&records[1]), "id");
+    }
+
+    #[test]
+    fn check_raw_values() {
+        let source = "{\"a\": {\"b\": [1, \"]}\"]},\n \"c\": [[2]], \"d\": 3}";
+        let options = ParserOptions { raw_depth: Some(1), ..ParserOptions::default() };
+        let json = options.parse_str(source).unwrap();
+        assert_eq!(json["a"], JsonValue::RAW(6..22, "{\"b\": [1, \"]}\"]}".to_string()));
+        assert_eq!(json["c"], JsonValue::RAW(30..35, "[[2]]".to_string()));
+        assert_eq!(json["d"], json!(3));
+        assert_eq!(options.parse_raw(source, &(6..22)).ok(), Some(json!({"b": [1, "]}"]})));
+        assert_eq!(options.parse_raw(source, &(30..35)).ok(), Some(json!([[2]])));
+        assert_eq!(json["a"].to_string(), "{\"b\": [1, \"]}\"]}");
+        assert_eq!(parse_str(&json.to_compact_string()).ok(), parse_str(source).ok());
+        assert!(json.to_canonical_string().is_err());
+
+        let error = options.parse_raw(source, &(30..34)).unwrap_err();
+        assert_eq!((error.row, error.offset), (1, 34));
+        assert!(options.parse_raw(source, &(30..99)).is_err());
+        assert!(options.parse_str("{\"a\": [1, 2}").is_err());
+        let result = options.parse_str("{\"a\": [1}, \"b\": 2}");
+        assert!(matches!(&result, Err(error) if error.kind == ParseErrorKind::UnexpectedChar && error.offset == 8), "{:?}", result);
+        assert!(options.parse_str("{\"a\": {\"b\": [\"}\"]}}").is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_from_value() {
+        use serde::Deserialize;
+        use crate::de::from_value;
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Role { Admin, Guest { until: u32 } }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct User { name: String, age: u8, score: f64, tags: Vec<String>, email: Option<String>, role: Role }
+
+        let json = parse_str(r#"{"name": "ann", "age": 30, "score": 1.5, "tags": ["a"], "email": null, "role": "Admin"}"#).unwrap();
+        assert_eq!(from_value::<User>(json), Ok(User {
+            name: "ann".to_string(), age: 30, score: 1.5, tags: vec!["a".to_string()], email: None, role: Role::Admin
+        }));
+        assert_eq!(from_value::<Role>(json!({"Guest": {"until": 5}})), Ok(Role::Guest { until: 5 }));
+        assert_eq!(from_value::<(i64, bool)>(json!([-2, true])), Ok((-2, true)));
+        assert_eq!(from_value::<u64>(JsonValue::RAWNUMBER("18446744073709551615".to_string())), Ok(u64::MAX));
+        assert_eq!(from_value::<u8>(json!(300)).map_err(|error| error.msg),
+            Err("invalid value: integer `300`, expected u8".to_string()));
+        assert!(from_value::<User>(json!({"name": "ann"})).is_err());
+        assert!(from_value::<u32>(json!(1.5)).is_err());
+    }
+
+    #[test]
+    fn check_select() {
+        let json = json!({"users": [{"email": "a@x", "tags": ["x", "y"]}, {"name": "b"}, {"email": "c@x", "tags": []}],
+            "meta": {"a": {"email": 1}, "b": {"email": 2}}, "w[e]ird": true});
+        assert_eq!(json.select("users.*.email"), vec![&json!("a@x"), &json!("c@x")]);
+        assert_eq!(json.select("users[*].email"), json.select("users.*.email"));
+        assert_eq!(json.select("users[0].tags[1]"), vec![&json!("y")]);
+        assert_eq!(json.select("users.2.email"), vec![&json!("c@x")]);
+        assert_eq!(json.select("users[*].tags[*]"), vec![&json!("x"), &json!("y")]);
+        assert_eq!(json.select("meta.*.email"), vec![&json!(1), &json!(2)]);
+        assert_eq!(json.select("meta[*]"), Vec::<&JsonValue>::new());
+        assert_eq!(json.select("w[e]ird"), vec![&JsonValue::TRUE]);
+        assert_eq!(json.select(""), vec![&json]);
+        assert!(json.select("users.*.missing").is_empty());
+        assert_eq!(WildcardPath::parse("*[*]").select(&json!([[1], [2, 3]])), vec![&json!(1), &json!(2), &json!(3)]);
+    }
+
+    #[test]
+    fn check_approx_size() {
+        let node = std::mem::size_of::<JsonValue>();
+        assert_eq!(JsonValue::NULL.approx_size_bytes(), node);
+        assert_eq!(JsonValue::STRING("abcd".to_string()).approx_size_bytes(), node + 4);
+        assert_eq!(JsonValue::ARRAY(vec![JsonValue::NULL, json!(1)]).approx_size_bytes(), 3 * node);
+        let mut array = Vec::with_capacity(4);
+        array.push(JsonValue::TRUE);
+        assert_eq!(JsonValue::ARRAY(array).approx_size_bytes(), 5 * node);
+
+        let small = json!({"ab": null});
+        assert!(small.approx_size_bytes() >= 2 * node + 2 + 3 * std::mem::size_of::<usize>());
+        let large = json!({"ab": ["a long string value", {"nested": [1, 2, 3]}]});
+        assert!(large.approx_size_bytes() > small.approx_size_bytes() + 4 * node + 19);
+    }
+
+    #[test]
+    fn check_approx_eq() {
+        let expected = json!({"id": 7, "price": 0.3, "tags": ["a", 1.0], "nested": {"ratio": 1e-9}});
+        let actual = parse_str(r#"{"nested": {"ratio": 0}, "tags": ["a", 1.0000001], "price": 0.30000000000000004, "id": 7}"#).unwrap();
+        assert!(actual.approx_eq(&expected, 1e-6));
+        assert!(! actual.approx_eq(&expected, 1e-12));
+        assert!(JsonValue::RAWNUMBER("1.5".to_string()).approx_eq(&json!(1.5), 0.0));
+        assert!(json!(f64::NAN).approx_eq(&json!(f64::NAN), 0.0));
+        assert!(! json!([1, 2]).approx_eq(&json!([1, 2, 3]), 1.0));
+        assert!(! json!({"a": 1}).approx_eq(&json!({"b": 1}), 1.0));
+        assert!(! json!("1").approx_eq(&json!(1), 1.0));
+    }
+
+    #[test]
+    fn check_coercion() {
+        let mut json = json!({"id": " 42 ", "price": "1.5e2", "hex": "0x10", "name": "7 dwarfs", "flags": ["TRUE", "false", "yes", true]});
+        assert_eq!(json.coerce_number_strings(), 2);
+        assert_eq!(json.coerce_bool_strings(), 2);
+        assert_eq!(json, json!({"id": 42, "price": 150, "hex": "0x10", "name": "7 dwarfs", "flags": [true, false, "yes", true]}));
+        assert_eq!(json.coerce_number_strings(), 0);
+
+        let mut users = json!([{"name": null, "age": 3, "tags": null, "note": null}, {"name": "b", "age": null, "tags": [null]}]);
+        let defaults = json!([{"name": "", "age": 0, "tags": [""]}]);
+        assert_eq!(users.nulls_to_defaults(&defaults), 4);
+        assert_eq!(users, json!([{"name": "", "age": 3, "tags": [""], "note": null}, {"name": "b", "age": 0, "tags": [""]}]));
+        assert_eq!(JsonValue::NULL.nulls_to_defaults(&JsonValue::NULL), 0);
+    }
+
+    #[test]
+    fn check_aggregation() {
+        let json = json!({"items": [{"price": 2.5}, {"price": 4}, {"price": "n/a"}, {"name": "x"}, {"price": -1}]});
+        assert_eq!(json.count_at("items[*].price"), 4);
+        assert_eq!(json.sum_at("items[*].price"), 5.5);
+        assert_eq!(json.min_at("items[*].price"), Some(-1.0));
+        assert_eq!(json.max_at("items.*.price"), Some(4.0));
+        assert_eq!(json.max_at("items[*].name"), None);
+        assert_eq!(json.sum_at("missing"), 0.0);
+        assert_eq!(json.count_at("items"), 1);
+    }
+
+    #[test]
+    fn check_stats_report() {
+        let json = json!([{"id": 3, "name": "a", "tags": [["x"]]}, {"id": 1, "tags": [], "meta": {"ok": true}}, 7]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        assert_eq!((report.path.as_str(), report.kind()), ("", "array"));
+        assert_eq!(report.types, vec!["number", "object"]);
+        assert_eq!(report.numbers.as_ref().map(|numbers| (numbers.count, numbers.sum)), Some((1, 7.0)));
+        let ReportDetails::Array { inner_arrays: None, inner_objects: Some(users) } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        assert_eq!(users.path, "[*]");
+        let ReportDetails::Object { nonobligatory, mandatory, primitive_keys, key_numbers, members, .. } = &users.details else {
+            panic!("unexpected details {:?}", users.details);
+        };
+        let keys: Vec<_> = nonobligatory.iter().chain(mandatory).map(String::as_str).collect();
+        assert_eq!(keys, vec!["meta", "name", "id", "tags"]);
+        assert!(primitive_keys.contains(&("id".to_string(), vec!["number".to_string()])));
+        let numbers = &key_numbers.iter().find(|(key, _)| key == "id").unwrap().1;
+        assert_eq!((numbers.maximums.as_slice(), numbers.minimums.as_slice(), numbers.avg), (&[3.0, 1.0][..], &[1.0, 3.0][..], 2.0));
+        let mut paths: Vec<_> = members.iter().map(|(_, stats)| stats.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["[*].meta", "[*].tags"]);
+
+        let value = JsonValue::from(&report);
+        assert_eq!(value["inner_objects"]["members"]["tags"]["inner_arrays"]["strings"], json!(["x"]));
+        assert_eq!(value["inner_arrays"], JsonValue::NULL);
+        assert!(report.to_string().starts_with("=== Heterogeneous arrays ===\n- [*]: object in 2/3 (66.7%), number in 1/3 (33.3%)\n\
+            Type: array\n--- Common info ---\nContaining types: number, object\n"));
+    }
+
+    #[test]
+    fn check_yaml() {
+        let json = json!({"name": "ann", "nested": [[1, [true]], {"e": {}, "k": null}], "tags": ["a b", "yes", "1", ""], "x: y": []});
+        let expected = concat!(
+            "name: ann\n",
+            "nested:\n",
+            "  - - 1\n",
+            "    - - true\n",
+            "  - e: {}\n",
+            "    k: null\n",
+            "tags:\n",
+            "  - a b\n",
+            "  - \"yes\"\n",
+            "  - \"1\"\n",
+            "  - \"\"\n",
+            "\"x: y\": []\n"
+        );
+        assert_eq!(json.to_yaml_string(), expected);
+        assert_eq!(json!("multi\nline").to_yaml_string(), "\"multi\\nline\"\n");
+        assert_eq!(json!([]).to_yaml_string(), "[]\n");
+    }
+
+    #[test]
+    fn check_stats_csv() {
+        let json = json!([{"id": 3, "tags": ["a", "b,c", 2.5, 0.5], "note": null}, {"id": 1, "tags": []}]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let rows = report.rows();
+        let paths: Vec<_> = rows.iter().map(|row| row.path.as_str()).collect();
+        assert_eq!(paths, vec!["[*]", "[*].id", "[*].note", "[*].tags", "[*].tags[*]"]);
+        let tags = &rows[4];
+        assert_eq!(tags.types, vec!["number", "string"]);
+        assert_eq!((tags.count, tags.min, tags.max, tags.avg, tags.distinct_strings), (Some(2), Some(0.5), Some(2.5), Some(1.5), Some(2)));
+
+        let csv = report.to_csv(',');
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], "path,types,presence %,null %,count,min,max,avg,distinct strings");
+        assert_eq!(lines[2], "[*].id,number,100.0,0.0,2,1,3,2,");
+        assert_eq!(lines[3], "[*].note,null,50.0,100.0,,,,,");
+        assert_eq!(lines[5], "[*].tags[*],number|string,,,2,0.5,2.5,1.5,2");
+        assert!(report.to_csv('\t').starts_with("path\ttypes\tpresence %"));
+        assert!(JsonComplexTypeStats::from_json(json!({"a,b": 1})).report().to_csv(',').contains("\n\"a,b\",number,"));
+    }
+
+    #[test]
+    fn check_stats_html() {
+        let json = json!({"<b>": "x & y", "items": [{"n": 1}, {"n": 2, "extra": true}]});
+        let html = JsonComplexTypeStats::from_json(json).report().to_html();
+        assert!(html.starts_with("<!DOCTYPE html>") && html.ends_with("</html>\n"));
+        assert_eq!(html.matches("<details").count(), html.matches("</details>").count());
+        assert_eq!(html.matches("<details").count(), 3);
+        assert!(html.contains("<summary><code>items[*]</code> object"));
+        assert!(html.contains("<code>&lt;b&gt;</code>") && html.contains("<code>x &amp; y</code>"));
+        assert!(! html.contains("<b>"));
+
+        let report = JsonComplexTypeStats::from_json(json!([1, 2, 2, 9])).report();
+        let html = report.to_html();
+        let histogram = report.numbers.as_ref().and_then(|numbers| numbers.histogram.as_ref()).unwrap();
+        assert!(html.contains("<th>histogram</th><th>count</th>"));
+        for (index, count) in histogram.counts.iter().enumerate() {
+            assert!(html.contains(&format!("<tr><td>{} to {}</td><td>{}</td>", histogram.edges[index], histogram.edges[index + 1], count)));
+        }
+        assert!(html.contains("<td>2</td><td><span class=\"bar\" style=\"width: 12.0em\"></span></td>"));
+    }
+
+    #[test]
+    fn check_json_schema() {
+        let json = json!([{"id": 1, "name": "a", "tags": ["x"], "owner": {"ok": true}}, {"id": 2, "tags": [], "owner": null}]);
+        let schema = JsonComplexTypeStats::from_json(json).report().to_json_schema();
+        assert_eq!(schema, json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "id": {"type": "number"},
+                    "name": {"type": "string"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "owner": {"type": ["null", "object"], "properties": {"ok": {"type": "boolean"}}, "required": ["ok"]}
+                },
+                "required": ["id", "owner", "tags"]
+            }
+        }));
+        assert_eq!(JsonComplexTypeStats::from_json(json!({})).report().to_json_schema()["properties"], json!({}));
+    }
+
+    #[test]
+    fn check_rust_codegen() {
+        let json = json!([
+            {"id": 1, "userName": "a", "type": "x", "tags": ["t"], "address": {"zip": "1"}, "extra": null},
+            {"id": 2, "userName": "b", "type": "y", "tags": [], "address": {"zip": "2"}, "extra": 1}
+        ]);
+        let code = JsonComplexTypeStats::from_json(json).report().to_rust("users");
+        let expected = concat!(
+            "use serde::{Deserialize, Serialize};\n\n",
+            "pub type Users = Vec<UsersItem>;\n\n",
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n",
+            "pub struct UsersItem {\n",
+            "    pub extra: Option<f64>,\n",
+            "    pub id: f64,\n",
+            "    pub r#type: String,\n",
+            "    #[serde(rename = \"userName\")]\n",
+            "    pub user_name: String,\n",
+            "    pub address: Address,\n",
+            "    pub tags: Vec<String>,\n",
+            "}\n\n",
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n",
+            "pub struct Address {\n",
+            "    pub zip: String,\n",
+            "}\n"
+        );
+        if cfg!(not(feature = "preserve_order")) {
+            assert_eq!(code, expected);
+        }
+        assert!(code.contains("pub struct UsersItem {") && code.contains("    pub r#type: String,\n"));
+
+        let optional = JsonComplexTypeStats::from_json(json!([{"a": 1, "9b": true}, {"a": 2}])).report().to_rust("Root");
+        assert!(optional.contains("    #[serde(rename = \"9b\")]\n    pub _9b: Option<bool>,\n"));
+        assert!(optional.contains("    pub a: f64,\n"));
+    }
+
+    #[test]
+    fn check_protobuf_avro() {
+        let json = json!([{"id": 1, "tags": ["t"], "owner": {"ok": true}, "mixed": "a"}, {"id": 2, "tags": [[1]], "mixed": 1}]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let (proto, warnings) = report.to_protobuf("Root");
+        assert!(proto.starts_with("syntax = \"proto3\";\n\nimport \"google/protobuf/struct.proto\";\n\nmessage Root {\n  repeated RootItem items = 1;\n}\n"));
+        assert!(proto.contains("  optional Owner owner = "));
+        assert!(proto.contains("  google.protobuf.Value mixed = "));
+        assert!(proto.contains("  double id = "));
+        assert!(proto.contains("message Owner {\n  bool ok = 1;\n}\n"));
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings.contains(&"[*].mixed: mixed types number, string, written as google.protobuf.Value".to_string()));
+        assert!(warnings.contains(&"[*].tags[*]: mixed types array, string, written as google.protobuf.Value".to_string()));
+
+        let (avro, warnings) = report.to_avro("Root");
+        assert_eq!(avro["type"], json!("array"));
+        assert_eq!(avro["items"]["name"], json!("RootItem"));
+        let fields = avro["items"]["fields"].as_array().unwrap();
+        let field = |name: &str| fields.iter().find(|field| field["name"] == json!(name)).unwrap().clone();
+        assert_eq!(field("mixed"), json!({"name": "mixed", "type": ["double", "string"]}));
+        assert_eq!(field("owner"), json!({"name": "owner", "default": null, "type": ["null", {
+            "type": "record", "name": "Owner", "fields": [{"name": "ok", "type": "boolean"}]
+        }]}));
+        assert!(warnings.is_empty());
+        let (_, warnings) = JsonComplexTypeStats::from_json(json!({"a-b": []})).report().to_avro("Root");
+        assert_eq!(warnings, vec!["a-b: key is no Avro name, renamed to a_b", "a-b[*]: no values, written as null"]);
+    }
+
+    #[test]
+    fn check_key_presence() {
+        let json = json!([{"id": 1, "email": "a", "meta": {}}, {"id": 2, "email": "b"}, {"id": 3}, {"id": 4, "email": null}]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let ReportDetails::Array { inner_objects: Some(users), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        assert_eq!(users.presence("email"), Some((3, 4)));
+        assert_eq!(users.presence("meta"), Some((1, 4)));
+        assert_eq!(users.presence("id"), Some((4, 4)));
+        assert_eq!(users.presence("name"), None);
+        assert_eq!(report.presence("id"), None);
+        let text = report.to_string();
+        assert!(text.contains("\nPresence of keys in 4 objects:\n- email: present in 3/4 (75.0%), null in 1/3 (33.3%)\n- id: present in 4/4 (100.0%)\n- meta: present in 1/4 (25.0%)\n"));
+        let value = JsonValue::from(&report);
+        assert_eq!(value["inner_objects"]["objects"], json!(4));
+        assert_eq!(value["inner_objects"]["presence"], json!({"email": 3, "id": 4, "meta": 1}));
+        let rows = report.rows();
+        assert_eq!(rows.iter().find(|row| row.path == "[*].meta").and_then(|row| row.presence), Some(25.0));
+        assert!(report.to_html().contains("<td>3/4 (75.0%), null in 1/3 (33.3%), likely nonobligatory</td>"));
+    }
+
+    #[test]
+    fn check_histogram() {
+        let numbers = json!([1, 2, 2, 3, 9.5, -1]);
+        let histogram = |options: &StatsOptions| JsonComplexTypeStats::from_json_with(numbers.clone(), options)
+            .report().numbers.unwrap().histogram;
+        let linear = histogram(&StatsOptions { histogram_buckets: 4, histogram_scale: HistogramScale::Linear, ..StatsOptions::default() }).unwrap();
+        assert_eq!(linear.edges, vec![-4.0, 0.0, 4.0, 8.0, 12.0]);
+        assert_eq!(linear.counts, vec![1, 4, 0, 1]);
+        assert_eq!(linear.sparkline(), "▂█ ▂");
+
+        let log = histogram(&StatsOptions { histogram_buckets: 8, histogram_scale: HistogramScale::Log, ..StatsOptions::default() }).unwrap();
+        assert_eq!(log.counts.iter().sum::<usize>(), 6);
+        assert!(log.counts.len() <= 8 && log.edges.len() == log.counts.len() + 1);
+        assert_eq!(log.edges.first().zip(log.edges.last()), Some((&-1.0, &15.0)));
+        assert_eq!(histogram(&StatsOptions { histogram_buckets: 0, ..StatsOptions::default() }), None);
+
+        let report = JsonComplexTypeStats::from_json(json!([5, 5])).report();
+        let single = report.numbers.as_ref().unwrap().histogram.as_ref().unwrap();
+        assert_eq!((single.counts.as_slice(), single.edges[0]), (&[2][..], 5.0));
+        assert!(report.to_string().contains("\nHistogram of 1 buckets from 5 to "));
+        assert_eq!(JsonValue::from(&report)["numbers"]["histogram"]["counts"], json!([2]));
+    }
+
+    #[test]
+    fn check_percentiles() {
+        let numbers = JsonComplexTypeStats::from_json(json!([2, 4, 4, 4, 5, 5, 7, 9])).report().numbers.unwrap();
+        assert_eq!(numbers.stddev, 2.0);
+        let few = JsonComplexTypeStats::from_json(json!([3, 1, 2])).report().numbers.unwrap();
+        assert_eq!((few.p50, few.p95, few.p99), (2.0, 3.0, 3.0));
+
+        let values: Vec<JsonValue> = (0..1000).map(|index| JsonValue::from((index * 7919 % 1000) as f64)).collect();
+        let report = JsonComplexTypeStats::from_array(values).report();
+        let numbers = report.numbers.as_ref().unwrap();
+        assert!((numbers.p50 - 500.0).abs() < 10.0, "{}", numbers.p50);
+        assert!((numbers.p95 - 950.0).abs() < 10.0, "{}", numbers.p95);
+        assert!((numbers.p99 - 990.0).abs() < 10.0, "{}", numbers.p99);
+        assert!((numbers.stddev - 288.675).abs() < 0.01);
+        assert!(report.to_string().contains(&format!("\nMedian = {}, p95 = {}, p99 = {}, stddev = ", numbers.p50, numbers.p95, numbers.p99)));
+        assert_eq!(JsonValue::from(&report)["numbers"]["p99"], JsonValue::from(numbers.p99));
+    }
+
+    #[test]
+    fn check_large_numbers() {
+        let numbers = JsonComplexTypeStats::from_json(json!([1e308, 1e308, 1e308])).report().numbers.unwrap();
+        assert_eq!(numbers.sum, f64::INFINITY);
+        assert_eq!((numbers.avg, numbers.stddev), (1e308, 0.0));
+        let negative = JsonComplexTypeStats::from_json(json!([-1e308, -1e308])).report().numbers.unwrap();
+        assert_eq!((negative.avg, negative.stddev), (-1e308, 0.0));
+        let mixed = JsonComplexTypeStats::from_json(json!([1e308, -1e308])).report().numbers.unwrap();
+        assert_eq!((mixed.avg, mixed.stddev), (0.0, 1e308));
+        let spread = JsonComplexTypeStats::from_json(json!([-1e308, 0, 1e308, 1.5])).report().numbers.unwrap();
+        assert!(spread.avg.is_finite() && spread.stddev.is_finite() && spread.stddev > 7e307, "{:?}", spread);
+    }
+
+    #[test]
+    fn check_number_kinds() {
+        let json = json!([{"id": 1, "price": 1.5}, {"id": 2, "price": 3, "tag": "a"}, {"id": -7e3, "price": null}]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        let ReportDetails::Object { number_kinds, key_numbers, .. } = &items.details else {
+            panic!("unexpected details {:?}", items.details);
+        };
+        let kinds = |key: &str| number_kinds.iter().find(|(other, _)| other == key).map(|(_, kinds)| *kinds);
+        assert_eq!(kinds("id"), Some(NumberKinds { integral: 3, fractional: 0 }));
+        assert_eq!(kinds("price"), Some(NumberKinds { integral: 1, fractional: 1 }));
+        assert_eq!(kinds("tag"), None);
+        assert!(items.numbers.is_none());
+        assert_eq!(key_numbers.iter().find(|(key, _)| key == "price").map(|(_, numbers)| numbers.kinds), Some(NumberKinds { integral: 1, fractional: 1 }));
+        assert!(report.to_string().contains("\n- price is null, number (1 integral, 1 fractional), e.g. 1.5, 3, null\n"));
+        assert_eq!(JsonValue::from(&report)["inner_objects"]["number_kinds"]["id"], json!({"integral": 3, "fractional": 0}));
+    }
+
+    #[test]
+    fn check_distinct_strings() {
+        let mut sketch = HyperLogLog::new(12);
+        assert_eq!(sketch.estimate(), 0);
+        for index in 0..100_000 {
+            sketch.insert(&format!("id-{}", index));
+            sketch.insert(&format!("id-{}", index / 2));
+        }
+        let estimate = sketch.estimate() as f64;
+        assert!((estimate - 100_000.0).abs() < 5_000.0, "{}", estimate);
+
+        let strings: Vec<JsonValue> = (0..50).map(|index| JsonValue::from(format!("s{}", index % 20))).collect();
+        let options = StatsOptions { exact_strings: 10, ..StatsOptions::default() };
+        let report = JsonComplexTypeStats::from_json_with(JsonValue::ARRAY(strings.clone()), &options).report();
+        assert!(report.strings.is_empty() && report.is_distinct_estimated);
+        assert_eq!(report.distinct_strings, 20);
+        assert!(report.to_string().contains("Encountered about 20 unique strings"));
+        let report = JsonComplexTypeStats::from_array(strings).report();
+        assert_eq!((report.strings.len(), report.distinct_strings, report.is_distinct_estimated), (20, 20, false));
+    }
+
+    #[test]
+    fn check_top_strings() {
+        let mut top = TopK::new(3);
+        for item in ["a", "b", "a", "c", "a", "b", "d", "a"] {
+            top.insert(item);
+        }
+        assert_eq!(top.top(2), vec![("a".to_string(), 4), ("b".to_string(), 2)]);
+        assert_eq!(top.top(10).len(), 3);
+
+        let json = json!(["b", "a", "b", "c", "b", "a", 1]);
+        let report = JsonComplexTypeStats::from_json(json.clone()).report();
+        assert_eq!(report.top_strings, vec![("b".to_string(), 3), ("a".to_string(), 2), ("c".to_string(), 1)]);
+        assert!(report.to_string().contains("Encountered 3 unique strings, the most frequent: 'b' (3), 'a' (2), 'c' (1)"));
+        assert_eq!(JsonValue::from(&report)["top_strings"][0], json!({"value": "b", "count": 3}));
+        let options = StatsOptions { top_strings: 1, ..StatsOptions::default() };
+        assert_eq!(JsonComplexTypeStats::from_json_with(json, &options).report().top_strings, vec![("b".to_string(), 3)]);
+    }
+
+    #[test]
+    fn check_samples() {
+        let mut reservoir = Reservoir::new(3);
+        (0..1000).for_each(|index| reservoir.insert_with(|| index));
+        assert_eq!(reservoir.items().len(), 3);
+        assert!(reservoir.items().iter().any(|index| *index >= 3));
+        let mut few = Reservoir::new(5);
+        few.insert_with(|| "a");
+        assert_eq!(few.items(), &["a"]);
+
+        let records: Vec<JsonValue> = (0..100).map(|index| json!({"id": index, "tags": [format!("t{}", index)], "ok": null})).collect();
+        let report = JsonComplexTypeStats::from_array(records.clone()).report();
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        let paths: Vec<&str> = items.samples.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.contains(&"[*].id") && paths.contains(&"[*].ok"));
+        assert!(items.samples.iter().all(|(_, values)| values.len() == 5));
+        let ReportDetails::Object { members, .. } = &items.details else {
+            panic!("unexpected details {:?}", items.details);
+        };
+        assert_eq!(members[0].1.samples[0].0, "[*].tags[*]");
+        assert!(report.to_string().contains("\n- [*].ok: null, null, null, null, null"));
+        assert_eq!(JsonValue::from(&report)["inner_objects"]["samples"]["[*].ok"], json!([null, null, null, null, null]));
+        let options = StatsOptions { samples: 0, ..StatsOptions::default() };
+        let report = JsonComplexTypeStats::from_json_with(JsonValue::ARRAY(records), &options).report();
+        assert!(! report.to_string().contains("=== Sample values ==="));
+    }
+
+    #[test]
+    fn check_date_detection() {
+        assert_eq!(parse_date("1970-01-02"), Some(86_400.0));
+        assert_eq!(parse_date("2000-02-29T12:30:15.5+02:00"), Some(951_820_215.5));
+        assert_eq!(parse_date("2000-02-29 10:30Z"), Some(951_820_200.0));
+        assert_eq!(parse_date("Tue, 29 Feb 2000 05:30:15 EST"), Some(951_820_215.0));
+        assert_eq!(parse_date("29 Feb 00 10:30:15 +0000"), Some(951_820_215.0));
+        assert_eq!(parse_date("951820215"), None);
+        assert_eq!(parse_date("0951820215"), Some(951_820_215.0));
+        assert_eq!(parse_date("1700000000123"), Some(1_700_000_000.123));
+        for invalid in ["2001-02-29", "2000-13-01", "2000-01-01T25:00", "2000-01-01T10:00+1", "Mon, 1 Jan", "17e8", ""] {
+            assert_eq!(parse_date(invalid), None, "{}", invalid);
+        }
+        assert_eq!(format_timestamp(951_820_215.5), "2000-02-29T10:30:15Z");
+        assert_eq!(format_timestamp(-1.0), "1969-12-31T23:59:59Z");
+        assert_eq!(detect("2020-01-01"), Some(StringFormat::Date));
+
+        let json = json!([{"at": "2021-03-04T05:06:07Z", "name": "x"}, {"at": "2020-01-01", "name": "2020"}, {"at": "soon"}]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        assert_eq!(items.formats.len(), 1);
+        let (path, formats) = &items.formats[0];
+        assert_eq!((path.as_str(), formats.strings, formats.matches.as_slice()), ("[*].at", 3, &[(StringFormat::Date, 2)][..]));
+        assert_eq!(formats.dates, Some((1_577_836_800.0, 1_614_834_367.0)));
+        assert!(report.to_string().contains("\n- [*].at: date in 2/3 strings (66.7%) from 2020-01-01T00:00:00Z to 2021-03-04T05:06:07Z"));
+        assert_eq!(JsonValue::from(&report)["inner_objects"]["formats"]["[*].at"]["dates"]["latest"], json!("2021-03-04T05:06:07Z"));
+    }
+
+    #[test]
+    fn check_uuid_hex_detection() {
+        assert!(is_uuid("123e4567-e89b-42d3-a456-426614174000"));
+        assert!(is_uuid("017F22E2-79B0-7CC3-98C4-DC0C0C07398F"));
+        assert!(! is_uuid("123e4567-e89b-82d3-a456-426614174000"));
+        assert!(! is_uuid("123e4567-e89b-42d3-c456-426614174000"));
+        assert!(! is_uuid("123e4567e89b42d3a456426614174000"));
+        assert!(is_hex("deadbeef") && is_hex("DEADBEEF01"));
+        assert!(! is_hex("deadbee") && ! is_hex("DeadBeef") && ! is_hex("12345678") && ! is_hex("deadbeeg"));
+        assert_eq!(detect("356a192b7913b04c54574d18c28d46e6395428ab"), Some(StringFormat::Hex(40)));
+        assert_eq!(StringFormat::Hex(40).to_string(), "hex-40 / sha1-like");
+        assert_eq!(StringFormat::Hex(10).to_string(), "hex-10");
+
+        let json = json!([
+            {"id": "123e4567-e89b-42d3-a456-426614174000", "hash": "d41d8cd98f00b204e9800998ecf8427e", "note": "abcdef12"},
+            {"id": "6ba7b810-9dad-11d1-80b4-00c04fd430c8", "hash": "9e107d9d372bb6826bd81d3542a419d6", "note": "plain"}
+        ]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        assert_eq!(items.consistent_format("[*].id"), Some(StringFormat::Uuid));
+        assert_eq!(items.consistent_format("[*].hash"), Some(StringFormat::Hex(32)));
+        assert_eq!(items.consistent_format("[*].note"), None);
+        assert!(report.to_string().contains("\n- hash is string, hex-32 / md5-like, e.g. 'd41d8cd98f00b204…', '9e107d9d372bb682…'\n"));
+        let schema = report.to_json_schema();
+        assert_eq!(schema["items"]["properties"]["id"], json!({"type": "string", "format": "uuid"}));
+        assert_eq!(schema["items"]["properties"]["hash"]["pattern"], json!("^[0-9a-fA-F]{32}$"));
+        assert_eq!(schema["items"]["properties"]["note"], json!({"type": "string"}));
+    }
+
+    #[test]
+    fn check_email_url_ip_detection() {
+        assert!(is_email("john.doe+tag@mail.example.com"));
+        assert!(! is_email("john..doe@example.com") && ! is_email("john@localhost") && ! is_email("@example.com"));
+        assert!(is_url("https://example.com/path?query=1#top") && is_url("ftp://user@files.example.com"));
+        assert!(! is_url("example.com") && ! is_url("https:///path") && ! is_url("http://exa mple.com"));
+        assert_eq!(detect("192.168.0.1"), Some(StringFormat::Ipv4));
+        assert_eq!(detect("192.168.0.256"), None);
+        assert_eq!(detect("2001:db8::ff00:42:8329"), Some(StringFormat::Ipv6));
+        assert_eq!(detect("mailto@example.org"), Some(StringFormat::Email));
+        assert!(StringFormat::Email.is_personal() && ! StringFormat::Url.is_personal());
+
+        let json = json!([
+            {"mail": "john@example.com", "site": "https://example.com", "ip": "10.0.0.1"},
+            {"mail": "jane@example.org", "site": "http://example.org/a", "ip": "::1"},
+            {"mail": "nobody", "site": "https://example.net", "ip": "127.0.0.1"}
+        ]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        assert_eq!(items.consistent_format("[*].site"), Some(StringFormat::Url));
+        let (_, ip) = items.formats.iter().find(|(path, _)| path == "[*].ip").unwrap();
+        assert_eq!(ip.matches, vec![(StringFormat::Ipv4, 2), (StringFormat::Ipv6, 1)]);
+        assert_eq!(ip.personal(), 3);
+        let text = report.to_string();
+        assert!(text.contains("\n- [*].mail: email in 2/3 strings (66.7%), personal data\n"));
+        assert!(text.contains("\n- [*].site: url in 3/3 strings (100.0%)\n"));
+        let schema = report.to_json_schema();
+        assert_eq!(schema["items"]["properties"]["site"]["format"], json!("uri"));
+        assert_eq!(schema["items"]["properties"]["mail"], json!({"type": "string"}));
+    }
+
+    #[test]
+    fn check_base64_detection() {
+        let blob = "SGVsbG8sIFdvcmxkISBUaGlzIGlzIGEgbG9uZyBiYXNlNjQgc3RyaW5nIHRvIGRldGVjdC4=";
+        assert_eq!(base64_size(blob), Some(53));
+        assert_eq!(base64_size(&format!("data:text/plain;base64,{}", blob)), Some(53));
+        assert_eq!(base64_size(&blob.replace('+', "-")), Some(53));
+        assert_eq!(base64_size("SGVsbG8="), None);
+        assert_eq!(base64_size(&"Word".repeat(20)), None);
+        assert_eq!(base64_size(&format!("{}==", blob)), None);
+        assert_eq!(detect(blob), Some(StringFormat::Base64));
+
+        let json = json!([{"data": blob}, {"data": format!("{}AAAA", &blob[..blob.len() - 1])}, {"data": "none"}]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        let (_, data) = &items.formats[0];
+        assert_eq!(data.blobs, Some(BlobSizes { smallest: 53, largest: 56, total: 109 }));
+        assert_eq!(data.average_blob(), Some(54.5));
+        assert!(report.to_string().contains(
+            "\n- [*].data: base64 in 2/3 strings (66.7%) of 53 to 56 bytes (54.5 on average, 109 in total), embedded binary\n"
+        ));
+        assert_eq!(JsonValue::from(data)["blobs"], json!({"smallest": 53, "largest": 56, "average": 54.5, "total": 109}));
+    }
+
+    #[test]
+    fn check_null_rate() {
+        let json = json!([
+            {"name": "a", "parent": null, "meta": {"x": 1}},
+            {"name": null, "parent": null},
+            {"name": "c", "parent": 1, "meta": null},
+            {"name": "d"}
+        ]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        assert_eq!(items.nulls("name"), Some((1, 4)));
+        assert_eq!(items.nulls("parent"), Some((2, 3)));
+        assert_eq!(items.nulls("meta"), Some((1, 2)));
+        assert_eq!(items.nulls("missing"), None);
+        let text = report.to_string();
+        assert!(text.contains("\n- parent: present in 3/4 (75.0%), null in 2/3 (66.7%)\n"));
+        assert!(text.contains("\n- name: present in 4/4 (100.0%), null in 1/4 (25.0%)\n"));
+        assert_eq!(JsonValue::from(&**items)["nulls"], json!({"meta": 1, "name": 1, "parent": 2}));
+        let row = report.rows().into_iter().find(|row| row.path == "[*].parent").unwrap();
+        assert_eq!((row.presence, row.nulls), (Some(75.0), Some(200.0 / 3.0)));
+    }
+
+    #[test]
+    fn check_depth() {
+        let first = json!({"id": 1, "tree": {"a": {"b": [1, [2]]}}, "tags": ["x"]});
+        let second = json!({"id": 2, "tree": {"a": 1}, "tags": [{"deep": [[[]]]}]});
+        let stats = JsonComplexTypeStats::from_json(first).merge_stats(second);
+        let report = stats.report();
+        assert_eq!(report.depth, Some(DepthReport { max: 6, avg: 5.5 }));
+        let ReportDetails::Object { members, .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        let depth = |key: &str| members.iter().find(|(other, _)| other == key).and_then(|(_, stats)| stats.depth);
+        assert_eq!(depth("tree"), Some(DepthReport { max: 4, avg: 2.5 }));
+        assert_eq!(depth("tags"), Some(DepthReport { max: 5, avg: 3.0 }));
+        let text = report.to_string();
+        assert!(text.starts_with("=== Type anomalies ===\n- tree.a: number in 1/2 (50.0%), object in 1/2 (50.0%)\n=== Heterogeneous arrays ===\n"));
+        assert!(text.contains("\n- tags[*]: object in 1/2 (50.0%), string in 1/2 (50.0%)\n"));
+        assert!(text.contains("\n- tree.a.b[*]: array in 1/2 (50.0%), number in 1/2 (50.0%)\n"));
+        assert!(text.contains("\nType: object\n--- Common info ---\nContaining types: array, number, object\nNesting depth: at most 6, 5.5 on average\n"));
+        assert_eq!(JsonValue::from(&report)["depth"], json!({"max": 6, "avg": 5.5}));
+
+        // the items of a different type than the first are not collected but still measured
+        let mixed = JsonComplexTypeStats::from_json(json!({"a": [1], "b": 1})).merge_stats(json!({"a": {"x": [[1]]}}));
+        assert_eq!(mixed.report().depth, Some(DepthReport { max: 4, avg: 3.0 }));
+        assert_eq!(JsonComplexTypeStats::from_json(json!(1)).report().depth, None);
+    }
+
+    #[test]
+    fn check_node_counts() {
+        let stats = JsonComplexTypeStats::from_json(json!([{"a": "x", "b": [1, 2.5, null]}, true]))
+            .merge_stats(json!([{"a": false, "c": {}}, "y"]))
+            .merge_stats(json!(7));
+        let report = stats.report();
+        let nodes = NodeCounts { objects: 3, arrays: 3, strings: 2, numbers: 3, booleans: 2, nulls: 1 };
+        assert_eq!(report.nodes, Some(nodes));
+        assert_eq!(nodes.total(), 14);
+        assert!(report.to_string().contains("\nNodes: 3 objects, 3 arrays, 2 strings, 3 numbers, 2 booleans, 1 nulls (14 in total)\n"));
+        assert_eq!(JsonValue::from(&report)["nodes"]["total"], json!(14));
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        assert_eq!(items.nodes, None);
+    }
+
+    #[test]
+    fn check_byte_sizes() {
+        let first = json!([{"id": 1, "blob": "abcdefghij", "meta": {"ok": true}}]);
+        let second = json!([{"id": 22, "meta": [false]}]);
+        let report = JsonComplexTypeStats::from_json(first.clone()).merge_stats(second.clone()).report();
+        assert_eq!(report.bytes, Some(first.to_compact_string().len() + second.to_compact_string().len()));
+        assert_eq!(report.sizes, vec![("[*]".to_string(), 47 + 24)]);
+        assert_eq!(report.largest_paths(3), vec![("[*]", 71), ("[*].meta", 18 + 14), ("[*].blob", 19)]);
+        assert_eq!(report.largest_paths(10).iter().find(|(path, _)| *path == "[*].id"), Some(&("[*].id", 6 + 7)));
+        let text = report.to_string();
+        assert!(text.contains("\nSize: 75 bytes of compact JSON, the largest paths:\n- [*]: 71 bytes (94.7%)\n- [*].meta: 32 bytes (42.7%)\n"));
+
+        let escaped = json!({"tags": ["x\"y", null]});
+        let report = JsonComplexTypeStats::from_json(escaped.clone()).report();
+        assert_eq!(report.bytes, Some(escaped.to_compact_string().len()));
+        assert_eq!(report.largest_paths(2), vec![("tags", 20), ("tags[*]", 10)]);
+    }
+
+    #[test]
+    fn check_top_k() {
+        let numbers = json!([5, 3, 9, 1, 7, 2, 8]);
+        let report = |top_k: usize| JsonComplexTypeStats::from_json_with(numbers.clone(), &StatsOptions { top_k, ..StatsOptions::default() })
+            .report();
+        let numbers_of = |top_k: usize| report(top_k).numbers.unwrap();
+        assert_eq!((numbers_of(2).maximums, numbers_of(2).minimums), (vec![9.0, 8.0], vec![1.0, 2.0]));
+        assert_eq!(numbers_of(100).maximums, vec![9.0, 8.0, 7.0, 5.0, 3.0, 2.0, 1.0]);
+        assert_eq!(numbers_of(0).maximums, Vec::<f64>::new());
+        let text = report(0).to_string();
+        assert!(text.contains("Encountered 7 numbers, sum = 35, avg = 5\nNumbers are 7 integral, 0 fractional\n"));
+        assert!(report(0).to_html().contains("<tr><td>7</td><td>35</td><td>5</td><td></td><td></td>"));
+        assert_eq!(StatsOptions::default().top_k, 10);
+    }
+
+    #[test]
+    fn check_stats_collector() {
+        let documents = [
+            "{\"id\": 1, \"tags\": [\"a\", {\"deep\": [[1, null]]}], \"meta\": {\"ok\": true}}",
+            "{\"id\": 2.5, \"tags\": [[\"x\"], \"b\"], \"meta\": [{\"skipped\": [false, \"q\\\"\"]}, 3]}",
+            "[1, {\"a\": []}]"
+        ];
+        let mut collector = StatsCollector::new(&StatsOptions::default());
+        let mut expected: Option<JsonComplexTypeStats> = None;
+        for text in documents {
+            assert!(parse_events(Cursor::new(text), |event| collector.event(event)).is_ok());
+            let json = parse_str(text).unwrap();
+            expected = Some(match expected {
+                Some(stats) => stats.merge_stats(json),
+                None => JsonComplexTypeStats::from_json(json)
+            });
+        }
+        let report = collector.finish().unwrap().report();
+        assert_eq!(report, expected.unwrap().report());
+        assert_eq!(report.nodes.map(|nodes| nodes.total()), Some(27));
+        assert_eq!(report.bytes, Some(documents.iter().map(|text| parse_str(text).unwrap().to_compact_string().len()).sum()));
+
+        let mut collector = StatsCollector::merging(JsonComplexTypeStats::from_json(json!([1])), &StatsOptions::default());
+        collector.value(&json!([2]));
+        assert_eq!(collector.finish().unwrap().report().nodes.map(|nodes| nodes.numbers), Some(2));
+        assert!(StatsCollector::new(&StatsOptions::default()).finish().is_none());
+    }
+
+    #[test]
+    fn check_file_summaries() {
+        let mut collector = StatsCollector::new(&StatsOptions::default());
+        assert!(parse_events(Cursor::new("{\"a\": [1, [true]], \"b\": {\"c\": null}}"), |event| collector.event(event)).is_ok());
+        let first = collector.summary("first.json");
+        assert_eq!(first, FileSummary {
+            file: "first.json".to_string(),
+            types: vec!["object".to_string()],
+            documents: 1,
+            nodes: NodeCounts { objects: 2, arrays: 2, strings: 0, numbers: 1, booleans: 1, nulls: 1 },
+            depth: 3,
+            bytes: 31
+        });
+        // the array of the first document is skipped in the second one
+        assert!(parse_events(Cursor::new("{\"a\": {\"x\": [[[]]]}}"), |event| collector.event(event)).is_ok());
+        collector.value(&json!("text"));
+        let second = collector.summary("second.json");
+        assert_eq!((second.types.clone(), second.documents, second.depth, second.nodes.total(), second.bytes), (vec!["object".to_string(), "string".to_string()], 2, 5, 6, 24));
+
+        let table = summaries_table(&[first, second]);
+        assert_eq!(table, "=== Files ===\n\
+            file         types          documents  nodes  depth  bytes  share\n\
+            first.json   object         1          7      3      31     56.4%\n\
+            second.json  object|string  2          6      5      24     43.6%\n");
+        let report = collector.finish().unwrap().report();
+        assert_eq!((report.nodes.map(|nodes| nodes.total()), report.bytes), (Some(13), Some(55)));
+    }
+
+    #[test]
+    fn check_stats_state() {
+        let options = StatsOptions { exact_strings: 2, ..StatsOptions::default() };
+        let first = json!([1.5, -3, "ann@example.com", "b", "c", {"id": "2024-01-02", "tags": [null, true], "n": 1e300}]);
+        let second = json!([[7], 1e300, "c", {"id": 4, "extra": {"x": "d"}}]);
+        let stats = JsonComplexTypeStats::from_json_with(first.clone(), &options)
+            .merge_stats_with(json!([1e308, 1e308]), &options);
+        let state = parse_str(&stats.to_state().to_compact_string()).unwrap();
+        let loaded = JsonComplexTypeStats::from_state(&state).unwrap();
+        assert_eq!(loaded.report(), stats.report());
+        assert_eq!(loaded.merge_stats_with(second.clone(), &options).report(), stats.merge_stats_with(second, &options).report());
+
+        assert_eq!(JsonComplexTypeStats::from_state(&json!({"stats": {}})).err().map(|error| error.msg), Some("missing \'version\'".to_string()));
+        assert!(JsonComplexTypeStats::from_state(&json!({"version": 2, "stats": {}})).is_err());
+        let mut broken = state.clone();
+        assert!(broken.set_by_pointer("/stats/types", json!(["integer"])).is_ok());
+        assert_eq!(JsonComplexTypeStats::from_state(&broken).err().map(|error| error.msg),
+            Some("unknown type \'integer\' in \'types\' in \'stats\'".to_string()));
+    }
+
+    #[test]
+    fn check_schema_drift() {
+        let report = |json: JsonValue| JsonValue::from(&JsonComplexTypeStats::from_json(json).report());
+        let baseline = report(json!([{"a": 1, "b": "x", "c": {"d": true}, "e": [1]}]));
+        let current = report(json!([{"a": "1", "c": {"d": false, "f": null}, "e": [2]}, {"a": 2}]));
+        assert_eq!(schema_paths(&baseline).unwrap().get("[*].c.d"), Some(&vec!["boolean".to_string()]));
+        let changes = schema_drift(&baseline, &current).unwrap();
+        assert_eq!(changes.iter().map(ToString::to_string).collect::<Vec<_>>(), [
+            "~ [*].a: number -> number|string",
+            "- [*].b: string",
+            "+ [*].c.f: null"
+        ]);
+        assert!(schema_drift(&baseline, &baseline).unwrap().is_empty());
+        assert_eq!(schema_drift(&JsonValue::NULL, &baseline).unwrap().len(), 7);
+        assert!(schema_drift(&json!({"path": ""}), &baseline).is_err());
+    }
+
+    #[test]
+    fn check_path_filters() {
+        let path = WildcardPath::parse("users[*].address.*");
+        assert!(path.matches(&[PathStep::Member("users"), PathStep::Item(3), PathStep::Member("address"), PathStep::Member("city")]));
+        assert!(! path.matches(&[PathStep::Member("users"), PathStep::Item(3), PathStep::Member("address")]));
+        assert!(path.leads_through(&[PathStep::Member("users"), PathStep::Item(3)]));
+        assert!(! path.leads_through(&[PathStep::Member("users"), PathStep::Member("3")]));
+        assert!(WildcardPath::parse("**.debug").matches(&[PathStep::Member("debug")]));
+        assert!(WildcardPath::parse("**.debug").matches(&[PathStep::Item(0), PathStep::Member("a"), PathStep::Member("debug")]));
+        assert_eq!(json!({"a": {"debug": 1}, "b": [{"debug": 2}], "debug": 3}).select("**.debug"), vec![&json!(3), &json!(1), &json!(2)]);
+
+        let text = "{\"users\": [{\"name\": \"a\", \"address\": {\"city\": \"X\", \"zip\": 1}, \"debug\": {\"t\": 1}}], \"meta\": {\"debug\": true, \"v\": 2}}";
+        let paths = |options: &StatsOptions| {
+            let mut collector = StatsCollector::new(options);
+            assert!(parse_events(Cursor::new(text), |event| collector.event(event)).is_ok());
+            let report = collector.finish().unwrap().report();
+            assert_eq!(report, JsonComplexTypeStats::from_json_with(parse_str(text).unwrap(), options).report());
+            report.rows().into_iter().map(|row| row.path).collect::<Vec<_>>()
+        };
+        let include = StatsOptions { include: vec![WildcardPath::parse("users[*].address.*")], ..StatsOptions::default() };
+        assert_eq!(paths(&include), ["users", "users[*]", "users[*].address", "users[*].address.city", "users[*].address.zip"]);
+        let exclude = StatsOptions { exclude: vec![WildcardPath::parse("**.debug")], ..StatsOptions::default() };
+        assert_eq!(paths(&exclude), ["meta", "meta.v", "users", "users[*]", "users[*].address", "users[*].address.city", "users[*].address.zip", "users[*].name"]);
+        let excluded = JsonComplexTypeStats::from_json_with(parse_str(text).unwrap(), &exclude).report();
+        assert_eq!(excluded.nodes.map(|nodes| nodes.total()), Some(9));
+    }
+
+    #[test]
+    fn check_stat_depth() {
+        let text = "{\"a\": {\"b\": {\"c\": [1, 2]}}, \"d\": [[true]], \"e\": 1}";
+        let options = StatsOptions { stat_depth: Some(1), ..StatsOptions::default() };
+        let mut collector = StatsCollector::new(&options);
+        assert!(parse_events(Cursor::new(text), |event| collector.event(event)).is_ok());
+        let report = collector.finish().unwrap().report();
+        assert_eq!(report, JsonComplexTypeStats::from_json_with(parse_str(text).unwrap(), &options).report());
+        assert_eq!(report.rows().into_iter().map(|row| (row.path, row.types)).collect::<Vec<_>>(), [
+            ("a".to_string(), vec!["object".to_string()]),
+            ("d".to_string(), vec!["array".to_string()]),
+            ("d[*]".to_string(), vec![]),
+            ("e".to_string(), vec!["number".to_string()])
+        ]);
+        // the values below are still counted
+        let full = JsonComplexTypeStats::from_json(parse_str(text).unwrap()).report();
+        assert_eq!(report.nodes, full.nodes);
+        assert_eq!(report.bytes, full.bytes);
+        assert_eq!(report.depth, full.depth);
+        let root = StatsOptions { stat_depth: Some(0), ..StatsOptions::default() };
+        assert!(JsonComplexTypeStats::from_json_with(parse_str(text).unwrap(), &root).report().rows().is_empty());
+    }
+
+    #[test]
+    fn check_raw_value_stats() {
+        let options = ParserOptions { raw_depth: Some(1), ..ParserOptions::default() };
+        let json = options.parse_str("{\"a\": {\"b\": [1]}, \"c\": [[2]], \"d\": 3}").unwrap();
+        let report = JsonComplexTypeStats::from_json(json.clone()).report();
+        assert_eq!(report.rows().into_iter().map(|row| (row.path, row.types)).collect::<Vec<_>>(), [
+            ("a".to_string(), vec!["object".to_string()]),
+            ("c".to_string(), vec!["array".to_string()]),
+            ("c[*]".to_string(), vec![]),
+            ("d".to_string(), vec!["number".to_string()])
+        ]);
+        assert_eq!(report.nodes, Some(NodeCounts { objects: 2, arrays: 1, strings: 0, numbers: 1, booleans: 0, nulls: 0 }));
+        assert_eq!(report.depth.map(|depth| depth.max), Some(2));
+
+        let mut collector = StatsCollector::new(&StatsOptions::default());
+        collector.value(&json["c"]);
+        let summary = collector.summary("raw.json");
+        assert_eq!((summary.types, summary.nodes.arrays, summary.nodes.nulls), (vec!["array".to_string()], 1, 0));
+    }
+
+    #[test]
+    fn check_type_anomalies() {
+        let mut people: Vec<JsonValue> = (0..99).map(|age| json!({"age": age, "ok": true})).collect();
+        people.push(json!({"age": "42", "ok": null, "meta": {"tag": [1]}}));
+        people.push(json!({"age": 7, "ok": false, "meta": {"tag": "x"}}));
+        let report = JsonComplexTypeStats::from_json(json!({"people": people})).report();
+        let anomalies = report.all_anomalies();
+        assert_eq!(anomalies.iter().map(ToString::to_string).collect::<Vec<_>>(), [
+            "people[*].age: number in 100/101 (99.0%), string in 1/101 (1.0%), e.g. \"42\"",
+            "people[*].meta.tag: array in 1/2 (50.0%), string in 1/2 (50.0%), e.g. \"x\""
+        ]);
+        assert_eq!(anomalies[0].values(), 101);
+        assert!(report.to_string().starts_with("=== Type anomalies ===\n- people[*].age: "));
+        assert!(report.to_html().contains("<h2>Type anomalies</h2>"));
+    }
+
+    #[test]
+    fn check_mixed_arrays() {
+        let events = json!([
+            {"id": 1, "name": "a"}, {"id": 2, "name": "b"},
+            {"kind": "click", "x": 1}, {"kind": "scroll", "dy": 3}, {"error": "timeout"}
+        ]);
+        let report = JsonComplexTypeStats::from_json(json!({"events": events, "ids": [1, 2, "3", null], "ok": [{"a": 1}, {"a": 2, "b": 3}]})).report();
+        let mixed = report.mixed_arrays();
+        assert_eq!(mixed.iter().map(|array| array.path.as_str()).collect::<Vec<_>>(), ["events[*]", "ids[*]"]);
+        // 9 keys present in 5 objects out of 6 keys
+        assert_eq!(mixed[0].key_overlap, Some(30.0));
+        assert_eq!(mixed[0].to_string(), "events[*]: object in 5/5 (100.0%), objects have 30.0% of the keys on average");
+        assert_eq!(mixed[1].to_string(), "ids[*]: number in 2/3 (66.7%), string in 1/3 (33.3%)");
+        assert_eq!(JsonValue::from(mixed[1])["key_overlap"], JsonValue::NULL);
+        assert!(report.to_html().contains("<h2>Heterogeneous arrays</h2>"));
+    }
+
+    #[test]
+    fn check_duplicates() {
+        let text = "{\"n\": [1, 1.0, 2], \"users\": [{\"id\": 1, \"tags\": [\"a\", \"a\"]}, {\"id\": 2}, {\"tags\": [\"a\", \"a\"], \"id\": 1}, {\"id\": 1, \"tags\": [\"a\", \"a\"]}]}";
+        let options = StatsOptions { duplicates: true, ..StatsOptions::default() };
+        let mut collector = StatsCollector::new(&options);
+        assert!(parse_events(Cursor::new(text), |event| collector.event(event)).is_ok());
+        let stats = collector.finish().unwrap();
+        let report = stats.report();
+        assert_eq!(report, JsonComplexTypeStats::from_json_with(parse_str(text).unwrap(), &options).report());
+        let duplicates = report.duplicate_items();
+        assert_eq!(duplicates.iter().map(|items| (items.path.as_str(), items.items, items.duplicates)).collect::<Vec<_>>(),
+            [("n[*]", 3, 1), ("users[*]", 4, 2), ("users[*].tags[*]", 6, 3)]);
+        assert_eq!(duplicates[0].to_string(), "n[*]: 1/3 items (33.3%) are duplicates, the most repeated 2 times: 1");
+        assert_eq!(duplicates[1].most, Some((json!({"id": 1, "tags": ["a", "a"]}), 3)));
+        assert!(report.to_string().starts_with("=== Duplicate items ===\n- n[*]: 1/3"));
+        let state = JsonComplexTypeStats::from_state(&stats.to_state()).unwrap();
+        assert_eq!(state.report(), report);
+        assert!(JsonComplexTypeStats::from_json(parse_str(text).unwrap()).report().duplicate_items().is_empty());
+
+        // items with content left out are not compared, excluded values are
+        let depth = StatsOptions { stat_depth: Some(2), ..options.clone() };
+        let report = JsonComplexTypeStats::from_json_with(parse_str(text).unwrap(), &depth).report();
+        assert_eq!(report.duplicate_items().iter().map(|items| items.path.as_str()).collect::<Vec<_>>(), ["n[*]"]);
+        let exclude = StatsOptions { exclude: vec![WildcardPath::parse("users[*].id")], ..options };
+        let report = JsonComplexTypeStats::from_json_with(parse_str(text).unwrap(), &exclude).report();
+        let users = report.duplicate_items().into_iter().find(|items| items.path == "users[*]").cloned().unwrap();
+        assert_eq!((users.items, users.duplicates, users.most), (4, 2, Some((json!({"tags": ["a", "a"]}), 3))));
+    }
+
+    #[test]
+    fn check_sparse_keys() {
+        let report = JsonComplexTypeStats::from_json(json!([
+            {"id": 1, "nick": "a", "meta": {"legacy": true}},
+            {"id": 2, "nick": "b", "meta": {}},
+            {"id": 3, "fax": null},
+            {"id": 4, "nick": "d", "fax": "1"}
+        ])).report();
+        assert_eq!(report.sparse_keys(10), [
+            ("[*].fax".to_string(), 2, 4),
+            ("[*].meta".to_string(), 2, 4),
+            ("[*].meta.legacy".to_string(), 1, 2),
+            ("[*].nick".to_string(), 3, 4)
+        ]);
+        assert_eq!(report.sparse_keys(1).len(), 1);
+        assert!(report.to_string().contains("\nSparse keys, the rarest first:\n- [*].fax: present in 2/4 (50.0%)\n- [*].meta: present in 2/4 (50.0%)\n"));
+        assert!(report.to_html().contains("<tr><td><code>[*].nick</code></td><td>3/4 (75.0%)</td></tr>"));
+    }
+
+    #[test]
+    fn check_key_numbers() {
+        let text = "[{\"price\": 9.5, \"quantity\": 2}, {\"price\": 20.5, \"quantity\": 1, \"id\": 1e400}, [3, 4], [5]]";
+        let mut collector = StatsCollector::new(&StatsOptions::default());
+        assert!(parse_events(Cursor::new(text), |event| collector.event(event)).is_ok());
+        let report = collector.finish().unwrap().report();
+        assert_eq!(report, JsonComplexTypeStats::from_json(parse_str(text).unwrap()).report());
+        let ReportDetails::Array { inner_arrays: Some(arrays), inner_objects: Some(items) } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        // the items of every array are counted, not only those of the first
+        assert_eq!(arrays.numbers.as_ref().map(|numbers| (numbers.count, numbers.sum)), Some((3, 12.0)));
+        let ReportDetails::Object { key_numbers, .. } = &items.details else {
+            panic!("unexpected details {:?}", items.details);
+        };
+        let sums: Vec<_> = key_numbers.iter().map(|(key, numbers)| (key.as_str(), numbers.count, numbers.sum)).collect();
+        assert!(sums.contains(&("price", 2, 30.0)) && sums.contains(&("quantity", 2, 3.0)));
+        assert_eq!(items.non_finite, 1);
+        let text = report.to_string();
+        assert!(text.contains("\n=== Numbers info of key price ===\nEncountered 2 numbers, sum = 30, avg = 15\n"));
+        assert!(report.to_html().contains("<th>numbers of <code>quantity</code></th>"));
+        assert_eq!(JsonValue::from(&report)["inner_objects"]["key_numbers"]["price"]["count"], json!(2));
+    }
+
+    #[test]
+    fn check_key_strings() {
+        let json = json!([
+            {"status": "open", "description": "first bug"},
+            {"status": "closed", "description": "second bug"},
+            {"status": "open", "description": "third bug"},
+            ["x", "y"], ["x"]
+        ]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let ReportDetails::Array { inner_arrays: Some(arrays), inner_objects: Some(items) } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        assert_eq!((arrays.distinct_strings, arrays.top_strings[0].clone()), (2, ("x".to_string(), 2)));
+        assert_eq!(items.distinct_strings, 0);
+        let ReportDetails::Object { key_strings, .. } = &items.details else {
+            panic!("unexpected details {:?}", items.details);
+        };
+        let status = &key_strings.iter().find(|(key, _)| key == "status").unwrap().1;
+        assert_eq!((status.strings.as_slice(), status.top_strings[0].clone()), (&["closed".to_string(), "open".to_string()][..], ("open".to_string(), 2)));
+        assert!(report.to_string().contains("\n=== Strings info of key status ===\nEncountered 2 unique strings, the most frequent: 'open' (2), 'closed' (1)"));
+        assert!(report.to_csv(',').contains("\n[*].description,string,100.0,0.0,,,,,3\n"));
+        assert_eq!(JsonValue::from(&report)["inner_objects"]["key_strings"]["description"]["distinct_strings"], json!(3));
+        assert!(report.to_html().contains("3 unique strings of <code>description</code>"));
+    }
+
+    #[test]
+    fn check_booleans() {
+        let json = json!([
+            {"active": true, "legacy": false},
+            {"active": false, "legacy": false},
+            {"active": true, "legacy": false},
+            {"active": true}
+        ]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        let ReportDetails::Object { booleans, .. } = &items.details else {
+            panic!("unexpected details {:?}", items.details);
+        };
+        let counts = |key: &str| booleans.iter().find(|(other, _)| other == key).map(|(_, counts)| *counts).unwrap();
+        assert_eq!(counts("active"), BooleanCounts { trues: 3, falses: 1 });
+        assert_eq!(counts("active").true_ratio(), 75.0);
+        assert!(counts("legacy").is_constant() && ! counts("active").is_constant());
+        let text = report.to_string();
+        assert!(text.contains("\n- active is false, true (3 true, 1 false, 75.0% true), e.g. true, false, true\n"));
+        assert!(text.contains("\n- legacy is false (0 true, 3 false, always false), e.g. false, false, false\n"));
+        assert_eq!(JsonValue::from(&report)["inner_objects"]["booleans"]["active"], json!({"true": 3, "false": 1, "true_ratio": 75.0}));
+        assert!(report.to_html().contains("<td>false (0 true, 3 false, always false)</td>"));
+    }
+
+    #[test]
+    fn check_inline_samples() {
+        let json = json!([
+            {"cmt_id": "679829c2e1f04b7d9a3c5e8f1b2d4a6c", "n": 1, "tags": ["a"]},
+            {"cmt_id": "short", "n": 2},
+            {"cmt_id": "it's", "n": 3},
+            {"cmt_id": "more", "n": 4}
+        ]);
+        let report = JsonComplexTypeStats::from_json(json).report();
+        let text = report.to_string();
+        assert!(text.contains("\n- cmt_id is string, e.g. '679829c2e1f04b7d…', 'short', 'it's'\n"));
+        assert!(text.contains("\n- n is number (4 integral, 0 fractional), e.g. 1, 2, 3\n"));
+        assert!(report.to_html().contains("<td>'679829c2e1f04b7d…', 'short', 'it's'</td>"));
+    }
+
+    #[test]
+    fn check_tree_view() {
+        let json = json!([{"a": 1, "b": {"c": [true, "x"]}, "d": [[1], [2]]}, {"a": "s"}]);
+        let tree = JsonComplexTypeStats::from_json(json).report().to_tree();
+        let lines: Vec<&str> = tree.lines().collect();
+        assert_eq!(lines[..2], ["(root): array", "└── [*]: object"]);
+        for line in [
+            "    ├── a: number|string",
+            "    │   └── c: array",
+            "    │       └── [*]: boolean|string",
+            "        └── [*]: array",
+            "            └── [*]: number"
+        ] {
+            assert!(lines.contains(&line), "{}", tree);
+        }
+        assert!(lines.iter().any(|line| line.ends_with("── b?: object")) && lines.iter().any(|line| line.ends_with("── d?: array")));
+        assert_eq!(JsonComplexTypeStats::from_json(json!({})).report().to_tree(), "(root): object\n");
+    }
+
+    #[test]
+    fn check_non_finite_samples() {
+        let records = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY].map(|number| json!({"x": JsonValue::NUMBER(number)}));
+        let report = JsonComplexTypeStats::from_array(records.to_vec()).report();
+        let ReportDetails::Array { inner_objects: Some(items), .. } = &report.details else {
+            panic!("unexpected details {:?}", report.details);
+        };
+        assert_eq!(items.samples[0].1.len(), 3);
+        let text = report.to_string();
+        assert!(text.contains("\n- [*].x: ") && text.contains("NaN") && text.contains("-Infinity"));
+        assert!(! text.contains("\n- [*].x: null"));
+        let mut json: Vec<JsonValue> = JsonValue::from(&report)["inner_objects"]["samples"]["[*].x"].as_array().unwrap().clone();
+        json.sort_by_key(ToString::to_string);
+        assert_eq!(json, [json!("-Infinity"), json!("Infinity"), json!("NaN")]);
+        assert!(report.to_html().contains("<code>Infinity</code>"));
     }
 }
@@ -0,0 +1,50 @@
+/// Builds a `JsonValue` from JSON-like syntax. Keys and scalars may be any
+/// Rust expressions convertible with `ToString` and `JsonValue::from`.
+///
+/// ```
+/// use json_stat::json;
+///
+/// let user = json!({"name": "ann", "tags": ["admin", null], "age": 30 + 1});
+/// assert_eq!(user["age"].as_f64(), Some(31.0));
+/// ```
+#[macro_export]
+macro_rules! json {
+    (null) => { $crate::parser::JsonValue::NULL };
+    (true) => { $crate::parser::JsonValue::TRUE };
+    (false) => { $crate::parser::JsonValue::FALSE };
+    ([]) => { $crate::parser::JsonValue::ARRAY(vec![]) };
+    ([ $($items:tt)+ ]) => { $crate::parser::JsonValue::ARRAY($crate::json!(@array [] [] $($items)+)) };
+    ({}) => { $crate::parser::JsonValue::OBJECT($crate::parser::JsonMap::new()) };
+    ({ $($members:tt)+ }) => {
+        $crate::parser::JsonValue::OBJECT($crate::json!(@object [] $($members)+).into_iter().collect())
+    };
+    ($other:expr) => { $crate::parser::JsonValue::from($other) };
+
+    // Items are collected token by token up to the next comma.
+    (@array [$($items:expr,)*] []) => { vec![$($items,)*] };
+    (@array [$($items:expr,)*] [$($item:tt)+]) => { vec![$($items,)* $crate::json!($($item)+)] };
+    (@array [$($items:expr,)*] [$($item:tt)+] , $($rest:tt)*) => {
+        $crate::json!(@array [$($items,)* $crate::json!($($item)+),] [] $($rest)*)
+    };
+    (@array [$($items:expr,)*] [$($item:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::json!(@array [$($items,)*] [$($item)* $next] $($rest)*)
+    };
+
+    // Members are a key token, a colon and the value tokens up to the next comma.
+    (@object [$($members:expr,)*]) => { [$($members,)*] };
+    (@object [$($members:expr,)*] $key:tt : $($rest:tt)+) => {
+        $crate::json!(@member [$($members,)*] $key [] $($rest)+)
+    };
+    (@member [$($members:expr,)*] $key:tt [$($value:tt)+]) => {
+        $crate::json!(@object [$($members,)* $crate::json!(@entry $key $($value)+),])
+    };
+    (@member [$($members:expr,)*] $key:tt [$($value:tt)+] , $($rest:tt)*) => {
+        $crate::json!(@object [$($members,)* $crate::json!(@entry $key $($value)+),] $($rest)*)
+    };
+    (@member [$($members:expr,)*] $key:tt [$($value:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::json!(@member [$($members,)*] $key [$($value)* $next] $($rest)*)
+    };
+    (@entry $key:tt $($value:tt)+) => {
+        ($crate::parser::JsonKey::from(::std::string::ToString::to_string(&$key)), $crate::json!($($value)+))
+    };
+}
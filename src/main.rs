@@ -1,9 +1,141 @@
-use clap::{arg, Command};
+use std::fs;
+use std::io::{self, Write};
 
-use json_stat::parser;
+use clap::{arg, Arg, ArgAction, ArgMatches, Command};
+
+use json_stat::drift;
+use json_stat::flatten;
+use json_stat::jsonpath::JsonPath;
+use json_stat::parser::{self, JsonValue, ParseError};
+use json_stat::patch;
+use json_stat::select::WildcardPath;
+use json_stat::serializer::{Formatter, Minifier, MinifyReport};
 use json_stat::sniffer;
 
 
+fn parser_args() -> Vec<Arg> {
+    vec![
+        arg!(--"max-depth" <DEPTH> "Maximum nesting depth of arrays and objects")
+            .value_parser(clap::value_parser!(usize)),
+        arg!(--"max-string-length" <LENGTH> "Maximum number of characters in a string or key")
+            .value_parser(clap::value_parser!(usize)),
+        arg!(--"max-keys" <COUNT> "Maximum number of keys in a single object")
+            .value_parser(clap::value_parser!(usize)),
+        arg!(--"max-nodes" <COUNT> "Maximum number of values in a document")
+            .value_parser(clap::value_parser!(usize)),
+        arg!(--"duplicate-keys" <POLICY> "How to treat repeated keys in an object")
+            .value_parser(["error", "warn", "keep-first", "keep-last"]),
+        arg!(--"allow-comments" "Treat // and /* */ comments as whitespace"),
+        arg!(--"lenient-commas" "Accept trailing commas in arrays and objects"),
+        arg!(--"allow-nan" "Accept NaN, Infinity and -Infinity literals"),
+        arg!(--"allow-single-quotes" "Accept single-quoted strings, reporting them as warnings"),
+        arg!(--"allow-control-characters" "Accept and count unescaped control characters in strings"),
+        arg!(--strict "Accept only RFC 8259 JSON")
+            .conflicts_with_all(["allow-comments", "lenient-commas", "allow-nan", "allow-single-quotes",
+                "allow-control-characters", "dialect"]),
+        arg!(--lenient "Accept comments, trailing commas, NaN, single-quoted strings and control characters")
+            .conflicts_with("strict"),
+        arg!(--dialect <DIALECT> "Syntax of the input")
+            .value_parser(["json", "json5"]),
+        arg!(--ndjson "Treat every line of the input as a separate JSON value"),
+        arg!(--"json-seq" "Read an RFC 7464 JSON text sequence of 0x1E-separated values")
+            .conflicts_with("ndjson")
+    ]
+}
+
+fn parser_options(matches: &ArgMatches) -> parser::ParserOptions {
+    let mut options = parser::ParserOptions::default();
+    if let Some(max_depth) = matches.get_one::<usize>("max-depth") {
+        options.max_depth = *max_depth;
+    }
+    options.limits = parser::Limits {
+        max_string_length: matches.get_one::<usize>("max-string-length").copied(),
+        max_keys: matches.get_one::<usize>("max-keys").copied(),
+        max_nodes: matches.get_one::<usize>("max-nodes").copied()
+    };
+    options.allow_comments = matches.get_flag("allow-comments");
+    options.allow_trailing_commas = matches.get_flag("lenient-commas");
+    options.allow_nan = matches.get_flag("allow-nan");
+    options.allow_single_quotes = matches.get_flag("allow-single-quotes");
+    options.allow_control_characters = matches.get_flag("allow-control-characters");
+    if matches.get_flag("lenient") {
+        options.set_mode(parser::Mode::Permissive);
+    }
+    if matches.get_one::<String>("dialect").is_some_and(|dialect| dialect == "json5") {
+        options.dialect = parser::Dialect::Json5;
+    }
+    if let Some(policy) = matches.get_one::<String>("duplicate-keys") {
+        options.duplicate_keys = match policy.as_str() {
+            "error" => parser::DuplicateKeys::Error,
+            "warn" => parser::DuplicateKeys::Warn,
+            "keep-first" => parser::DuplicateKeys::KeepFirst,
+            _ => parser::DuplicateKeys::KeepLast
+        };
+    }
+    options
+}
+
+/// Stats settings of the `stat` subcommand, the defaults for the others.
+fn stats_options(matches: &ArgMatches) -> sniffer::StatsOptions {
+    let mut options = sniffer::StatsOptions::default();
+    if let Ok(Some(buckets)) = matches.try_get_one::<usize>("histogram-buckets") {
+        options.histogram_buckets = *buckets;
+    }
+    if let Ok(Some(exact_strings)) = matches.try_get_one::<usize>("exact-strings") {
+        options.exact_strings = *exact_strings;
+    }
+    if let Ok(Some(top_strings)) = matches.try_get_one::<usize>("top-strings") {
+        options.top_strings = *top_strings;
+    }
+    if let Ok(Some(samples)) = matches.try_get_one::<usize>("samples") {
+        options.samples = *samples;
+    }
+    if let Ok(Some(top_k)) = matches.try_get_one::<usize>("top-k") {
+        options.top_k = *top_k;
+    }
+    if let Ok(Some(depth)) = matches.try_get_one::<usize>("stat-depth") {
+        options.stat_depth = Some(*depth);
+    }
+    if let Ok(Some(duplicates)) = matches.try_get_one::<bool>("duplicates") {
+        options.duplicates = *duplicates;
+    }
+    if let Ok(Some(paths)) = matches.try_get_many::<String>("include") {
+        options.include = paths.map(|path| WildcardPath::parse(path)).collect();
+    }
+    if let Ok(Some(paths)) = matches.try_get_many::<String>("exclude") {
+        options.exclude = paths.map(|path| WildcardPath::parse(path)).collect();
+    }
+    if let Ok(Some(scale)) = matches.try_get_one::<String>("histogram-scale") {
+        options.histogram_scale = match scale.as_str() {
+            "log" => sniffer::HistogramScale::Log,
+            _ => sniffer::HistogramScale::Linear
+        };
+    }
+    options
+}
+
+/// Parser arguments for subcommands that read exactly one document per file.
+fn document_args() -> Vec<Arg> {
+    parser_args().into_iter()
+        .filter(|arg| ! matches!(arg.get_id().as_str(), "ndjson" | "json-seq"))
+        .collect()
+}
+
+type Values = Box<dyn Iterator<Item = Result<JsonValue, ParseError>>>;
+
+/// Values of `file`: a single document, or a sequence of them with
+/// `--ndjson` or `--json-seq`.
+fn file_values(options: &parser::ParserOptions, file: &String, matches: &ArgMatches) -> Values {
+    let values = if matches.get_flag("ndjson") {
+        options.ndjson_file(file).map(|values| Box::new(values) as Values)
+    } else if matches.get_flag("json-seq") {
+        options.json_seq_file(file).map(|values| Box::new(values) as Values)
+    } else {
+        return Box::new(options.single_json(file).transpose().into_iter());
+    };
+    values.unwrap_or_else(|error| Box::new(std::iter::once(Err(error))))
+}
+
 fn cli() -> Command {
     Command::new("json-stat")
         .about("Tool for verifying and analyzing JSON")
@@ -14,57 +146,441 @@ fn cli() -> Command {
             Command::new("check")
                 .about("Verifies JSON file(s)")
                 .arg(arg!(<JSON>... "Path to JSON file"))
+                .args(parser_args())
                 .arg_required_else_help(true)
         )
         .subcommand(
             Command::new("stat")
                 .about("Analyzes JSON file(s)")
                 .arg(arg!(<JSON>... "Path to JSON file"))
+                .arg(arg!(--output <FORMAT> "Format of the stats report")
+                    .value_parser(["text", "json", "yaml", "csv", "tsv", "html", "tree"])
+                    .default_value("text"))
+                .arg(arg!(--"output-file" <FILE> "Write the stats report to FILE instead of stdout"))
+                .arg(arg!(--"histogram-buckets" <COUNT> "Maximum number of buckets of number histograms, 0 for none")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("10"))
+                .arg(arg!(--"histogram-scale" <SCALE> "Scale number histograms split evenly")
+                    .value_parser(["linear", "log"])
+                    .default_value("linear"))
+                .arg(arg!(--"exact-strings" <COUNT> "Unique strings kept per place, beyond which they are only estimated")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("10000"))
+                .arg(arg!(--"top-strings" <COUNT> "Most frequent strings reported per place")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("10"))
+                .arg(arg!(--samples <COUNT> "Example values kept per key or array")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("5"))
+                .arg(arg!(--"top-k" <COUNT> "Greatest and least numbers reported per place, 0 for none")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("10"))
+                .arg(arg!(--"stat-depth" <DEPTH> "Only count the values nested deeper than DEPTH, 0 being the documents")
+                    .value_parser(clap::value_parser!(usize)))
+                .arg(arg!(--duplicates "Hash the items of arrays to count the exact duplicates"))
+                .arg(arg!(--include <PATH> "Collect only the values at PATH, e.g. 'users[*].address.*', and the containers leading to them")
+                    .action(ArgAction::Append))
+                .arg(arg!(--exclude <PATH> "Leave out the values at PATH, e.g. '**.debug'")
+                    .action(ArgAction::Append))
+                .arg(arg!(--"save-state" <FILE> "Write the stats collected to FILE for a later run to merge with --load-state"))
+                .arg(arg!(--"load-state" <FILE> "Merge the documents into the stats written to FILE by --save-state"))
+                .arg(arg!(--baseline <REPORT> "Compare the schema with a report written by --output json, failing on drift"))
+                .args(parser_args())
+                .arg_required_else_help(true)
+        )
+        .subcommand(
+            Command::new("schema")
+                .about("Infers a JSON Schema (draft 2020-12) from JSON file(s)")
+                .arg(arg!(<JSON>... "Path to JSON file"))
+                .arg(arg!(--"output-file" <FILE> "Write the schema to FILE instead of stdout"))
+                .args(parser_args())
+                .arg_required_else_help(true)
+        )
+        .subcommand(
+            Command::new("codegen")
+                .about("Generates type declarations matching JSON file(s)")
+                .arg(arg!(<LANG> "Language of the declarations")
+                    .value_parser(["rust", "protobuf", "avro"]))
+                .arg(arg!(<JSON>... "Path to JSON file"))
+                .arg(arg!(--name <NAME> "Name of the type of a whole document")
+                    .default_value("Root"))
+                .arg(arg!(--"output-file" <FILE> "Write the declarations to FILE instead of stdout"))
+                .args(parser_args())
+                .arg_required_else_help(true)
+        )
+        .subcommand(
+            Command::new("query")
+                .about("Selects values from JSON file(s) with a JSONPath expression")
+                .arg(arg!(<PATH> "JSONPath expression, e.g. $.store.book[*].author"))
+                .arg(arg!(<JSON>... "Path to JSON file"))
+                .args(parser_args())
+                .arg_required_else_help(true)
+        )
+        .subcommand(
+            Command::new("minify")
+                .about("Writes JSON file(s) without whitespace, reporting the bytes saved")
+                .arg(arg!(<JSON>... "Path to JSON file"))
+                .arg(arg!(--"shortest-numbers" "Write numbers in exponent notation where that is shorter"))
+                .arg(arg!(--canonical "Write RFC 8785 canonical JSON, e.g. for hashing or signing")
+                    .conflicts_with("shortest-numbers"))
+                .args(parser_args())
+                .arg_required_else_help(true)
+        )
+        .subcommand(
+            Command::new("flatten")
+                .about("Writes every leaf of JSON file(s) on its own line, e.g. json.users[0].name = \"bob\"")
+                .arg(arg!(<JSON>... "Path to JSON file"))
+                .args(parser_args())
+                .arg_required_else_help(true)
+        )
+        .subcommand(
+            Command::new("unflatten")
+                .about("Rebuilds JSON from file(s) written by flatten")
+                .arg(arg!(<FLAT>... "Path to flattened file"))
+                .arg_required_else_help(true)
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Writes the JSON Patch (RFC 6902) turning one JSON file into another")
+                .arg(arg!(<FROM> "Path to the original JSON file"))
+                .arg(arg!(<TO> "Path to the changed JSON file"))
+                .args(document_args())
+                .arg_required_else_help(true)
+        )
+        .subcommand(
+            Command::new("patch")
+                .about("Applies a JSON Patch (RFC 6902) to a JSON file and writes the result")
+                .arg(arg!(<JSON> "Path to JSON file"))
+                .arg(arg!(<PATCH> "Path to the JSON Patch file"))
+                .args(document_args())
                 .arg_required_else_help(true)
         )
 }
 
 
+fn print_error(file: &str, error: &parser::ParseError) {
+    println!("\'{}\' has error at ({}, {}), bytes {}..{}: {}",
+        file, error.row, error.col, error.span.start, error.span.end, error.msg);
+}
+
+/// Changes of the schema of `report` from the one of the JSON report in
+/// `file`, as lines headed by a summary.
+fn schema_drift(file: &String, report: Option<&sniffer::JsonStatsReport>) -> Result<(String, bool), std::io::Error> {
+    let baseline = match parser::ParserOptions::default().single_json(file) {
+        Ok(baseline) => baseline.unwrap_or(JsonValue::NULL),
+        Err(error) => {
+            print_error(file, &error);
+            return Err(std::io::Error::from_raw_os_error(22));
+        }
+    };
+    let changes = drift::schema_drift(&baseline, &report.map_or(JsonValue::NULL, JsonValue::from)).map_err(|error| {
+        eprintln!("\'{}\' has {}", file, error);
+        std::io::Error::from_raw_os_error(22)
+    })?;
+    if changes.is_empty() {
+        return Ok((format!("No schema drift from {}\n", file), false));
+    }
+    let mut text = format!("Schema drift from {}: {} changes\n", file, changes.len());
+    for change in &changes {
+        text.push_str(&format!("{}\n", change));
+    }
+    Ok((text, true))
+}
+
+/// Stats written by `--save-state` to `file`.
+fn load_state(file: &String) -> Result<sniffer::JsonComplexTypeStats, std::io::Error> {
+    let state = match parser::ParserOptions::default().single_json(file) {
+        Ok(state) => state.unwrap_or(JsonValue::NULL),
+        Err(error) => {
+            print_error(file, &error);
+            return Err(std::io::Error::from_raw_os_error(22));
+        }
+    };
+    sniffer::JsonComplexTypeStats::from_state(&state).map_err(|error| {
+        eprintln!("\'{}\' has {}", file, error);
+        std::io::Error::from_raw_os_error(22)
+    })
+}
+
+fn print_warnings(file: &str, out: &mut impl Write) {
+    for warning in parser::take_warnings() {
+        let _ = writeln!(out, "\'{}\' has warning at ({}, {}): {}", file, warning.row, warning.col, warning.msg);
+    }
+    let control_characters = parser::take_control_characters();
+    if control_characters > 0 {
+        let _ = writeln!(out, "\'{}\' has {} unescaped control characters in strings", file, control_characters);
+    }
+}
+
+fn query(matches: &ArgMatches) -> Result<(), std::io::Error> {
+    let expression = matches.get_one::<String>("PATH").map(String::as_str).unwrap_or("$");
+    let path = JsonPath::parse(expression).map_err(|error| {
+        println!("invalid JSONPath \'{}\': {}", expression, error);
+        std::io::Error::from_raw_os_error(22)
+    })?;
+    let options = parser_options(matches);
+    for file in matches.get_many::<String>("JSON").into_iter().flatten() {
+        for value in file_values(&options, file, matches) {
+            let json = value.map_err(|error| {
+                print_error(file, &error);
+                std::io::Error::from_raw_os_error(22)
+            })?;
+            for found in path.select(&json) {
+                println!("{}: {} = {}", file, found.path, found.value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the minified values to stdout and the sizes to stderr, so the
+/// output can be redirected to a file.
+fn minify(matches: &ArgMatches) -> Result<(), std::io::Error> {
+    let minifier = Minifier { shortest_numbers: matches.get_flag("shortest-numbers") };
+    let options = parser_options(matches);
+    for file in matches.get_many::<String>("JSON").into_iter().flatten() {
+        let original_bytes = fs::metadata(file).map_or(0, |metadata| metadata.len() as usize);
+        let mut compact_bytes = 0;
+        for value in file_values(&options, file, matches) {
+            let json = value.map_err(|error| {
+                print_error(file, &error);
+                std::io::Error::from_raw_os_error(22)
+            })?;
+            let text = if matches.get_flag("canonical") {
+                json.to_canonical_string().map_err(|error| {
+                    println!("\'{}\' has no canonical form: {}", file, error);
+                    std::io::Error::from_raw_os_error(22)
+                })?
+            } else {
+                minifier.minify(&json)
+            };
+            compact_bytes += text.len();
+            println!("{}", text);
+        }
+        let report = MinifyReport { original_bytes, compact_bytes };
+        eprintln!("{}: {} -> {} bytes, saved {} ({:.1}%)", file, report.original_bytes,
+            report.compact_bytes, report.saved_bytes(), report.saved_percent());
+    }
+    Ok(())
+}
+
+fn flatten(matches: &ArgMatches) -> Result<(), std::io::Error> {
+    let options = parser_options(matches);
+    for file in matches.get_many::<String>("JSON").into_iter().flatten() {
+        for value in file_values(&options, file, matches) {
+            let json = value.map_err(|error| {
+                print_error(file, &error);
+                std::io::Error::from_raw_os_error(22)
+            })?;
+            for (path, leaf) in json.flatten() {
+                println!("{} = {}", path, leaf);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn unflatten(matches: &ArgMatches) -> Result<(), std::io::Error> {
+    for file in matches.get_many::<String>("FLAT").into_iter().flatten() {
+        let content = fs::read_to_string(file)?;
+        let mut entries = vec![];
+        for (row, line) in content.lines().enumerate().filter(|(_, line)| ! line.trim().is_empty()) {
+            entries.push(flatten::parse_line(line).map_err(|error| {
+                println!("\'{}\' has error at line {}: {}", file, row + 1, error);
+                std::io::Error::from_raw_os_error(22)
+            })?);
+        }
+        let json = flatten::unflatten(entries).map_err(|error| {
+            println!("\'{}\' has error: {}", file, error);
+            std::io::Error::from_raw_os_error(22)
+        })?;
+        println!("{}", Formatter::default().format(&json));
+    }
+    Ok(())
+}
+
+fn read_document(options: &parser::ParserOptions, file: &String) -> Result<JsonValue, std::io::Error> {
+    match options.single_json(file) {
+        Ok(json) => Ok(json.unwrap_or(JsonValue::NULL)),
+        Err(error) => {
+            print_error(file, &error);
+            Err(std::io::Error::from_raw_os_error(22))
+        }
+    }
+}
+
+fn diff(matches: &ArgMatches) -> Result<(), std::io::Error> {
+    let options = parser_options(matches);
+    let argument = |name: &str| matches.get_one::<String>(name).cloned().unwrap_or_default();
+    let from = read_document(&options, &argument("FROM"))?;
+    let to = read_document(&options, &argument("TO"))?;
+    let formatter = Formatter { sort_keys: true, ..Formatter::default() };
+    println!("{}", formatter.format(&patch::patch_to_json(&patch::diff(&from, &to))));
+    Ok(())
+}
+
+fn apply_patch(matches: &ArgMatches) -> Result<(), std::io::Error> {
+    let options = parser_options(matches);
+    let argument = |name: &str| matches.get_one::<String>(name).cloned().unwrap_or_default();
+    let (file, patch_file) = (argument("JSON"), argument("PATCH"));
+    let mut json = read_document(&options, &file)?;
+    let ops = read_document(&options, &patch_file)?;
+    if let Err(error) = patch::parse_patch(&ops).and_then(|ops| json.apply_patch(&ops)) {
+        println!("'{}' cannot be applied to '{}': {}", patch_file, file, error);
+        return Err(std::io::Error::from_raw_os_error(22));
+    }
+    println!("{}", Formatter::default().format(&json));
+    Ok(())
+}
+
+/// The stats in the `--output` format, or the code generated from them;
+/// `None` if there are none and the format has no way to tell.
+fn render_report(output: &str, report: Option<sniffer::JsonStatsReport>, matches: &ArgMatches) -> Option<String> {
+    match (output, report) {
+        ("json", report) => {
+            let report = report.as_ref().map_or(JsonValue::NULL, JsonValue::from);
+            Some(format!("{}\n", Formatter { sort_keys: true, ..Formatter::default() }.format(&report)))
+        },
+        ("yaml", report) => Some(report.as_ref().map_or(JsonValue::NULL, JsonValue::from).to_yaml_string()),
+        ("csv", report) => report.map(|report| report.to_csv(',')),
+        ("tsv", report) => report.map(|report| report.to_csv('\t')),
+        ("html", report) => report.map(|report| report.to_html()),
+        ("tree", report) => report.map(|report| report.to_tree()),
+        ("schema", report) => report.map(|report| {
+            format!("{}\n", Formatter { sort_keys: true, ..Formatter::default() }.format(&report.to_json_schema()))
+        }),
+        ("rust" | "protobuf" | "avro", report) => report.map(|report| {
+            let name = matches.get_one::<String>("name").map_or("Root", String::as_str);
+            let (code, warnings) = match output {
+                "protobuf" => report.to_protobuf(name),
+                "avro" => {
+                    let (schema, warnings) = report.to_avro(name);
+                    (format!("{}\n", Formatter { sort_keys: true, ..Formatter::default() }.format(&schema)), warnings)
+                },
+                _ => (report.to_rust(name), vec![])
+            };
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
+            }
+            code
+        }),
+        (_, report) => report.map(|report| report.to_string())
+    }
+}
+
 fn main() -> Result<(), std::io::Error> {
-    let (should_stat, files) = match cli().get_matches().subcommand() {
-        Some(("check", sub_matches)) => if let Some(argv) = sub_matches.get_many::<String>("JSON") {
-            Ok((false, argv.into_iter().map(String::clone).collect::<Vec<String>>()))
-        } else { Err(std::io::Error::from_raw_os_error(22)) },
-        Some(("stat", sub_matches)) => if let Some(argv) = sub_matches.get_many::<String>("JSON") {
-            Ok((true, argv.into_iter().map(String::clone).collect::<Vec<String>>()))
-        } else { Err(std::io::Error::from_raw_os_error(22)) },
+    let matches = cli().get_matches();
+    let (should_stat, sub_matches) = match matches.subcommand() {
+        Some(("check", sub_matches)) => Ok((false, sub_matches)),
+        Some(("stat" | "schema" | "codegen", sub_matches)) => Ok((true, sub_matches)),
+        Some(("query", sub_matches)) => return query(sub_matches),
+        Some(("minify", sub_matches)) => return minify(sub_matches),
+        Some(("flatten", sub_matches)) => return flatten(sub_matches),
+        Some(("unflatten", sub_matches)) => return unflatten(sub_matches),
+        Some(("diff", sub_matches)) => return diff(sub_matches),
+        Some(("patch", sub_matches)) => return apply_patch(sub_matches),
         _ => Err(std::io::Error::from_raw_os_error(22))
     }?;
+    let files = if let Some(argv) = sub_matches.get_many::<String>("JSON") {
+        Ok(argv.into_iter().map(String::clone).collect::<Vec<String>>())
+    } else { Err(std::io::Error::from_raw_os_error(22)) }?;
+    let options = parser_options(sub_matches);
+    let stats_options = stats_options(sub_matches);
+    let is_sequence = sub_matches.get_flag("ndjson") || sub_matches.get_flag("json-seq");
+    let (output, output_file) = match matches.subcommand_name() {
+        Some("stat") => (sub_matches.get_one::<String>("output").map_or("text", String::as_str), sub_matches.get_one::<String>("output-file")),
+        Some("schema") => ("schema", sub_matches.get_one::<String>("output-file")),
+        Some("codegen") => (sub_matches.get_one::<String>("LANG").map_or("rust", String::as_str), sub_matches.get_one::<String>("output-file")),
+        _ => ("text", None)
+    };
+    let is_stdout_free = output == "text" || output_file.is_some();
 
-    let mut maybe_stats: Option<sniffer::JsonComplexTypeStats> = None;
+    let mut collector = match sub_matches.try_get_one::<String>("load-state") {
+        Ok(Some(state_file)) => sniffer::StatsCollector::merging(load_state(state_file)?, &stats_options),
+        _ => sniffer::StatsCollector::new(&stats_options)
+    };
+    let mut summaries = vec![];
+    let mut has_invalid = false;
     for file in files {
-        let maybe_json = match parser::single_json(&file) {
-            Ok(maybe_value) => Ok(maybe_value),
-            Err(error) => {
-                println!("\'{}\' has error at ({}, {}): {}", file, error.row, error.col, error.msg);
-                Err(std::io::Error::from_raw_os_error(22))
+        if ! should_stat {
+            let errors = if is_sequence {
+                file_values(&options, &file, sub_matches).filter_map(Result::err).collect()
+            } else {
+                options.diagnose_file(&file)
+            };
+            print_warnings(&file, &mut io::stdout());
+            let source = if errors.is_empty() {
+                String::new()
+            } else {
+                parser::decode_file(&file).unwrap_or_default()
+            };
+            for error in &errors {
+                print_error(&file, error);
+                if error.kind != parser::ParseErrorKind::Io {
+                    print!("{}", error.render(&source));
+                }
             }
-        }?;
-
-        if let Some(json) = maybe_json {
-            println!("{} is valid JSON", file);
-            if should_stat {
-                maybe_stats = Some(match maybe_stats {
-                    Some(prev) => prev.merge_stats(json),
-                    None => sniffer::JsonComplexTypeStats::from_json(json)
-                });
+            if errors.is_empty() {
+                println!("{} is valid JSON ({})", file, options.mode());
             }
+            has_invalid |= ! errors.is_empty();
             continue;
         }
-        println!("{} is not valid JSON - SKIP", file);
+
+        let result = if is_sequence {
+            file_values(&options, &file, sub_matches).try_for_each(|value| value.map(|json| collector.value(&json)))
+        } else {
+            // a single document is collected as it is parsed, never held whole
+            options.events_file(&file, |event| collector.event(event))
+        };
+        if let Err(error) = result {
+            print_error(&file, &error);
+            return Err(std::io::Error::from_raw_os_error(22));
+        }
+        summaries.push(collector.summary(&file));
+        // keep stdout for the report when it is meant for other programs
+        if is_stdout_free {
+            print_warnings(&file, &mut io::stdout());
+            println!("{} is valid JSON ({})", file, options.mode());
+        } else {
+            print_warnings(&file, &mut io::stderr());
+            eprintln!("{} is valid JSON ({})", file, options.mode());
+        }
     }
 
     if should_stat {
-        if let Some(stats) = maybe_stats {
-            sniffer::print_complex_stats(stats);
-        } else {
-            println!("No stat information collected - SKIP");
+        let stats = collector.finish();
+        if let (Some(stats), Ok(Some(state_file))) = (&stats, sub_matches.try_get_one::<String>("save-state")) {
+            fs::write(state_file, stats.to_state().to_compact_string())?;
+        }
+        let report = stats.map(|stats| stats.report());
+        let drift = match sub_matches.try_get_one::<String>("baseline") {
+            Ok(Some(baseline)) => Some(schema_drift(baseline, report.as_ref())?),
+            _ => None
+        };
+        let mut report = render_report(output, report, sub_matches);
+        // files are compared before the merged report so that outliers stand out
+        if let Some(text) = report.as_mut().filter(|_| output == "text" && summaries.len() > 1) {
+            text.insert_str(0, &format!("{}\n", sniffer::summaries_table(&summaries)));
+        }
+        match (report, output_file) {
+            (Some(text), Some(output_file)) => fs::write(output_file, text)?,
+            (Some(text), None) => print!("{}", text),
+            (None, _) if is_stdout_free => println!("No stat information collected - SKIP"),
+            (None, _) => eprintln!("No stat information collected - SKIP")
         }
+        if let Some((text, has_drift)) = drift {
+            if is_stdout_free {
+                print!("{}", text);
+            } else {
+                eprint!("{}", text);
+            }
+            // drift fails the run so that CI jobs can gate on it
+            has_invalid |= has_drift;
+        }
+    }
+    if has_invalid {
+        return Err(std::io::Error::from_raw_os_error(22));
     }
     Ok(())
 }
@@ -1,54 +1,662 @@
-use std::{cell::RefCell, collections::HashMap, fs};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    error::Error,
+    fmt,
+    fs,
+    io::{BufRead, BufReader, Cursor, Read},
+    ops::Range,
+    sync::Arc
+};
 
+use crate::scan;
 
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedChar,
+    UnexpectedEof,
+    InvalidEscape,
+    DepthExceeded,
+    DuplicateKey,
+    StringTooLong,
+    TooManyKeys,
+    TooManyNodes,
+    /// Option of `ParserOptions` not implemented by the parser used.
+    Unsupported,
+    Io
+}
+
+#[derive(Clone, Debug)]
 pub struct ParseError {
+    pub kind: ParseErrorKind,
     pub row: usize,
     pub col: usize,
+    /// Byte offset of the error from the beginning of the input, counted
+    /// in UTF-8 after a BOM is skipped and UTF-16/UTF-32 is transcoded.
+    pub offset: usize,
+    /// Byte range of the offending token.
+    pub span: Range<usize>,
+    /// Tokens the grammar would have accepted at the error position.
+    pub expected: Vec<String>,
     pub msg: String
 }
 
-#[derive(Debug, PartialEq)]
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ParseErrorKind::UnexpectedChar => "unexpected character",
+            ParseErrorKind::UnexpectedEof => "unexpected end of input",
+            ParseErrorKind::InvalidEscape => "invalid escape sequence",
+            ParseErrorKind::DepthExceeded => "nesting depth exceeded",
+            ParseErrorKind::DuplicateKey => "duplicate key",
+            ParseErrorKind::StringTooLong => "string length limit exceeded",
+            ParseErrorKind::TooManyKeys => "object key limit exceeded",
+            ParseErrorKind::TooManyNodes => "value count limit exceeded",
+            ParseErrorKind::Unsupported => "unsupported option",
+            ParseErrorKind::Io => "input/output error"
+        })
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at ({}, {})", self.msg, self.row, self.col)
+    }
+}
+
+impl Error for ParseError {}
+
+impl ParseError {
+    /// Renders the offending line of `source` with a caret marker under the
+    /// failing column, similar to the snippets in rustc diagnostics.
+    pub fn render(&self, source: &str) -> String {
+        let line = source.split('\n').nth(self.row).unwrap_or("").trim_end_matches('\r');
+        let line_number = (self.row + 1).to_string();
+        let gutter = " ".repeat(line_number.len());
+        let indent: String = line.chars().take(self.col)
+            .map(|symbol| if symbol == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = source.get(self.span.clone())
+            .map_or(0, |token| token.split('\n').next().unwrap_or("").chars().count())
+            .max(1);
+        format!("{} |\n{} | {}\n{} | {}{}\n",
+            gutter, line_number, line, gutter, indent, "^".repeat(width))
+    }
+}
+
+/// Suspicious but accepted construct noticed while parsing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseWarning {
+    pub row: usize,
+    pub col: usize,
+    pub msg: String
+}
+
+/// What to do when an object contains the same key more than once.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DuplicateKeys {
+    Error,
+    Warn,
+    KeepFirst,
+    KeepLast
+}
+
+/// Syntax accepted by the parser.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    /// Strict RFC 8259 JSON.
+    Json,
+    /// JSON5: comments, unquoted keys, single quotes, trailing commas, hex
+    /// numbers, leading `+`, bare decimal points, `Infinity` and `NaN`.
+    Json5
+}
+
+/// How much the parser deviates from RFC 8259, see `ParserOptions::set_mode`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Only RFC 8259 JSON is accepted.
+    Strict,
+    /// Comments, trailing commas, `NaN` and `Infinity`, single-quoted
+    /// strings and unescaped control characters are accepted as well.
+    Permissive
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Strict => "strict",
+            Mode::Permissive => "permissive"
+        })
+    }
+}
+
+/// Magnitude above which not every integer is exact in `f64`, 2^53.
+pub const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Default number of arrays and objects allowed to be open at once.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Bounds on the size of the input guarding against resource exhaustion by
+/// untrusted JSON; `None` means unlimited. Checked by the parsers of
+/// `ParserOptions` and by `PushParser`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    /// Maximum number of characters between the quotes of a string or key,
+    /// counted as written, so an escape sequence counts in full.
+    pub max_string_length: Option<usize>,
+    /// Maximum number of distinct keys in a single object.
+    pub max_keys: Option<usize>,
+    /// Maximum number of values in a document, containers included.
+    pub max_nodes: Option<usize>
+}
+
+#[derive(Clone, Debug)]
+pub struct ParserOptions {
+    /// Maximum number of arrays and objects allowed to be open at once.
+    pub max_depth: usize,
+    /// Replace unpaired UTF-16 surrogates in `\u` escapes with U+FFFD
+    /// instead of rejecting them.
+    pub allow_lone_surrogates: bool,
+    pub duplicate_keys: DuplicateKeys,
+    /// Keep numbers as their exact source text in `JsonValue::RAWNUMBER`
+    /// instead of converting them to `f64`.
+    pub preserve_number_literals: bool,
+    /// Skip `//` line and `/* */` block comments like whitespace (JSONC).
+    pub allow_comments: bool,
+    /// Accept a comma after the last member of an array or object.
+    pub allow_trailing_commas: bool,
+    /// Accept the `NaN`, `Infinity` and `-Infinity` literals written by
+    /// e.g. Python's `json.dumps(allow_nan=True)`.
+    pub allow_nan: bool,
+    /// Accept single-quoted strings and keys, reporting each one as a
+    /// warning.
+    pub allow_single_quotes: bool,
+    /// Accept unescaped control characters (U+0000 to U+001F) in strings,
+    /// counting them, see `take_control_characters`.
+    pub allow_control_characters: bool,
+    pub dialect: Dialect,
+    pub limits: Limits,
+    /// Keep arrays and objects nested this deep, the root being at depth 0,
+    /// unparsed in `JsonValue::RAW`; only their brackets are matched, so
+    /// brackets inside comments are not recognized, and neither `Limits`
+    /// nor the control character check apply inside them. Ignored when
+    /// streaming events; `PushParser`, `parse_borrowed` and `parse_document`
+    /// reject it.
+    pub raw_depth: Option<usize>
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            allow_lone_surrogates: false,
+            duplicate_keys: DuplicateKeys::KeepLast,
+            preserve_number_literals: false,
+            allow_comments: false,
+            allow_trailing_commas: false,
+            allow_nan: false,
+            allow_single_quotes: false,
+            allow_control_characters: false,
+            dialect: Dialect::Json,
+            limits: Limits::default(),
+            raw_depth: None
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Enables all lenient extensions for `Mode::Permissive` and disables
+    /// them for `Mode::Strict`.
+    pub fn set_mode(&mut self, mode: Mode) {
+        let is_permissive = mode == Mode::Permissive;
+        self.allow_comments = is_permissive;
+        self.allow_trailing_commas = is_permissive;
+        self.allow_nan = is_permissive;
+        self.allow_single_quotes = is_permissive;
+        self.allow_control_characters = is_permissive;
+    }
+
+    /// `Mode::Strict` if no extension of RFC 8259 is enabled, either one by
+    /// one or through the JSON5 dialect.
+    pub fn mode(&self) -> Mode {
+        let is_strict = ! (self.allow_comments || self.allow_trailing_commas || self.allow_nan
+            || self.allow_single_quotes || self.allow_control_characters || self.dialect == Dialect::Json5);
+        if is_strict { Mode::Strict } else { Mode::Permissive }
+    }
+
+    /// Error for the first extension of RFC 8259 or `raw_depth` enabled in
+    /// these options that `parser` does not implement; of them it may
+    /// support comments.
+    pub(crate) fn unsupported_error(&self, parser: &str, supports_comments: bool) -> Option<ParseError> {
+        let extensions = [
+            ("allow_comments", self.allow_comments && ! supports_comments),
+            ("allow_trailing_commas", self.allow_trailing_commas),
+            ("allow_nan", self.allow_nan),
+            ("allow_single_quotes", self.allow_single_quotes),
+            ("the JSON5 dialect", self.dialect == Dialect::Json5),
+            ("raw_depth", self.raw_depth.is_some())
+        ];
+        let (name, _) = extensions.into_iter().find(|(_, is_enabled)| *is_enabled)?;
+        Some(unsupported_option(name, parser))
+    }
+}
+
+/// Error for the option `name` not being implemented by `parser`.
+pub(crate) fn unsupported_option(name: &str, parser: &str) -> ParseError {
+    ParseError {
+        kind: ParseErrorKind::Unsupported,
+        row: 0,
+        col: 0,
+        offset: 0,
+        span: 0..0,
+        expected: vec![],
+        msg: format!("{} is not supported by the {}", name, parser)
+    }
+}
+
+/// Map backing JSON objects: keeps keys in document order when the
+/// `preserve_order` feature is enabled.
+#[cfg(feature = "preserve_order")]
+pub type JsonMap<V> = indexmap::IndexMap<JsonKey, V>;
+#[cfg(not(feature = "preserve_order"))]
+pub type JsonMap<V> = std::collections::HashMap<JsonKey, V>;
+
+/// Key of an object member. Parsers share one allocation between equal
+/// keys, see `intern_key`.
+pub type JsonKey = Arc<str>;
+
+/// Equality and hashing treat numbers by value, with `0.0` equal to
+/// `-0.0` and every `NaN` equal to each other, see `impl Eq for JsonValue`.
+#[derive(Clone, Debug)]
 pub enum JsonValue {
     STRING(String),
     NUMBER(f64),
-    OBJECT(HashMap<String, Box<JsonValue>>),
+    /// Number kept as written in the source, see
+    /// `ParserOptions::preserve_number_literals`.
+    RAWNUMBER(String),
+    OBJECT(JsonMap<JsonValue>),
     ARRAY(Vec<JsonValue>),
     TRUE,
     FALSE,
     NULL,
-    KEYVALUE((String, Box<JsonValue>))
+    /// Array or object left unparsed, with its source text, see
+    /// `ParserOptions::raw_depth` and `ParserOptions::parse_raw`.
+    /// Serializers write the text as it was read.
+    RAW(Span, String)
+}
+
+/// Range of byte offsets in the parsed text.
+pub type Span = Range<usize>;
+
+/// Parsing event reported by `ParserOptions::parse_events` in document
+/// order; borrowed strings are only valid during the callback.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JsonEvent<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(&'a str),
+    String(&'a str),
+    Number(f64),
+    /// Number literal, see `ParserOptions::preserve_number_literals`.
+    RawNumber(&'a str),
+    Bool(bool),
+    Null
+}
+
+/// Receiver of parsing events. Without a handler values are collected into
+/// a `JsonValue` tree instead.
+struct EventSink<'h> {
+    handler: Option<&'h mut dyn FnMut(JsonEvent<'_>)>
+}
+
+impl EventSink<'_> {
+    fn is_streaming(&self) -> bool {
+        self.handler.is_some()
+    }
+
+    fn emit(&mut self, event: JsonEvent<'_>) {
+        if let Some(handler) = self.handler.as_mut() {
+            handler(event);
+        }
+    }
+
+    fn emit_scalar(&mut self, value: &JsonValue) {
+        match value {
+            JsonValue::STRING(line) => self.emit(JsonEvent::String(line)),
+            JsonValue::NUMBER(number) => self.emit(JsonEvent::Number(*number)),
+            JsonValue::RAWNUMBER(literal) => self.emit(JsonEvent::RawNumber(literal)),
+            JsonValue::TRUE => self.emit(JsonEvent::Bool(true)),
+            JsonValue::FALSE => self.emit(JsonEvent::Bool(false)),
+            JsonValue::NULL => self.emit(JsonEvent::Null),
+            JsonValue::ARRAY(_) => {
+                self.emit(JsonEvent::StartArray);
+                self.emit(JsonEvent::EndArray);
+            },
+            JsonValue::OBJECT(_) => {
+                self.emit(JsonEvent::StartObject);
+                self.emit(JsonEvent::EndObject);
+            },
+            JsonValue::RAW(..) => {}
+        }
+    }
+}
+
+/// Unicode encoding of the input, see `detect_encoding`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le | Encoding::Utf16Be => "UTF-16",
+            Encoding::Utf32Le | Encoding::Utf32Be => "UTF-32"
+        }
+    }
+}
+
+/// Detects the encoding from the byte order mark or, without one, from the
+/// pattern of zero bytes in the first four octets (RFC 4627, section 3),
+/// as the first two characters of a JSON text are always ASCII. Returns the
+/// encoding and the length of the BOM to skip.
+fn detect_encoding(head: &[u8]) -> (Encoding, usize) {
+    match head {
+        [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+        [0x00, 0x00, 0xFE, 0xFF, ..] => (Encoding::Utf32Be, 4),
+        [0xFF, 0xFE, 0x00, 0x00, ..] => (Encoding::Utf32Le, 4),
+        [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+        [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+        [0, 0, 0, _, ..] => (Encoding::Utf32Be, 0),
+        [_, 0, 0, 0, ..] => (Encoding::Utf32Le, 0),
+        [0, _, ..] => (Encoding::Utf16Be, 0),
+        [_, 0, ..] => (Encoding::Utf16Le, 0),
+        _ => (Encoding::Utf8, 0)
+    }
+}
+
+/// Lazily decoded characters of the input, refilled chunk by chunk from
+/// the underlying reader so the whole document never has to be in memory.
+/// UTF-16 and UTF-32 inputs are transcoded and a leading BOM is skipped.
+struct CharStream {
+    reader: Box<dyn BufRead>,
+    encoding: Option<Encoding>,
+    /// Decoded text of the current chunk and the byte position of the next
+    /// character in it.
+    text: String,
+    position: usize,
+    incomplete: Vec<u8>,
+    read_error: Option<String>
+}
+
+impl CharStream {
+    fn new(reader: Box<dyn BufRead>) -> Self {
+        Self {
+            reader,
+            encoding: None,
+            text: String::new(),
+            position: 0,
+            incomplete: vec![],
+            read_error: None
+        }
+    }
+
+    fn fill(&mut self) {
+        if self.position < self.text.len() {
+            return;
+        }
+        self.text.clear();
+        self.position = 0;
+        while self.text.is_empty() && self.read_error.is_none() {
+            let chunk = match self.reader.fill_buf() {
+                Ok(chunk) => chunk.to_vec(),
+                Err(e) => {
+                    self.read_error = Some(e.to_string());
+                    return;
+                }
+            };
+            let is_eof = chunk.is_empty();
+            self.reader.consume(chunk.len());
+            self.incomplete.extend(chunk);
+
+            let encoding = match self.encoding {
+                Some(encoding) => encoding,
+                None if self.incomplete.len() < 4 && ! is_eof => continue,
+                None => {
+                    let (encoding, bom) = detect_encoding(&self.incomplete);
+                    self.incomplete.drain(..bom);
+                    self.encoding = Some(encoding);
+                    encoding
+                }
+            };
+            let is_valid = match encoding {
+                Encoding::Utf8 => self.decode_utf8(),
+                Encoding::Utf16Le => self.decode_utf16(u16::from_le_bytes, is_eof),
+                Encoding::Utf16Be => self.decode_utf16(u16::from_be_bytes, is_eof),
+                Encoding::Utf32Le => self.decode_utf32(u32::from_le_bytes),
+                Encoding::Utf32Be => self.decode_utf32(u32::from_be_bytes)
+            };
+            if ! is_valid || (is_eof && ! self.incomplete.is_empty()) {
+                self.read_error = Some(format!("stream did not contain valid {}", encoding.name()));
+            }
+            if is_eof {
+                return;
+            }
+        }
+    }
+
+    /// Moves the decodable prefix of `incomplete` into `text`, keeping a
+    /// truncated trailing sequence for the next chunk.
+    fn decode_utf8(&mut self) -> bool {
+        let valid = match std::str::from_utf8(&self.incomplete) {
+            Ok(decoded) => decoded.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return false
+        };
+        let tail = self.incomplete.split_off(valid);
+        if let Ok(decoded) = std::str::from_utf8(&self.incomplete) {
+            self.text.push_str(decoded);
+        }
+        self.incomplete = tail;
+        true
+    }
+
+    fn decode_utf16(&mut self, unit: fn([u8; 2]) -> u16, is_eof: bool) -> bool {
+        let mut units: Vec<u16> = self.incomplete.chunks_exact(2)
+            .map(|pair| unit([pair[0], pair[1]]))
+            .collect();
+        if ! is_eof && units.last().is_some_and(|last| (0xD800..0xDC00).contains(last)) {
+            units.pop();
+        }
+        self.incomplete.drain(..units.len() * 2);
+        for decoded in char::decode_utf16(units) {
+            match decoded {
+                Ok(symbol) => self.text.push(symbol),
+                Err(_) => return false
+            }
+        }
+        true
+    }
+
+    fn decode_utf32(&mut self, unit: fn([u8; 4]) -> u32) -> bool {
+        let units: Vec<u32> = self.incomplete.chunks_exact(4)
+            .map(|quad| unit([quad[0], quad[1], quad[2], quad[3]]))
+            .collect();
+        self.incomplete.drain(..units.len() * 4);
+        for code in units {
+            match char::from_u32(code) {
+                Some(symbol) => self.text.push(symbol),
+                None => return false
+            }
+        }
+        true
+    }
+
+    fn peek(&mut self) -> char {
+        self.fill();
+        self.text[self.position..].chars().next().unwrap_or('\0')
+    }
+
+    fn advance(&mut self) {
+        if let Some(symbol) = self.text[self.position..].chars().next() {
+            self.position += symbol.len_utf8();
+        }
+    }
+
+    /// Consumes the run of buffered text whose length in bytes `scan`
+    /// reports, stopping at a character boundary.
+    fn take_run(&mut self, scan: impl Fn(&[u8]) -> usize) -> &str {
+        self.fill();
+        let start = self.position;
+        self.position += scan(&self.text.as_bytes()[start..]);
+        &self.text[start..self.position]
+    }
+}
+
+/// Array or object whose members are still being parsed; an object also
+/// keeps the key its next value belongs to, once that key has been read.
+enum Container {
+    Array(Vec<JsonValue>),
+    Object(JsonMap<JsonValue>, Option<String>)
+}
+
+impl Container {
+    fn close(self) -> JsonValue {
+        match self {
+            Container::Array(arr) => JsonValue::ARRAY(arr),
+            Container::Object(obj, _) => JsonValue::OBJECT(obj)
+        }
+    }
 }
 
 thread_local! {
-    static COLUMN: RefCell<usize> = RefCell::new(0);
-    static ROW: RefCell<usize> = RefCell::new(0);
-    static CHAR_STREAM: RefCell<&'static str> = RefCell::new("");
-    static RAW_CHARS: RefCell<Vec<char>> = RefCell::new(vec![]);
+    static COLUMN: RefCell<usize> = const { RefCell::new(0) };
+    static ROW: RefCell<usize> = const { RefCell::new(0) };
+    static OFFSET: RefCell<usize> = const { RefCell::new(0) };
+    static CHAR_STREAM: RefCell<Option<CharStream>> = const { RefCell::new(None) };
+    static RAW_CHARS: RefCell<Vec<char>> = const { RefCell::new(vec![]) };
+    static FAILURE: RefCell<Option<ParseError>> = const { RefCell::new(None) };
+    static WARNINGS: RefCell<Vec<ParseWarning>> = const { RefCell::new(vec![]) };
+    static CONTROL_CHARACTERS: RefCell<usize> = const { RefCell::new(0) };
+    static RECOVERING: RefCell<bool> = const { RefCell::new(false) };
+    /// Quote of the string in which the last error occurred, so that
+    /// recovery resumes after its end.
+    static UNCLOSED_QUOTE: RefCell<Option<char>> = const { RefCell::new(None) };
+    static ERRORS: RefCell<Vec<ParseError>> = const { RefCell::new(vec![]) };
+    static EXPECTED: RefCell<(usize, Vec<String>)> = const { RefCell::new((0, vec![])) };
+    static OPTIONS: RefCell<ParserOptions> = RefCell::new(ParserOptions::default());
+    static KEYS: RefCell<HashSet<JsonKey>> = RefCell::new(HashSet::new());
+}
+
+/// Keys kept by the interner, which is emptied once full so that documents
+/// with ever new keys do not grow it without bound.
+const INTERNED_KEYS: usize = 4096;
+
+/// Shared copy of `key`, allocated only the first time the key is seen by
+/// the parsers of this thread.
+pub(crate) fn intern_key(key: &str) -> JsonKey {
+    KEYS.with(|rc| {
+        let mut keys = rc.borrow_mut();
+        if let Some(interned) = keys.get(key) {
+            return interned.clone();
+        }
+        if keys.len() >= INTERNED_KEYS {
+            keys.clear();
+        }
+        let interned = JsonKey::from(key);
+        keys.insert(interned.clone());
+        interned
+    })
 }
 
 fn get_next_char() -> char {
-    let next = CHAR_STREAM.with(|rc| rc.borrow().chars().next());
-    if let Some(symbol) = next {
-        return symbol;
+    CHAR_STREAM.with(|rc| match rc.borrow_mut().as_mut() {
+        Some(stream) => stream.peek(),
+        None => '\0'
+    })
+}
+
+fn is_json5() -> bool {
+    OPTIONS.with(|rc| rc.borrow().dialect == Dialect::Json5)
+}
+
+fn allows_single_quotes() -> bool {
+    OPTIONS.with(|rc| rc.borrow().allow_single_quotes) || is_json5()
+}
+
+fn get_read_error() -> Option<String> {
+    CHAR_STREAM.with(|rc| rc.borrow().as_ref()
+        .and_then(|stream| stream.read_error.clone()))
+}
+
+fn describe_char(symbol: char) -> String {
+    if symbol.is_ascii_digit() {
+        "digit".to_string()
+    } else {
+        format!("{:?}", symbol)
     }
-    '\0'
+}
+
+/// Remembers that `token` would have been accepted at the current offset.
+fn expect(token: String) {
+    let offset = OFFSET.with(|rc| *rc.borrow());
+    EXPECTED.with(|rc| {
+        let (at, tokens) = &mut *rc.borrow_mut();
+        if *at != offset {
+            *at = offset;
+            tokens.clear();
+        }
+        if ! tokens.contains(&token) {
+            tokens.push(token);
+        }
+    });
+}
+
+/// Replaces whatever was expected at the current offset with `token`.
+fn expect_only(token: &str) {
+    EXPECTED.with(|rc| rc.borrow_mut().1.clear());
+    expect(token.to_string());
+}
+
+fn current_expected() -> Vec<String> {
+    let offset = OFFSET.with(|rc| *rc.borrow());
+    EXPECTED.with(|rc| match &*rc.borrow() {
+        (at, tokens) if *at == offset => tokens.clone(),
+        _ => vec![]
+    })
 }
 
 fn accept_common(jval: JsonValue, expected: char, should_ignore: bool) -> Result<JsonValue, JsonValue> {
     let actual = get_next_char();
+    if actual != expected && ! (should_ignore && expected.is_ascii_whitespace()) {
+        expect(describe_char(expected));
+    }
     if actual == '\0' {
         return Err(jval);
     }
 
     if actual == expected {
         COLUMN.with(|rc| { *rc.borrow_mut() += 1; });
+        OFFSET.with(|rc| { *rc.borrow_mut() += actual.len_utf8(); });
         if ! should_ignore {
             RAW_CHARS.with(|rc| { rc.borrow_mut().push(expected); });
         } else if actual == '\n' {
             ROW.with(|rc| { *rc.borrow_mut() += 1; });
             COLUMN.with(|rc| { *rc.borrow_mut() = 0; })
         }
-        CHAR_STREAM.with(|rc| { rc.replace_with(|&mut old| &old[1..]); });
+        CHAR_STREAM.with(|rc| {
+            if let Some(stream) = rc.borrow_mut().as_mut() {
+                stream.advance();
+            }
+        });
 
         Ok(jval)
     } else {
@@ -85,41 +693,109 @@ fn accept_ignoring_case_cb(expected: char) -> impl FnOnce(JsonValue) -> Result<J
     move |jval: JsonValue| accept_ignoring_case(jval, expected)
 }
 
+fn accept_word(jval: JsonValue, word: &str) -> Result<JsonValue, JsonValue> {
+    word.chars().try_fold(jval, accept)
+}
+
+fn accept_line_comment(jval: JsonValue) -> Result<JsonValue, JsonValue> {
+    let mut jval = accept_delimiter(jval, '/')?;
+    loop {
+        let symbol = get_next_char();
+        if symbol == '\n' || symbol == '\0' {
+            return Ok(jval);
+        }
+        jval = accept_delimiter(jval, symbol)?;
+    }
+}
+
+fn accept_block_comment(jval: JsonValue) -> Result<JsonValue, JsonValue> {
+    let mut jval = accept_delimiter(jval, '*')?;
+    loop {
+        match get_next_char() {
+            '\0' => return Err(jval),
+            '*' => {
+                jval = accept_delimiter(jval, '*')?;
+                if get_next_char() == '/' {
+                    return accept_delimiter(jval, '/');
+                }
+            },
+            symbol => jval = accept_delimiter(jval, symbol)?
+        }
+    }
+}
+
+/// Skips a `//` or `/* */` comment when `ParserOptions::allow_comments` is
+/// set. A malformed comment is recorded as a failure since whitespace
+/// itself never fails.
+fn accept_comment(jval: JsonValue) -> Result<JsonValue, JsonValue> {
+    if get_next_char() != '/' || ! (OPTIONS.with(|rc| rc.borrow().allow_comments) || is_json5()) {
+        return Err(jval);
+    }
+    let start = current_position();
+    accept_delimiter(jval, '/')
+        .and_then(|r_jval| if get_next_char() == '*' {
+            accept_block_comment(r_jval).inspect_err(|_| fail_at(start, current_position().offset,
+                ParseErrorKind::UnexpectedEof, "unterminated block comment".to_string()))
+        } else {
+            accept_line_comment(r_jval).inspect_err(|_| fail_here())
+        })
+}
+
 fn accept_whitespaces(jval: JsonValue) -> Result<JsonValue, JsonValue> {
     accept_delimiter(jval, ' ')
         .or_else(accept_delimiter_cb('\n'))
         .or_else(accept_delimiter_cb('\r'))
         .or_else(accept_delimiter_cb('\t'))
+        .or_else(accept_comment)
 }
 
-pub fn accept_whitespace(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept_whitespaces(jval)
-        .and_then(accept_whitespace)
-        .or_else(just_accept)
+/// Skips a run of whitespace at once instead of char by char.
+fn skip_whitespace_run() {
+    CHAR_STREAM.with(|rc| if let Some(stream) = rc.borrow_mut().as_mut() {
+        let run = stream.take_run(scan::whitespace_len);
+        OFFSET.with(|rc| { *rc.borrow_mut() += run.len(); });
+        match run.rfind('\n') {
+            Some(newline) => {
+                ROW.with(|rc| { *rc.borrow_mut() += run.matches('\n').count(); });
+                COLUMN.with(|rc| { *rc.borrow_mut() = run[newline + 1..].len(); });
+            },
+            None => COLUMN.with(|rc| { *rc.borrow_mut() += run.len(); })
+        }
+    });
+}
+
+pub fn accept_whitespace(mut jval: JsonValue) -> Result<JsonValue, JsonValue> {
+    loop {
+        skip_whitespace_run();
+        match accept_whitespaces(jval) {
+            Ok(r_jval) => jval = r_jval,
+            Err(r_jval) => return Ok(r_jval)
+        }
+    }
 }
 
 pub fn accept_true(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept(jval, 't')
-        .and_then(accept_cb('r'))
-        .and_then(accept_cb('u'))
-        .and_then(accept_cb('e'))
+    accept_delimiter(jval, 't')
+        .and_then(accept_delimiter_cb('r'))
+        .and_then(accept_delimiter_cb('u'))
+        .and_then(accept_delimiter_cb('e'))
         .and(Ok(JsonValue::TRUE))
 }
 
 pub fn accept_false(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept(jval, 'f')
-        .and_then(accept_cb('a'))
-        .and_then(accept_cb('l'))
-        .and_then(accept_cb('s'))
-        .and_then(accept_cb('e'))
+    accept_delimiter(jval, 'f')
+        .and_then(accept_delimiter_cb('a'))
+        .and_then(accept_delimiter_cb('l'))
+        .and_then(accept_delimiter_cb('s'))
+        .and_then(accept_delimiter_cb('e'))
         .and(Ok(JsonValue::FALSE))
 }
 
 pub fn accept_null(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept(jval, 'n')
-        .and_then(accept_cb('u'))
-        .and_then(accept_cb('l'))
-        .and_then(accept_cb('l'))
+    accept_delimiter(jval, 'n')
+        .and_then(accept_delimiter_cb('u'))
+        .and_then(accept_delimiter_cb('l'))
+        .and_then(accept_delimiter_cb('l'))
         .and(Ok(JsonValue::NULL))
 }
 
@@ -140,100 +816,134 @@ fn accept_digit(jval: JsonValue) -> Result<JsonValue, JsonValue> {
 }
 
 fn accept_digits(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    let digit = accept_digit(jval);
-    if digit.is_err() {
-        return digit;
+    let mut jval = accept_digit(jval)?;
+    loop {
+        match accept_digit(jval) {
+            Ok(r_jval) => jval = r_jval,
+            Err(r_jval) => return Ok(r_jval)
+        }
     }
-    digit.and_then(accept_digits)
-        .or_else(just_accept)
 }
 
 fn accept_exponent(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept_ignoring_case(jval, 'e')
-        .and_then(|r_jval| accept(r_jval, '+')
-            .or_else(accept_cb('-'))
-            .or_else(just_accept))
+    let jval = match accept_ignoring_case(jval, 'e') {
+        Ok(r_jval) => r_jval,
+        Err(r_jval) => return Ok(r_jval)
+    };
+    accept(jval, '+')
+        .or_else(accept_cb('-'))
+        .or_else(just_accept)
         .and_then(accept_digits)
 }
 
 fn accept_fraction(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept(jval, '.')
-        .and_then(accept_digits)
+    let jval = match accept(jval, '.') {
+        Ok(r_jval) => r_jval,
+        Err(r_jval) => return Ok(r_jval)
+    };
+    if is_json5() && ! get_next_char().is_ascii_digit() {
+        return Ok(jval);
+    }
+    accept_digits(jval)
 }
 
-fn accept_integer(jval: JsonValue) -> Result<JsonValue, JsonValue> {
+fn accept_sign(jval: JsonValue) -> Result<JsonValue, JsonValue> {
     accept(jval, '-')
+        .or_else(|r_jval| if is_json5() { accept(r_jval, '+') } else { Err(r_jval) })
         .or_else(just_accept)
-        .and_then(accept_cb('0'))
+}
+
+fn accept_integer(jval: JsonValue) -> Result<JsonValue, JsonValue> {
+    accept(jval, '0')
         .or_else(|r_jval| accept_nonzero(r_jval)
-            .and_then(accept_digits)
-            .or_else(just_accept))
+            .and_then(|r_jval| accept_digits(r_jval).or_else(just_accept)))
+}
+
+fn accept_hex_digits(jval: JsonValue) -> Result<JsonValue, JsonValue> {
+    let mut jval = accept_ignoring_case(jval, 'x').and_then(accept_hex)?;
+    loop {
+        if ! get_next_char().is_ascii_hexdigit() {
+            return Ok(jval);
+        }
+        jval = accept_hex(jval)?;
+    }
+}
+
+/// Converts a number literal accepted by the parser, including JSON5 hex
+/// literals, into its value.
+pub fn number_value(literal: &str) -> Option<f64> {
+    let (negative, unsigned) = match literal.as_bytes().first() {
+        Some(b'-') => (true, &literal[1..]),
+        Some(b'+') => (false, &literal[1..]),
+        _ => (false, literal)
+    };
+    let Some(digits) = unsigned.strip_prefix("0x").or_else(|| unsigned.strip_prefix("0X")) else {
+        return literal.parse::<f64>().ok();
+    };
+    let magnitude = digits.chars()
+        .try_fold(0f64, |value, symbol| symbol.to_digit(16).map(|digit| value * 16f64 + digit as f64))?;
+    Some(if negative { -magnitude } else { magnitude })
 }
 
 pub fn accept_number(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    let _jval_integer = accept_integer(JsonValue::NULL)?;
-    let _jval_number = accept_fraction(JsonValue::NULL)
-        .or_else(just_accept)
-        .and_then(accept_exponent)
-        .or_else(just_accept)?;
-    let maybe_parsed = RAW_CHARS.with(
-        |rc| rc.borrow().iter().collect::<String>().parse::<f64>());
+    let start = current_position();
+    let json5 = is_json5();
+    let allow_nan = json5 || OPTIONS.with(|rc| rc.borrow().allow_nan);
+    let accepted = accept_sign(JsonValue::NULL)
+        .and_then(|r_jval| match get_next_char() {
+            'I' if allow_nan => accept_word(r_jval, "Infinity"),
+            'N' if allow_nan => accept_word(r_jval, "NaN"),
+            '.' if json5 => accept_fraction(r_jval).and_then(accept_exponent),
+            _ => accept_integer(r_jval).and_then(|r_jval| {
+                let is_hex = json5 && matches!(get_next_char(), 'x' | 'X')
+                    && RAW_CHARS.with(|rc| matches!(rc.borrow().as_slice(), ['0'] | ['-' | '+', '0']));
+                if is_hex {
+                    accept_hex_digits(r_jval)
+                } else {
+                    accept_fraction(r_jval).and_then(accept_exponent)
+                }
+            })
+        });
+    if accepted.is_err() {
+        return Err(jval);
+    }
+    let raw = RAW_CHARS.with(|rc| rc.borrow().iter().collect::<String>());
 
-    match maybe_parsed {
-        Ok(number) => {
+    match number_value(&raw) {
+        Some(number) => {
             RAW_CHARS.with(|rc| rc.borrow_mut().clear());
+            if OPTIONS.with(|rc| rc.borrow().preserve_number_literals) {
+                return Ok(JsonValue::RAWNUMBER(raw));
+            }
+            if is_inexact_integer(&raw) {
+                warn_at(start, format!("integer {} exceeds 2^53 and may lose precision", raw));
+            }
             Ok(JsonValue::NUMBER(number))
         },
-        Err(_) => Err(jval)
+        None => Err(jval)
     }
 }
 
-fn accept_values(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    if let JsonValue::ARRAY(mut arr) = jval {
-        let first_value = accept_value(JsonValue::NULL)?;
-        arr.push(first_value);
-        return accept_delimiter(JsonValue::ARRAY(arr), ',')
-            .and_then(accept_values)
-            .or_else(just_accept);
-    }
-    Err(jval)
+/// Tells whether the integer `literal` is too large in magnitude for every
+/// integer up to it to be exact in `f64`.
+fn is_inexact_integer(literal: &str) -> bool {
+    let digits = literal.trim_start_matches(['-', '+']);
+    ! digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit())
+        && digits.parse::<u128>().map_or(true, |magnitude| magnitude > 1 << 53)
 }
 
 pub fn accept_array(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept_delimiter(jval, '[')
-        .and_then(|_r_jval| accept_values(JsonValue::ARRAY(vec![])))
-        .and_then(accept_delimiter_cb(']'))
-}
-
-fn accept_key_value(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    let key_jval = accept_whitespace(JsonValue::NULL)
-        .and_then(accept_string)
-        .and_then(accept_whitespace)
-        .and_then(accept_delimiter_cb(':'))?;
-    let value_jval = accept_value(JsonValue::NULL)?;
-    if let JsonValue::STRING(key) = key_jval {
-        return Ok(JsonValue::KEYVALUE((key, Box::new(value_jval))));
-    }
-    Err(jval)
-}
-
-fn accept_key_values(mut jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    if let JsonValue::OBJECT(ref mut obj) = jval {
-        let first_key_value = accept_key_value(JsonValue::NULL)?;
-        if let JsonValue::KEYVALUE((key, value)) = first_key_value {
-            obj.insert(key, value);
-            return accept_delimiter(jval, ',')
-                .and_then(accept_key_values)
-                .or_else(|r_frag| Ok(r_frag));
-        }
+    if get_next_char() != '[' {
+        return Err(jval);
     }
-    Err(jval)
+    accept_value(jval)
 }
 
 pub fn accept_object(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept_delimiter(jval, '{')
-        .and_then(|_r_jval| accept_key_values(JsonValue::OBJECT(HashMap::new())))
-        .and_then(accept_delimiter_cb('}'))
+    if get_next_char() != '{' {
+        return Err(jval);
+    }
+    accept_value(jval)
 }
 
 fn accept_hex(jval: JsonValue) -> Result<JsonValue, JsonValue> {
@@ -244,6 +954,7 @@ fn accept_hex(jval: JsonValue) -> Result<JsonValue, JsonValue> {
         .or_else(accept_ignoring_case_cb('d'))
         .or_else(accept_ignoring_case_cb('e'))
         .or_else(accept_ignoring_case_cb('f'))
+        .inspect_err(|_| expect_only("hex digit"))
 }
 
 fn accept_unicode(jval: JsonValue) -> Result<JsonValue, JsonValue> {
@@ -257,6 +968,7 @@ fn accept_unicode(jval: JsonValue) -> Result<JsonValue, JsonValue> {
 fn accept_control_characters(jval: JsonValue) -> Result<JsonValue, JsonValue> {
     accept(jval, '\\')
         .and_then(|r_jval| accept(r_jval, '"')
+            .or_else(|r_jval| if allows_single_quotes() { accept(r_jval, '\'') } else { Err(r_jval) })
             .or_else(accept_cb('\\'))
             .or_else(accept_cb('/'))
             .or_else(accept_cb('b'))
@@ -267,70 +979,869 @@ fn accept_control_characters(jval: JsonValue) -> Result<JsonValue, JsonValue> {
             .or_else(accept_unicode))
 }
 
-fn accept_symbol(jval: JsonValue) -> Result<JsonValue, JsonValue> {
+fn accept_symbol(jval: JsonValue, quote: char) -> Result<JsonValue, JsonValue> {
     let actual = get_next_char();
-    if actual == '\0' {
+    if actual == '\0' || actual == quote {
         return Err(jval);
     }
 
-    if actual != '"' && actual != '\\' {
+    if actual < ' ' {
+        if ! OPTIONS.with(|rc| rc.borrow().allow_control_characters) {
+            fail_with(ParseErrorKind::UnexpectedChar,
+                format!("unescaped control character {:?} in string", actual));
+            UNCLOSED_QUOTE.with(|rc| { rc.replace(Some(quote)); });
+            return Err(jval);
+        }
+        count_control_characters(1);
+        accept(jval, actual)
+    } else if actual != '\\' {
         accept(jval, actual)
     } else {
         accept_control_characters(jval)
     }
 }
 
-fn accept_symbols(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept_symbol(jval)
-        .and_then(accept_symbols)
-        .or_else(|r_jval| Ok(r_jval))
+/// Accepts a run of string characters other than `quote`, backslash and
+/// control characters at once instead of char by char.
+fn accept_plain_run(quote: char) {
+    CHAR_STREAM.with(|rc| if let Some(stream) = rc.borrow_mut().as_mut() {
+        let run = stream.take_run(|bytes| scan::string_special(bytes, quote as u8).unwrap_or(bytes.len()));
+        OFFSET.with(|rc| { *rc.borrow_mut() += run.len(); });
+        COLUMN.with(|rc| { *rc.borrow_mut() += run.chars().count(); });
+        RAW_CHARS.with(|rc| rc.borrow_mut().extend(run.chars()));
+    });
+}
+
+/// Consumes the run up to the next bracket, quote or backslash, appending
+/// it to `text`.
+fn skip_raw_run(text: &mut String) {
+    CHAR_STREAM.with(|rc| if let Some(stream) = rc.borrow_mut().as_mut() {
+        let run = stream.take_run(|bytes| bytes.iter()
+            .position(|byte| matches!(byte, b'"' | b'\'' | b'\\' | b'[' | b']' | b'{' | b'}'))
+            .unwrap_or(bytes.len()));
+        text.push_str(run);
+        OFFSET.with(|rc| { *rc.borrow_mut() += run.len(); });
+        match run.rfind('\n') {
+            Some(newline) => {
+                ROW.with(|rc| { *rc.borrow_mut() += run.matches('\n').count(); });
+                COLUMN.with(|rc| { *rc.borrow_mut() = run[newline + 1..].chars().count(); });
+            },
+            None => COLUMN.with(|rc| { *rc.borrow_mut() += run.chars().count(); })
+        }
+    });
+}
+
+/// Consumes an array or object without building it, only matching its
+/// brackets outside of strings.
+fn accept_raw() -> Result<JsonValue, ()> {
+    let start = current_position().offset;
+    let mut text = String::new();
+    let mut closings: Vec<char> = vec![];
+    let mut quote = None;
+    loop {
+        skip_raw_run(&mut text);
+        let symbol = get_next_char();
+        if symbol == '\0' {
+            return Err(());
+        }
+        if quote.is_none() && matches!(symbol, ']' | '}') && closings.last() != Some(&symbol) {
+            let expected = closings.last().copied().unwrap_or(symbol);
+            fail_with(ParseErrorKind::UnexpectedChar, format!("unexpected symbol {:?}, expected {:?}", symbol, expected));
+            return Err(());
+        }
+        let _ = accept_delimiter(JsonValue::NULL, symbol);
+        text.push(symbol);
+        match (quote, symbol) {
+            (Some(_), '\\') => {
+                let escaped = get_next_char();
+                if escaped == '\0' {
+                    return Err(());
+                }
+                let _ = accept_delimiter(JsonValue::NULL, escaped);
+                text.push(escaped);
+            },
+            (Some(opening), _) if symbol == opening => quote = None,
+            (Some(_), _) => {},
+            (None, '"') => quote = Some(symbol),
+            (None, '\'') if allows_single_quotes() => quote = Some(symbol),
+            (None, '[') => closings.push(']'),
+            (None, '{') => closings.push('}'),
+            (None, ']' | '}') => {
+                closings.pop();
+                if closings.is_empty() {
+                    return Ok(JsonValue::RAW(start..current_position().offset, text));
+                }
+            },
+            (None, _) => {}
+        }
+    }
+}
+
+fn accept_symbols(mut jval: JsonValue, quote: char, opening: Position) -> Result<JsonValue, JsonValue> {
+    let max_length = OPTIONS.with(|rc| rc.borrow().limits.max_string_length);
+    loop {
+        accept_plain_run(quote);
+        if let Some(max_length) = max_length.filter(|max| RAW_CHARS.with(|rc| rc.borrow().len()) > *max) {
+            fail_at(opening, OFFSET.with(|rc| *rc.borrow()), ParseErrorKind::StringTooLong,
+                format!("string too long, limit is {} characters", max_length));
+            return Err(jval);
+        }
+        let start = current_position().offset;
+        match accept_symbol(jval, quote) {
+            Ok(r_jval) => jval = r_jval,
+            Err(r_jval) if current_position().offset != start => return Err(r_jval),
+            Err(r_jval) => return Ok(r_jval)
+        }
+    }
+}
+
+fn hex_code(raw: &[char], at: usize) -> Option<u32> {
+    raw.get(at..at + 4)?.iter()
+        .try_fold(0, |code, symbol| symbol.to_digit(16).map(|digit| code * 16 + digit))
+}
+
+fn is_high_surrogate(code: u32) -> bool {
+    (0xD800..0xDC00).contains(&code)
+}
+
+fn is_low_surrogate(code: u32) -> bool {
+    (0xDC00..0xE000).contains(&code)
+}
+
+/// Replaces escape sequences in the raw string body with the characters
+/// they denote, combining UTF-16 surrogate pairs into single code points.
+pub(crate) fn unescape(raw: &[char], allow_lone_surrogates: bool) -> Result<String, String> {
+    let mut result = String::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        let symbol = raw[i];
+        i += 1;
+        if symbol != '\\' {
+            result.push(symbol);
+            continue;
+        }
+        let escaped = raw.get(i).copied().unwrap_or('\0');
+        i += 1;
+        let decoded = match escaped {
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let Some(code) = hex_code(raw, i) else {
+                    return Err("invalid unicode escape".to_string());
+                };
+                i += 4;
+                let low = match raw.get(i..i + 2) {
+                    Some(['\\', 'u']) if is_high_surrogate(code) => hex_code(raw, i + 2)
+                        .filter(|low| is_low_surrogate(*low)),
+                    _ => None
+                };
+                let code = match low {
+                    Some(low) => {
+                        i += 6;
+                        0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)
+                    },
+                    None if is_high_surrogate(code) || is_low_surrogate(code) => {
+                        if ! allow_lone_surrogates {
+                            return Err(format!("unpaired surrogate \\u{:04x}", code));
+                        }
+                        0xFFFD
+                    },
+                    None => code
+                };
+                char::from_u32(code).unwrap_or('\u{FFFD}')
+            },
+            other => other
+        };
+        result.push(decoded);
+    }
+    Ok(result)
 }
 
 pub fn accept_string(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    let _jval_string = accept_delimiter(jval, '"')
-        .and_then(accept_symbols)
-        .and_then(accept_delimiter_cb('"'))?;
-    let res = RAW_CHARS.with(|rc| rc.borrow().iter().collect::<String>());
+    accept_quoted(jval, '"')
+}
+
+fn accept_quoted(jval: JsonValue, quote: char) -> Result<JsonValue, JsonValue> {
+    let start = current_position();
+    let jval = accept_delimiter(jval, quote)
+        .and_then(|r_jval| accept_symbols(r_jval, quote, start))
+        .and_then(accept_delimiter_cb(quote))?;
+    let raw = RAW_CHARS.with(|rc| std::mem::take(&mut *rc.borrow_mut()));
+    let allow_lone_surrogates = OPTIONS.with(|rc| rc.borrow().allow_lone_surrogates);
+    if quote == '\'' && ! is_json5() {
+        warn_at(start, "single-quoted string".to_string());
+    }
+    match unescape(&raw, allow_lone_surrogates) {
+        Ok(res) => Ok(JsonValue::STRING(res)),
+        Err(reason) => {
+            fail_at(start, OFFSET.with(|rc| *rc.borrow()), ParseErrorKind::InvalidEscape, reason);
+            Err(jval)
+        }
+    }
+}
+
+fn is_identifier_start(symbol: char) -> bool {
+    symbol.is_alphabetic() || symbol == '_' || symbol == '$'
+}
+
+/// Unquoted JSON5 object key.
+fn accept_identifier(mut jval: JsonValue) -> Result<JsonValue, JsonValue> {
+    while is_identifier_start(get_next_char()) || get_next_char().is_alphanumeric() {
+        jval = accept(jval, get_next_char())?;
+    }
+    Ok(JsonValue::STRING(RAW_CHARS.with(|rc| std::mem::take(&mut *rc.borrow_mut())).into_iter().collect()))
+}
+
+fn accept_key(jval: JsonValue) -> Result<JsonValue, JsonValue> {
+    match get_next_char() {
+        '\'' if allows_single_quotes() => accept_quoted(jval, '\''),
+        symbol if is_json5() && is_identifier_start(symbol) => accept_identifier(jval),
+        _ => accept_string(jval)
+    }
+}
+
+fn accept_scalar(jval: JsonValue) -> Result<JsonValue, JsonValue> {
+    match get_next_char() {
+        '"' => accept_string(jval),
+        '\'' if allows_single_quotes() => accept_quoted(jval, '\''),
+        't' => accept_true(jval),
+        'f' => accept_false(jval),
+        'n' => accept_null(jval),
+        _ => accept_number(jval)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Position {
+    row: usize,
+    col: usize,
+    offset: usize
+}
+
+fn current_position() -> Position {
+    Position {
+        row: ROW.with(|rc| *rc.borrow()),
+        col: COLUMN.with(|rc| *rc.borrow()),
+        offset: OFFSET.with(|rc| *rc.borrow())
+    }
+}
+
+fn next_char_end() -> usize {
+    OFFSET.with(|rc| *rc.borrow()) + match get_next_char() {
+        '\0' => 0,
+        symbol => symbol.len_utf8()
+    }
+}
+
+fn error_at(start: Position, end: usize, kind: ParseErrorKind, msg: String) -> ParseError {
+    ParseError {
+        kind,
+        row: start.row,
+        col: start.col,
+        offset: start.offset,
+        span: start.offset..end,
+        expected: vec![],
+        msg
+    }
+}
+
+fn join_expected(expected: &[String]) -> String {
+    match expected {
+        [] => String::new(),
+        [single] => single.clone(),
+        [init @ .., last] => format!("{} or {}", init.join(", "), last)
+    }
+}
+
+fn unexpected_error(position: Position, end: usize, kind: ParseErrorKind, msg: String) -> ParseError {
+    let expected = current_expected();
+    let msg = if expected.is_empty() {
+        msg
+    } else {
+        format!("{}, expected {}", msg, join_expected(&expected))
+    };
+    ParseError { expected, ..error_at(position, end, kind, msg) }
+}
+
+fn fail_at(start: Position, end: usize, kind: ParseErrorKind, msg: String) {
+    FAILURE.with(|rc| { rc.replace(Some(error_at(start, end, kind, msg))); });
+}
+
+/// Records the usual unexpected symbol error at the current position.
+fn fail_here() {
+    let error = unexpected_symbol_error();
+    FAILURE.with(|rc| { rc.replace(Some(error)); });
+}
+
+fn has_failed() -> bool {
+    FAILURE.with(|rc| rc.borrow().is_some())
+}
+
+fn fail_with(kind: ParseErrorKind, msg: String) {
+    fail_at(current_position(), next_char_end(), kind, msg);
+}
+
+fn warn_at(position: Position, msg: String) {
+    WARNINGS.with(|rc| rc.borrow_mut().push(ParseWarning { row: position.row, col: position.col, msg }));
+}
+
+/// Warnings collected during the last parse on this thread.
+pub fn take_warnings() -> Vec<ParseWarning> {
+    WARNINGS.with(|rc| std::mem::take(&mut *rc.borrow_mut()))
+}
+
+/// Number of unescaped control characters accepted in strings on this
+/// thread since the previous call, see
+/// `ParserOptions::allow_control_characters`.
+pub fn take_control_characters() -> usize {
+    CONTROL_CHARACTERS.with(|rc| std::mem::take(&mut *rc.borrow_mut()))
+}
+
+pub(crate) fn count_control_characters(count: usize) {
+    CONTROL_CHARACTERS.with(|rc| { *rc.borrow_mut() += count; });
+}
+
+fn accept_object_key(object: &JsonMap<JsonValue>) -> Option<String> {
+    let _ = accept_whitespace(JsonValue::NULL);
+    let position = current_position();
+    let Ok(JsonValue::STRING(key)) = accept_key(JsonValue::NULL) else {
+        return None;
+    };
+    let end = OFFSET.with(|rc| *rc.borrow());
+    accept_whitespace(JsonValue::NULL)
+        .and_then(accept_delimiter_cb(':'))
+        .ok()?;
+    if ! object.contains_key(key.as_str()) {
+        let max_keys = OPTIONS.with(|rc| rc.borrow().limits.max_keys);
+        if let Some(max_keys) = max_keys.filter(|max| object.len() >= *max) {
+            fail_at(position, end, ParseErrorKind::TooManyKeys,
+                format!("too many keys in object, limit is {}", max_keys));
+            return None;
+        }
+    } else {
+        match OPTIONS.with(|rc| rc.borrow().duplicate_keys) {
+            DuplicateKeys::Error => {
+                fail_at(position, end, ParseErrorKind::DuplicateKey, format!("duplicate key \'{}\'", key));
+                return None;
+            },
+            DuplicateKeys::Warn => warn_at(position, format!("duplicate key \'{}\'", key)),
+            _ => {}
+        }
+    }
+    Some(key)
+}
+
+/// Reads the next member of the innermost container: a scalar, an empty
+/// container, or the opening of a container which is pushed onto the stack.
+fn accept_member(containers: &mut Vec<Container>, options: &ParserOptions, nodes: &mut usize,
+        sink: &mut EventSink) -> Result<Option<JsonValue>, ()> {
+    if let Some(Container::Object(obj, key @ None)) = containers.last_mut() {
+        let name = accept_object_key(obj).ok_or(())?;
+        sink.emit(JsonEvent::Key(&name));
+        *key = Some(name);
+    }
+    let _ = accept_whitespace(JsonValue::NULL);
+    *nodes += 1;
+    if let Some(max_nodes) = options.limits.max_nodes.filter(|max| *nodes > *max) {
+        fail_with(ParseErrorKind::TooManyNodes, format!("too many values, limit is {}", max_nodes));
+        return Err(());
+    }
+    let opening = get_next_char();
+    if opening != '[' && opening != '{' {
+        let start = current_position().offset;
+        return accept_scalar(JsonValue::NULL).map(|value| {
+            sink.emit_scalar(&value);
+            Some(value)
+        }).map_err(|_| {
+            if current_position().offset == start {
+                expect_only("value");
+            }
+        });
+    }
+    if options.raw_depth.is_some_and(|depth| containers.len() >= depth) && ! sink.is_streaming() {
+        return accept_raw().map(Some);
+    }
+    if containers.len() == options.max_depth {
+        fail_with(ParseErrorKind::DepthExceeded,
+            format!("nesting too deep, limit is {} levels", options.max_depth));
+        return Err(());
+    }
+    let _ = accept_delimiter(JsonValue::NULL, opening);
+    let _ = accept_whitespace(JsonValue::NULL);
+    if opening == '[' {
+        sink.emit(JsonEvent::StartArray);
+        if accept_delimiter(JsonValue::NULL, ']').is_ok() {
+            sink.emit(JsonEvent::EndArray);
+            return Ok(Some(JsonValue::ARRAY(vec![])));
+        }
+        containers.push(Container::Array(vec![]));
+    } else {
+        sink.emit(JsonEvent::StartObject);
+        if accept_delimiter(JsonValue::NULL, '}').is_ok() {
+            sink.emit(JsonEvent::EndObject);
+            return Ok(Some(JsonValue::OBJECT(JsonMap::new())));
+        }
+        containers.push(Container::Object(JsonMap::new(), None));
+    }
+    Ok(None)
+}
+
+/// Consumes the comma after a member and tells whether another member
+/// follows; a trailing comma right before `closing`, when allowed, ends the
+/// container.
+fn accept_separator(closing: char) -> bool {
+    if accept_delimiter(JsonValue::NULL, ',').is_err() {
+        return false;
+    }
+    if OPTIONS.with(|rc| rc.borrow().allow_trailing_commas) || is_json5() {
+        let _ = accept_whitespace(JsonValue::NULL);
+        return get_next_char() != closing;
+    }
+    true
+}
+
+/// Stores the completed value into its container and closes every container
+/// that ends right after it; yields the root value once the stack is empty.
+/// While streaming events only the keys are kept, to detect duplicates.
+fn accept_closing(containers: &mut Vec<Container>, mut value: JsonValue,
+        duplicate_keys: DuplicateKeys, sink: &mut EventSink) -> Result<Option<JsonValue>, ()> {
+    loop {
+        let _ = accept_whitespace(JsonValue::NULL);
+        if sink.is_streaming() {
+            value = JsonValue::NULL;
+        }
+        match containers.last_mut() {
+            None => return Ok(Some(value)),
+            Some(Container::Array(arr)) => {
+                if ! sink.is_streaming() {
+                    arr.push(value);
+                }
+                if accept_separator(']') {
+                    return Ok(None);
+                }
+                accept_delimiter(JsonValue::NULL, ']').map_err(|_| ())?;
+                sink.emit(JsonEvent::EndArray);
+            },
+            Some(Container::Object(obj, key)) => {
+                if let Some(key) = key.take() {
+                    if duplicate_keys == DuplicateKeys::KeepFirst {
+                        obj.entry(intern_key(&key)).or_insert(value);
+                    } else {
+                        obj.insert(intern_key(&key), value);
+                    }
+                }
+                if accept_separator('}') {
+                    return Ok(None);
+                }
+                accept_delimiter(JsonValue::NULL, '}').map_err(|_| ())?;
+                sink.emit(JsonEvent::EndObject);
+            }
+        }
+        value = containers.pop().map(Container::close).unwrap_or(JsonValue::NULL);
+    }
+}
+
+/// Consumes the rest of a string up to and including its closing `quote`.
+fn skip_string(quote: char) {
+    loop {
+        let symbol = get_next_char();
+        if symbol == '\0' {
+            return;
+        }
+        let _ = accept_delimiter(JsonValue::NULL, symbol);
+        if symbol == quote {
+            return;
+        }
+        if symbol == '\\' && get_next_char() != '\0' {
+            let _ = accept_delimiter(JsonValue::NULL, get_next_char());
+        }
+    }
+}
+
+/// Skips input up to the next `,`, `]` or `}` of an open container after a
+/// syntax error. Returns the container closed by the skip, if any, or
+/// `Err` when the input ended first.
+fn synchronize(containers: &mut Vec<Container>) -> Result<Option<JsonValue>, ()> {
     RAW_CHARS.with(|rc| rc.borrow_mut().clear());
-    Ok(JsonValue::STRING(res))
+    if let Some(quote) = UNCLOSED_QUOTE.with(|rc| rc.take()) {
+        skip_string(quote);
+    }
+    loop {
+        let symbol = get_next_char();
+        if symbol == '\0' {
+            return Err(());
+        }
+        if symbol == '"' && accept_string(JsonValue::NULL).is_ok() {
+            continue;
+        }
+        FAILURE.with(|rc| { rc.replace(None); });
+        let _ = accept_delimiter(JsonValue::NULL, symbol);
+        match (symbol, containers.last_mut()) {
+            (',', Some(Container::Object(_, key))) => {
+                *key = None;
+                return Ok(None);
+            },
+            (',', Some(Container::Array(_))) => return Ok(None),
+            (']' | '}', Some(_)) => return Ok(containers.pop().map(Container::close)),
+            _ => {}
+        }
+    }
 }
 
+/// Parses a value of any nesting using an explicit stack of the containers
+/// being filled instead of the native call stack.
 pub fn accept_value(jval: JsonValue) -> Result<JsonValue, JsonValue> {
-    accept_whitespace(jval)
-        .and_then(accept_string)
-        .or_else(accept_number)
-        .or_else(accept_object)
-        .or_else(accept_array)
-        .or_else(accept_true)
-        .or_else(accept_false)
-        .or_else(accept_null)
-        .and_then(accept_whitespace)
+    accept_events(jval, &mut EventSink { handler: None })
 }
 
-pub fn prepare_environment(content: String) {
+fn accept_events(jval: JsonValue, sink: &mut EventSink) -> Result<JsonValue, JsonValue> {
+    let options = OPTIONS.with(|rc| rc.borrow().clone());
+    let recovering = RECOVERING.with(|rc| *rc.borrow());
+    let mut containers: Vec<Container> = vec![];
+    let mut nodes = 0;
+    let mut completed: Option<JsonValue> = None;
+    loop {
+        let step = match completed.take() {
+            Some(value) => accept_closing(&mut containers, value, options.duplicate_keys, sink),
+            None => accept_member(&mut containers, &options, &mut nodes, sink)
+                .map(|member| { completed = member; None })
+        };
+        match step {
+            Ok(Some(root)) => return Ok(root),
+            Ok(None) => {},
+            Err(()) if recovering => {
+                let error = unexpected_symbol_error();
+                // exceeding a limit means the input should not be read any further
+                let is_limit = matches!(error.kind,
+                    ParseErrorKind::StringTooLong | ParseErrorKind::TooManyKeys | ParseErrorKind::TooManyNodes);
+                ERRORS.with(|rc| rc.borrow_mut().push(error));
+                if is_limit {
+                    return Err(jval);
+                }
+                match synchronize(&mut containers) {
+                    Ok(closed) => completed = closed,
+                    Err(()) => {
+                        let offset = current_position().offset;
+                        let is_reported = ERRORS.with(|rc| rc.borrow().last()
+                            .is_some_and(|last| last.offset == offset));
+                        if ! containers.is_empty() && ! is_reported {
+                            let error = unexpected_symbol_error();
+                            ERRORS.with(|rc| rc.borrow_mut().push(error));
+                        }
+                        return Err(jval);
+                    }
+                }
+            },
+            Err(()) => return Err(jval)
+        }
+    }
+}
+
+fn prepare_stream(reader: Box<dyn BufRead>) {
     COLUMN.with(|rc| { *rc.borrow_mut() = 0; });
     ROW.with(|rc| { *rc.borrow_mut() = 0; });
+    OFFSET.with(|rc| { *rc.borrow_mut() = 0; });
+    EXPECTED.with(|rc| { rc.replace((0, vec![])); });
     RAW_CHARS.with(|rc| rc.borrow_mut().clear());
-    CHAR_STREAM.with(|rc| { rc.replace(Box::leak(content.into_boxed_str())); });
+    FAILURE.with(|rc| { rc.replace(None); });
+    UNCLOSED_QUOTE.with(|rc| { rc.replace(None); });
+    WARNINGS.with(|rc| rc.borrow_mut().clear());
+    ERRORS.with(|rc| rc.borrow_mut().clear());
+    CHAR_STREAM.with(|rc| { rc.replace(Some(CharStream::new(reader))); });
 }
 
-pub fn single_json(file: &String) -> Result<Option<JsonValue>, ParseError> {
-    match fs::read_to_string(file) {
-        Ok(content) => {
-            prepare_environment(content);
-            match accept_value(JsonValue::NULL) {
-                Ok(jval) => Ok(Some(jval)),
-                Err(_) => Err(ParseError {
-                    row: ROW.with(|rc| *rc.borrow()),
-                    col: COLUMN.with(|rc| *rc.borrow()),
-                    msg: format!("unexpected symbol \'{}\'", get_next_char())
-                })
-            }
-        },
-        Err(e) => Err(ParseError {
-            row: 0,
-            col: 0,
-            msg: format!("unable to open/read file: {}", e.to_string())
+pub fn prepare_environment(content: String) {
+    prepare_stream(Box::new(Cursor::new(content.into_bytes())));
+}
+
+fn unexpected_symbol_error() -> ParseError {
+    let position = current_position();
+    match (get_read_error(), FAILURE.with(|rc| rc.borrow_mut().take())) {
+        (Some(e), _) => error_at(position, position.offset,
+            ParseErrorKind::Io, format!("unable to read input: {}", e)),
+        (None, Some(failure)) => failure,
+        (None, None) => match get_next_char() {
+            '\0' => unexpected_error(position, position.offset,
+                ParseErrorKind::UnexpectedEof, "unexpected end of input".to_string()),
+            symbol => unexpected_error(position, next_char_end(),
+                ParseErrorKind::UnexpectedChar, format!("unexpected symbol {:?}", symbol))
+        }
+    }
+}
+
+fn parse_stream(reader: Box<dyn BufRead>, options: &ParserOptions) -> Result<JsonValue, ParseError> {
+    parse_stream_events(reader, options, &mut EventSink { handler: None })
+}
+
+fn parse_stream_events(reader: Box<dyn BufRead>, options: &ParserOptions,
+        sink: &mut EventSink) -> Result<JsonValue, ParseError> {
+    prepare_stream(reader);
+    OPTIONS.with(|rc| { rc.replace(options.clone()); });
+    let result = accept_events(JsonValue::NULL, sink)
+        .map_err(|_| unexpected_symbol_error())
+        .and_then(|jval| match get_next_char() {
+            '\0' if get_read_error().is_none() && ! has_failed() => Ok(jval),
+            _ => Err(unexpected_symbol_error())
+        });
+    CHAR_STREAM.with(|rc| { rc.replace(None); });
+    result
+}
+
+/// Parses the whole input, resynchronizing after each syntax error, and
+/// returns every error found.
+fn diagnose_stream(reader: Box<dyn BufRead>, options: &ParserOptions) -> Vec<ParseError> {
+    prepare_stream(reader);
+    OPTIONS.with(|rc| { rc.replace(options.clone()); });
+    RECOVERING.with(|rc| { rc.replace(true); });
+    let is_parsed = accept_value(JsonValue::NULL).is_ok();
+    if is_parsed && (get_next_char() != '\0' || get_read_error().is_some() || has_failed()) {
+        let error = unexpected_symbol_error();
+        ERRORS.with(|rc| rc.borrow_mut().push(error));
+    }
+    RECOVERING.with(|rc| { rc.replace(false); });
+    CHAR_STREAM.with(|rc| { rc.replace(None); });
+    ERRORS.with(|rc| std::mem::take(&mut *rc.borrow_mut()))
+}
+
+impl ParserOptions {
+    pub fn parse_str(&self, content: &str) -> Result<JsonValue, ParseError> {
+        parse_stream(Box::new(Cursor::new(content.as_bytes().to_vec())), self)
+    }
+
+    /// Parses a value kept in `JsonValue::RAW` out of the `source` it was
+    /// found in; error positions are relative to the whole source.
+    pub fn parse_raw(&self, source: &str, span: &Span) -> Result<JsonValue, ParseError> {
+        let before = &source[..span.start.min(source.len())];
+        let row = before.matches('\n').count();
+        let col = before.chars().rev().take_while(|symbol| *symbol != '\n').count();
+        let Some(text) = source.get(span.clone()) else {
+            return Err(error_at(Position { row, col, offset: span.start }, span.end, ParseErrorKind::UnexpectedEof,
+                format!("span {}..{} is outside of the source", span.start, span.end)));
+        };
+        let options = Self { raw_depth: None, ..self.clone() };
+        options.parse_str(text).map_err(|error| ParseError {
+            col: if error.row == 0 { error.col + col } else { error.col },
+            row: error.row + row,
+            offset: error.offset + span.start,
+            span: error.span.start + span.start..error.span.end + span.start,
+            ..error
         })
     }
+
+    /// Parses JSON pulled from `reader` in chunks, so the input does not
+    /// have to fit into memory at once.
+    pub fn parse_buffered(&self, reader: impl BufRead + 'static) -> Result<JsonValue, ParseError> {
+        parse_stream(Box::new(reader), self)
+    }
+
+    pub fn parse_reader(&self, reader: impl Read + 'static) -> Result<JsonValue, ParseError> {
+        self.parse_buffered(BufReader::new(reader))
+    }
+
+    /// Reports the input to `handler` as a stream of events without
+    /// building a `JsonValue` tree. Events seen before a syntax error have
+    /// already been delivered when the error is returned.
+    pub fn parse_events(&self, reader: impl BufRead + 'static,
+            mut handler: impl FnMut(JsonEvent<'_>)) -> Result<(), ParseError> {
+        parse_stream_events(Box::new(reader), self, &mut EventSink { handler: Some(&mut handler) })
+            .map(|_| ())
+    }
+
+    pub fn single_json(&self, file: &String) -> Result<Option<JsonValue>, ParseError> {
+        match fs::File::open(file) {
+            Ok(handle) => self.parse_reader(handle).map(Some),
+            Err(e) => Err(open_error(e))
+        }
+    }
+
+    /// Parses newline-delimited JSON (JSON Lines): every non-blank line is
+    /// a separate value. Error positions are relative to the whole input.
+    pub fn parse_ndjson(&self, reader: impl BufRead + 'static) -> impl Iterator<Item = Result<JsonValue, ParseError>> {
+        SequenceValues::new(Box::new(reader), self, b'\n')
+    }
+
+    pub fn ndjson_file(&self, file: &String) -> Result<impl Iterator<Item = Result<JsonValue, ParseError>>, ParseError> {
+        match fs::File::open(file) {
+            Ok(handle) => Ok(self.parse_ndjson(BufReader::new(handle))),
+            Err(e) => Err(open_error(e))
+        }
+    }
+
+    /// Parses an RFC 7464 JSON text sequence (`application/json-seq`),
+    /// where every value is preceded by the 0x1E record separator.
+    pub fn parse_json_seq(&self, reader: impl BufRead + 'static) -> impl Iterator<Item = Result<JsonValue, ParseError>> {
+        SequenceValues::new(Box::new(reader), self, RECORD_SEPARATOR)
+    }
+
+    pub fn json_seq_file(&self, file: &String) -> Result<impl Iterator<Item = Result<JsonValue, ParseError>>, ParseError> {
+        match fs::File::open(file) {
+            Ok(handle) => Ok(self.parse_json_seq(BufReader::new(handle))),
+            Err(e) => Err(open_error(e))
+        }
+    }
+
+    /// Reports the content of `file` to `handler` as events, see
+    /// `parse_events`.
+    pub fn events_file(&self, file: &String, handler: impl FnMut(JsonEvent<'_>)) -> Result<(), ParseError> {
+        match fs::File::open(file) {
+            Ok(handle) => self.parse_events(BufReader::new(handle), handler),
+            Err(e) => Err(open_error(e))
+        }
+    }
+
+    /// Collects all syntax errors of `content` instead of stopping at the
+    /// first one.
+    pub fn diagnose_str(&self, content: &str) -> Vec<ParseError> {
+        diagnose_stream(Box::new(Cursor::new(content.as_bytes().to_vec())), self)
+    }
+
+    pub fn diagnose_buffered(&self, reader: impl BufRead + 'static) -> Vec<ParseError> {
+        diagnose_stream(Box::new(reader), self)
+    }
+
+    pub fn diagnose_file(&self, file: &String) -> Vec<ParseError> {
+        match fs::File::open(file) {
+            Ok(handle) => self.diagnose_buffered(BufReader::new(handle)),
+            Err(e) => vec![open_error(e)]
+        }
+    }
+}
+
+/// Record separator starting every text of an RFC 7464 JSON sequence.
+const RECORD_SEPARATOR: u8 = 0x1E;
+
+/// Values of an input split into records by `separator`: a newline for
+/// NDJSON, the record separator for JSON text sequences. Blank records are
+/// skipped.
+struct SequenceValues {
+    reader: Box<dyn BufRead>,
+    options: ParserOptions,
+    separator: u8,
+    row: usize,
+    col: usize,
+    offset: usize,
+    is_done: bool
+}
+
+impl SequenceValues {
+    fn new(reader: Box<dyn BufRead>, options: &ParserOptions, separator: u8) -> Self {
+        Self { reader, options: options.clone(), separator, row: 0, col: 0, offset: 0, is_done: false }
+    }
+}
+
+impl Iterator for SequenceValues {
+    type Item = Result<JsonValue, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while ! self.is_done {
+            let mut record = vec![];
+            let read = match self.reader.read_until(self.separator, &mut record) {
+                Ok(0) => return None,
+                Ok(read) => read,
+                Err(e) => {
+                    self.is_done = true;
+                    return Some(Err(open_error(e)));
+                }
+            };
+            let (row, col, offset) = (self.row, self.col, self.offset);
+            let consumed = String::from_utf8_lossy(&record);
+            self.row += consumed.matches('\n').count();
+            self.col = match consumed.rfind('\n') {
+                Some(newline) => consumed[newline + 1..].chars().count(),
+                None => col + consumed.chars().count()
+            };
+            self.offset += read;
+            if record.last() == Some(&self.separator) {
+                record.pop();
+            }
+            if record.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            let earlier = take_warnings();
+            let result = parse_stream(Box::new(Cursor::new(record)), &self.options);
+            WARNINGS.with(|rc| {
+                let mut warnings = rc.borrow_mut();
+                for warning in warnings.iter_mut() {
+                    if warning.row == 0 {
+                        warning.col += col;
+                    }
+                    warning.row += row;
+                }
+                warnings.splice(0..0, earlier);
+            });
+            return Some(result.map_err(|error| ParseError {
+                row: error.row + row,
+                col: if error.row == 0 { error.col + col } else { error.col },
+                offset: error.offset + offset,
+                span: error.span.start + offset..error.span.end + offset,
+                ..error
+            }));
+        }
+        None
+    }
+}
+
+fn open_error(e: std::io::Error) -> ParseError {
+    ParseError {
+        kind: ParseErrorKind::Io,
+        row: 0,
+        col: 0,
+        offset: 0,
+        span: 0..0,
+        expected: vec![],
+        msg: format!("unable to open/read file: {}", e)
+    }
+}
+
+pub fn parse_str(content: &str) -> Result<JsonValue, ParseError> {
+    ParserOptions::default().parse_str(content)
+}
+
+pub fn parse_buffered(reader: impl BufRead + 'static) -> Result<JsonValue, ParseError> {
+    ParserOptions::default().parse_buffered(reader)
+}
+
+pub fn parse_reader(reader: impl Read + 'static) -> Result<JsonValue, ParseError> {
+    ParserOptions::default().parse_reader(reader)
+}
+
+pub fn single_json(file: &String) -> Result<Option<JsonValue>, ParseError> {
+    ParserOptions::default().single_json(file)
+}
+
+pub fn parse_events(reader: impl BufRead + 'static, handler: impl FnMut(JsonEvent<'_>)) -> Result<(), ParseError> {
+    ParserOptions::default().parse_events(reader, handler)
+}
+
+pub fn parse_ndjson(reader: impl BufRead + 'static) -> impl Iterator<Item = Result<JsonValue, ParseError>> {
+    ParserOptions::default().parse_ndjson(reader)
+}
+
+pub fn parse_json_seq(reader: impl BufRead + 'static) -> impl Iterator<Item = Result<JsonValue, ParseError>> {
+    ParserOptions::default().parse_json_seq(reader)
+}
+
+/// Text of the input as the parser reads it, transcoded to UTF-8 without a
+/// BOM, which error positions refer to; an undecodable tail is left out.
+pub fn decode_buffered(reader: impl BufRead + 'static) -> String {
+    let mut stream = CharStream::new(Box::new(reader));
+    let mut text = String::new();
+    stream.fill();
+    while stream.position < stream.text.len() {
+        text.push_str(&stream.text[stream.position..]);
+        stream.position = stream.text.len();
+        stream.fill();
+    }
+    text
+}
+
+pub fn decode_file(file: &String) -> Result<String, ParseError> {
+    match fs::File::open(file) {
+        Ok(handle) => Ok(decode_buffered(BufReader::new(handle))),
+        Err(e) => Err(open_error(e))
+    }
 }
@@ -0,0 +1,216 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::jsonpath::members;
+use crate::parser::JsonValue;
+use crate::pointer;
+
+
+/// Operation of a JSON Patch (RFC 6902). Paths are JSON Pointers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchOp {
+    Add { path: String, value: JsonValue },
+    Remove { path: String },
+    Replace { path: String, value: JsonValue },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: JsonValue }
+}
+
+/// Operation of a patch that is malformed or cannot be applied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatchError {
+    /// Position of the operation in the patch.
+    pub index: usize,
+    pub msg: String
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in operation {}", self.msg, self.index)
+    }
+}
+
+impl Error for PatchError {}
+
+impl From<&PatchOp> for JsonValue {
+    fn from(op: &PatchOp) -> Self {
+        let (name, members) = match op {
+            PatchOp::Add { path, value } => ("add", vec![("path", JsonValue::from(path.as_str())), ("value", value.clone())]),
+            PatchOp::Remove { path } => ("remove", vec![("path", JsonValue::from(path.as_str()))]),
+            PatchOp::Replace { path, value } => ("replace", vec![("path", JsonValue::from(path.as_str())), ("value", value.clone())]),
+            PatchOp::Move { from, path } => ("move", vec![("from", JsonValue::from(from.as_str())), ("path", JsonValue::from(path.as_str()))]),
+            PatchOp::Copy { from, path } => ("copy", vec![("from", JsonValue::from(from.as_str())), ("path", JsonValue::from(path.as_str()))]),
+            PatchOp::Test { path, value } => ("test", vec![("path", JsonValue::from(path.as_str())), ("value", value.clone())])
+        };
+        JsonValue::object(std::iter::once(("op", JsonValue::from(name))).chain(members))
+    }
+}
+
+/// Patch document: an array of operation objects.
+pub fn patch_to_json(ops: &[PatchOp]) -> JsonValue {
+    JsonValue::array(ops.iter().map(JsonValue::from))
+}
+
+/// Reads the operations of a patch document.
+pub fn parse_patch(patch: &JsonValue) -> Result<Vec<PatchOp>, PatchError> {
+    let Some(items) = patch.as_array() else {
+        return Err(PatchError { index: 0, msg: "patch is not an array".to_string() });
+    };
+    items.iter().enumerate().map(|(index, item)| {
+        let fail = |msg: String| PatchError { index, msg };
+        let member = |name: &str| item.get(name).ok_or_else(|| fail(format!("missing \'{}\'", name)));
+        let pointer = |name: &str| member(name)?.as_str().map(str::to_string)
+            .ok_or_else(|| fail(format!("\'{}\' is not a string", name)));
+        let op = member("op")?.as_str().ok_or_else(|| fail("\'op\' is not a string".to_string()))?;
+        Ok(match op {
+            "add" => PatchOp::Add { path: pointer("path")?, value: member("value")?.clone() },
+            "remove" => PatchOp::Remove { path: pointer("path")? },
+            "replace" => PatchOp::Replace { path: pointer("path")?, value: member("value")?.clone() },
+            "move" => PatchOp::Move { from: pointer("from")?, path: pointer("path")? },
+            "copy" => PatchOp::Copy { from: pointer("from")?, path: pointer("path")? },
+            "test" => PatchOp::Test { path: pointer("path")?, value: member("value")?.clone() },
+            _ => return Err(fail(format!("unknown operation \'{}\'", op)))
+        })
+    }).collect()
+}
+
+/// Value whose member or item `pointer` refers to, and the last token.
+fn parent_mut<'a>(root: &'a mut JsonValue, pointer: &str) -> Result<(&'a mut JsonValue, String), String> {
+    let mut tokens = pointer::tokens(pointer).map_err(|error| error.to_string())?;
+    let last = tokens.pop().ok_or("the root has no parent")?;
+    let mut parent = root;
+    for token in &tokens {
+        parent = match parent {
+            JsonValue::OBJECT(object) => object.get_mut(token.as_str()),
+            JsonValue::ARRAY(array) => pointer::index(token).and_then(|index| array.get_mut(index)),
+            _ => None
+        }.ok_or_else(|| format!("no value at \'{}\'", token))?;
+    }
+    Ok((parent, last))
+}
+
+fn add(root: &mut JsonValue, path: &str, value: JsonValue) -> Result<(), String> {
+    if path.is_empty() {
+        *root = value;
+        return Ok(());
+    }
+    let (parent, last) = parent_mut(root, path)?;
+    match parent {
+        JsonValue::OBJECT(object) => {
+            object.insert(last.into(), value);
+        },
+        JsonValue::ARRAY(array) => {
+            let index = if last == "-" { Some(array.len()) } else { pointer::index(&last) };
+            match index {
+                Some(index) if index <= array.len() => array.insert(index, value),
+                _ => return Err(format!("invalid array index \'{}\'", last))
+            }
+        },
+        _ => return Err(format!("parent of \'{}\' is neither an object nor an array", path))
+    }
+    Ok(())
+}
+
+fn remove(root: &mut JsonValue, path: &str) -> Result<JsonValue, String> {
+    let (parent, last) = parent_mut(root, path)?;
+    let removed = match parent {
+        JsonValue::OBJECT(_) => parent.remove(&last),
+        JsonValue::ARRAY(array) => pointer::index(&last).filter(|index| *index < array.len())
+            .map(|index| array.remove(index)),
+        _ => None
+    };
+    removed.ok_or_else(|| format!("no value at \'{}\'", path))
+}
+
+fn apply(root: &mut JsonValue, op: &PatchOp) -> Result<(), String> {
+    let value_at = |root: &JsonValue, path: &str| root.pointer(path).cloned()
+        .ok_or_else(|| format!("no value at \'{}\'", path));
+    match op {
+        PatchOp::Add { path, value } => add(root, path, value.clone()),
+        PatchOp::Remove { path } => remove(root, path).map(drop),
+        PatchOp::Replace { path, value } => {
+            value_at(root, path)?;
+            root.set_by_pointer(path, value.clone()).map(drop).map_err(|error| error.to_string())
+        },
+        PatchOp::Move { from, path } => {
+            if path.strip_prefix(from.as_str()).is_some_and(|rest| rest.starts_with('/')) {
+                return Err(format!("cannot move \'{}\' into itself", from));
+            }
+            if from == path {
+                return value_at(root, from).map(drop);
+            }
+            let value = remove(root, from)?;
+            add(root, path, value)
+        },
+        PatchOp::Copy { from, path } => {
+            let value = value_at(root, from)?;
+            add(root, path, value)
+        },
+        PatchOp::Test { path, value } => match root.pointer(path) {
+            Some(actual) if actual == value => Ok(()),
+            Some(actual) => Err(format!("value at \'{}\' is {}, not {}", path, actual, value)),
+            None => Err(format!("no value at \'{}\'", path))
+        }
+    }
+}
+
+impl JsonValue {
+    /// Applies a JSON Patch (RFC 6902). The patch is atomic: if any of the
+    /// operations fails the value is left unchanged.
+    pub fn apply_patch(&mut self, ops: &[PatchOp]) -> Result<(), PatchError> {
+        let mut patched = self.clone();
+        for (index, op) in ops.iter().enumerate() {
+            apply(&mut patched, op).map_err(|msg| PatchError { index, msg })?;
+        }
+        *self = patched;
+        Ok(())
+    }
+}
+
+fn diff_values(from: &JsonValue, to: &JsonValue, path: &str, ops: &mut Vec<PatchOp>) {
+    if from == to {
+        return;
+    }
+    match (from, to) {
+        (JsonValue::OBJECT(left), JsonValue::OBJECT(right)) => {
+            for (key, _) in members(left) {
+                if ! right.contains_key(key) {
+                    ops.push(PatchOp::Remove { path: format!("{}/{}", path, pointer::escape(key)) });
+                }
+            }
+            for (key, value) in members(right) {
+                let member = format!("{}/{}", path, pointer::escape(key));
+                match left.get(key) {
+                    Some(previous) => diff_values(previous, value, &member, ops),
+                    None => ops.push(PatchOp::Add { path: member, value: value.clone() })
+                }
+            }
+        },
+        (JsonValue::ARRAY(left), JsonValue::ARRAY(right)) => {
+            let prefix = left.iter().zip(right).take_while(|(a, b)| a == b).count();
+            let suffix = left[prefix..].iter().rev().zip(right[prefix..].iter().rev())
+                .take_while(|(a, b)| a == b).count();
+            let (left, right) = (&left[prefix..left.len() - suffix], &right[prefix..right.len() - suffix]);
+            for (offset, (a, b)) in left.iter().zip(right).enumerate() {
+                diff_values(a, b, &format!("{}/{}", path, prefix + offset), ops);
+            }
+            for offset in (right.len()..left.len()).rev() {
+                ops.push(PatchOp::Remove { path: format!("{}/{}", path, prefix + offset) });
+            }
+            for (offset, value) in right.iter().enumerate().skip(left.len()) {
+                ops.push(PatchOp::Add { path: format!("{}/{}", path, prefix + offset), value: value.clone() });
+            }
+        },
+        _ => ops.push(PatchOp::Replace { path: path.to_string(), value: to.clone() })
+    }
+}
+
+/// Patch turning `from` into `to`. Objects are compared member by member;
+/// arrays item by item after their common prefix and suffix, so a single
+/// inserted or removed item costs one operation.
+pub fn diff(from: &JsonValue, to: &JsonValue) -> Vec<PatchOp> {
+    let mut ops = vec![];
+    diff_values(from, to, "", &mut ops);
+    ops
+}
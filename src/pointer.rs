@@ -0,0 +1,91 @@
+use std::error::Error;
+use std::fmt;
+
+
+/// Malformed JSON Pointer (RFC 6901), or one that does not lead to a value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointerError {
+    pub pointer: String,
+    pub msg: String
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in pointer \'{}\'", self.msg, self.pointer)
+    }
+}
+
+impl Error for PointerError {}
+
+impl PointerError {
+    pub(crate) fn new(pointer: &str, msg: impl Into<String>) -> Self {
+        Self { pointer: pointer.to_string(), msg: msg.into() }
+    }
+}
+
+/// Reference tokens of `pointer` with `~1` and `~0` unescaped; the empty
+/// pointer has none and refers to the whole document.
+pub(crate) fn tokens(pointer: &str) -> Result<Vec<String>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(PointerError::new(pointer, "missing leading '/'"));
+    };
+    rest.split('/').map(|token| {
+        let is_valid = token.match_indices('~').all(|(at, _)| matches!(token.as_bytes().get(at + 1), Some(b'0' | b'1')));
+        if ! is_valid {
+            return Err(PointerError::new(pointer, format!("invalid escape in token \'{}\'", token)));
+        }
+        Ok(token.replace("~1", "/").replace("~0", "~"))
+    }).collect()
+}
+
+/// Reference token naming the key `key`, with `~` and `/` escaped.
+pub(crate) fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Array index named by `token`: digits without leading zeros.
+pub(crate) fn index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) || ! token.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Parsed JSON Pointer, e.g. `/users/0/name`. The default one has no
+/// tokens and refers to the whole document.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct JsonPointer {
+    tokens: Vec<String>
+}
+
+impl JsonPointer {
+    pub fn parse(pointer: &str) -> Result<Self, PointerError> {
+        tokens(pointer).map(|tokens| Self { tokens })
+    }
+
+    /// Unescaped reference tokens.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// Number of tokens, i.e. how deep the value is nested.
+    pub fn depth(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Pointer to the member `token` of the value this one refers to.
+    pub fn child(&self, token: impl Into<String>) -> Self {
+        let mut tokens = self.tokens.clone();
+        tokens.push(token.into());
+        Self { tokens }
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.tokens.iter().try_for_each(|token| write!(f, "/{}", escape(token)))
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::HashSet;
+use std::mem;
+
+use crate::lexer::number_length;
+use crate::parser::{
+    count_control_characters, intern_key, number_value, unescape, DuplicateKeys, JsonEvent, JsonMap, JsonValue,
+    ParseError, ParseErrorKind, ParserOptions
+};
+
+
+/// What the grammar allows after the input consumed so far.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Expect {
+    Value,
+    ValueOrEnd,
+    KeyOrEnd,
+    Key,
+    Colon,
+    CommaOrEnd,
+    Done
+}
+
+/// Token whose bytes may span several chunks.
+enum Pending {
+    None,
+    /// String body as read so far and its length in characters.
+    Str { raw: Vec<u8>, length: usize, is_escaped: bool },
+    Number(Vec<u8>),
+    Word(Vec<u8>)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Frame {
+    Array,
+    Object
+}
+
+#[derive(Clone, Copy, Default)]
+struct Position {
+    row: usize,
+    col: usize,
+    offset: usize
+}
+
+trait EventTarget {
+    /// Receives the next event; `Err` carries the message of a duplicate
+    /// key error.
+    fn event(&mut self, event: JsonEvent<'_>) -> Result<(), String>;
+}
+
+struct Handler<F>(F);
+
+impl<F: FnMut(JsonEvent<'_>)> EventTarget for Handler<F> {
+    fn event(&mut self, event: JsonEvent<'_>) -> Result<(), String> {
+        (self.0)(event);
+        Ok(())
+    }
+}
+
+enum Node {
+    Array(Vec<JsonValue>),
+    Object(JsonMap<JsonValue>, Option<String>)
+}
+
+/// Assembles events into a `JsonValue` tree.
+struct TreeBuilder {
+    duplicate_keys: DuplicateKeys,
+    nodes: Vec<Node>,
+    root: Option<JsonValue>
+}
+
+impl TreeBuilder {
+    fn add(&mut self, value: JsonValue) {
+        match self.nodes.last_mut() {
+            None => self.root = Some(value),
+            Some(Node::Array(arr)) => arr.push(value),
+            Some(Node::Object(obj, key)) => if let Some(key) = key.take() {
+                if self.duplicate_keys == DuplicateKeys::KeepFirst {
+                    obj.entry(intern_key(&key)).or_insert(value);
+                } else {
+                    obj.insert(intern_key(&key), value);
+                }
+            }
+        }
+    }
+}
+
+impl EventTarget for TreeBuilder {
+    fn event(&mut self, event: JsonEvent<'_>) -> Result<(), String> {
+        match event {
+            JsonEvent::StartArray => self.nodes.push(Node::Array(vec![])),
+            JsonEvent::StartObject => self.nodes.push(Node::Object(JsonMap::new(), None)),
+            JsonEvent::EndArray | JsonEvent::EndObject => {
+                let value = match self.nodes.pop() {
+                    Some(Node::Array(arr)) => JsonValue::ARRAY(arr),
+                    Some(Node::Object(obj, _)) => JsonValue::OBJECT(obj),
+                    None => JsonValue::NULL
+                };
+                self.add(value);
+            },
+            JsonEvent::Key(name) => if let Some(Node::Object(obj, key)) = self.nodes.last_mut() {
+                if obj.contains_key(name) && self.duplicate_keys == DuplicateKeys::Error {
+                    return Err(format!("duplicate key \'{}\'", name));
+                }
+                *key = Some(name.to_string());
+            },
+            JsonEvent::String(line) => self.add(JsonValue::STRING(line.to_string())),
+            JsonEvent::Number(number) => self.add(JsonValue::NUMBER(number)),
+            JsonEvent::RawNumber(literal) => self.add(JsonValue::RAWNUMBER(literal.to_string())),
+            JsonEvent::Bool(true) => self.add(JsonValue::TRUE),
+            JsonEvent::Bool(false) => self.add(JsonValue::FALSE),
+            JsonEvent::Null => self.add(JsonValue::NULL)
+        }
+        Ok(())
+    }
+}
+
+/// Incremental parser driven by the caller: bytes are pushed in chunks of
+/// any size with `feed` and the end of input is signalled with `finish`.
+/// Use either `feed`/`finish` to get a value, or `feed_events`/
+/// `finish_events` to get events as soon as their tokens are complete.
+///
+/// Only strict JSON is accepted: the lenient extensions of `ParserOptions`
+/// and `raw_depth` fail the first call to `feed` or `finish` with
+/// `ParseErrorKind::Unsupported`. The depth limit, lone surrogates, number
+/// literals, control characters, the `Limits` and the duplicate key policy
+/// are honoured, the latter without warnings.
+pub struct PushParser {
+    options: ParserOptions,
+    expect: Expect,
+    frames: Vec<Frame>,
+    /// Distinct keys of the open objects, tracked only with
+    /// `Limits::max_keys`.
+    keys: Vec<HashSet<String>>,
+    /// Values started so far, containers included.
+    nodes: usize,
+    pending: Pending,
+    token_start: Position,
+    position: Position,
+    failure: Option<ParseError>,
+    tree: TreeBuilder
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new(ParserOptions::default())
+    }
+}
+
+fn is_number_byte(byte: u8) -> bool {
+    byte.is_ascii_digit() || matches!(byte, b'-' | b'+' | b'.' | b'e' | b'E')
+}
+
+/// Checks that every backslash in a string body starts a valid escape.
+fn has_valid_escapes(raw: &[char]) -> bool {
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == '\\' {
+            match raw.get(i + 1) {
+                Some('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => i += 1,
+                Some('u') if raw.get(i + 2..i + 6).is_some_and(|hex| hex.iter().all(char::is_ascii_hexdigit)) => i += 5,
+                _ => return false
+            }
+        }
+        i += 1;
+    }
+    true
+}
+
+impl PushParser {
+    pub fn new(options: ParserOptions) -> Self {
+        let duplicate_keys = options.duplicate_keys;
+        let failure = options.unsupported_error("push parser", false);
+        Self {
+            options,
+            expect: Expect::Value,
+            frames: vec![],
+            keys: vec![],
+            nodes: 0,
+            pending: Pending::None,
+            token_start: Position::default(),
+            position: Position::default(),
+            failure,
+            tree: TreeBuilder { duplicate_keys, nodes: vec![], root: None }
+        }
+    }
+
+    /// Consumes the next chunk of input, building the value.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), ParseError> {
+        let mut tree = mem::replace(&mut self.tree,
+            TreeBuilder { duplicate_keys: DuplicateKeys::KeepLast, nodes: vec![], root: None });
+        let result = self.drive(chunk, &mut tree);
+        self.tree = tree;
+        result
+    }
+
+    /// Ends the input and returns the value built from all chunks.
+    pub fn finish(mut self) -> Result<JsonValue, ParseError> {
+        let mut tree = mem::replace(&mut self.tree,
+            TreeBuilder { duplicate_keys: DuplicateKeys::KeepLast, nodes: vec![], root: None });
+        self.end(&mut tree)?;
+        Ok(tree.root.unwrap_or(JsonValue::NULL))
+    }
+
+    /// Consumes the next chunk of input, reporting every completed event
+    /// to `handler`.
+    pub fn feed_events(&mut self, chunk: &[u8], handler: impl FnMut(JsonEvent<'_>)) -> Result<(), ParseError> {
+        self.drive(chunk, &mut Handler(handler))
+    }
+
+    /// Ends the input, reporting a number or literal still pending.
+    pub fn finish_events(mut self, handler: impl FnMut(JsonEvent<'_>)) -> Result<(), ParseError> {
+        self.end(&mut Handler(handler))
+    }
+
+    fn drive(&mut self, chunk: &[u8], target: &mut dyn EventTarget) -> Result<(), ParseError> {
+        if let Some(error) = &self.failure {
+            return Err(error.clone());
+        }
+        for &byte in chunk {
+            if let Err(error) = self.step(byte, target) {
+                self.failure = Some(error.clone());
+                return Err(error);
+            }
+            self.position.offset += 1;
+            if byte == b'\n' {
+                self.position.row += 1;
+                self.position.col = 0;
+            } else if byte & 0xC0 != 0x80 {
+                self.position.col += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, target: &mut dyn EventTarget) -> Result<(), ParseError> {
+        if let Some(error) = &self.failure {
+            return Err(error.clone());
+        }
+        if matches!(self.pending, Pending::Number(_) | Pending::Word(_)) {
+            self.end_token(target)?;
+        }
+        if matches!(self.pending, Pending::Str { .. }) || self.expect != Expect::Done {
+            return Err(self.unexpected_error(None));
+        }
+        Ok(())
+    }
+
+    fn error(&self, start: Position, end: usize, kind: ParseErrorKind, msg: String) -> ParseError {
+        ParseError {
+            kind,
+            row: start.row,
+            col: start.col,
+            offset: start.offset,
+            span: start.offset..end,
+            expected: vec![],
+            msg
+        }
+    }
+
+    /// Error for `byte`, or for the end of input when it is `None`, at the
+    /// current position.
+    fn unexpected_error(&self, byte: Option<u8>) -> ParseError {
+        let closing = if self.frames.last() == Some(&Frame::Array) { "']'" } else { "'}'" };
+        let expected: Vec<String> = match self.expect {
+            _ if ! matches!(self.pending, Pending::None) => vec![],
+            Expect::Value => vec!["value"],
+            Expect::ValueOrEnd => vec!["value", "']'"],
+            Expect::KeyOrEnd => vec!["'}'", "'\"'"],
+            Expect::Key => vec!["'\"'"],
+            Expect::Colon => vec!["':'"],
+            Expect::CommaOrEnd => vec!["','", closing],
+            Expect::Done => vec![]
+        }.into_iter().map(String::from).collect();
+        let (kind, end, msg) = match byte {
+            Some(byte) if byte.is_ascii() => (ParseErrorKind::UnexpectedChar, self.position.offset + 1,
+                format!("unexpected symbol {:?}", byte as char)),
+            Some(byte) => (ParseErrorKind::UnexpectedChar, self.position.offset + 1,
+                format!("unexpected byte 0x{:02x}", byte)),
+            None => (ParseErrorKind::UnexpectedEof, self.position.offset, "unexpected end of input".to_string())
+        };
+        let msg = match expected.as_slice() {
+            [] => msg,
+            [single] => format!("{}, expected {}", msg, single),
+            [init @ .., last] => format!("{}, expected {} or {}", msg, init.join(", "), last)
+        };
+        ParseError { expected, ..self.error(self.position, end, kind, msg) }
+    }
+
+    fn step(&mut self, byte: u8, target: &mut dyn EventTarget) -> Result<(), ParseError> {
+        match &mut self.pending {
+            Pending::Str { raw, length, is_escaped } => {
+                if byte == b'"' && ! *is_escaped {
+                    return self.end_string(target);
+                }
+                if byte < b' ' {
+                    if ! self.options.allow_control_characters {
+                        return Err(self.error(self.position, self.position.offset + 1, ParseErrorKind::UnexpectedChar,
+                            format!("unescaped control character {:?} in string", byte as char)));
+                    }
+                    count_control_characters(1);
+                }
+                *is_escaped = byte == b'\\' && ! *is_escaped;
+                raw.push(byte);
+                if byte & 0xC0 != 0x80 {
+                    *length += 1;
+                }
+                let length = *length;
+                return match self.options.limits.max_string_length.filter(|max| length > *max) {
+                    Some(max_length) => Err(self.error(self.token_start, self.position.offset + 1,
+                        ParseErrorKind::StringTooLong, format!("string too long, limit is {} characters", max_length))),
+                    None => Ok(())
+                };
+            },
+            Pending::Number(raw) if is_number_byte(byte) => {
+                raw.push(byte);
+                return Ok(());
+            },
+            Pending::Word(raw) if byte.is_ascii_alphabetic() => {
+                raw.push(byte);
+                return Ok(());
+            },
+            Pending::Number(_) | Pending::Word(_) => self.end_token(target)?,
+            Pending::None => {}
+        }
+        self.start(byte, target)
+    }
+
+    fn start(&mut self, byte: u8, target: &mut dyn EventTarget) -> Result<(), ParseError> {
+        if matches!(byte, b' ' | b'\n' | b'\r' | b'\t') {
+            return Ok(());
+        }
+        let last = self.frames.last().copied();
+        if matches!(self.expect, Expect::Value | Expect::ValueOrEnd) && ! matches!(byte, b']' | b',') {
+            self.nodes += 1;
+            if let Some(max_nodes) = self.options.limits.max_nodes.filter(|max| self.nodes > *max) {
+                return Err(self.error(self.position, self.position.offset + 1, ParseErrorKind::TooManyNodes,
+                    format!("too many values, limit is {}", max_nodes)));
+            }
+        }
+        match (self.expect, byte) {
+            (Expect::Value | Expect::ValueOrEnd, b'[' | b'{') => {
+                if self.frames.len() == self.options.max_depth {
+                    return Err(self.error(self.position, self.position.offset + 1, ParseErrorKind::DepthExceeded,
+                        format!("nesting too deep, limit is {} levels", self.options.max_depth)));
+                }
+                if byte == b'[' {
+                    self.emit(target, JsonEvent::StartArray)?;
+                    self.frames.push(Frame::Array);
+                    self.expect = Expect::ValueOrEnd;
+                } else {
+                    self.emit(target, JsonEvent::StartObject)?;
+                    self.frames.push(Frame::Object);
+                    if self.options.limits.max_keys.is_some() {
+                        self.keys.push(HashSet::new());
+                    }
+                    self.expect = Expect::KeyOrEnd;
+                }
+            },
+            (Expect::ValueOrEnd | Expect::CommaOrEnd, b']') if last == Some(Frame::Array) => {
+                self.frames.pop();
+                self.emit(target, JsonEvent::EndArray)?;
+                self.complete_value();
+            },
+            (Expect::KeyOrEnd | Expect::CommaOrEnd, b'}') if last == Some(Frame::Object) => {
+                self.frames.pop();
+                self.keys.pop();
+                self.emit(target, JsonEvent::EndObject)?;
+                self.complete_value();
+            },
+            (Expect::CommaOrEnd, b',') => {
+                self.expect = if last == Some(Frame::Array) { Expect::Value } else { Expect::Key };
+            },
+            (Expect::Colon, b':') => self.expect = Expect::Value,
+            (Expect::Value | Expect::ValueOrEnd | Expect::KeyOrEnd | Expect::Key, b'"') => {
+                self.token_start = self.position;
+                self.pending = Pending::Str { raw: vec![], length: 0, is_escaped: false };
+            },
+            (Expect::Value | Expect::ValueOrEnd, b'-' | b'0'..=b'9') => {
+                self.token_start = self.position;
+                self.pending = Pending::Number(vec![byte]);
+            },
+            (Expect::Value | Expect::ValueOrEnd, b't' | b'f' | b'n') => {
+                self.token_start = self.position;
+                self.pending = Pending::Word(vec![byte]);
+            },
+            _ => return Err(self.unexpected_error(Some(byte)))
+        }
+        Ok(())
+    }
+
+    fn emit(&self, target: &mut dyn EventTarget, event: JsonEvent<'_>) -> Result<(), ParseError> {
+        target.event(event).map_err(|msg|
+            self.error(self.token_start, self.position.offset, ParseErrorKind::DuplicateKey, msg))
+    }
+
+    fn complete_value(&mut self) {
+        self.expect = if self.frames.is_empty() { Expect::Done } else { Expect::CommaOrEnd };
+    }
+
+    fn end_string(&mut self, target: &mut dyn EventTarget) -> Result<(), ParseError> {
+        let Pending::Str { raw, .. } = mem::replace(&mut self.pending, Pending::None) else {
+            return Ok(());
+        };
+        let end = self.position.offset + 1;
+        let Ok(body) = String::from_utf8(raw) else {
+            return Err(self.error(self.token_start, end, ParseErrorKind::Io,
+                "unable to read input: stream did not contain valid UTF-8".to_string()));
+        };
+        let chars: Vec<char> = body.chars().collect();
+        if ! has_valid_escapes(&chars) {
+            return Err(self.error(self.token_start, end, ParseErrorKind::InvalidEscape,
+                "invalid escape sequence".to_string()));
+        }
+        let decoded = unescape(&chars, self.options.allow_lone_surrogates)
+            .map_err(|reason| self.error(self.token_start, end, ParseErrorKind::InvalidEscape, reason))?;
+        if matches!(self.expect, Expect::KeyOrEnd | Expect::Key) {
+            if let Some((keys, max_keys)) = self.keys.last_mut().zip(self.options.limits.max_keys) {
+                if ! keys.contains(&decoded) && keys.len() >= max_keys {
+                    return Err(self.error(self.token_start, end, ParseErrorKind::TooManyKeys,
+                        format!("too many keys in object, limit is {}", max_keys)));
+                }
+                keys.insert(decoded.clone());
+            }
+            self.emit(target, JsonEvent::Key(&decoded))?;
+            self.expect = Expect::Colon;
+        } else {
+            self.emit(target, JsonEvent::String(&decoded))?;
+            self.complete_value();
+        }
+        Ok(())
+    }
+
+    fn end_token(&mut self, target: &mut dyn EventTarget) -> Result<(), ParseError> {
+        let raw = match mem::replace(&mut self.pending, Pending::None) {
+            Pending::Number(raw) | Pending::Word(raw) => raw,
+            _ => return Ok(())
+        };
+        let text = String::from_utf8_lossy(&raw);
+        let end = self.token_start.offset + raw.len();
+        let event = match text.as_ref() {
+            "true" => JsonEvent::Bool(true),
+            "false" => JsonEvent::Bool(false),
+            "null" => JsonEvent::Null,
+            number if number_length(number) == Some(number.len()) => match number_value(number) {
+                Some(_) if self.options.preserve_number_literals => JsonEvent::RawNumber(number),
+                Some(value) => JsonEvent::Number(value),
+                None => JsonEvent::Null
+            },
+            other => return Err(self.error(self.token_start, end, ParseErrorKind::UnexpectedChar,
+                format!("unexpected token {:?}", other)))
+        };
+        self.emit(target, event)?;
+        self.complete_value();
+        Ok(())
+    }
+}
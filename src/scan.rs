@@ -0,0 +1,48 @@
+//! Bulk scanning of whitespace runs and string bodies. With the `simd`
+//! feature the searches use vectorized routines.
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\n' | b'\r' | b'\t')
+}
+
+/// Length of the run of bytes matching `is_member` at the beginning of
+/// `bytes`.
+#[cfg(not(feature = "simd"))]
+fn run_len(bytes: &[u8], is_member: impl Fn(u8) -> bool) -> usize {
+    bytes.iter().position(|byte| ! is_member(*byte)).unwrap_or(bytes.len())
+}
+
+/// Length of the run of bytes matching `is_member` at the beginning of
+/// `bytes`, checked 16 bytes at a time without early exit so the compiler
+/// can vectorize it.
+#[cfg(feature = "simd")]
+fn run_len(bytes: &[u8], is_member: impl Fn(u8) -> bool) -> usize {
+    let mut length = 0;
+    for block in bytes.chunks(16) {
+        let is_run = block.iter().fold(true, |is_run, byte| is_run & is_member(*byte));
+        if ! is_run {
+            return length + block.iter().position(|byte| ! is_member(*byte)).unwrap_or(block.len());
+        }
+        length += block.len();
+    }
+    length
+}
+
+/// Length of the whitespace run at the beginning of `bytes`.
+pub(crate) fn whitespace_len(bytes: &[u8]) -> usize {
+    run_len(bytes, is_whitespace)
+}
+
+/// Position of the first `quote`, backslash or control character in
+/// `bytes`; control characters are left to the parser to reject or count.
+#[cfg(not(feature = "simd"))]
+pub(crate) fn string_special(bytes: &[u8], quote: u8) -> Option<usize> {
+    bytes.iter().position(|byte| *byte == quote || *byte == b'\\' || *byte < 0x20)
+}
+
+#[cfg(feature = "simd")]
+pub(crate) fn string_special(bytes: &[u8], quote: u8) -> Option<usize> {
+    let end = memchr::memchr2(quote, b'\\', bytes);
+    let head = &bytes[..end.unwrap_or(bytes.len())];
+    Some(run_len(head, |byte| byte >= 0x20)).filter(|length| *length < head.len()).or(end)
+}
@@ -0,0 +1,110 @@
+use crate::detect::StringFormat;
+use crate::parser::JsonValue;
+use crate::sniffer::{member_path, JsonStatsReport, ReportDetails};
+
+
+/// Dialect declared by the inferred schemas.
+pub const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// `type` keyword for the stats' type names, a single type written on its
+/// own.
+fn type_keyword<'a>(type_names: impl IntoIterator<Item = &'a str>) -> JsonValue {
+    let mut types: Vec<&str> = type_names.into_iter()
+        .map(|type_name| match type_name {
+            "true" | "false" => "boolean",
+            other => other
+        })
+        .collect();
+    types.sort();
+    types.dedup();
+    match types.as_slice() {
+        [single] => JsonValue::from(*single),
+        _ => JsonValue::from(types)
+    }
+}
+
+/// Schema of values of the given types; the arrays and objects among them
+/// are described by `containers`, whose types are added if missing.
+fn values_schema<'a>(type_names: impl IntoIterator<Item = &'a str>, containers: &[&JsonStatsReport]) -> JsonValue {
+    let kinds = containers.iter().map(|container| container.kind());
+    let mut schema = json!({"type": type_keyword(type_names.into_iter().chain(kinds))});
+    for container in containers {
+        container.add_keywords(&mut schema);
+    }
+    schema
+}
+
+/// Adds the keyword the strings of `format` satisfy, if there is one.
+fn add_format(schema: &mut JsonValue, format: Option<StringFormat>) {
+    match format {
+        Some(StringFormat::Uuid) => {
+            schema.insert("format", "uuid".into());
+        },
+        Some(StringFormat::Email) => {
+            schema.insert("format", "email".into());
+        },
+        Some(StringFormat::Url) => {
+            schema.insert("format", "uri".into());
+        },
+        Some(StringFormat::Ipv4) => {
+            schema.insert("format", "ipv4".into());
+        },
+        Some(StringFormat::Ipv6) => {
+            schema.insert("format", "ipv6".into());
+        },
+        Some(StringFormat::Base64) => {
+            schema.insert("contentEncoding", "base64".into());
+        },
+        Some(StringFormat::Hex(length)) => {
+            schema.insert("pattern", format!("^[0-9a-fA-F]{{{}}}$", length).into());
+        },
+        _ => {}
+    }
+}
+
+impl JsonStatsReport {
+    /// Adds `properties` and `required`, or `items`, to `schema`.
+    fn add_keywords(&self, schema: &mut JsonValue) {
+        match &self.details {
+            ReportDetails::Object { mandatory, primitive_keys, members, .. } => {
+                let mut properties: Vec<(&str, Vec<&str>, Option<&JsonStatsReport>)> = primitive_keys.iter()
+                    .map(|(key, types)| (key.as_str(), types.iter().map(String::as_str).collect(), None))
+                    .collect();
+                for (key, stats) in members {
+                    match properties.iter_mut().find(|(name, _, _)| name == key) {
+                        Some((_, types, container)) => {
+                            types.push(stats.kind());
+                            *container = Some(stats);
+                        },
+                        None => properties.push((key, vec![stats.kind()], Some(stats)))
+                    }
+                }
+                schema.insert("properties", JsonValue::object(properties.into_iter().map(|(key, types, container)| {
+                    let mut property = values_schema(types, container.as_slice());
+                    add_format(&mut property, self.consistent_format(&member_path(&self.path, key)));
+                    (key, property)
+                })));
+                if ! mandatory.is_empty() {
+                    schema.insert("required", mandatory.clone().into());
+                }
+            },
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                let containers: Vec<&JsonStatsReport> = inner_arrays.iter().chain(inner_objects).map(AsRef::as_ref).collect();
+                if self.types.is_empty() && containers.is_empty() {
+                    return;
+                }
+                let mut items = values_schema(self.types.iter().map(String::as_str), &containers);
+                add_format(&mut items, self.consistent_format(&format!("{}[*]", self.path)));
+                schema.insert("items", items);
+            }
+        }
+    }
+
+    /// JSON Schema (draft 2020-12) the collected documents conform to:
+    /// types, `properties` with the mandatory keys `required`, and `items`.
+    pub fn to_json_schema(&self) -> JsonValue {
+        let mut schema = json!({"$schema": DRAFT, "type": self.kind()});
+        self.add_keywords(&mut schema);
+        schema
+    }
+}
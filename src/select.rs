@@ -0,0 +1,172 @@
+use crate::jsonpath::members;
+use crate::parser::JsonValue;
+use crate::pointer;
+
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    /// Member of an object, or item of an array if the key is an index.
+    Key(String),
+    Index(usize),
+    /// `*`, any member of an object or item of an array.
+    Any,
+    /// `[*]`, any item of an array.
+    AnyItem,
+    /// `**`, any number of members or items, none included.
+    AnyPath
+}
+
+/// Step from a value to one inside it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathStep<'a> {
+    Member(&'a str),
+    Item(usize)
+}
+
+impl Segment {
+    /// Whether the segment takes `step`, `**` aside.
+    fn takes(&self, step: PathStep<'_>) -> bool {
+        match (self, step) {
+            (Segment::Key(key), PathStep::Member(member)) => key == member,
+            (Segment::Key(key), PathStep::Item(index)) => pointer::index(key) == Some(index),
+            (Segment::Index(index), PathStep::Item(item)) => *index == item,
+            (Segment::Any, _) | (Segment::AnyItem, PathStep::Item(_)) => true,
+            _ => false
+        }
+    }
+}
+
+/// Dotted path with wildcards, e.g. `users.*.email`, `users[*].tags[0]` or
+/// `**.debug`; a simpler alternative to `JsonPath`. The empty pattern refers to the
+/// whole value. Brackets holding neither `*` nor an index are part of the
+/// key.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WildcardPath {
+    segments: Vec<Segment>
+}
+
+/// Segments of `part`, a run of `[*]` and `[N]`; `None` if anything else
+/// is in it.
+fn brackets(part: &str) -> Option<Vec<Segment>> {
+    let mut segments = vec![];
+    let mut rest = part;
+    while ! rest.is_empty() {
+        let (inside, tail) = rest.strip_prefix('[')?.split_once(']')?;
+        segments.push(match inside {
+            "*" => Segment::AnyItem,
+            _ => Segment::Index(pointer::index(inside)?)
+        });
+        rest = tail;
+    }
+    Some(segments)
+}
+
+impl WildcardPath {
+    pub fn parse(pattern: &str) -> Self {
+        if pattern.is_empty() {
+            return Self::default();
+        }
+        let mut segments = vec![];
+        for part in pattern.split('.') {
+            let suffix = part.find('[').and_then(|at| brackets(&part[at..]).map(|indices| (at, indices)));
+            let (key, indices) = suffix.map_or((part, vec![]), |(at, indices)| (&part[..at], indices));
+            match key {
+                "*" => segments.push(Segment::Any),
+                "**" => segments.push(Segment::AnyPath),
+                "" if ! indices.is_empty() => {},
+                _ => segments.push(Segment::Key(key.to_string()))
+            }
+            segments.extend(indices);
+        }
+        Self { segments }
+    }
+
+    /// Values of `root` matched by the path, members of objects in the
+    /// order of `JsonPath` results.
+    pub fn select<'a>(&self, root: &'a JsonValue) -> Vec<&'a JsonValue> {
+        let mut nodes = vec![root];
+        for segment in &self.segments {
+            nodes = nodes.into_iter().flat_map(|node| -> Vec<&JsonValue> {
+                match (segment, node) {
+                    (Segment::AnyPath, node) => {
+                        let mut nodes = vec![];
+                        descendants(node, &mut nodes);
+                        nodes
+                    },
+                    (Segment::Key(key), JsonValue::OBJECT(object)) => object.get(key.as_str()).into_iter().collect(),
+                    (Segment::Key(key), JsonValue::ARRAY(array)) => pointer::index(key)
+                        .and_then(|index| array.get(index)).into_iter().collect(),
+                    (Segment::Index(index), JsonValue::ARRAY(array)) => array.get(*index).into_iter().collect(),
+                    (Segment::Any, JsonValue::OBJECT(object)) => members(object).into_iter().map(|(_, member)| member).collect(),
+                    (Segment::Any | Segment::AnyItem, JsonValue::ARRAY(array)) => array.iter().collect(),
+                    _ => vec![]
+                }
+            }).collect();
+        }
+        nodes
+    }
+
+    fn matches_from(segments: &[Segment], steps: &[PathStep<'_>], is_partial: bool) -> bool {
+        match (segments.split_first(), steps.split_first()) {
+            (None, None) => true,
+            (Some((Segment::AnyPath, rest)), _) => Self::matches_from(rest, steps, is_partial)
+                || (! steps.is_empty() && Self::matches_from(segments, &steps[1..], is_partial)),
+            (None, Some(_)) => false,
+            (Some(_), None) => is_partial,
+            (Some((segment, rest)), Some((step, tail))) => segment.takes(*step) && Self::matches_from(rest, tail, is_partial)
+        }
+    }
+
+    /// Whether the path matches the value `steps` lead to from the root.
+    pub fn matches(&self, steps: &[PathStep<'_>]) -> bool {
+        Self::matches_from(&self.segments, steps, false)
+    }
+
+    /// Whether the path may match values inside the one `steps` lead to,
+    /// or that value itself.
+    pub fn leads_through(&self, steps: &[PathStep<'_>]) -> bool {
+        Self::matches_from(&self.segments, steps, true)
+    }
+}
+
+/// `node` and the values inside it, depth-first.
+fn descendants<'a>(node: &'a JsonValue, nodes: &mut Vec<&'a JsonValue>) {
+    nodes.push(node);
+    match node {
+        JsonValue::OBJECT(object) => members(object).into_iter().for_each(|(_, member)| descendants(member, nodes)),
+        JsonValue::ARRAY(array) => array.iter().for_each(|item| descendants(item, nodes)),
+        _ => {}
+    }
+}
+
+impl JsonValue {
+    /// Values matched by the wildcard path `pattern`, see `WildcardPath`.
+    pub fn select(&self, pattern: &str) -> Vec<&JsonValue> {
+        WildcardPath::parse(pattern).select(self)
+    }
+
+    /// Numbers matched by `pattern`, other values skipped.
+    fn numbers_at(&self, pattern: &str) -> impl Iterator<Item = f64> + '_ {
+        self.select(pattern).into_iter().filter_map(JsonValue::as_f64)
+    }
+
+    /// Number of values matched by `pattern`.
+    pub fn count_at(&self, pattern: &str) -> usize {
+        self.select(pattern).len()
+    }
+
+    /// Sum of the numbers matched by `pattern`, ignoring other values.
+    pub fn sum_at(&self, pattern: &str) -> f64 {
+        self.numbers_at(pattern).sum()
+    }
+
+    /// Least of the numbers matched by `pattern`, ignoring other values.
+    pub fn min_at(&self, pattern: &str) -> Option<f64> {
+        self.numbers_at(pattern).reduce(f64::min)
+    }
+
+    /// Greatest of the numbers matched by `pattern`, ignoring other values.
+    pub fn max_at(&self, pattern: &str) -> Option<f64> {
+        self.numbers_at(pattern).reduce(f64::max)
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::parser::{number_value, JsonMap, JsonValue, MAX_EXACT_INTEGER};
+
+
+fn serialize_number<S: Serializer>(number: f64, serializer: S) -> Result<S::Ok, S::Error> {
+    if number.fract() == 0f64 && number.abs() <= MAX_EXACT_INTEGER {
+        return serializer.serialize_i64(number as i64);
+    }
+    serializer.serialize_f64(number)
+}
+
+/// Numbers are written as integers when they have no fraction, so that they
+/// stay integers in e.g. `serde_json::Value`; number literals are written
+/// as integers when they fit into `i64` or `u64`.
+impl Serialize for JsonValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonValue::STRING(line) => serializer.serialize_str(line),
+            JsonValue::NUMBER(number) => serialize_number(*number, serializer),
+            JsonValue::RAWNUMBER(literal) => match (literal.parse::<i64>(), literal.parse::<u64>()) {
+                (Ok(integer), _) => serializer.serialize_i64(integer),
+                (_, Ok(integer)) => serializer.serialize_u64(integer),
+                _ => serializer.serialize_f64(number_value(literal).unwrap_or(f64::NAN))
+            },
+            JsonValue::OBJECT(object) => {
+                let mut map = serializer.serialize_map(Some(object.len()))?;
+                for (key, value) in object {
+                    map.serialize_entry(&**key, value)?;
+                }
+                map.end()
+            },
+            JsonValue::ARRAY(array) => {
+                let mut seq = serializer.serialize_seq(Some(array.len()))?;
+                for item in array {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            },
+            JsonValue::TRUE => serializer.serialize_bool(true),
+            JsonValue::FALSE => serializer.serialize_bool(false),
+            JsonValue::NULL => serializer.serialize_unit(),
+            JsonValue::RAW(..) => Err(serde::ser::Error::custom("unresolved raw value"))
+        }
+    }
+}
+
+struct JsonValueVisitor;
+
+impl<'de> Visitor<'de> for JsonValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, flag: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(flag))
+    }
+
+    fn visit_i64<E>(self, number: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::NUMBER(number as f64))
+    }
+
+    fn visit_u64<E>(self, number: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::NUMBER(number as f64))
+    }
+
+    fn visit_f64<E>(self, number: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::NUMBER(number))
+    }
+
+    fn visit_str<E>(self, line: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::STRING(line.to_string()))
+    }
+
+    fn visit_string<E>(self, line: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::STRING(line))
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::NULL)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::NULL)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        JsonValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            array.push(item);
+        }
+        Ok(JsonValue::ARRAY(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut object = JsonMap::new();
+        while let Some((key, value)) = map.next_entry::<String, JsonValue>()? {
+            object.insert(key.into(), value);
+        }
+        Ok(JsonValue::OBJECT(object))
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonValueVisitor)
+    }
+}
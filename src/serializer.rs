@@ -0,0 +1,505 @@
+use std::fmt::{self, Write};
+
+use crate::lexer::number_length;
+use crate::jsonpath::members;
+use crate::parser::{number_value, JsonMap, JsonValue};
+
+
+/// Writes `line` as a JSON string literal, escaping quotes, backslashes and
+/// control characters.
+pub(crate) fn write_string(out: &mut impl Write, line: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for symbol in line.chars() {
+        match symbol {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\u{8}' => out.write_str("\\b")?,
+            '\u{c}' => out.write_str("\\f")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            _ if symbol < ' ' => write!(out, "\\u{:04x}", symbol as u32)?,
+            _ => out.write_char(symbol)?
+        }
+    }
+    out.write_char('"')
+}
+
+/// Writes `number` in the shortest form that reads back to the same value;
+/// `NaN` and infinities have no JSON form and are written as `null`.
+pub(crate) fn write_number(out: &mut impl Write, number: f64) -> fmt::Result {
+    if ! number.is_finite() {
+        return out.write_str("null");
+    }
+    if number.fract() == 0f64 && number.abs() < 1e16 {
+        return write!(out, "{}", number);
+    }
+    write!(out, "{:?}", number)
+}
+
+/// Writes a number literal as it was read, unless it is only valid JSON5
+/// (hexadecimal, leading `+` or bare decimal point).
+pub(crate) fn write_literal(out: &mut impl Write, literal: &str) -> fmt::Result {
+    if number_length(literal) == Some(literal.len()) {
+        return out.write_str(literal);
+    }
+    write_number(out, number_value(literal).unwrap_or(f64::NAN))
+}
+
+/// Writes the value as JSON text on a single line, with a space after every
+/// `,` and `:`. Object members follow the map order, which is the document
+/// order with the `preserve_order` feature.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::STRING(line) => write_string(f, line),
+            JsonValue::NUMBER(number) => write_number(f, *number),
+            JsonValue::RAWNUMBER(literal) => write_literal(f, literal),
+            JsonValue::OBJECT(object) => {
+                f.write_char('{')?;
+                for (index, (key, value)) in object.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ": {}", value)?;
+                }
+                f.write_char('}')
+            },
+            JsonValue::ARRAY(array) => {
+                f.write_char('[')?;
+                for (index, item) in array.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_char(']')
+            },
+            JsonValue::TRUE => f.write_str("true"),
+            JsonValue::FALSE => f.write_str("false"),
+            JsonValue::NULL => f.write_str("null"),
+            JsonValue::RAW(_, text) => f.write_str(text)
+        }
+    }
+}
+
+/// Indentation of nested lines written by `Formatter`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs
+}
+
+/// Pretty-printer writing every array item and object member on its own
+/// line.
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    pub indent: Indent,
+    /// Write object members in the order of their keys instead of the map
+    /// order, so that the output does not depend on how the value was built.
+    pub sort_keys: bool,
+    /// End the output with a newline.
+    pub trailing_newline: bool
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(2),
+            sort_keys: false,
+            trailing_newline: false
+        }
+    }
+}
+
+impl Formatter {
+    pub fn format(&self, value: &JsonValue) -> String {
+        let mut text = String::new();
+        // writing into a String cannot fail
+        let _ = self.write(&mut text, value);
+        text
+    }
+
+    pub fn write(&self, out: &mut impl Write, value: &JsonValue) -> fmt::Result {
+        self.write_value(out, value, 0)?;
+        if self.trailing_newline {
+            out.write_char('\n')?;
+        }
+        Ok(())
+    }
+
+    fn write_line(&self, out: &mut impl Write, depth: usize) -> fmt::Result {
+        out.write_char('\n')?;
+        match self.indent {
+            Indent::Spaces(width) => (0..width * depth).try_for_each(|_| out.write_char(' ')),
+            Indent::Tabs => (0..depth).try_for_each(|_| out.write_char('\t'))
+        }
+    }
+
+    fn write_members(&self, out: &mut impl Write, mut members: Vec<(&str, &JsonValue)>, depth: usize) -> fmt::Result {
+        if members.is_empty() {
+            return out.write_str("{}");
+        }
+        if self.sort_keys {
+            members.sort_by_key(|(key, _)| *key);
+        }
+        out.write_char('{')?;
+        for (index, (key, value)) in members.into_iter().enumerate() {
+            if index > 0 {
+                out.write_char(',')?;
+            }
+            self.write_line(out, depth + 1)?;
+            write_string(out, key)?;
+            out.write_str(": ")?;
+            self.write_value(out, value, depth + 1)?;
+        }
+        self.write_line(out, depth)?;
+        out.write_char('}')
+    }
+
+    fn write_value(&self, out: &mut impl Write, value: &JsonValue, depth: usize) -> fmt::Result {
+        match value {
+            JsonValue::OBJECT(object) => self.write_members(out,
+                object.iter().map(|(key, value)| (key.as_ref(), value)).collect(), depth),
+            JsonValue::ARRAY(array) if ! array.is_empty() => {
+                out.write_char('[')?;
+                for (index, item) in array.iter().enumerate() {
+                    if index > 0 {
+                        out.write_char(',')?;
+                    }
+                    self.write_line(out, depth + 1)?;
+                    self.write_value(out, item, depth + 1)?;
+                }
+                self.write_line(out, depth)?;
+                out.write_char(']')
+            },
+            scalar => write!(out, "{}", scalar)
+        }
+    }
+}
+
+/// Serializer writing JSON text without any whitespace.
+#[derive(Clone, Debug, Default)]
+pub struct Minifier {
+    /// Write numbers in exponent notation where that is shorter, e.g.
+    /// `1e6` for `1000000`. Number literals kept with
+    /// `ParserOptions::preserve_number_literals` are written as read.
+    pub shortest_numbers: bool
+}
+
+/// Sizes of a document before and after minification.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MinifyReport {
+    pub original_bytes: usize,
+    pub compact_bytes: usize
+}
+
+impl MinifyReport {
+    pub fn saved_bytes(&self) -> usize {
+        self.original_bytes.saturating_sub(self.compact_bytes)
+    }
+
+    /// Saved bytes in percent of the original size.
+    pub fn saved_percent(&self) -> f64 {
+        if self.original_bytes == 0 {
+            return 0f64;
+        }
+        self.saved_bytes() as f64 * 100f64 / self.original_bytes as f64
+    }
+}
+
+impl Minifier {
+    pub fn minify(&self, value: &JsonValue) -> String {
+        let mut text = String::new();
+        // writing into a String cannot fail
+        let _ = self.write(&mut text, value);
+        text
+    }
+
+    /// Minifies `value` parsed from `original_bytes` of text, reporting the
+    /// bytes saved.
+    pub fn minify_with_report(&self, value: &JsonValue, original_bytes: usize) -> (String, MinifyReport) {
+        let text = self.minify(value);
+        let report = MinifyReport { original_bytes, compact_bytes: text.len() };
+        (text, report)
+    }
+
+    fn write_number(&self, out: &mut impl Write, number: f64) -> fmt::Result {
+        let mut plain = String::new();
+        write_number(&mut plain, number)?;
+        if self.shortest_numbers && number.is_finite() {
+            let exponent = format!("{:e}", number);
+            if exponent.len() < plain.len() {
+                return out.write_str(&exponent);
+            }
+        }
+        out.write_str(&plain)
+    }
+
+    pub fn write(&self, out: &mut impl Write, value: &JsonValue) -> fmt::Result {
+        match value {
+            JsonValue::NUMBER(number) => self.write_number(out, *number),
+            JsonValue::OBJECT(object) => {
+                out.write_char('{')?;
+                for (index, (key, value)) in object.iter().enumerate() {
+                    if index > 0 {
+                        out.write_char(',')?;
+                    }
+                    write_string(out, key)?;
+                    out.write_char(':')?;
+                    self.write(out, value)?;
+                }
+                out.write_char('}')
+            },
+            JsonValue::ARRAY(array) => {
+                out.write_char('[')?;
+                for (index, item) in array.iter().enumerate() {
+                    if index > 0 {
+                        out.write_char(',')?;
+                    }
+                    self.write(out, item)?;
+                }
+                out.write_char(']')
+            },
+            scalar => write!(out, "{}", scalar)
+        }
+    }
+}
+
+/// Sink counting the bytes written to it.
+struct ByteCount(usize);
+
+impl Write for ByteCount {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.0 += text.len();
+        Ok(())
+    }
+}
+
+/// Bytes of the JSON text of the value without any whitespace.
+pub(crate) fn compact_size(value: &JsonValue) -> usize {
+    let mut count = ByteCount(0);
+    // counting cannot fail
+    let _ = Minifier::default().write(&mut count, value);
+    count.0
+}
+
+/// Bytes of `line` as a JSON string literal.
+pub(crate) fn string_size(line: &str) -> usize {
+    let mut count = ByteCount(0);
+    let _ = write_string(&mut count, line);
+    count.0
+}
+
+/// Bytes of a number literal once written, see `write_literal`.
+pub(crate) fn literal_size(literal: &str) -> usize {
+    let mut count = ByteCount(0);
+    let _ = write_literal(&mut count, literal);
+    count.0
+}
+
+impl JsonValue {
+    /// JSON text of the value without any whitespace.
+    pub fn to_compact_string(&self) -> String {
+        Minifier::default().minify(self)
+    }
+}
+
+/// Value that has no canonical form: `NaN` or an infinity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CanonicalError {
+    pub msg: String
+}
+
+impl fmt::Display for CanonicalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for CanonicalError {}
+
+/// Writes `number` as ECMAScript's `Number.prototype.toString` does, which
+/// RFC 8785 requires: the shortest digits that read back to the same value,
+/// in exponent notation only below 1e-6 and from 1e21 on.
+fn write_canonical_number(out: &mut String, number: f64) -> Result<(), CanonicalError> {
+    if ! number.is_finite() {
+        return Err(CanonicalError { msg: format!("{} has no canonical form", number) });
+    }
+    if number == 0f64 {
+        out.push('0');
+        return Ok(());
+    }
+    if number < 0f64 {
+        out.push('-');
+    }
+    let scientific = format!("{:e}", number.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits = mantissa.replace('.', "");
+    let count = digits.len() as i32;
+    // position of the decimal point relative to the start of the digits
+    let point = exponent.parse::<i32>().unwrap_or(0) + 1;
+    if count <= point && point <= 21 {
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((point - count) as usize));
+    } else if 0 < point && point <= 21 {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat(-point as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if count > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        let exponent = point - 1;
+        out.push_str(&format!("e{}{}", if exponent < 0 { '-' } else { '+' }, exponent.abs()));
+    }
+    Ok(())
+}
+
+fn write_canonical(out: &mut String, value: &JsonValue) -> Result<(), CanonicalError> {
+    match value {
+        JsonValue::NUMBER(number) => write_canonical_number(out, *number),
+        JsonValue::RAWNUMBER(literal) => write_canonical_number(out, number_value(literal).unwrap_or(f64::NAN)),
+        JsonValue::OBJECT(object) => {
+            let mut members: Vec<_> = object.iter().collect();
+            // RFC 8785 orders keys by their UTF-16 code units
+            members.sort_by(|(left, _), (right, _)| left.encode_utf16().cmp(right.encode_utf16()));
+            out.push('{');
+            for (index, (key, value)) in members.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                let _ = write_string(out, key);
+                out.push(':');
+                write_canonical(out, value)?;
+            }
+            out.push('}');
+            Ok(())
+        },
+        JsonValue::ARRAY(array) => {
+            out.push('[');
+            for (index, item) in array.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, item)?;
+            }
+            out.push(']');
+            Ok(())
+        },
+        JsonValue::RAW(..) => Err(CanonicalError { msg: "unresolved raw value has no canonical form".to_string() }),
+        scalar => {
+            let _ = write!(out, "{}", scalar);
+            Ok(())
+        }
+    }
+}
+
+impl JsonValue {
+    /// Canonical JSON text of the value per RFC 8785 (JCS): no whitespace,
+    /// keys sorted, numbers formatted as in ECMAScript and only the escapes
+    /// JSON requires, so equal values always yield the same bytes.
+    pub fn to_canonical_string(&self) -> Result<String, CanonicalError> {
+        let mut text = String::new();
+        write_canonical(&mut text, self)?;
+        Ok(text)
+    }
+}
+
+/// Whether `line` can be written as a plain YAML scalar and still be read
+/// back as the same string.
+fn is_plain_yaml(line: &str) -> bool {
+    const RESERVED: [&str; 10] = ["true", "false", "null", "yes", "no", "on", "off", "y", "n", "~"];
+    line.starts_with(|symbol: char| symbol.is_ascii_alphabetic() || symbol == '_')
+        && ! line.ends_with(' ')
+        && line.chars().all(|symbol| symbol.is_ascii_alphanumeric() || " _-./".contains(symbol))
+        && ! RESERVED.iter().any(|word| line.eq_ignore_ascii_case(word))
+}
+
+fn write_yaml_string(out: &mut impl Write, line: &str) -> fmt::Result {
+    if is_plain_yaml(line) {
+        return out.write_str(line);
+    }
+    // YAML double-quoted scalars accept the escapes of JSON strings
+    write_string(out, line)
+}
+
+fn write_yaml_indent(out: &mut impl Write, indent: usize) -> fmt::Result {
+    (0..indent).try_for_each(|_| out.write_char(' '))
+}
+
+/// Writes the members of an object on their own lines, the first one
+/// continuing the current line if `is_inline`.
+fn write_yaml_members(out: &mut impl Write, object: &JsonMap<JsonValue>, indent: usize, is_inline: bool) -> fmt::Result {
+    for (index, (key, value)) in members(object).into_iter().enumerate() {
+        if index > 0 || ! is_inline {
+            write_yaml_indent(out, indent)?;
+        }
+        write_yaml_string(out, key)?;
+        out.write_char(':')?;
+        match value {
+            JsonValue::OBJECT(object) if ! object.is_empty() => {
+                out.write_char('\n')?;
+                write_yaml_members(out, object, indent + 2, false)?;
+            },
+            JsonValue::ARRAY(array) if ! array.is_empty() => {
+                out.write_char('\n')?;
+                write_yaml_items(out, array, indent + 2, false)?;
+            },
+            scalar => {
+                out.write_char(' ')?;
+                write_yaml_scalar(out, scalar)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the items of an array as `- ` entries, the first one continuing
+/// the current line if `is_inline`.
+fn write_yaml_items(out: &mut impl Write, array: &[JsonValue], indent: usize, is_inline: bool) -> fmt::Result {
+    for (index, item) in array.iter().enumerate() {
+        if index > 0 || ! is_inline {
+            write_yaml_indent(out, indent)?;
+        }
+        out.write_str("- ")?;
+        match item {
+            JsonValue::OBJECT(object) if ! object.is_empty() => write_yaml_members(out, object, indent + 2, true)?,
+            JsonValue::ARRAY(array) if ! array.is_empty() => write_yaml_items(out, array, indent + 2, true)?,
+            scalar => write_yaml_scalar(out, scalar)?
+        }
+    }
+    Ok(())
+}
+
+/// Writes a value other than a non-empty container on the rest of the line.
+fn write_yaml_scalar(out: &mut impl Write, value: &JsonValue) -> fmt::Result {
+    match value {
+        JsonValue::STRING(line) => write_yaml_string(out, line)?,
+        scalar => write!(out, "{}", scalar)?
+    }
+    out.write_char('\n')
+}
+
+impl JsonValue {
+    /// YAML block-style text of the value, ending with a newline. Object
+    /// members are in the order of `jsonpath` results and strings are
+    /// quoted unless they would be read back as the same string anyway.
+    pub fn to_yaml_string(&self) -> String {
+        let mut text = String::new();
+        // writing into a String cannot fail
+        let _ = match self {
+            JsonValue::OBJECT(object) if ! object.is_empty() => write_yaml_members(&mut text, object, 0, false),
+            JsonValue::ARRAY(array) if ! array.is_empty() => write_yaml_items(&mut text, array, 0, false),
+            scalar => write_yaml_scalar(&mut text, scalar)
+        };
+        text
+    }
+}
@@ -0,0 +1,145 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+
+/// 64-bit hash of `item`, the same in every run.
+pub(crate) fn hash_of<T: Hash + ?Sized>(item: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Approximate count of distinct items (Flajolet et al.) in `2^precision`
+/// bytes, with a standard error of about `1.04 / sqrt(2^precision)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HyperLogLog {
+    pub(crate) precision: u32,
+    pub(crate) registers: Vec<u8>
+}
+
+impl HyperLogLog {
+    /// Sketch with `2^precision` registers, `precision` clamped to 4..=16.
+    pub fn new(precision: u32) -> Self {
+        let precision = precision.clamp(4, 16);
+        Self { precision, registers: vec![0; 1 << precision] }
+    }
+
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        let hash = hash_of(item);
+        let index = (hash >> (64 - self.precision)) as usize;
+        // a sentinel bit bounds the rank for the hashes with no bits left
+        let rest = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// Estimated number of distinct items inserted.
+    pub fn estimate(&self) -> usize {
+        let registers = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / registers)
+        };
+        let sum: f64 = self.registers.iter().map(|register| 2f64.powi(-i32::from(*register))).sum();
+        let estimate = alpha * registers * registers / sum;
+        let zeros = self.registers.iter().filter(|register| **register == 0).count();
+        if estimate <= 2.5 * registers && zeros > 0 {
+            // linear counting is more accurate for few items
+            return (registers * (registers / zeros as f64).ln()).round() as usize;
+        }
+        estimate.round() as usize
+    }
+}
+
+/// Most frequent items by the Space-Saving algorithm (Metwally et al.): at
+/// most `capacity` items are counted, a new one replacing the least
+/// frequent and inheriting its count as the error of its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopK<T: Hash + Eq> {
+    pub(crate) capacity: usize,
+    /// Counts of the items and how much they may be overestimated.
+    pub(crate) counts: HashMap<T, (usize, usize)>
+}
+
+impl<T: Hash + Eq + Clone + Ord> TopK<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, counts: HashMap::new() }
+    }
+
+    pub fn insert<Q: Hash + Eq + ToOwned<Owned = T> + ?Sized>(&mut self, item: &Q) where T: Borrow<Q> {
+        if let Some((count, _)) = self.counts.get_mut(item) {
+            *count += 1;
+            return;
+        }
+        if self.counts.len() < self.capacity {
+            self.counts.insert(item.to_owned(), (1, 0));
+            return;
+        }
+        let least = self.counts.iter()
+            .min_by(|left, right| left.1.0.cmp(&right.1.0).then_with(|| right.0.cmp(left.0)))
+            .map(|(least, (count, _))| (least.clone(), *count));
+        if let Some((least, count)) = least {
+            self.counts.remove::<T>(&least);
+            self.counts.insert(item.to_owned(), (count + 1, count));
+        }
+    }
+
+    /// At most `limit` items with the number of times they were surely
+    /// inserted, exact until items were replaced; the most frequent first
+    /// and equally frequent ones in order.
+    pub fn top(&self, limit: usize) -> Vec<(T, usize)> {
+        let mut items: Vec<(T, usize)> = self.counts.iter()
+            .map(|(item, (count, error))| (item.clone(), count - error))
+            .collect();
+        items.sort_by(|left, right| right.1.cmp(&left.1).then_with(|| left.0.cmp(&right.0)));
+        items.truncate(limit);
+        items
+    }
+}
+
+/// Uniform sample of at most `capacity` of the items inserted (Vitter's
+/// algorithm R), drawn by a seeded generator so that runs agree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reservoir<T> {
+    pub(crate) capacity: usize,
+    pub(crate) seen: u64,
+    pub(crate) state: u64,
+    pub(crate) items: Vec<T>
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, seen: 0, state: 0x9E37_79B9_7F4A_7C15, items: vec![] }
+    }
+
+    /// SplitMix64.
+    fn next_random(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut mixed = self.state;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        mixed ^ (mixed >> 31)
+    }
+
+    /// Offers an item, made by `item` only if it gets into the sample.
+    pub fn insert_with(&mut self, item: impl FnOnce() -> T) {
+        self.seen += 1;
+        if self.items.len() < self.capacity {
+            self.items.push(item());
+            return;
+        }
+        let slot = (self.next_random() % self.seen) as usize;
+        if slot < self.capacity {
+            self.items[slot] = item();
+        }
+    }
+
+    /// The sample, in no particular order.
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+}
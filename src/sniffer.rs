@@ -1,12 +1,15 @@
-use std::collections::{HashSet, HashMap, BinaryHeap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, BinaryHeap};
 use std::cmp::{Reverse, Ordering};
+use std::error::Error;
+use std::fmt;
 
-use crate::parser::JsonValue;
-
-const JSON_TYPES_NAMES: [&'static str; 8] = [
-    "string", "number", "object", "array",
-    "true", "false", "null", "key-value"
-];
+use crate::detect::{base64_size, detect, format_timestamp, parse_date, StringFormat};
+use crate::parser::{intern_key, number_value, JsonEvent, JsonKey, JsonMap, JsonValue, Span, MAX_EXACT_INTEGER};
+use crate::pointer::JsonPointer;
+use crate::select::{PathStep, WildcardPath};
+use crate::serializer::{compact_size, literal_size, string_size};
+use crate::sketch::{hash_of, HyperLogLog, Reservoir, TopK};
+use crate::visitor::{visit, JsonVisitor};
 
 #[derive(PartialEq)]
 struct NonNan(f64);
@@ -15,391 +18,3510 @@ impl Eq for NonNan {}
 
 impl PartialOrd for NonNan {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for NonNan {
     fn cmp(&self, other: &NonNan) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        self.0.partial_cmp(&other.0).unwrap()
     }
 }
 
-struct JsonNumbersStats {
-    limit: usize,
-    minimums: BinaryHeap<NonNan>,
-    maximums: BinaryHeap<Reverse<NonNan>>,
-    sum: f64,
-    number: usize
+/// How the number histograms split the range of the numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistogramScale {
+    /// Buckets of equal width.
+    Linear,
+    /// Buckets of equal width on the `sign(x) * log2(1 + |x|)` scale, for
+    /// numbers spanning orders of magnitude.
+    Log
 }
 
-struct JsonArrayStats {
-    inner_arrays_stats: Option<JsonComplexTypeStats>,
-    inner_objects_stats: Option<JsonComplexTypeStats>
+impl HistogramScale {
+    fn position(self, number: f64) -> f64 {
+        match self {
+            HistogramScale::Linear => number,
+            HistogramScale::Log => number.signum() * number.abs().ln_1p() / std::f64::consts::LN_2
+        }
+    }
+
+    fn number(self, position: f64) -> f64 {
+        match self {
+            HistogramScale::Linear => position,
+            HistogramScale::Log => position.signum() * (position.abs().exp2() - 1.0)
+        }
+    }
 }
 
-struct JsonObjectStats {
-    primitives_keys: HashMap<String, HashSet<&'static str>>,
-    complex_stats: HashMap<String, JsonComplexTypeStats>,
-    nonobligatory: HashSet<String>
+/// Settings of the stats collection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsOptions {
+    /// Maximum number of buckets of the number histograms, 0 for none.
+    pub histogram_buckets: usize,
+    pub histogram_scale: HistogramScale,
+    /// Unique strings kept per place; beyond them they are only counted,
+    /// approximately.
+    pub exact_strings: usize,
+    /// Most frequent strings reported per place.
+    pub top_strings: usize,
+    /// Example values kept per key or array.
+    pub samples: usize,
+    /// Greatest and least numbers reported per place, 0 for none.
+    pub top_k: usize,
+    /// Values collected with what is inside them, all of them if there
+    /// are none; the containers leading to them are collected too.
+    pub include: Vec<WildcardPath>,
+    /// Values left out with what is inside them, whatever `include` says.
+    pub exclude: Vec<WildcardPath>,
+    /// Nesting below which values are only counted, the containers at it
+    /// collected without their content; no limit if `None`.
+    pub stat_depth: Option<usize>,
+    /// Whether items of arrays are hashed to count those repeating an
+    /// earlier item of the same array.
+    pub duplicates: bool
 }
 
-enum JsonSpecificTypeStats {
-    ARRAY(Box<JsonArrayStats>),
-    OBJECT(Box<JsonObjectStats>)
+impl Default for StatsOptions {
+    fn default() -> Self {
+        Self {
+            histogram_buckets: 10,
+            histogram_scale: HistogramScale::Linear,
+            exact_strings: 10_000,
+            top_strings: 10,
+            samples: 5,
+            top_k: 10,
+            include: vec![],
+            exclude: vec![],
+            stat_depth: None,
+            duplicates: false
+        }
+    }
 }
 
-pub struct JsonComplexTypeStats {
-    values_types: HashSet<&'static str>,
-    numbers: JsonNumbersStats,
-    strings: HashSet<String>,
-    type_stats: JsonSpecificTypeStats
+/// Registers of the distinct strings estimates are `2^STRINGS_PRECISION`.
+const STRINGS_PRECISION: u32 = 12;
+
+/// Unique strings, kept until there are more than a cutoff of them and
+/// from then on counted by a `HyperLogLog`.
+enum DistinctStrings {
+    Exact {
+        strings: HashSet<String>,
+        cutoff: usize
+    },
+    Estimated(HyperLogLog)
 }
 
-fn json_type(json: &JsonValue) -> usize {
-    match json {
-        JsonValue::STRING(_) => 0,
-        JsonValue::NUMBER(_) => 1,
-        JsonValue::OBJECT(_) => 2,
-        JsonValue::ARRAY(_) => 3,
-        JsonValue::TRUE => 4,
-        JsonValue::FALSE => 5,
-        JsonValue::NULL => 6,
-        JsonValue::KEYVALUE(_) => 7
+impl DistinctStrings {
+    fn new(cutoff: usize) -> Self {
+        DistinctStrings::Exact { strings: HashSet::new(), cutoff }
+    }
+
+    fn insert(&mut self, line: &str) {
+        match self {
+            DistinctStrings::Exact { strings, .. } if strings.contains(line) => {},
+            DistinctStrings::Exact { strings, cutoff } if strings.len() < *cutoff => {
+                strings.insert(line.to_string());
+            },
+            DistinctStrings::Exact { strings, .. } => {
+                let mut sketch = HyperLogLog::new(STRINGS_PRECISION);
+                strings.iter().for_each(|known| sketch.insert(known.as_str()));
+                sketch.insert(line);
+                *self = DistinctStrings::Estimated(sketch);
+            },
+            DistinctStrings::Estimated(sketch) => sketch.insert(line)
+        }
     }
 }
 
+/// Strings counted per value for each reported one.
+const STRINGS_PER_TOP: usize = 100;
+
+struct StringsStats {
+    distinct: DistinctStrings,
+    frequent: TopK<String>,
+    /// Most frequent strings to report.
+    top: usize
+}
+
+impl StringsStats {
+    fn new(options: &StatsOptions) -> Self {
+        Self {
+            distinct: DistinctStrings::new(options.exact_strings),
+            frequent: TopK::new(options.top_strings * STRINGS_PER_TOP),
+            top: options.top_strings
+        }
+    }
+
+    fn insert(&mut self, line: &str) {
+        self.distinct.insert(line);
+        self.frequent.insert(line);
+    }
 
-fn is_array_type(json: &JsonValue) -> bool {
-    match json {
-        JsonValue::ARRAY(_) => true,
-        _ => false
+    fn report(&self) -> StringsReport {
+        let (strings, distinct_strings, is_distinct_estimated) = match &self.distinct {
+            DistinctStrings::Exact { strings, .. } => (sorted(strings.iter().cloned()), strings.len(), false),
+            DistinctStrings::Estimated(sketch) => (vec![], sketch.estimate(), true)
+        };
+        StringsReport { strings, distinct_strings, is_distinct_estimated, top_strings: self.frequent.top(self.top) }
     }
 }
 
-fn is_object_type(json: &JsonValue) -> bool {
-    match json {
-        JsonValue::OBJECT(_) => true,
-        _ => false
+/// Histogram built as the numbers come: buckets are `width` wide on the
+/// scale and aligned on its multiples, `width` doubling whenever the
+/// numbers would take more buckets than allowed.
+struct Histogram {
+    buckets: usize,
+    scale: HistogramScale,
+    width: f64,
+    counts: BTreeMap<i64, usize>
+}
+
+impl Histogram {
+    fn new(buckets: usize, scale: HistogramScale) -> Self {
+        Self { buckets, scale, width: f64::MIN_POSITIVE, counts: BTreeMap::new() }
+    }
+
+    fn span(&self) -> usize {
+        match (self.counts.first_key_value(), self.counts.last_key_value()) {
+            (Some((first, _)), Some((last, _))) => (last - first) as usize + 1,
+            _ => 0
+        }
+    }
+
+    fn coarsen(&mut self) {
+        self.width *= 2.0;
+        for (index, count) in std::mem::take(&mut self.counts) {
+            *self.counts.entry(index.div_euclid(2)).or_insert(0) += count;
+        }
+    }
+
+    fn add(&mut self, number: f64) {
+        if self.buckets == 0 {
+            return;
+        }
+        let position = self.scale.position(number);
+        while (position / self.width).abs() >= MAX_EXACT_INTEGER {
+            self.coarsen();
+        }
+        *self.counts.entry((position / self.width).floor() as i64).or_insert(0) += 1;
+        while self.span() > self.buckets {
+            self.coarsen();
+        }
+    }
+
+    fn report(&self) -> Option<HistogramReport> {
+        let (first, last) = (*self.counts.first_key_value()?.0, *self.counts.last_key_value()?.0);
+        Some(HistogramReport {
+            edges: (first..=last + 1).map(|index| self.scale.number(index as f64 * self.width)).collect(),
+            counts: (first..=last).map(|index| self.counts.get(&index).copied().unwrap_or(0)).collect()
+        })
     }
 }
 
-fn is_complex_type(json: &JsonValue) -> bool {
-    is_array_type(json) || is_object_type(json)
+/// Estimate of a quantile by the P² algorithm (Jain and Chlamtac), which
+/// keeps five markers instead of the numbers.
+struct Quantile {
+    p: f64,
+    count: usize,
+    /// Heights of the markers, the numbers themselves until there are five.
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5]
 }
 
-fn merge_as_array_stats(mut stats: JsonArrayStats, json_array: JsonValue) -> JsonArrayStats {
-    assert!(is_array_type(&json_array));
-    if let JsonValue::ARRAY(array) = json_array {
-        for value in array {
-            if is_array_type(&value) {
-                stats.inner_arrays_stats = Some(match stats.inner_arrays_stats {
-                    Some(prev) => prev.merge_stats(value),
-                    None => JsonComplexTypeStats::from_json(value)
-                });
-                continue;
+impl Quantile {
+    fn new(p: f64) -> Self {
+        Self {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0]
+        }
+    }
+
+    fn add(&mut self, number: f64) {
+        self.count += 1;
+        if self.count <= 5 {
+            self.heights[self.count - 1] = number;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
             }
-            if is_object_type(&value) {
-                stats.inner_objects_stats = Some(match stats.inner_objects_stats {
-                    Some(prev) => prev.merge_stats(value),
-                    None => JsonComplexTypeStats::from_json(value)
-                });
+            return;
+        }
+        let cell = if number < self.heights[0] {
+            self.heights[0] = number;
+            0
+        } else if number >= self.heights[4] {
+            self.heights[4] = number;
+            3
+        } else {
+            (1..5).find(|index| number < self.heights[*index]).map_or(3, |index| index - 1)
+        };
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+        for index in 1..4 {
+            let offset = self.desired[index] - self.positions[index];
+            if (offset >= 1.0 && self.positions[index + 1] - self.positions[index] > 1.0)
+                || (offset <= -1.0 && self.positions[index - 1] - self.positions[index] < -1.0) {
+                let step = offset.signum();
+                let height = self.parabolic(index, step);
+                self.heights[index] = if self.heights[index - 1] < height && height < self.heights[index + 1] {
+                    height
+                } else {
+                    self.linear(index, step)
+                };
+                self.positions[index] += step;
             }
         }
     }
-    stats
+
+    fn parabolic(&self, index: usize, step: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        q[index] + step / (n[index + 1] - n[index - 1])
+            * ((n[index] - n[index - 1] + step) * (q[index + 1] - q[index]) / (n[index + 1] - n[index])
+                + (n[index + 1] - n[index] - step) * (q[index] - q[index - 1]) / (n[index] - n[index - 1]))
+    }
+
+    fn linear(&self, index: usize, step: f64) -> f64 {
+        let neighbour = if step > 0.0 { index + 1 } else { index - 1 };
+        self.heights[index] + step * (self.heights[neighbour] - self.heights[index])
+            / (self.positions[neighbour] - self.positions[index])
+    }
+
+    /// The estimate, exact (nearest rank) while there are five numbers or
+    /// fewer; `NaN` if there are none.
+    fn value(&self) -> f64 {
+        if self.count > 5 {
+            return self.heights[2];
+        }
+        let mut numbers = self.heights[..self.count].to_vec();
+        numbers.sort_by(f64::total_cmp);
+        let rank = (self.p * self.count as f64).ceil() as usize;
+        numbers.get(rank.max(1) - 1).copied().unwrap_or(f64::NAN)
+    }
 }
 
-fn merge_as_object_stats(mut stats: JsonObjectStats, json_object: JsonValue) -> JsonObjectStats {
-    assert!(is_object_type(&json_object));
-    if let JsonValue::OBJECT(object) = json_object {
-        for (key_mold, value) in object {
-            if is_complex_type(value.as_ref()) {
-                if stats.complex_stats.contains_key(&key_mold) {
-                    stats.nonobligatory.insert(key_mold.clone());
-                }
-                let new = match stats.complex_stats.remove(&key_mold) {
-                    Some(prev) => prev.merge_stats(*value),
-                    None => JsonComplexTypeStats::from_json(*value)
-                };
-                stats.complex_stats.insert(key_mold, new);
-                continue;
-            }
-            let is_nonobligatory = stats.primitives_keys.contains_key(&key_mold);
-            let primitive_type = JSON_TYPES_NAMES[json_type(value.as_ref())];
-            stats.primitives_keys.entry(key_mold.clone())
-                .and_modify(|types| { types.insert(primitive_type); })
-                .or_insert_with(|| HashSet::from([primitive_type]));
-            if is_nonobligatory {
-                stats.nonobligatory.insert(key_mold.clone());
-            }
+/// Numbers counted by whether they have a fractional part.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NumberKinds {
+    pub integral: usize,
+    pub fractional: usize
+}
+
+impl NumberKinds {
+    /// Counts a finite number.
+    fn add(&mut self, number: f64) {
+        if number.fract() == 0f64 {
+            self.integral += 1;
+        } else {
+            self.fractional += 1;
         }
     }
-    stats
 }
 
-impl JsonNumbersStats {
-    pub fn new(limit: usize) -> Self {
-        Self {
-            limit,
-            minimums: BinaryHeap::new(),
-            maximums: BinaryHeap::new(),
-            sum: 0f64,
-            number: 0
+impl fmt::Display for NumberKinds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} integral, {} fractional", self.integral, self.fractional)
+    }
+}
+
+impl From<&NumberKinds> for JsonValue {
+    fn from(kinds: &NumberKinds) -> Self {
+        json!({"integral": kinds.integral, "fractional": kinds.fractional})
+    }
+}
+
+/// Booleans counted by their value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BooleanCounts {
+    pub trues: usize,
+    pub falses: usize
+}
+
+impl BooleanCounts {
+    fn add(&mut self, value: bool) {
+        if value {
+            self.trues += 1;
+        } else {
+            self.falses += 1;
         }
     }
 
-    pub fn add(&mut self, number: f64) {
-        assert!(! number.is_nan());
+    /// Share of the booleans that are `true`, in percents.
+    pub fn true_ratio(&self) -> f64 {
+        percentage(self.trues, self.trues + self.falses)
+    }
 
-        if self.minimums.len() == self.limit {
-            self.minimums.pop();
+    /// Whether the booleans are all the same, making the key a likely
+    /// constant.
+    pub fn is_constant(&self) -> bool {
+        self.trues == 0 || self.falses == 0
+    }
+}
+
+/// `3 true, 1 false, 75.0% true`, or `always true` instead of the ratio
+/// for constants.
+impl fmt::Display for BooleanCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} true, {} false, ", self.trues, self.falses)?;
+        if self.is_constant() {
+            write!(f, "always {}", self.trues > 0)
+        } else {
+            write!(f, "{:.1}% true", self.true_ratio())
         }
-        self.minimums.push(NonNan(number));
-        if self.maximums.len() == self.limit {
-            self.maximums.pop();
+    }
+}
+
+impl From<&BooleanCounts> for JsonValue {
+    fn from(booleans: &BooleanCounts) -> Self {
+        json!({"true": booleans.trues, "false": booleans.falses, "true_ratio": booleans.true_ratio()})
+    }
+}
+
+struct JsonNumbersStats {
+    limit: usize,
+    histogram: Histogram,
+    /// Median, 95th and 99th percentiles.
+    quantiles: [Quantile; 3],
+    /// Running mean, kept apart from `sum` which may overflow.
+    mean: f64,
+    /// Power of two, at least 1, the deviations are divided by so that
+    /// neither they nor their squares overflow.
+    scale: f64,
+    /// Sum of the squared scaled deviations from the running mean (Welford).
+    squares: f64,
+    kinds: NumberKinds,
+    minimums: BinaryHeap<NonNan>,
+    maximums: BinaryHeap<Reverse<NonNan>>,
+    sum: f64,
+    number: usize,
+    /// `NaN` and infinities, kept out of the other numbers stats.
+    non_finite: usize,
+    /// Integers of magnitude 2^53 or more, which may have been rounded
+    /// when converted to `f64`.
+    imprecise: usize
+}
+
+/// Strings of a key or of the items of arrays checked for the
+/// `StringFormat`s.
+#[derive(Default)]
+struct FormatsStats {
+    strings: usize,
+    matches: BTreeMap<StringFormat, usize>,
+    /// Earliest and latest dates, in seconds since the Unix epoch.
+    dates: Option<(f64, f64)>,
+    blobs: Option<BlobSizes>
+}
+
+impl FormatsStats {
+    fn add(&mut self, line: &str) {
+        self.strings += 1;
+        let Some(format) = detect(line) else {
+            return;
+        };
+        *self.matches.entry(format).or_insert(0) += 1;
+        if let Some(seconds) = parse_date(line).filter(|_| format == StringFormat::Date) {
+            let (earliest, latest) = self.dates.unwrap_or((seconds, seconds));
+            self.dates = Some((earliest.min(seconds), latest.max(seconds)));
         }
-        self.maximums.push(Reverse(NonNan(number)));
-        self.number += 1;
-        self.sum += number;
+        if let Some(size) = base64_size(line).filter(|_| format == StringFormat::Base64) {
+            let blobs = self.blobs.get_or_insert(BlobSizes { smallest: size, largest: size, total: 0 });
+            blobs.smallest = blobs.smallest.min(size);
+            blobs.largest = blobs.largest.max(size);
+            blobs.total += size;
+        }
+    }
+
+    fn report(&self) -> Option<FormatsReport> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        Some(FormatsReport {
+            strings: self.strings,
+            matches: self.matches.iter().map(|(format, count)| (*format, *count)).collect(),
+            dates: self.dates,
+            blobs: self.blobs
+        })
     }
 }
 
-impl JsonComplexTypeStats {
-    pub fn array() -> Self {
-        Self {
-            values_types: HashSet::new(),
-            numbers: JsonNumbersStats::new(10),
-            strings: HashSet::new(),
-            type_stats: JsonSpecificTypeStats::ARRAY(Box::new(JsonArrayStats {
-                inner_arrays_stats: None,
-                inner_objects_stats: None
-            }))
+/// Example values kept per type of a key for the anomalies.
+const ANOMALY_EXAMPLES: usize = 3;
+
+/// Values of a key or items of arrays by type, `true` and `false` being
+/// booleans alike and `null` left out, with the first few of each type
+/// that is primitive.
+#[derive(Default)]
+struct ValueTypes {
+    counts: BTreeMap<&'static str, usize>,
+    examples: BTreeMap<&'static str, Vec<JsonValue>>
+}
+
+impl ValueTypes {
+    fn add(&mut self, type_name: &'static str, example: impl FnOnce() -> Option<JsonValue>) {
+        *self.counts.entry(type_name).or_insert(0) += 1;
+        let examples = self.examples.entry(type_name).or_default();
+        if examples.len() < ANOMALY_EXAMPLES {
+            examples.extend(example());
         }
     }
 
-    pub fn object() -> Self {
-        Self {
-            values_types: HashSet::new(),
-            numbers: JsonNumbersStats::new(10),
-            strings: HashSet::new(),
-            type_stats: JsonSpecificTypeStats::OBJECT(Box::new(JsonObjectStats {
-                complex_stats: HashMap::new(),
-                primitives_keys: HashMap::new(),
-                nonobligatory: HashSet::new()
-            }))
+    /// Types and their counts, the most common first.
+    fn distribution(&self) -> Vec<(String, usize)> {
+        let mut types: Vec<(String, usize)> = self.counts.iter().map(|(name, count)| (name.to_string(), *count)).collect();
+        types.sort_by(|left, right| right.1.cmp(&left.1).then_with(|| left.0.cmp(&right.0)));
+        types
+    }
+
+    /// The anomaly of the key at `path`, if its values have several types.
+    fn report(&self, path: String) -> Option<Anomaly> {
+        if self.counts.len() < 2 {
+            return None;
         }
+        let types = self.distribution();
+        let examples = types[1..].iter()
+            .flat_map(|(name, _)| self.examples.get(name.as_str()).into_iter().flatten().cloned())
+            .take(ANOMALY_EXAMPLES)
+            .collect();
+        Some(Anomaly { path, types, examples })
     }
+}
 
-    pub fn is_array_type(&self) -> bool {
-        match self.type_stats {
-            JsonSpecificTypeStats::ARRAY(_) => true,
-            _ => false
+/// Items of the arrays at one place equal to an earlier item of the same
+/// array, see `StatsOptions::duplicates`.
+#[derive(Default)]
+struct DuplicateStats {
+    /// Items compared.
+    items: usize,
+    duplicates: usize,
+    /// Item repeated the most times in one array, with that count.
+    most: Option<(JsonValue, usize)>
+}
+
+impl DuplicateStats {
+    /// Adds an item seen `count` times in its array so far.
+    fn add(&mut self, item: &JsonValue, count: usize) {
+        self.items += 1;
+        if count < 2 {
+            return;
+        }
+        self.duplicates += 1;
+        match &mut self.most {
+            Some((most, most_count)) if most == item => *most_count = (*most_count).max(count),
+            Some((_, most_count)) if *most_count >= count => {},
+            _ => self.most = Some((item.clone(), count))
         }
     }
 
-    pub fn is_object_type(&self) -> bool {
-        match self.type_stats {
-            JsonSpecificTypeStats::OBJECT(_) => true,
-            _ => false
+    fn report(&self, path: String) -> Option<DuplicatesReport> {
+        (self.items > 0).then(|| DuplicatesReport { path, items: self.items, duplicates: self.duplicates, most: self.most.clone() })
+    }
+}
+
+struct JsonArrayStats {
+    inner_arrays_stats: Option<JsonComplexTypeStats>,
+    inner_objects_stats: Option<JsonComplexTypeStats>,
+    /// Example items that are not containers.
+    samples: Reservoir<JsonValue>,
+    formats: FormatsStats,
+    /// Compact JSON bytes of the items.
+    sizes: usize,
+    types: ValueTypes,
+    duplicates: DuplicateStats
+}
+
+struct JsonObjectStats {
+    primitives_keys: JsonMap<HashSet<&'static str>>,
+    complex_stats: JsonMap<JsonComplexTypeStats>,
+    /// Number of objects collected.
+    objects: usize,
+    /// Number of objects having each key.
+    presence: JsonMap<usize>,
+    /// Number of objects having each key set to `null`.
+    nulls: JsonMap<usize>,
+    /// Integral and fractional numbers under each key.
+    number_kinds: JsonMap<NumberKinds>,
+    /// `true` and `false` under each key.
+    booleans: JsonMap<BooleanCounts>,
+    /// Numbers under each key.
+    numbers: JsonMap<JsonNumbersStats>,
+    /// Strings under each key.
+    strings: JsonMap<StringsStats>,
+    /// Example values under each key that are not containers.
+    samples: JsonMap<Reservoir<JsonValue>>,
+    formats: JsonMap<FormatsStats>,
+    /// Compact JSON bytes of the members of each key, the keys included.
+    sizes: JsonMap<usize>,
+    types: JsonMap<ValueTypes>
+}
+
+impl JsonObjectStats {
+    fn add_key(&mut self, key: &JsonKey) {
+        match self.presence.get_mut(&**key) {
+            Some(count) => *count += 1,
+            None => {
+                self.presence.insert(key.clone(), 1);
+            }
+        }
+    }
+
+    /// Keys missing from some of the objects.
+    fn is_nonobligatory(&self, key: &str) -> bool {
+        self.presence.get(key).is_some_and(|count| *count < self.objects)
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+enum JsonSpecificTypeStats {
+    ARRAY(Box<JsonArrayStats>),
+    OBJECT(Box<JsonObjectStats>)
+}
+
+/// Depths of the containers collected at one place, a container holding
+/// no containers being 1 deep.
+#[derive(Default)]
+struct DepthStats {
+    containers: usize,
+    total: usize,
+    max: usize
+}
+
+impl DepthStats {
+    fn add(&mut self, depth: usize) {
+        self.containers += 1;
+        self.total += depth;
+        self.max = self.max.max(depth);
+    }
+
+    fn report(&self) -> Option<DepthReport> {
+        (self.containers > 0).then(|| DepthReport { max: self.max, avg: self.total as f64 / self.containers as f64 })
+    }
+}
+
+/// Numbers of values of each type in the documents.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NodeCounts {
+    pub objects: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    pub numbers: usize,
+    pub booleans: usize,
+    pub nulls: usize
+}
+
+impl NodeCounts {
+    pub fn total(&self) -> usize {
+        self.objects + self.arrays + self.strings + self.numbers + self.booleans + self.nulls
+    }
+
+    fn count(&mut self, json: &JsonValue) {
+        match json {
+            JsonValue::RAW(_, text) if is_raw_object(text) => self.objects += 1,
+            JsonValue::OBJECT(_) => self.objects += 1,
+            JsonValue::ARRAY(_) | JsonValue::RAW(..) => self.arrays += 1,
+            JsonValue::STRING(_) => self.strings += 1,
+            JsonValue::NUMBER(_) | JsonValue::RAWNUMBER(_) => self.numbers += 1,
+            JsonValue::TRUE | JsonValue::FALSE => self.booleans += 1,
+            JsonValue::NULL => self.nulls += 1
+        }
+    }
+
+    fn merge(&mut self, other: &NodeCounts) {
+        self.objects += other.objects;
+        self.arrays += other.arrays;
+        self.strings += other.strings;
+        self.numbers += other.numbers;
+        self.booleans += other.booleans;
+        self.nulls += other.nulls;
+    }
+}
+
+impl fmt::Display for NodeCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} objects, {} arrays, {} strings, {} numbers, {} booleans, {} nulls ({} in total)",
+            self.objects, self.arrays, self.strings, self.numbers, self.booleans, self.nulls, self.total())
+    }
+}
+
+impl From<&NodeCounts> for JsonValue {
+    fn from(nodes: &NodeCounts) -> Self {
+        json!({
+            "objects": nodes.objects,
+            "arrays": nodes.arrays,
+            "strings": nodes.strings,
+            "numbers": nodes.numbers,
+            "booleans": nodes.booleans,
+            "nulls": nodes.nulls,
+            "total": nodes.total()
+        })
+    }
+}
+
+/// Whether the unparsed container `text` is an object rather than an array.
+fn is_raw_object(text: &str) -> bool {
+    text.starts_with('{')
+}
+
+/// Depth, compact JSON bytes and content of a container left out of the
+/// stats, which are still accounted for.
+#[derive(Default)]
+struct Extent {
+    depth: usize,
+    bytes: usize,
+    /// Values inside the container, not counting itself.
+    nodes: NodeCounts
+}
+
+impl Extent {
+    /// Adds a value of `extent`, its key taking `key_bytes`.
+    fn add(&mut self, extent: Extent, key_bytes: usize, is_first: bool) {
+        self.depth = self.depth.max(extent.depth + 1);
+        self.bytes += key_bytes + extent.bytes + usize::from(! is_first);
+        self.nodes.merge(&extent.nodes);
+    }
+
+    fn of_object(object: &JsonMap<JsonValue>) -> Extent {
+        let mut extent = Extent { depth: 1, bytes: 2, nodes: NodeCounts::default() };
+        for (index, (key, value)) in object.iter().enumerate() {
+            extent.add(Extent::of(value), string_size(key) + 1, index == 0);
+        }
+        extent
+    }
+
+    fn of_array(array: &[JsonValue]) -> Extent {
+        let mut extent = Extent { depth: 1, bytes: 2, nodes: NodeCounts::default() };
+        for (index, item) in array.iter().enumerate() {
+            extent.add(Extent::of(item), 0, index == 0);
         }
+        extent
+    }
+
+    /// Extent of an unparsed container, whose content is unknown and whose
+    /// bytes are those of its source text.
+    fn of_raw(text: &str) -> Extent {
+        Extent { depth: 1, bytes: text.len(), nodes: NodeCounts::default() }
+    }
+
+    /// Extent of `json`, counting itself.
+    fn of(json: &JsonValue) -> Extent {
+        let mut extent = match json {
+            JsonValue::OBJECT(object) => Extent::of_object(object),
+            JsonValue::ARRAY(array) => Extent::of_array(array),
+            JsonValue::RAW(_, text) => Extent::of_raw(text),
+            scalar => Extent { depth: 0, bytes: compact_size(scalar), nodes: NodeCounts::default() }
+        };
+        extent.nodes.count(json);
+        extent
+    }
+}
+
+pub struct JsonComplexTypeStats {
+    values_types: HashSet<&'static str>,
+    /// Numbers and strings among the items of arrays, those of objects
+    /// being kept per key.
+    numbers: JsonNumbersStats,
+    strings: StringsStats,
+    depths: DepthStats,
+    /// Values of the documents merged into these stats, kept for the root.
+    nodes: NodeCounts,
+    /// Compact JSON bytes of those documents, kept for the root.
+    bytes: usize,
+    type_stats: JsonSpecificTypeStats
+}
+
+/// Where the stats of a container go once its content has been visited.
+enum Destination {
+    Root,
+    Member(JsonKey),
+    InnerArrays,
+    InnerObjects
+}
+
+struct Frame {
+    stats: JsonComplexTypeStats,
+    /// Whether a container of the same type was merged into `stats` before,
+    /// in which case only the structure of this one is collected.
+    merging: bool,
+    /// Depth of the deepest container visited in this one so far.
+    inner_depth: usize,
+    /// Compact JSON bytes of the content visited so far, separators aside.
+    bytes: usize,
+    /// Number of items or members visited so far.
+    items: usize,
+    /// Whether the container is selected as a whole, see `Selection`.
+    selected: bool,
+    /// The container as visited so far if it is an item of an array or
+    /// inside one, see `StatsOptions::duplicates`; dropped once some of
+    /// its content is not visited.
+    value: Option<JsonValue>,
+    /// Items of the array visited so far by digest, with their counts.
+    digests: HashMap<u64, usize>,
+    destination: Destination
+}
+
+/// How much of a value `StatsOptions::include` and `exclude` select.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Selection {
+    None,
+    /// Containers leading to selected values, collected without the rest
+    /// of their content.
+    Path,
+    All
+}
+
+/// Visitor collecting the stats of a value, merged into `root` if there is
+/// one already.
+struct Sniffer {
+    root: Option<JsonComplexTypeStats>,
+    frames: Vec<Frame>,
+    /// Values visited, added to the root once done.
+    nodes: NodeCounts,
+    /// Compact JSON bytes of the documents visited.
+    bytes: usize,
+    /// Settings of the stats created for new containers.
+    options: StatsOptions
+}
+
+fn member_key(pointer: &JsonPointer) -> JsonKey {
+    intern_key(pointer.tokens().last().map_or("", String::as_str))
+}
+
+impl Sniffer {
+    fn collect(root: Option<JsonComplexTypeStats>, json: &JsonValue, options: &StatsOptions) -> JsonComplexTypeStats {
+        let mut sniffer = Self::new(root, options);
+        visit(json, &mut sniffer);
+        sniffer.finish().unwrap_or_else(|| JsonComplexTypeStats::array_with(options))
+    }
+
+    fn new(root: Option<JsonComplexTypeStats>, options: &StatsOptions) -> Self {
+        Self { root, frames: vec![], nodes: NodeCounts::default(), bytes: 0, options: options.clone() }
+    }
+
+    /// The root stats with the values and bytes visited added.
+    fn finish(self) -> Option<JsonComplexTypeStats> {
+        let mut root = self.root?;
+        root.nodes.merge(&self.nodes);
+        root.bytes += self.bytes;
+        Some(root)
+    }
+
+    /// Steps to the value at `pointer`, whose containers are all entered.
+    fn steps<'a>(&self, pointer: &'a JsonPointer) -> Vec<PathStep<'a>> {
+        pointer.tokens().iter().zip(&self.frames)
+            .map(|(token, frame)| if frame.stats.is_object_type() {
+                PathStep::Member(token)
+            } else {
+                PathStep::Item(token.parse().unwrap_or(usize::MAX))
+            })
+            .collect()
     }
 
-    fn merge_primitives_stats(mut self, value: JsonValue) -> Self {
-        if let JsonValue::NUMBER(num) = value {
-            self.numbers.add(num);
+    fn selection(&self, pointer: &JsonPointer) -> Selection {
+        let (include, exclude) = (&self.options.include, &self.options.exclude);
+        if include.is_empty() && exclude.is_empty() {
+            return Selection::All;
         }
-        if let JsonValue::STRING(line) = value {
-            self.strings.insert(line);
+        let steps = self.steps(pointer);
+        if exclude.iter().any(|path| path.matches(&steps)) {
+            Selection::None
+        } else if include.is_empty() || self.frames.last().is_some_and(|frame| frame.selected)
+            || include.iter().any(|path| path.matches(&steps)) {
+            Selection::All
+        } else if include.iter().any(|path| path.leads_through(&steps)) {
+            Selection::Path
+        } else {
+            Selection::None
         }
-        self
     }
 
-    fn merge_complex_stats(mut self, value: JsonValue) -> Self {
-        self.type_stats = match self.type_stats {
-            JsonSpecificTypeStats::ARRAY(arr_stats) => JsonSpecificTypeStats::ARRAY(Box::new(merge_as_array_stats(*arr_stats, value))),
-            JsonSpecificTypeStats::OBJECT(obj_stats) => JsonSpecificTypeStats::OBJECT(Box::new(merge_as_object_stats(*obj_stats, value)))
+    fn is_selected(&self, pointer: &JsonPointer) -> bool {
+        self.selection(pointer) == Selection::All
+    }
+
+    /// Records a primitive value in the container it belongs to; a value on
+    /// its own is collected as if it were the only item of an array.
+    fn primitive(&mut self, pointer: &JsonPointer, type_name: &'static str, record: impl FnOnce(&mut JsonComplexTypeStats)) {
+        let Some(frame) = self.frames.last_mut() else {
+            let mut stats = self.root.take().unwrap_or_else(|| JsonComplexTypeStats::array_with(&self.options));
+            stats.values_types.insert(type_name);
+            record(&mut stats);
+            self.root = Some(stats);
+            return;
         };
-        self
+        if let JsonSpecificTypeStats::OBJECT(ref mut obj_stats) = frame.stats.type_stats {
+            let key = member_key(pointer);
+            obj_stats.add_key(&key);
+            obj_stats.primitives_keys.entry(key)
+                .and_modify(|types| { types.insert(type_name); })
+                .or_insert_with(|| HashSet::from([type_name]));
+        }
+        if ! frame.merging {
+            frame.stats.values_types.insert(type_name);
+            record(&mut frame.stats);
+        }
     }
 
-    pub fn from_object(object: HashMap<String, Box<JsonValue>>) -> Self {
-        let mut stats = Self::object();
-        for (key, value) in object {
-            stats.values_types.insert(JSON_TYPES_NAMES[json_type(&value)]);
-            if is_complex_type(&value) {
-                if let JsonSpecificTypeStats::OBJECT(ref mut obj_stats) = stats.type_stats {
-                    let new = match obj_stats.complex_stats.remove(&key) {
-                        Some(prev) => prev.merge_stats(*value),
-                        None => Self::from_json(*value)
-                    };
-                    obj_stats.complex_stats.insert(key, new);
+    /// Offers a value to the samples of its key or array, even when merging.
+    fn sample(&mut self, pointer: &JsonPointer, value: impl FnOnce() -> JsonValue) {
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        match frame.stats.type_stats {
+            JsonSpecificTypeStats::OBJECT(ref mut obj_stats) => {
+                let key = member_key(pointer);
+                match obj_stats.samples.get_mut(&*key) {
+                    Some(samples) => samples.insert_with(value),
+                    None => {
+                        let mut samples = Reservoir::new(self.options.samples);
+                        samples.insert_with(value);
+                        obj_stats.samples.insert(key, samples);
+                    }
                 }
-                continue;
-            }
-            if let JsonSpecificTypeStats::OBJECT(ref mut obj_stats) = stats.type_stats {
-                let primitive_type = JSON_TYPES_NAMES[json_type(value.as_ref())];
-                obj_stats.primitives_keys.entry(key)
-                    .and_modify(|types| { types.insert(primitive_type); })
-                    .or_insert_with(|| HashSet::from([primitive_type]));
-            }
-            stats = stats.merge_primitives_stats(*value);
+            },
+            JsonSpecificTypeStats::ARRAY(ref mut arr_stats) => arr_stats.samples.insert_with(value)
         }
-        stats
     }
 
-    pub fn from_array(array: Vec<JsonValue>) -> Self {
-        let mut stats = Self::array();
-        for value in array {
-            stats.values_types.insert(JSON_TYPES_NAMES[json_type(&value)]);
-            if is_complex_type(&value) {
-                stats.type_stats = if let JsonSpecificTypeStats::ARRAY(mut arr_stats) = stats.type_stats {
-                    if is_object_type(&value) {
-                        arr_stats.inner_objects_stats = Some(match arr_stats.inner_objects_stats {
-                            Some(prev) => prev.merge_stats(value),
-                            None => Self::from_json(value)
-                        });
-                    } else if is_array_type(&value) {
-                        arr_stats.inner_arrays_stats = Some(match arr_stats.inner_arrays_stats {
-                            Some(prev) => prev.merge_stats(value),
-                            None => Self::from_json(value)
-                        })
+    /// Checks a string of a key or array for formats, even when merging.
+    fn string_format(&mut self, pointer: &JsonPointer, line: &str) {
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        match frame.stats.type_stats {
+            JsonSpecificTypeStats::OBJECT(ref mut obj_stats) => {
+                let key = member_key(pointer);
+                match obj_stats.formats.get_mut(&*key) {
+                    Some(formats) => formats.add(line),
+                    None => {
+                        let mut formats = FormatsStats::default();
+                        formats.add(line);
+                        obj_stats.formats.insert(key, formats);
                     }
-                    JsonSpecificTypeStats::ARRAY(arr_stats)
-                } else { stats.type_stats };
-                continue;
-            }
-            stats = stats.merge_primitives_stats(value);
+                }
+            },
+            JsonSpecificTypeStats::ARRAY(ref mut arr_stats) => arr_stats.formats.add(line)
         }
-        stats
     }
 
-    fn is_complex_matches(&self, value: &JsonValue) -> bool {
-        (self.is_array_type() && is_array_type(value))
-        || (self.is_object_type() && is_object_type(value))
+    /// Counts a `null` of an object member, even when merging.
+    fn null(&mut self, pointer: &JsonPointer) {
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        if let JsonSpecificTypeStats::OBJECT(ref mut obj_stats) = frame.stats.type_stats {
+            *obj_stats.nulls.entry(member_key(pointer)).or_insert(0) += 1;
+        }
     }
 
-    pub fn merge_stats(mut self, value: JsonValue) -> Self {
-        if self.is_complex_matches(&value) {
-            return self.merge_complex_stats(value);
+    /// Counts a number of an object member by its kind, even when merging.
+    fn number_kind(&mut self, pointer: &JsonPointer, number: f64) {
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        if let JsonSpecificTypeStats::OBJECT(ref mut obj_stats) = frame.stats.type_stats {
+            if number.is_finite() {
+                obj_stats.number_kinds.entry(member_key(pointer)).or_default().add(number);
+            }
         }
-        self.values_types.insert(JSON_TYPES_NAMES[json_type(&value)]);
-        self.merge_primitives_stats(value)
     }
 
-    pub fn from_json(json: JsonValue) -> Self {
-        if is_complex_type(&json) {
-            return match json {
-                JsonValue::OBJECT(object) => Self::from_object(object),
-                JsonValue::ARRAY(array) => Self::from_array(array),
-                _ => unreachable!()
-            };
-        }
-        Self::from_array(vec![json])
-    }
-}
-
-
-fn stringify_complex_stats(specific: &JsonSpecificTypeStats) -> &str {
-    match specific {
-        JsonSpecificTypeStats::ARRAY(_) => "array",
-        JsonSpecificTypeStats::OBJECT(_) => "object"
-    }
-}
-
-pub fn print_complex_stats(mut stats: JsonComplexTypeStats) {
-    println!("Type: {}", stringify_complex_stats(&stats.type_stats));
-    println!("--- Common info ---");
-    print!("Containing types: ");
-    let mut is_not_first = false;
-    for value_type in stats.values_types {
-        if is_not_first { print!(", "); }
-        print!("{}", value_type);
-        is_not_first = true;
-    }
-    if stats.numbers.number > 0 {
-        let avg = stats.numbers.sum / stats.numbers.number as f64;
-        println!("\n=== Numbers info ===");
-        print!("Encountered {} numbers", stats.numbers.number);
-        println!(", sum = {}, avg = {}", stats.numbers.sum, avg);
-        print!("{} most maximum numbers: ", stats.numbers.maximums.len());
-        is_not_first = false;
-        while let Some(wrapped) = stats.numbers.maximums.pop() {
-            if is_not_first { print!(", "); }
-            print!("{}", wrapped.0.0);
-            is_not_first = true;
-
-        }
-        print!("\n{} most minimum numbers: ", stats.numbers.minimums.len());
-        is_not_first = false;
-        while let Some(wrapped) = stats.numbers.minimums.pop() {
-            if is_not_first { print!(", "); }
-            print!("{}", wrapped.0);
-            is_not_first = true;
-        }
-    }
-    if stats.strings.len() > 0 {
-        println!("\n=== Strings info ===");
-        print!("Encountered {} unique strings: ", stats.strings.len());
-        is_not_first = false;
-        for string in stats.strings {
-            if is_not_first { print!(", "); }
-            print!("'{}'", string);
-            is_not_first = true;
-        }
-    }
-    match stats.type_stats {
-        JsonSpecificTypeStats::ARRAY(mut arr_stats) => {
-            println!("\n=== Array specific info ===");
-            let inner_arrays = arr_stats.inner_arrays_stats.take();
-            if let Some(inner_arrays_stats) = inner_arrays {
-                println!("*** Inner arrays info ***");
-                print_complex_stats(inner_arrays_stats);
-            }
-            let inner_objects = arr_stats.inner_objects_stats.take();
-            if let Some(inner_objects_stats) = inner_objects {
-                println!("*** Inner objects info ***");
-                print_complex_stats(inner_objects_stats)
+    /// Counts a boolean of an object member by its value, even when merging.
+    fn boolean(&mut self, pointer: &JsonPointer, value: bool) {
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        if let JsonSpecificTypeStats::OBJECT(ref mut obj_stats) = frame.stats.type_stats {
+            obj_stats.booleans.entry(member_key(pointer)).or_default().add(value);
+        }
+    }
+
+    /// Adds a number to the stats of its key or array, even when merging.
+    fn number(&mut self, pointer: &JsonPointer, record: impl FnOnce(&mut JsonNumbersStats)) {
+        let Some(frame) = self.frames.last_mut() else {
+            if let Some(root) = &mut self.root {
+                record(&mut root.numbers);
             }
-        },
-        JsonSpecificTypeStats::OBJECT(obj_stats) => {
-            println!("\n=== Object specific info ===");
-            print!("{} keys are likely nonobligatory: ", obj_stats.nonobligatory.len());
-            is_not_first = false;
-            for key in &obj_stats.nonobligatory {
-                if is_not_first { print!(", "); }
-                print!("'{}'", key);
-                is_not_first = true;
-            }
-
-            let mut mandatory_keys = HashSet::new();
-            for key in obj_stats.primitives_keys.keys().chain(obj_stats.complex_stats.keys()) {
-                mandatory_keys.insert(key.clone());
-            }
-            let mandatory_keys: Vec<_> = mandatory_keys.difference(&obj_stats.nonobligatory).collect();
-            print!("\n{} keys are likely mandatory: ", mandatory_keys.len());
-            is_not_first = false;
-            for key in mandatory_keys {
-                if is_not_first { print!(", "); }
-                print!("'{}'", key);
-                is_not_first = true;
-            }
-            println!("\n{} keys have primitive values:", obj_stats.primitives_keys.len());
-            for (key, types) in obj_stats.primitives_keys {
-                print!("- {} is ", key);
-                is_not_first = false;
-                for type_name in types {
-                    if is_not_first { print!(", "); }
-                    print!("{}", type_name);
-                    is_not_first = true;
+            return;
+        };
+        match frame.stats.type_stats {
+            JsonSpecificTypeStats::OBJECT(ref mut obj_stats) => {
+                let key = member_key(pointer);
+                match obj_stats.numbers.get_mut(&*key) {
+                    Some(numbers) => record(numbers),
+                    None => {
+                        let mut numbers = JsonNumbersStats::new(&self.options);
+                        record(&mut numbers);
+                        obj_stats.numbers.insert(key, numbers);
+                    }
                 }
-                println!();
-            }
-            for (key, inner_stats) in obj_stats.complex_stats {
-                println!("*** Info for value at key {} ***", key);
-                print_complex_stats(inner_stats);
-            }
+            },
+            JsonSpecificTypeStats::ARRAY(_) => record(&mut frame.stats.numbers)
         }
-    };
-    println!();
+    }
+
+    /// Adds a string to the stats of its key or array, even when merging.
+    fn string(&mut self, pointer: &JsonPointer, line: &str) {
+        let Some(frame) = self.frames.last_mut() else {
+            if let Some(root) = &mut self.root {
+                root.strings.insert(line);
+            }
+            return;
+        };
+        match frame.stats.type_stats {
+            JsonSpecificTypeStats::OBJECT(ref mut obj_stats) => {
+                let key = member_key(pointer);
+                match obj_stats.strings.get_mut(&*key) {
+                    Some(strings) => strings.insert(line),
+                    None => {
+                        let mut strings = StringsStats::new(&self.options);
+                        strings.insert(line);
+                        obj_stats.strings.insert(key, strings);
+                    }
+                }
+            },
+            JsonSpecificTypeStats::ARRAY(_) => frame.stats.strings.insert(line)
+        }
+    }
+
+    /// Counts a value of an object member or an item by its type, even
+    /// when merging.
+    fn value_type(&mut self, pointer: &JsonPointer, type_name: &'static str, example: impl FnOnce() -> Option<JsonValue>) {
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        match frame.stats.type_stats {
+            JsonSpecificTypeStats::OBJECT(ref mut obj_stats) => {
+                obj_stats.types.entry(member_key(pointer)).or_default().add(type_name, example);
+            },
+            JsonSpecificTypeStats::ARRAY(ref mut arr_stats) => arr_stats.types.add(type_name, example)
+        }
+    }
+
+    /// Whether a value visited now is built for `StatsOptions::duplicates`.
+    fn is_building(&self) -> bool {
+        self.options.duplicates && self.frames.last().is_some_and(|frame| frame.stats.is_array_type() || frame.value.is_some())
+    }
+
+    /// Adds a value to the container being built around it and counts it
+    /// among the items of its array, see `StatsOptions::duplicates`, even
+    /// when merging; `None` for a value whose content was not all visited,
+    /// which drops the container.
+    fn built(&mut self, pointer: &JsonPointer, value: impl FnOnce() -> Option<JsonValue>) {
+        if ! self.is_building() {
+            return;
+        }
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        let Some(value) = value() else {
+            frame.value = None;
+            return;
+        };
+        if let JsonSpecificTypeStats::ARRAY(ref mut arr_stats) = frame.stats.type_stats {
+            let count = frame.digests.entry(hash_of(&value)).or_insert(0);
+            *count += 1;
+            arr_stats.duplicates.add(&value, *count);
+        }
+        match &mut frame.value {
+            Some(JsonValue::ARRAY(array)) => array.push(value),
+            Some(object) => {
+                object.insert(member_key(pointer), value);
+            },
+            None => {}
+        }
+    }
+
+    /// Attributes the `size` bytes of a value to its key or array, even
+    /// when merging.
+    fn size(&mut self, pointer: &JsonPointer, size: usize) {
+        let Some(frame) = self.frames.last_mut() else {
+            self.bytes += size;
+            return;
+        };
+        let size = match frame.stats.type_stats {
+            JsonSpecificTypeStats::OBJECT(ref mut obj_stats) => {
+                let key = member_key(pointer);
+                let size = string_size(&key) + 1 + size;
+                *obj_stats.sizes.entry(key).or_insert(0) += size;
+                size
+            },
+            JsonSpecificTypeStats::ARRAY(ref mut arr_stats) => {
+                arr_stats.sizes += size;
+                size
+            }
+        };
+        frame.bytes += size;
+        frame.items += 1;
+    }
+
+    /// Counts a container and starts collecting it, see `enter`; one not
+    /// selected is left out altogether and one at `StatsOptions::stat_depth`
+    /// is collected without its content, which `skipped` counts.
+    fn start(&mut self, pointer: &JsonPointer, is_object: bool) -> bool {
+        let selection = self.selection(pointer);
+        if selection == Selection::None {
+            return false;
+        }
+        if is_object {
+            self.nodes.objects += 1;
+        } else {
+            self.nodes.arrays += 1;
+        }
+        self.value_type(pointer, if is_object { "object" } else { "array" }, || None);
+        let entered = self.enter(pointer, is_object, selection == Selection::All);
+        if entered && self.options.stat_depth.is_some_and(|depth| pointer.tokens().len() >= depth) {
+            if let Some(frame) = self.frames.pop() {
+                self.store(frame.stats, frame.destination);
+            }
+            return false;
+        }
+        entered
+    }
+
+    /// Accounts for a container whose content is not collected, unless it
+    /// is not selected.
+    fn skipped(&mut self, pointer: &JsonPointer, extent: Extent) {
+        if ! self.is_selected(pointer) {
+            return;
+        }
+        self.inner_depth(extent.depth);
+        self.size(pointer, extent.bytes);
+        self.nodes.merge(&extent.nodes);
+        self.built(pointer, || None);
+    }
+
+    /// Accounts for a container of `depth` in the one being visited.
+    fn inner_depth(&mut self, depth: usize) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.inner_depth = frame.inner_depth.max(depth);
+        }
+    }
+
+    /// Starts collecting a container, returning whether its content should
+    /// be visited: a container whose stats were collected for another type
+    /// only has its type recorded.
+    fn enter(&mut self, pointer: &JsonPointer, is_object: bool, selected: bool) -> bool {
+        let type_name = if is_object { "object" } else { "array" };
+        let value = self.is_building().then(|| if is_object { JsonValue::OBJECT(JsonMap::default()) } else { JsonValue::ARRAY(vec![]) });
+        let (previous, destination) = match self.frames.last_mut() {
+            None => (self.root.take(), Destination::Root),
+            Some(frame) => {
+                if ! frame.merging {
+                    frame.stats.values_types.insert(type_name);
+                }
+                match frame.stats.type_stats {
+                    JsonSpecificTypeStats::OBJECT(ref mut obj_stats) => {
+                        let key = member_key(pointer);
+                        obj_stats.add_key(&key);
+                        let previous = obj_stats.complex_stats.get_mut(&*key)
+                            .map(|slot| std::mem::replace(slot, JsonComplexTypeStats::array()));
+                        (previous, Destination::Member(key))
+                    },
+                    JsonSpecificTypeStats::ARRAY(ref mut arr_stats) if is_object => (arr_stats.inner_objects_stats.take(), Destination::InnerObjects),
+                    JsonSpecificTypeStats::ARRAY(ref mut arr_stats) => (arr_stats.inner_arrays_stats.take(), Destination::InnerArrays)
+                }
+            }
+        };
+        match previous {
+            Some(mut stats) if stats.is_object_type() != is_object => {
+                stats.values_types.insert(type_name);
+                self.store(stats, destination);
+                false
+            },
+            Some(mut stats) => {
+                stats.count_object();
+                self.frames.push(Frame { stats, merging: true, inner_depth: 0, bytes: 0, items: 0, selected, value, digests: HashMap::new(), destination });
+                true
+            },
+            None => {
+                let mut stats = if is_object {
+                    JsonComplexTypeStats::object_with(&self.options)
+                } else {
+                    JsonComplexTypeStats::array_with(&self.options)
+                };
+                stats.count_object();
+                self.frames.push(Frame { stats, merging: false, inner_depth: 0, bytes: 0, items: 0, selected, value, digests: HashMap::new(), destination });
+                true
+            }
+        }
+    }
+
+    fn leave(&mut self, pointer: &JsonPointer) {
+        if let Some(mut frame) = self.frames.pop() {
+            frame.stats.depths.add(frame.inner_depth + 1);
+            self.inner_depth(frame.inner_depth + 1);
+            self.size(pointer, 2 + frame.bytes + frame.items.saturating_sub(1));
+            self.store(frame.stats, frame.destination);
+            self.built(pointer, || frame.value);
+        }
+    }
+
+    fn store(&mut self, stats: JsonComplexTypeStats, destination: Destination) {
+        let Some(parent) = self.frames.last_mut() else {
+            self.root = Some(stats);
+            return;
+        };
+        match (&mut parent.stats.type_stats, destination) {
+            (JsonSpecificTypeStats::OBJECT(obj_stats), Destination::Member(key)) => match obj_stats.complex_stats.get_mut(&*key) {
+                Some(slot) => *slot = stats,
+                None => {
+                    obj_stats.complex_stats.insert(key, stats);
+                }
+            },
+            (JsonSpecificTypeStats::ARRAY(arr_stats), Destination::InnerObjects) => arr_stats.inner_objects_stats = Some(stats),
+            (JsonSpecificTypeStats::ARRAY(arr_stats), Destination::InnerArrays) => arr_stats.inner_arrays_stats = Some(stats),
+            (_, Destination::Root) => self.root = Some(stats),
+            _ => unreachable!()
+        }
+    }
+}
+
+impl JsonVisitor for Sniffer {
+    fn visit_object(&mut self, pointer: &JsonPointer, object: &JsonMap<JsonValue>) -> bool {
+        let entered = self.start(pointer, true);
+        if ! entered {
+            self.skipped(pointer, Extent::of_object(object));
+        }
+        entered
+    }
+
+    fn leave_object(&mut self, pointer: &JsonPointer) {
+        self.leave(pointer);
+    }
+
+    fn visit_array(&mut self, pointer: &JsonPointer, array: &[JsonValue]) -> bool {
+        let entered = self.start(pointer, false);
+        if ! entered {
+            self.skipped(pointer, Extent::of_array(array));
+        }
+        entered
+    }
+
+    fn leave_array(&mut self, pointer: &JsonPointer) {
+        self.leave(pointer);
+    }
+
+    fn visit_string(&mut self, pointer: &JsonPointer, line: &str) {
+        if ! self.is_selected(pointer) {
+            return;
+        }
+        self.nodes.strings += 1;
+        self.size(pointer, string_size(line));
+        self.sample(pointer, || JsonValue::from(line));
+        self.string_format(pointer, line);
+        self.value_type(pointer, "string", || Some(JsonValue::from(line)));
+        self.built(pointer, || Some(JsonValue::from(line)));
+        self.primitive(pointer, "string", |_| {});
+        self.string(pointer, line);
+    }
+
+    fn visit_number(&mut self, pointer: &JsonPointer, number: f64) {
+        if ! self.is_selected(pointer) {
+            return;
+        }
+        self.nodes.numbers += 1;
+        self.size(pointer, compact_size(&JsonValue::NUMBER(number)));
+        self.number_kind(pointer, number);
+        self.value_type(pointer, "number", || Some(JsonValue::NUMBER(number)));
+        self.built(pointer, || Some(JsonValue::NUMBER(number)));
+        self.sample(pointer, || JsonValue::NUMBER(number));
+        self.primitive(pointer, "number", |_| {});
+        self.number(pointer, |numbers| {
+            if number.fract() == 0f64 && number.abs() >= MAX_EXACT_INTEGER {
+                numbers.imprecise += 1;
+            }
+            numbers.add(number);
+        });
+    }
+
+    fn visit_raw_number(&mut self, pointer: &JsonPointer, literal: &str) {
+        if ! self.is_selected(pointer) {
+            return;
+        }
+        self.nodes.numbers += 1;
+        self.size(pointer, literal_size(literal));
+        let number = number_value(literal);
+        if let Some(number) = number {
+            self.number_kind(pointer, number);
+        }
+        self.value_type(pointer, "number", || Some(JsonValue::RAWNUMBER(literal.to_string())));
+        self.built(pointer, || Some(JsonValue::RAWNUMBER(literal.to_string())));
+        self.sample(pointer, || JsonValue::RAWNUMBER(literal.to_string()));
+        self.primitive(pointer, "number", |_| {});
+        if let Some(number) = number {
+            self.number(pointer, |numbers| numbers.add(number));
+        }
+    }
+
+    fn visit_bool(&mut self, pointer: &JsonPointer, value: bool) {
+        if ! self.is_selected(pointer) {
+            return;
+        }
+        self.nodes.booleans += 1;
+        self.size(pointer, if value { 4 } else { 5 });
+        self.value_type(pointer, "boolean", || Some(JsonValue::from(value)));
+        self.boolean(pointer, value);
+        self.built(pointer, || Some(JsonValue::from(value)));
+        self.sample(pointer, || JsonValue::from(value));
+        self.primitive(pointer, if value { "true" } else { "false" }, |_| {});
+    }
+
+    fn visit_null(&mut self, pointer: &JsonPointer) {
+        if ! self.is_selected(pointer) {
+            return;
+        }
+        self.nodes.nulls += 1;
+        self.size(pointer, 4);
+        self.null(pointer);
+        self.sample(pointer, || JsonValue::NULL);
+        self.built(pointer, || Some(JsonValue::NULL));
+        self.primitive(pointer, "null", |_| {});
+    }
+
+    /// Counts an unparsed container like one at `StatsOptions::stat_depth`,
+    /// its content being unknown.
+    fn visit_raw(&mut self, pointer: &JsonPointer, _span: &Span, text: &str) {
+        if self.start(pointer, is_raw_object(text)) {
+            if let Some(frame) = self.frames.pop() {
+                self.store(frame.stats, frame.destination);
+            }
+        }
+        self.skipped(pointer, Extent::of_raw(text));
+    }
+}
+
+/// Lightweight stats of the documents of one file, see
+/// `StatsCollector::summary`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileSummary {
+    pub file: String,
+    /// Types of the documents, sorted.
+    pub types: Vec<String>,
+    pub documents: usize,
+    pub nodes: NodeCounts,
+    /// Depth of the deepest document.
+    pub depth: usize,
+    /// Compact JSON bytes of the documents.
+    pub bytes: usize
+}
+
+impl FileSummary {
+    fn add_document(&mut self, kind: &str, depth: usize) {
+        self.documents += 1;
+        self.depth = self.depth.max(depth);
+        if let Err(index) = self.types.binary_search_by(|other| other.as_str().cmp(kind)) {
+            self.types.insert(index, kind.to_string());
+        }
+    }
+}
+
+/// Table comparing the files of `summaries`, one line each and the share
+/// of the bytes of all of them.
+pub fn summaries_table(summaries: &[FileSummary]) -> String {
+    let total: usize = summaries.iter().map(|summary| summary.bytes).sum();
+    let mut rows = vec![["file", "types", "documents", "nodes", "depth", "bytes", "share"].map(String::from)];
+    for summary in summaries {
+        rows.push([
+            summary.file.clone(),
+            summary.types.join("|"),
+            summary.documents.to_string(),
+            summary.nodes.total().to_string(),
+            summary.depth.to_string(),
+            summary.bytes.to_string(),
+            format!("{:.1}%", if total == 0 { 0.0 } else { percentage(summary.bytes, total) })
+        ]);
+    }
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| rows.iter().map(|row| row[column].chars().count()).max().unwrap_or(0))
+        .collect();
+    let mut table = String::from("=== Files ===\n");
+    for row in rows {
+        let cells: Vec<String> = row.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = *width))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Container being read by a `StatsCollector`.
+struct Level {
+    pointer: JsonPointer,
+    is_object: bool,
+    /// Items read so far, numbering the next one.
+    items: usize
+}
+
+/// Container whose content a `StatsCollector` does not collect, measured
+/// as it is read.
+struct Skip {
+    pointer: JsonPointer,
+    extent: Extent,
+    /// Items read so far of the containers open inside it, itself first.
+    items: Vec<usize>
+}
+
+impl Skip {
+    /// Measures an event, returning whether the container is over.
+    fn event(&mut self, event: JsonEvent<'_>) -> bool {
+        let extent = &mut self.extent;
+        match event {
+            JsonEvent::Key(key) => {
+                extent.bytes += string_size(key) + 1;
+                return false;
+            },
+            JsonEvent::EndObject | JsonEvent::EndArray => {
+                self.items.pop();
+                return self.items.is_empty();
+            },
+            _ => {}
+        }
+        if let Some(items) = self.items.last_mut() {
+            extent.bytes += usize::from(*items > 0);
+            *items += 1;
+        }
+        match event {
+            JsonEvent::StartObject | JsonEvent::StartArray => {
+                if event == JsonEvent::StartObject {
+                    extent.nodes.objects += 1;
+                } else {
+                    extent.nodes.arrays += 1;
+                }
+                extent.bytes += 2;
+                self.items.push(0);
+                extent.depth = extent.depth.max(self.items.len());
+            },
+            JsonEvent::String(line) => {
+                extent.nodes.strings += 1;
+                extent.bytes += string_size(line);
+            },
+            JsonEvent::Number(number) => {
+                extent.nodes.numbers += 1;
+                extent.bytes += compact_size(&JsonValue::NUMBER(number));
+            },
+            JsonEvent::RawNumber(literal) => {
+                extent.nodes.numbers += 1;
+                extent.bytes += literal_size(literal);
+            },
+            JsonEvent::Bool(value) => {
+                extent.nodes.booleans += 1;
+                extent.bytes += if value { 4 } else { 5 };
+            },
+            JsonEvent::Null => {
+                extent.nodes.nulls += 1;
+                extent.bytes += 4;
+            },
+            _ => {}
+        }
+        false
+    }
+}
+
+/// Collects stats from parsing events, see `ParserOptions::parse_events`,
+/// so that documents are never built as `JsonValue` trees: memory depends
+/// on the depth of the documents and the stats kept, not on their size.
+/// Documents following each other are merged.
+pub struct StatsCollector {
+    sniffer: Sniffer,
+    levels: Vec<Level>,
+    /// Key of the member read next.
+    key: Option<String>,
+    skip: Option<Skip>,
+    /// Documents collected since the last `summary`, their values and
+    /// bytes aside.
+    summary: FileSummary,
+    /// Values and bytes of the documents summarized already.
+    nodes: NodeCounts,
+    bytes: usize
+}
+
+/// Type of a value, as `StatsCollector` reads it.
+fn event_kind(event: JsonEvent<'_>) -> &'static str {
+    match event {
+        JsonEvent::StartObject | JsonEvent::EndObject | JsonEvent::Key(_) => "object",
+        JsonEvent::StartArray | JsonEvent::EndArray => "array",
+        JsonEvent::String(_) => "string",
+        JsonEvent::Number(_) | JsonEvent::RawNumber(_) => "number",
+        JsonEvent::Bool(_) => "boolean",
+        JsonEvent::Null => "null"
+    }
+}
+
+impl StatsCollector {
+    pub fn new(options: &StatsOptions) -> Self {
+        Self {
+            sniffer: Sniffer::new(None, options),
+            levels: vec![],
+            key: None,
+            skip: None,
+            summary: FileSummary::default(),
+            nodes: NodeCounts::default(),
+            bytes: 0
+        }
+    }
+
+    /// Collector merging documents into `stats`.
+    pub fn merging(stats: JsonComplexTypeStats, options: &StatsOptions) -> Self {
+        Self { sniffer: Sniffer::new(Some(stats), options), ..Self::new(options) }
+    }
+
+    /// Pointer of the value read next.
+    fn next_pointer(&mut self) -> JsonPointer {
+        match self.levels.last_mut() {
+            None => JsonPointer::default(),
+            Some(level) if level.is_object => level.pointer.child(self.key.take().unwrap_or_default()),
+            Some(level) => {
+                level.items += 1;
+                level.pointer.child((level.items - 1).to_string())
+            }
+        }
+    }
+
+    pub fn event(&mut self, event: JsonEvent<'_>) {
+        if let Some(skip) = &mut self.skip {
+            if matches!(event, JsonEvent::StartObject | JsonEvent::StartArray) {
+                self.summary.depth = self.summary.depth.max(self.levels.len() + skip.items.len() + 1);
+            }
+            if skip.event(event) {
+                let Skip { pointer, extent, .. } = self.skip.take().unwrap();
+                self.sniffer.skipped(&pointer, extent);
+            }
+            return;
+        }
+        if self.levels.is_empty() {
+            self.summary.add_document(event_kind(event), 0);
+        }
+        match event {
+            JsonEvent::Key(key) => self.key = Some(key.to_string()),
+            JsonEvent::StartObject | JsonEvent::StartArray => {
+                self.summary.depth = self.summary.depth.max(self.levels.len() + 1);
+                let is_object = event == JsonEvent::StartObject;
+                let pointer = self.next_pointer();
+                if self.sniffer.start(&pointer, is_object) {
+                    self.levels.push(Level { pointer, is_object, items: 0 });
+                } else {
+                    let extent = Extent { depth: 1, bytes: 2, nodes: NodeCounts::default() };
+                    self.skip = Some(Skip { pointer, extent, items: vec![0] });
+                }
+            },
+            JsonEvent::EndObject | JsonEvent::EndArray => if let Some(level) = self.levels.pop() {
+                self.sniffer.leave(&level.pointer);
+            },
+            JsonEvent::String(line) => {
+                let pointer = self.next_pointer();
+                self.sniffer.visit_string(&pointer, line);
+            },
+            JsonEvent::Number(number) => {
+                let pointer = self.next_pointer();
+                self.sniffer.visit_number(&pointer, number);
+            },
+            JsonEvent::RawNumber(literal) => {
+                let pointer = self.next_pointer();
+                self.sniffer.visit_raw_number(&pointer, literal);
+            },
+            JsonEvent::Bool(value) => {
+                let pointer = self.next_pointer();
+                self.sniffer.visit_bool(&pointer, value);
+            },
+            JsonEvent::Null => {
+                let pointer = self.next_pointer();
+                self.sniffer.visit_null(&pointer);
+            }
+        }
+    }
+
+    /// Collects a whole document at once, between those read as events.
+    pub fn value(&mut self, json: &JsonValue) {
+        let kind = match json {
+            JsonValue::RAW(_, text) if is_raw_object(text) => "object",
+            JsonValue::OBJECT(_) => "object",
+            JsonValue::ARRAY(_) | JsonValue::RAW(..) => "array",
+            JsonValue::STRING(_) => "string",
+            JsonValue::TRUE | JsonValue::FALSE => "boolean",
+            JsonValue::NULL => "null",
+            JsonValue::NUMBER(_) | JsonValue::RAWNUMBER(_) => "number"
+        };
+        self.summary.add_document(kind, Extent::of(json).depth);
+        visit(json, &mut self.sniffer);
+    }
+
+    /// Summary of the documents collected since the previous one, which
+    /// were read from `file`.
+    pub fn summary(&mut self, file: &str) -> FileSummary {
+        let mut summary = std::mem::take(&mut self.summary);
+        summary.file = file.to_string();
+        summary.nodes = std::mem::take(&mut self.sniffer.nodes);
+        summary.bytes = std::mem::take(&mut self.sniffer.bytes);
+        self.nodes.merge(&summary.nodes);
+        self.bytes += summary.bytes;
+        summary
+    }
+
+    /// Stats of the documents collected, none if there were none.
+    pub fn finish(mut self) -> Option<JsonComplexTypeStats> {
+        self.sniffer.nodes.merge(&self.nodes);
+        self.sniffer.bytes += self.bytes;
+        self.sniffer.finish()
+    }
+}
+
+impl JsonNumbersStats {
+    pub fn new(options: &StatsOptions) -> Self {
+        Self {
+            limit: options.top_k,
+            histogram: Histogram::new(options.histogram_buckets, options.histogram_scale),
+            quantiles: [Quantile::new(0.5), Quantile::new(0.95), Quantile::new(0.99)],
+            mean: 0f64,
+            scale: 1f64,
+            squares: 0f64,
+            kinds: NumberKinds::default(),
+            minimums: BinaryHeap::new(),
+            maximums: BinaryHeap::new(),
+            sum: 0f64,
+            number: 0,
+            non_finite: 0,
+            imprecise: 0
+        }
+    }
+
+    pub fn add(&mut self, number: f64) {
+        if ! number.is_finite() {
+            self.non_finite += 1;
+            return;
+        }
+
+        // the heaps drop their greatest and least numbers past the limit
+        self.minimums.push(NonNan(number));
+        if self.minimums.len() > self.limit {
+            self.minimums.pop();
+        }
+        self.maximums.push(Reverse(NonNan(number)));
+        if self.maximums.len() > self.limit {
+            self.maximums.pop();
+        }
+        self.histogram.add(number);
+        self.kinds.add(number);
+        for quantile in &mut self.quantiles {
+            quantile.add(number);
+        }
+        // dividing by a power of two is exact, so scaling keeps the rounding
+        let scale = self.scale.max(2f64.powi(number.abs().log2().floor() as i32));
+        self.squares *= (self.scale / scale).powi(2);
+        self.scale = scale;
+        let deviation = number / scale - self.mean / scale;
+        self.number += 1;
+        self.sum += number;
+        self.mean += deviation / self.number as f64 * scale;
+        self.squares += deviation * (number / scale - self.mean / scale);
+    }
+}
+
+impl JsonComplexTypeStats {
+    pub fn array() -> Self {
+        Self::array_with(&StatsOptions::default())
+    }
+
+    pub fn object() -> Self {
+        Self::object_with(&StatsOptions::default())
+    }
+
+    fn array_with(options: &StatsOptions) -> Self {
+        Self {
+            values_types: HashSet::new(),
+            numbers: JsonNumbersStats::new(options),
+            strings: StringsStats::new(options),
+            depths: DepthStats::default(),
+            nodes: NodeCounts::default(),
+            bytes: 0,
+            type_stats: JsonSpecificTypeStats::ARRAY(Box::new(JsonArrayStats {
+                inner_arrays_stats: None,
+                inner_objects_stats: None,
+                samples: Reservoir::new(options.samples),
+                formats: FormatsStats::default(),
+                sizes: 0,
+                types: ValueTypes::default(),
+                duplicates: DuplicateStats::default()
+            }))
+        }
+    }
+
+    fn object_with(options: &StatsOptions) -> Self {
+        Self {
+            values_types: HashSet::new(),
+            numbers: JsonNumbersStats::new(options),
+            strings: StringsStats::new(options),
+            depths: DepthStats::default(),
+            nodes: NodeCounts::default(),
+            bytes: 0,
+            type_stats: JsonSpecificTypeStats::OBJECT(Box::new(JsonObjectStats {
+                complex_stats: JsonMap::new(),
+                primitives_keys: JsonMap::new(),
+                objects: 0,
+                presence: JsonMap::new(),
+                nulls: JsonMap::new(),
+                number_kinds: JsonMap::new(),
+                booleans: JsonMap::new(),
+                numbers: JsonMap::new(),
+                strings: JsonMap::new(),
+                samples: JsonMap::new(),
+                formats: JsonMap::new(),
+                sizes: JsonMap::new(),
+                types: JsonMap::new()
+            }))
+        }
+    }
+
+    fn count_object(&mut self) {
+        if let JsonSpecificTypeStats::OBJECT(ref mut obj_stats) = self.type_stats {
+            obj_stats.objects += 1;
+        }
+    }
+
+    pub fn is_array_type(&self) -> bool {
+        matches!(self.type_stats, JsonSpecificTypeStats::ARRAY(_))
+    }
+
+    pub fn is_object_type(&self) -> bool {
+        matches!(self.type_stats, JsonSpecificTypeStats::OBJECT(_))
+    }
+
+    pub fn from_object(object: JsonMap<JsonValue>) -> Self {
+        Self::from_json(JsonValue::OBJECT(object))
+    }
+
+    pub fn from_array(array: Vec<JsonValue>) -> Self {
+        Self::from_json(JsonValue::ARRAY(array))
+    }
+
+    pub fn merge_stats(self, value: JsonValue) -> Self {
+        self.merge_stats_with(value, &StatsOptions::default())
+    }
+
+    pub fn from_json(json: JsonValue) -> Self {
+        Self::from_json_with(json, &StatsOptions::default())
+    }
+
+    /// Merges the stats of `value`, its new containers collected with
+    /// `options`.
+    pub fn merge_stats_with(self, value: JsonValue, options: &StatsOptions) -> Self {
+        Sniffer::collect(Some(self), &value, options)
+    }
+
+    pub fn from_json_with(json: JsonValue, options: &StatsOptions) -> Self {
+        Sniffer::collect(None, &json, options)
+    }
+}
+
+
+/// Stats state, see `JsonComplexTypeStats::to_state`, that cannot be read.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateError {
+    pub msg: String
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid stats state: {}", self.msg)
+    }
+}
+
+impl Error for StateError {}
+
+/// Layout of the state written by `JsonComplexTypeStats::to_state`.
+const STATE_VERSION: usize = 1;
+
+/// Names `Sniffer` records the types of values by, `boolean` for `ValueTypes`.
+const TYPE_NAMES: [&str; 8] = ["object", "array", "string", "number", "true", "false", "null", "boolean"];
+
+fn state_error(msg: impl Into<String>) -> StateError {
+    StateError { msg: msg.into() }
+}
+
+/// Number in the state, a string if it is not finite as JSON has no
+/// literals for those.
+fn float_state(number: f64) -> JsonValue {
+    if number.is_finite() { JsonValue::from(number) } else { JsonValue::from(number.to_string()) }
+}
+
+fn map_state<V>(map: &JsonMap<V>, state: impl Fn(&V) -> JsonValue) -> JsonValue {
+    JsonValue::object(map.iter().map(|(key, value)| (key.clone(), state(value))))
+}
+
+fn types_state(types: &HashSet<&'static str>) -> JsonValue {
+    JsonValue::from(sorted(types.iter().copied()))
+}
+
+/// Reads the member `name` of `state`, which errors say where they are.
+fn member<'a, T>(state: &'a JsonValue, name: &str, read: impl FnOnce(&'a JsonValue) -> Result<T, StateError>) -> Result<T, StateError> {
+    let value = state.get(name).ok_or_else(|| state_error(format!("missing \'{}\'", name)))?;
+    read(value).map_err(|error| state_error(format!("{} in \'{}\'", error.msg, name)))
+}
+
+fn read_float(state: &JsonValue) -> Result<f64, StateError> {
+    state.as_f64().or_else(|| state.as_str()?.parse().ok()).ok_or_else(|| state_error("expected a number"))
+}
+
+fn read_count(state: &JsonValue) -> Result<usize, StateError> {
+    state.as_f64().filter(|count| *count >= 0.0 && count.fract() == 0.0).map(|count| count as usize)
+        .ok_or_else(|| state_error("expected a count"))
+}
+
+fn read_str(state: &JsonValue) -> Result<&str, StateError> {
+    state.as_str().ok_or_else(|| state_error("expected a string"))
+}
+
+fn read_items<'a, T>(state: &'a JsonValue, read: impl Fn(&'a JsonValue) -> Result<T, StateError>) -> Result<Vec<T>, StateError> {
+    state.as_array().ok_or_else(|| state_error("expected an array"))?.iter().map(read).collect()
+}
+
+fn read_array<'a, T, const N: usize>(state: &'a JsonValue, read: impl Fn(&'a JsonValue) -> Result<T, StateError>) -> Result<[T; N], StateError> {
+    read_items(state, read)?.try_into().map_err(|_| state_error(format!("expected {} items", N)))
+}
+
+fn read_map<'a, T>(state: &'a JsonValue, read: impl Fn(&'a JsonValue) -> Result<T, StateError>) -> Result<JsonMap<T>, StateError> {
+    state.as_object().ok_or_else(|| state_error("expected an object"))?.iter()
+        .map(|(key, value)| read(value).map(|value| (key.clone(), value))
+            .map_err(|error| state_error(format!("{} in \'{}\'", error.msg, key))))
+        .collect()
+}
+
+fn read_optional<'a, T>(state: &'a JsonValue, read: impl FnOnce(&'a JsonValue) -> Result<T, StateError>) -> Result<Option<T>, StateError> {
+    if state.is_null() { Ok(None) } else { read(state).map(Some) }
+}
+
+fn type_name(name: &str) -> Result<&'static str, StateError> {
+    TYPE_NAMES.iter().find(|known| **known == name).copied()
+        .ok_or_else(|| state_error(format!("unknown type \'{}\'", name)))
+}
+
+fn read_types(state: &JsonValue) -> Result<HashSet<&'static str>, StateError> {
+    read_items(state, |name| type_name(read_str(name)?)).map(HashSet::from_iter)
+}
+
+impl HyperLogLog {
+    fn to_state(&self) -> JsonValue {
+        json!({"precision": self.precision, "registers": self.registers.clone()})
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        let precision = member(state, "precision", read_count)?;
+        let registers = member(state, "registers", |registers| read_items(registers, |register| {
+            read_count(register).ok().and_then(|register| u8::try_from(register).ok()).ok_or_else(|| state_error("expected a register"))
+        }))?;
+        if ! (4..=16).contains(&precision) || registers.len() != 1 << precision {
+            return Err(state_error("registers do not match the precision"));
+        }
+        Ok(Self { precision: precision as u32, registers })
+    }
+}
+
+impl TopK<String> {
+    fn to_state(&self) -> JsonValue {
+        json!({
+            "capacity": self.capacity,
+            "counts": sorted(self.counts.iter()).into_iter()
+                .map(|(item, (count, error))| json!([item.as_str(), *count, *error]))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        let counts = member(state, "counts", |counts| read_items(counts, |counted| {
+            let [item, count, error] = read_array(counted, Ok)?;
+            Ok((read_str(item)?.to_string(), (read_count(count)?, read_count(error)?)))
+        }))?;
+        Ok(Self { capacity: member(state, "capacity", read_count)?, counts: counts.into_iter().collect() })
+    }
+}
+
+impl Reservoir<JsonValue> {
+    fn to_state(&self) -> JsonValue {
+        // the generator state takes all 64 bits, more than JSON numbers keep
+        json!({"capacity": self.capacity, "seen": self.seen, "state": self.state.to_string(), "items": self.items.clone()})
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        Ok(Self {
+            capacity: member(state, "capacity", read_count)?,
+            seen: member(state, "seen", read_count)? as u64,
+            state: member(state, "state", |seed| read_str(seed)?.parse().map_err(|_| state_error("expected an integer")))?,
+            items: member(state, "items", |items| read_items(items, |item| Ok(item.clone())))?
+        })
+    }
+}
+
+impl DistinctStrings {
+    fn to_state(&self) -> JsonValue {
+        match self {
+            DistinctStrings::Exact { strings, cutoff } => json!({"cutoff": *cutoff, "strings": sorted(strings.iter().map(String::as_str))}),
+            DistinctStrings::Estimated(sketch) => json!({"sketch": sketch.to_state()})
+        }
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        if state.get("sketch").is_some() {
+            return member(state, "sketch", HyperLogLog::from_state).map(DistinctStrings::Estimated);
+        }
+        Ok(DistinctStrings::Exact {
+            strings: member(state, "strings", |strings| read_items(strings, |line| read_str(line).map(str::to_string)))?.into_iter().collect(),
+            cutoff: member(state, "cutoff", read_count)?
+        })
+    }
+}
+
+impl ValueTypes {
+    fn to_state(&self) -> JsonValue {
+        json!({
+            "counts": JsonValue::object(self.counts.iter().map(|(name, count)| (*name, JsonValue::from(*count)))),
+            "examples": JsonValue::object(self.examples.iter().map(|(name, examples)| (*name, JsonValue::from(examples.clone()))))
+        })
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        let counts = member(state, "counts", |counts| read_map(counts, read_count))?;
+        let examples = member(state, "examples", |examples| read_map(examples, |examples| read_items(examples, |example| Ok(example.clone()))))?;
+        Ok(Self {
+            counts: counts.into_iter().map(|(name, count)| Ok((type_name(&name)?, count))).collect::<Result<_, StateError>>()?,
+            examples: examples.into_iter().map(|(name, examples)| Ok((type_name(&name)?, examples))).collect::<Result<_, StateError>>()?
+        })
+    }
+}
+
+impl DuplicateStats {
+    fn to_state(&self) -> JsonValue {
+        json!({
+            "items": self.items,
+            "duplicates": self.duplicates,
+            "most": self.most.as_ref().map(|(item, count)| json!([item.clone(), *count]))
+        })
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        Ok(Self {
+            items: member(state, "items", read_count)?,
+            duplicates: member(state, "duplicates", read_count)?,
+            most: member(state, "most", |most| read_optional(most, |most| {
+                let [item, count] = read_array(most, Ok)?;
+                Ok((item.clone(), read_count(count)?))
+            }))?
+        })
+    }
+}
+
+impl StringsStats {
+    fn to_state(&self) -> JsonValue {
+        json!({"distinct": self.distinct.to_state(), "frequent": self.frequent.to_state(), "top": self.top})
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        Ok(Self {
+            distinct: member(state, "distinct", DistinctStrings::from_state)?,
+            frequent: member(state, "frequent", TopK::from_state)?,
+            top: member(state, "top", read_count)?
+        })
+    }
+}
+
+impl Histogram {
+    fn to_state(&self) -> JsonValue {
+        let scale = match self.scale {
+            HistogramScale::Linear => "linear",
+            HistogramScale::Log => "log"
+        };
+        json!({
+            "buckets": self.buckets,
+            "scale": scale,
+            "width": float_state(self.width),
+            "counts": self.counts.iter().map(|(index, count)| json!([*index, *count])).collect::<Vec<_>>()
+        })
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        let scale = match member(state, "scale", read_str)? {
+            "linear" => HistogramScale::Linear,
+            "log" => HistogramScale::Log,
+            scale => return Err(state_error(format!("unknown histogram scale \'{}\'", scale)))
+        };
+        let counts = member(state, "counts", |counts| read_items(counts, |bucket| {
+            let [index, count] = read_array(bucket, Ok)?;
+            Ok((read_float(index)? as i64, read_count(count)?))
+        }))?;
+        Ok(Self {
+            buckets: member(state, "buckets", read_count)?,
+            scale,
+            width: member(state, "width", read_float)?,
+            counts: counts.into_iter().collect()
+        })
+    }
+}
+
+impl Quantile {
+    fn to_state(&self) -> JsonValue {
+        json!({
+            "p": self.p,
+            "count": self.count,
+            "heights": self.heights.map(float_state).to_vec(),
+            "positions": self.positions.to_vec(),
+            "desired": self.desired.to_vec()
+        })
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        Ok(Self {
+            count: member(state, "count", read_count)?,
+            heights: member(state, "heights", |heights| read_array(heights, read_float))?,
+            positions: member(state, "positions", |positions| read_array(positions, read_float))?,
+            desired: member(state, "desired", |desired| read_array(desired, read_float))?,
+            ..Quantile::new(member(state, "p", read_float)?)
+        })
+    }
+}
+
+impl JsonNumbersStats {
+    fn to_state(&self) -> JsonValue {
+        json!({
+            "limit": self.limit,
+            "histogram": self.histogram.to_state(),
+            "quantiles": self.quantiles.iter().map(Quantile::to_state).collect::<Vec<_>>(),
+            "mean": float_state(self.mean),
+            "scale": float_state(self.scale),
+            "squares": float_state(self.squares),
+            "kinds": [self.kinds.integral, self.kinds.fractional],
+            "minimums": self.minimums.iter().map(|number| number.0).collect::<Vec<_>>(),
+            "maximums": self.maximums.iter().map(|number| number.0.0).collect::<Vec<_>>(),
+            "sum": float_state(self.sum),
+            "number": self.number,
+            "non_finite": self.non_finite,
+            "imprecise": self.imprecise
+        })
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        let [integral, fractional] = member(state, "kinds", |kinds| read_array(kinds, read_count))?;
+        Ok(Self {
+            limit: member(state, "limit", read_count)?,
+            histogram: member(state, "histogram", Histogram::from_state)?,
+            quantiles: member(state, "quantiles", |quantiles| read_array(quantiles, Quantile::from_state))?,
+            mean: member(state, "mean", read_float)?,
+            scale: member(state, "scale", read_float)?,
+            squares: member(state, "squares", read_float)?,
+            kinds: NumberKinds { integral, fractional },
+            minimums: member(state, "minimums", |numbers| read_items(numbers, read_float))?.into_iter().map(NonNan).collect(),
+            maximums: member(state, "maximums", |numbers| read_items(numbers, read_float))?.into_iter()
+                .map(|number| Reverse(NonNan(number))).collect(),
+            sum: member(state, "sum", read_float)?,
+            number: member(state, "number", read_count)?,
+            non_finite: member(state, "non_finite", read_count)?,
+            imprecise: member(state, "imprecise", read_count)?
+        })
+    }
+}
+
+impl FormatsStats {
+    fn to_state(&self) -> JsonValue {
+        json!({
+            "strings": self.strings,
+            "matches": JsonValue::object(self.matches.iter().map(|(format, count)| (format.to_string(), JsonValue::from(*count)))),
+            "dates": self.dates.map(|(earliest, latest)| vec![earliest, latest]),
+            "blobs": self.blobs.map(|blobs| vec![blobs.smallest, blobs.largest, blobs.total])
+        })
+    }
+
+    fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        let matches = member(state, "matches", |matches| read_map(matches, read_count))?;
+        Ok(Self {
+            strings: member(state, "strings", read_count)?,
+            matches: matches.into_iter()
+                .map(|(format, count)| format.parse().map(|format| (format, count)).map_err(state_error))
+                .collect::<Result<_, _>>()?,
+            dates: member(state, "dates", |dates| read_optional(dates, |dates| read_array(dates, read_float)))?
+                .map(|[earliest, latest]| (earliest, latest)),
+            blobs: member(state, "blobs", |blobs| read_optional(blobs, |blobs| read_array(blobs, read_count)))?
+                .map(|[smallest, largest, total]| BlobSizes { smallest, largest, total })
+        })
+    }
+}
+
+impl JsonComplexTypeStats {
+    /// Everything collected, as JSON that `from_state` reads back so that
+    /// later runs can merge more documents into the stats.
+    pub fn to_state(&self) -> JsonValue {
+        json!({"version": STATE_VERSION, "stats": self.state()})
+    }
+
+    pub fn from_state(state: &JsonValue) -> Result<Self, StateError> {
+        match member(state, "version", read_count)? {
+            STATE_VERSION => member(state, "stats", Self::read_state),
+            version => Err(state_error(format!("unsupported version {}", version)))
+        }
+    }
+
+    fn state(&self) -> JsonValue {
+        let mut state = json!({
+            "types": types_state(&self.values_types),
+            "numbers": self.numbers.to_state(),
+            "strings": self.strings.to_state(),
+            "depths": [self.depths.containers, self.depths.total, self.depths.max],
+            "nodes": &self.nodes,
+            "bytes": self.bytes
+        });
+        match &self.type_stats {
+            JsonSpecificTypeStats::ARRAY(array) => state.insert("array", json!({
+                "inner_arrays": array.inner_arrays_stats.as_ref().map(Self::state),
+                "inner_objects": array.inner_objects_stats.as_ref().map(Self::state),
+                "samples": array.samples.to_state(),
+                "formats": array.formats.to_state(),
+                "sizes": array.sizes,
+                "types": array.types.to_state(),
+                "duplicates": array.duplicates.to_state()
+            })),
+            JsonSpecificTypeStats::OBJECT(object) => state.insert("object", json!({
+                "primitives_keys": map_state(&object.primitives_keys, types_state),
+                "complex_stats": map_state(&object.complex_stats, Self::state),
+                "objects": object.objects,
+                "presence": map_state(&object.presence, |count| JsonValue::from(*count)),
+                "nulls": map_state(&object.nulls, |count| JsonValue::from(*count)),
+                "number_kinds": map_state(&object.number_kinds, |kinds| json!([kinds.integral, kinds.fractional])),
+                "booleans": map_state(&object.booleans, |booleans| json!([booleans.trues, booleans.falses])),
+                "numbers": map_state(&object.numbers, JsonNumbersStats::to_state),
+                "strings": map_state(&object.strings, StringsStats::to_state),
+                "samples": map_state(&object.samples, Reservoir::to_state),
+                "formats": map_state(&object.formats, FormatsStats::to_state),
+                "sizes": map_state(&object.sizes, |size| JsonValue::from(*size)),
+                "types": map_state(&object.types, ValueTypes::to_state)
+            }))
+        };
+        state
+    }
+
+    fn read_state(state: &JsonValue) -> Result<Self, StateError> {
+        let [containers, total, max] = member(state, "depths", |depths| read_array(depths, read_count))?;
+        let nodes = member(state, "nodes", |nodes| Ok(NodeCounts {
+            objects: member(nodes, "objects", read_count)?,
+            arrays: member(nodes, "arrays", read_count)?,
+            strings: member(nodes, "strings", read_count)?,
+            numbers: member(nodes, "numbers", read_count)?,
+            booleans: member(nodes, "booleans", read_count)?,
+            nulls: member(nodes, "nulls", read_count)?
+        }))?;
+        let type_stats = if state.get("array").is_some() {
+            member(state, "array", |array| Ok(JsonSpecificTypeStats::ARRAY(Box::new(JsonArrayStats {
+                inner_arrays_stats: member(array, "inner_arrays", |stats| read_optional(stats, Self::read_state))?,
+                inner_objects_stats: member(array, "inner_objects", |stats| read_optional(stats, Self::read_state))?,
+                samples: member(array, "samples", Reservoir::from_state)?,
+                formats: member(array, "formats", FormatsStats::from_state)?,
+                sizes: member(array, "sizes", read_count)?,
+                types: member(array, "types", ValueTypes::from_state)?,
+                duplicates: member(array, "duplicates", DuplicateStats::from_state)?
+            }))))?
+        } else {
+            member(state, "object", |object| Ok(JsonSpecificTypeStats::OBJECT(Box::new(JsonObjectStats {
+                primitives_keys: member(object, "primitives_keys", |keys| read_map(keys, read_types))?,
+                complex_stats: member(object, "complex_stats", |stats| read_map(stats, Self::read_state))?,
+                objects: member(object, "objects", read_count)?,
+                presence: member(object, "presence", |presence| read_map(presence, read_count))?,
+                nulls: member(object, "nulls", |nulls| read_map(nulls, read_count))?,
+                number_kinds: member(object, "number_kinds", |kinds| read_map(kinds, |kinds| {
+                    let [integral, fractional] = read_array(kinds, read_count)?;
+                    Ok(NumberKinds { integral, fractional })
+                }))?,
+                booleans: member(object, "booleans", |booleans| read_map(booleans, |booleans| {
+                    let [trues, falses] = read_array(booleans, read_count)?;
+                    Ok(BooleanCounts { trues, falses })
+                }))?,
+                numbers: member(object, "numbers", |numbers| read_map(numbers, JsonNumbersStats::from_state))?,
+                strings: member(object, "strings", |strings| read_map(strings, StringsStats::from_state))?,
+                samples: member(object, "samples", |samples| read_map(samples, Reservoir::from_state))?,
+                formats: member(object, "formats", |formats| read_map(formats, FormatsStats::from_state))?,
+                sizes: member(object, "sizes", |sizes| read_map(sizes, read_count))?,
+                types: member(object, "types", |types| read_map(types, ValueTypes::from_state))?
+            }))))?
+        };
+        Ok(Self {
+            values_types: member(state, "types", read_types)?,
+            numbers: member(state, "numbers", JsonNumbersStats::from_state)?,
+            strings: member(state, "strings", StringsStats::from_state)?,
+            depths: DepthStats { containers, total, max },
+            nodes,
+            bytes: member(state, "bytes", read_count)?,
+            type_stats
+        })
+    }
+}
+
+/// Histogram of a `NumbersReport`: `counts[i]` numbers are at least
+/// `edges[i]` and less than `edges[i + 1]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramReport {
+    pub edges: Vec<f64>,
+    pub counts: Vec<usize>
+}
+
+/// Bars of the sparkline, from the lowest.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+impl HistogramReport {
+    /// One bar per bucket, as high as its count relative to the greatest
+    /// one; blank for empty buckets.
+    pub fn sparkline(&self) -> String {
+        let highest = self.counts.iter().copied().max().unwrap_or(0);
+        self.counts.iter().map(|count| match count {
+            0 => ' ',
+            count => SPARKS[(count * SPARKS.len()).div_ceil(highest) - 1]
+        }).collect()
+    }
+}
+
+/// Formats found among the strings at a path of a `JsonStatsReport`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatsReport {
+    /// Number of strings checked.
+    pub strings: usize,
+    /// Formats found and the number of strings of each.
+    pub matches: Vec<(StringFormat, usize)>,
+    /// Earliest and latest dates, in seconds since the Unix epoch.
+    pub dates: Option<(f64, f64)>,
+    /// Decoded sizes of the base64 strings.
+    pub blobs: Option<BlobSizes>
+}
+
+/// Sizes in bytes of the data of base64 strings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlobSizes {
+    pub smallest: usize,
+    pub largest: usize,
+    pub total: usize
+}
+
+impl FormatsReport {
+    /// The format of all the strings, if they have one.
+    pub fn consistent(&self) -> Option<StringFormat> {
+        match self.matches.as_slice() {
+            [(format, count)] if *count == self.strings => Some(*format),
+            _ => None
+        }
+    }
+
+    /// Number of strings of formats that may identify people.
+    pub fn personal(&self) -> usize {
+        self.matches.iter().filter(|(format, _)| format.is_personal()).map(|(_, count)| count).sum()
+    }
+
+    /// Average decoded size of the base64 strings.
+    pub fn average_blob(&self) -> Option<f64> {
+        let count = self.matches.iter().find(|(format, _)| *format == StringFormat::Base64)?.1;
+        self.blobs.map(|blobs| blobs.total as f64 / count as f64)
+    }
+}
+
+impl fmt::Display for FormatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (format, count)) in self.matches.iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} in {}/{} strings ({:.1}%)", format, count, self.strings, percentage(*count, self.strings))?;
+            if let (StringFormat::Date, Some((earliest, latest))) = (format, self.dates) {
+                write!(f, " from {} to {}", format_timestamp(earliest), format_timestamp(latest))?;
+            }
+            if let (StringFormat::Base64, Some(blobs), Some(average)) = (format, self.blobs, self.average_blob()) {
+                write!(f, " of {} to {} bytes ({:.1} on average, {} in total)", blobs.smallest, blobs.largest, average, blobs.total)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&FormatsReport> for JsonValue {
+    fn from(formats: &FormatsReport) -> Self {
+        let mut json = json!({
+            "strings": formats.strings,
+            "matches": JsonValue::object(formats.matches.iter().map(|(format, count)| (format.to_string(), JsonValue::from(*count))))
+        });
+        if formats.personal() > 0 {
+            json.insert("personal", formats.personal().into());
+        }
+        if let Some((earliest, latest)) = formats.dates {
+            json.insert("dates", json!({"earliest": format_timestamp(earliest), "latest": format_timestamp(latest)}));
+        }
+        if let (Some(blobs), Some(average)) = (formats.blobs, formats.average_blob()) {
+            json.insert("blobs", json!({"smallest": blobs.smallest, "largest": blobs.largest, "average": average, "total": blobs.total}));
+        }
+        json
+    }
+}
+
+/// Strings stats of a key of a `JsonStatsReport`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StringsReport {
+    /// Unique strings, none if there were too many to keep them.
+    pub strings: Vec<String>,
+    /// Number of unique strings, estimated if `strings` were not kept.
+    pub distinct_strings: usize,
+    pub is_distinct_estimated: bool,
+    /// Most frequent strings with their counts, the most frequent first;
+    /// counts are lower bounds when there were many unique strings.
+    pub top_strings: Vec<(String, usize)>
+}
+
+/// Numbers stats of a `JsonStatsReport`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NumbersReport {
+    pub count: usize,
+    pub sum: f64,
+    pub avg: f64,
+    /// Greatest numbers, the greatest first.
+    pub maximums: Vec<f64>,
+    /// Least numbers, the least first.
+    pub minimums: Vec<f64>,
+    pub kinds: NumberKinds,
+    /// Estimated median, 95th and 99th percentiles.
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    /// Population standard deviation.
+    pub stddev: f64,
+    pub histogram: Option<HistogramReport>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum ReportDetails {
+    Array {
+        inner_arrays: Option<Box<JsonStatsReport>>,
+        inner_objects: Option<Box<JsonStatsReport>>
+    },
+    Object {
+        nonobligatory: Vec<String>,
+        mandatory: Vec<String>,
+        /// Number of objects collected.
+        objects: usize,
+        /// Keys and the number of objects having them.
+        presence: Vec<(String, usize)>,
+        /// Keys and the number of objects having them set to `null`, for
+        /// the keys ever `null`.
+        nulls: Vec<(String, usize)>,
+        /// Keys with primitive values and the types of those values.
+        primitive_keys: Vec<(String, Vec<String>)>,
+        /// Keys with numbers and the kinds of those numbers.
+        number_kinds: Vec<(String, NumberKinds)>,
+        /// Keys with booleans and the counts of `true` and `false`.
+        booleans: Vec<(String, BooleanCounts)>,
+        /// Keys with numbers and the stats of those numbers.
+        key_numbers: Vec<(String, NumbersReport)>,
+        /// Keys with strings and the stats of those strings.
+        key_strings: Vec<(String, StringsReport)>,
+        /// Keys with arrays or objects and their stats.
+        members: Vec<(String, JsonStatsReport)>
+    }
+}
+
+/// Stats of the arrays or objects found at one place of the documents,
+/// materialized from `JsonComplexTypeStats`. Lists are sorted, keys in the
+/// document order with the `preserve_order` feature.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonStatsReport {
+    /// Wildcard path of the containers, see `WildcardPath`; empty for the
+    /// root.
+    pub path: String,
+    /// Types of the values these containers hold.
+    pub types: Vec<String>,
+    /// Numbers among the items of these arrays, none for objects whose
+    /// numbers are reported per key.
+    pub numbers: Option<NumbersReport>,
+    /// `NaN` and infinities, kept out of the numbers stats.
+    pub non_finite: usize,
+    /// Integers of magnitude 2^53 or more, which may have lost precision.
+    pub imprecise: usize,
+    /// Strings among the items of these arrays, see `StringsReport`; none
+    /// for objects whose strings are reported per key.
+    pub strings: Vec<String>,
+    pub distinct_strings: usize,
+    pub is_distinct_estimated: bool,
+    pub top_strings: Vec<(String, usize)>,
+    /// Paths of the keys or array items that are not containers and
+    /// randomly picked examples of them.
+    pub samples: Vec<(String, Vec<JsonValue>)>,
+    /// Paths of the keys or array items with strings of known formats.
+    pub formats: Vec<(String, FormatsReport)>,
+    /// Depths of these containers, none for values on their own.
+    pub depth: Option<DepthReport>,
+    /// Values of each type in all the documents, for the root only.
+    pub nodes: Option<NodeCounts>,
+    /// Compact JSON bytes of all the documents, for the root only.
+    pub bytes: Option<usize>,
+    /// Paths of the keys or array items and the compact JSON bytes of
+    /// their values, keys included.
+    pub sizes: Vec<(String, usize)>,
+    /// Keys whose values are of several types.
+    pub anomalies: Vec<Anomaly>,
+    /// Items of these arrays if they look heterogeneous, none for objects.
+    pub mixed: Option<MixedArray>,
+    /// Duplicates among the items of these arrays, none for objects or if
+    /// the items were not compared.
+    pub duplicates: Option<DuplicatesReport>,
+    pub details: ReportDetails
+}
+
+/// Average share of their keys the objects among the items of arrays have
+/// below which they are heterogeneous, in percents.
+const KEY_OVERLAP_THRESHOLD: f64 = 50.0;
+
+/// Items of the arrays at a path of a `JsonStatsReport` that mix types, or
+/// objects sharing few of their keys; such arrays usually come from bugs of
+/// whatever wrote them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MixedArray {
+    /// Path of the items, e.g. `users[*]`.
+    pub path: String,
+    /// Types of the items, `null` aside, and the number of items of each,
+    /// the most common first.
+    pub types: Vec<(String, usize)>,
+    /// Average share of all the keys of the object items that each of them
+    /// has, in percents; none if fewer than two items were objects.
+    pub key_overlap: Option<f64>
+}
+
+/// `path: number in 6/10 (60.0%), string in 4/10 (40.0%)`, followed by
+/// `, objects have 25.0% of the keys on average` if they share few keys.
+impl fmt::Display for MixedArray {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        let items: usize = self.types.iter().map(|(_, count)| count).sum();
+        let shown: Vec<String> = self.types.iter()
+            .map(|(name, count)| format!("{} in {}/{} ({:.1}%)", name, count, items, percentage(*count, items)))
+            .collect();
+        write_list(f, &shown, "")?;
+        if let Some(overlap) = self.key_overlap.filter(|overlap| *overlap < KEY_OVERLAP_THRESHOLD) {
+            write!(f, ", objects have {:.1}% of the keys on average", overlap)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&MixedArray> for JsonValue {
+    fn from(mixed: &MixedArray) -> Self {
+        json!({
+            "path": mixed.path.as_str(),
+            "types": JsonValue::object(mixed.types.iter().map(|(name, count)| (name.as_str(), JsonValue::from(*count)))),
+            "key_overlap": mixed.key_overlap
+        })
+    }
+}
+
+/// Items of the arrays at a path of a `JsonStatsReport` equal to an
+/// earlier item of the same array, see `StatsOptions::duplicates`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuplicatesReport {
+    /// Path of the items, e.g. `users[*]`.
+    pub path: String,
+    /// Items compared.
+    pub items: usize,
+    pub duplicates: usize,
+    /// Item repeated the most times in one array, with that count.
+    pub most: Option<(JsonValue, usize)>
+}
+
+/// `path: 3/10 items (30.0%) are duplicates`, followed by
+/// `, the most repeated 2 times: "a"` if there are any.
+impl fmt::Display for DuplicatesReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}/{} items ({:.1}%) are duplicates", self.path, self.duplicates, self.items, percentage(self.duplicates, self.items))?;
+        if let Some((item, count)) = &self.most {
+            write!(f, ", the most repeated {} times: {}", count, item)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&DuplicatesReport> for JsonValue {
+    fn from(duplicates: &DuplicatesReport) -> Self {
+        json!({
+            "path": duplicates.path.as_str(),
+            "items": duplicates.items,
+            "duplicates": duplicates.duplicates,
+            "most": duplicates.most.as_ref().map(|(item, count)| json!({"value": item.clone(), "count": *count}))
+        })
+    }
+}
+
+/// Key of a `JsonStatsReport` whose values are of several types, `null`
+/// aside, e.g. numbers mostly but strings in a few objects.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Anomaly {
+    pub path: String,
+    /// Types and the number of values of each, the most common first.
+    pub types: Vec<(String, usize)>,
+    /// First values of the less common types that are primitive.
+    pub examples: Vec<JsonValue>
+}
+
+impl Anomaly {
+    pub fn values(&self) -> usize {
+        self.types.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// `path: number in 99/100 (99.0%), string in 1/100 (1.0%), e.g. "42"`.
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        let values = self.values();
+        let shown: Vec<String> = self.types.iter()
+            .map(|(name, count)| format!("{} in {}/{} ({:.1}%)", name, count, values, percentage(*count, values)))
+            .collect();
+        write_list(f, &shown, "")?;
+        if ! self.examples.is_empty() {
+            f.write_str(", e.g. ")?;
+            write_list(f, &self.examples, "")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&Anomaly> for JsonValue {
+    fn from(anomaly: &Anomaly) -> Self {
+        json!({
+            "path": anomaly.path.as_str(),
+            "types": JsonValue::object(anomaly.types.iter().map(|(name, count)| (name.as_str(), JsonValue::from(*count)))),
+            "examples": anomaly.examples.clone()
+        })
+    }
+}
+
+/// Greatest and average depth of the containers of a `JsonStatsReport`, a
+/// container holding no containers being 1 deep.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthReport {
+    pub max: usize,
+    pub avg: f64
+}
+
+fn sorted<T: Ord>(items: impl IntoIterator<Item = T>) -> Vec<T> {
+    let mut items: Vec<T> = items.into_iter().collect();
+    items.sort();
+    items
+}
+
+/// Keys of `map` in a stable order, see `jsonpath::members`.
+fn ordered_keys<V>(map: &JsonMap<V>) -> Vec<&str> {
+    #[allow(unused_mut)]
+    let mut keys: Vec<&str> = map.keys().map(AsRef::as_ref).collect();
+    #[cfg(not(feature = "preserve_order"))]
+    keys.sort();
+    keys
+}
+
+pub(crate) fn member_path(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) }
+}
+
+impl JsonNumbersStats {
+    fn report(&self) -> Option<NumbersReport> {
+        if self.number == 0 {
+            return None;
+        }
+        let mut maximums: Vec<f64> = self.maximums.iter().map(|wrapped| wrapped.0.0).collect();
+        maximums.sort_by(|left, right| right.total_cmp(left));
+        let mut minimums: Vec<f64> = self.minimums.iter().map(|wrapped| wrapped.0).collect();
+        minimums.sort_by(f64::total_cmp);
+        Some(NumbersReport {
+            count: self.number,
+            sum: self.sum,
+            avg: self.mean,
+            maximums,
+            minimums,
+            kinds: self.kinds,
+            p50: self.quantiles[0].value(),
+            p95: self.quantiles[1].value(),
+            p99: self.quantiles[2].value(),
+            stddev: self.scale * (self.squares / self.number as f64).sqrt(),
+            histogram: self.histogram.report()
+        })
+    }
+}
+
+impl JsonComplexTypeStats {
+    pub fn report(&self) -> JsonStatsReport {
+        self.report_at(String::new())
+    }
+
+    fn report_at(&self, path: String) -> JsonStatsReport {
+        let details = match &self.type_stats {
+            JsonSpecificTypeStats::ARRAY(arr_stats) => {
+                let inner = |stats: &Option<JsonComplexTypeStats>| stats.as_ref()
+                    .map(|stats| Box::new(stats.report_at(format!("{}[*]", path))));
+                ReportDetails::Array {
+                    inner_arrays: inner(&arr_stats.inner_arrays_stats),
+                    inner_objects: inner(&arr_stats.inner_objects_stats)
+                }
+            },
+            JsonSpecificTypeStats::OBJECT(obj_stats) => {
+                let keys: HashSet<&str> = obj_stats.primitives_keys.keys().chain(obj_stats.complex_stats.keys())
+                    .map(AsRef::as_ref)
+                    .collect();
+                ReportDetails::Object {
+                    nonobligatory: sorted(keys.iter()
+                        .filter(|key| obj_stats.is_nonobligatory(key))
+                        .map(|key| key.to_string())),
+                    mandatory: sorted(keys.iter()
+                        .filter(|key| ! obj_stats.is_nonobligatory(key))
+                        .map(|key| key.to_string())),
+                    objects: obj_stats.objects,
+                    presence: sorted(obj_stats.presence.iter().map(|(key, count)| (key.to_string(), *count))),
+                    nulls: sorted(obj_stats.nulls.iter().map(|(key, count)| (key.to_string(), *count))),
+                    primitive_keys: ordered_keys(&obj_stats.primitives_keys).into_iter()
+                        .map(|key| (key.to_string(), sorted(obj_stats.primitives_keys[key].iter().map(|name| name.to_string()))))
+                        .collect(),
+                    number_kinds: ordered_keys(&obj_stats.number_kinds).into_iter()
+                        .map(|key| (key.to_string(), obj_stats.number_kinds[key]))
+                        .collect(),
+                    booleans: ordered_keys(&obj_stats.booleans).into_iter()
+                        .map(|key| (key.to_string(), obj_stats.booleans[key]))
+                        .collect(),
+                    key_numbers: ordered_keys(&obj_stats.numbers).into_iter()
+                        .filter_map(|key| obj_stats.numbers[key].report().map(|numbers| (key.to_string(), numbers)))
+                        .collect(),
+                    key_strings: ordered_keys(&obj_stats.strings).into_iter()
+                        .map(|key| (key.to_string(), obj_stats.strings[key].report()))
+                        .collect(),
+                    members: ordered_keys(&obj_stats.complex_stats).into_iter()
+                        .map(|key| (key.to_string(), obj_stats.complex_stats[key].report_at(member_path(&path, key))))
+                        .collect()
+                }
+            }
+        };
+        let samples = match &self.type_stats {
+            JsonSpecificTypeStats::ARRAY(arr_stats) if ! arr_stats.samples.items().is_empty() => {
+                vec![(format!("{}[*]", path), arr_stats.samples.items().to_vec())]
+            },
+            JsonSpecificTypeStats::ARRAY(_) => vec![],
+            JsonSpecificTypeStats::OBJECT(obj_stats) => ordered_keys(&obj_stats.samples).into_iter()
+                .filter(|key| ! obj_stats.samples[*key].items().is_empty())
+                .map(|key| (member_path(&path, key), obj_stats.samples[key].items().to_vec()))
+                .collect()
+        };
+        let formats = match &self.type_stats {
+            JsonSpecificTypeStats::ARRAY(arr_stats) => arr_stats.formats.report()
+                .map(|formats| (format!("{}[*]", path), formats))
+                .into_iter()
+                .collect(),
+            JsonSpecificTypeStats::OBJECT(obj_stats) => ordered_keys(&obj_stats.formats).into_iter()
+                .filter_map(|key| obj_stats.formats[key].report().map(|formats| (member_path(&path, key), formats)))
+                .collect()
+        };
+        let StringsReport { strings, distinct_strings, is_distinct_estimated, top_strings } = self.strings.report();
+        let sizes = match &self.type_stats {
+            JsonSpecificTypeStats::ARRAY(arr_stats) if arr_stats.sizes > 0 => vec![(format!("{}[*]", path), arr_stats.sizes)],
+            JsonSpecificTypeStats::ARRAY(_) => vec![],
+            JsonSpecificTypeStats::OBJECT(obj_stats) => ordered_keys(&obj_stats.sizes).into_iter()
+                .map(|key| (member_path(&path, key), obj_stats.sizes[key]))
+                .collect()
+        };
+        let anomalies = match &self.type_stats {
+            JsonSpecificTypeStats::ARRAY(_) => vec![],
+            JsonSpecificTypeStats::OBJECT(obj_stats) => ordered_keys(&obj_stats.types).into_iter()
+                .filter_map(|key| obj_stats.types[key].report(member_path(&path, key)))
+                .collect()
+        };
+        let mixed = match &self.type_stats {
+            JsonSpecificTypeStats::ARRAY(arr_stats) => {
+                let key_overlap = arr_stats.inner_objects_stats.as_ref().and_then(|stats| match &stats.type_stats {
+                    JsonSpecificTypeStats::OBJECT(obj_stats) if obj_stats.objects > 1 && ! obj_stats.presence.is_empty() => {
+                        let present: usize = obj_stats.presence.values().sum();
+                        Some(percentage(present, obj_stats.objects * obj_stats.presence.len()))
+                    },
+                    _ => None
+                });
+                let is_mixed = arr_stats.types.counts.len() > 1 || key_overlap.is_some_and(|overlap| overlap < KEY_OVERLAP_THRESHOLD);
+                is_mixed.then(|| MixedArray { path: format!("{}[*]", path), types: arr_stats.types.distribution(), key_overlap })
+            },
+            JsonSpecificTypeStats::OBJECT(_) => None
+        };
+        let duplicates = match &self.type_stats {
+            JsonSpecificTypeStats::ARRAY(arr_stats) => arr_stats.duplicates.report(format!("{}[*]", path)),
+            JsonSpecificTypeStats::OBJECT(_) => None
+        };
+        let (non_finite, imprecise) = match &self.type_stats {
+            JsonSpecificTypeStats::ARRAY(_) => (self.numbers.non_finite, self.numbers.imprecise),
+            JsonSpecificTypeStats::OBJECT(obj_stats) => obj_stats.numbers.values()
+                .fold((self.numbers.non_finite, self.numbers.imprecise), |(non_finite, imprecise), numbers| {
+                    (non_finite + numbers.non_finite, imprecise + numbers.imprecise)
+                })
+        };
+        let nodes = path.is_empty().then_some(self.nodes);
+        let bytes = path.is_empty().then_some(self.bytes);
+        JsonStatsReport {
+            path,
+            types: sorted(self.values_types.iter().map(|name| name.to_string())),
+            numbers: self.numbers.report(),
+            non_finite,
+            imprecise,
+            strings,
+            distinct_strings,
+            is_distinct_estimated,
+            top_strings,
+            samples,
+            formats,
+            depth: self.depths.report(),
+            nodes,
+            bytes,
+            sizes,
+            anomalies,
+            mixed,
+            duplicates,
+            details
+        }
+    }
+}
+
+/// Number of the largest paths shown in the text and HTML reports.
+const LARGEST_PATHS: usize = 10;
+
+/// Number of the rarest keys shown in the text and HTML reports.
+const SPARSE_KEYS: usize = 20;
+
+/// Number of the samples shown next to each key in the text and HTML
+/// reports.
+const INLINE_SAMPLES: usize = 3;
+
+/// Characters of the strings shown next to keys, longer ones being cut.
+const INLINE_SAMPLE_CHARS: usize = 16;
+
+/// Sample `value` as text: `NaN` and infinities, which JSON writes as
+/// `null`, are named.
+fn sample_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::NUMBER(number) if number.is_nan() => "NaN".to_string(),
+        JsonValue::NUMBER(number) if number.is_infinite() => {
+            if number.is_sign_negative() { "-Infinity" } else { "Infinity" }.to_string()
+        },
+        other => other.to_string()
+    }
+}
+
+/// Sample `value` in the JSON report, `NaN` and infinities as strings.
+fn sample_json(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::NUMBER(number) if ! number.is_finite() => JsonValue::STRING(sample_text(value)),
+        other => other.clone()
+    }
+}
+
+/// `value` as shown next to its key, strings quoted and cut short.
+fn inline_sample(value: &JsonValue) -> String {
+    match value {
+        JsonValue::STRING(line) if line.chars().count() > INLINE_SAMPLE_CHARS => {
+            format!("'{}…'", line.chars().take(INLINE_SAMPLE_CHARS).collect::<String>())
+        },
+        JsonValue::STRING(line) => format!("'{}'", line),
+        other => sample_text(other)
+    }
+}
+
+/// `count` out of `total` in percents.
+fn percentage(count: usize, total: usize) -> f64 {
+    100.0 * count as f64 / total as f64
+}
+
+impl JsonStatsReport {
+    /// Format of all the strings at `path`, if they have one.
+    pub fn consistent_format(&self, path: &str) -> Option<StringFormat> {
+        self.formats.iter().find(|(other, _)| other == path).and_then(|(_, formats)| formats.consistent())
+    }
+
+    /// Samples of the values of `key` as shown next to it, see
+    /// `INLINE_SAMPLES`; `None` for keys without samples.
+    fn inline_samples(&self, key: &str) -> Option<String> {
+        let path = member_path(&self.path, key);
+        let (_, values) = self.samples.iter().find(|(other, _)| *other == path)?;
+        let shown: Vec<String> = values.iter().take(INLINE_SAMPLES).map(inline_sample).collect();
+        Some(shown.join(", "))
+    }
+
+    /// `array` or `object`.
+    pub fn kind(&self) -> &'static str {
+        match self.details {
+            ReportDetails::Array { .. } => "array",
+            ReportDetails::Object { .. } => "object"
+        }
+    }
+
+    /// Number of objects having `key` and number of objects collected;
+    /// `None` for arrays and keys never seen.
+    pub fn presence(&self, key: &str) -> Option<(usize, usize)> {
+        let ReportDetails::Object { objects, presence, .. } = &self.details else {
+            return None;
+        };
+        presence.iter().find(|(other, _)| other == key).map(|(_, count)| (*count, *objects))
+    }
+
+    /// Paths with the most bytes, at most `limit` of them, the largest
+    /// first; see `sizes`.
+    pub fn largest_paths(&self, limit: usize) -> Vec<(&str, usize)> {
+        let mut sizes = vec![];
+        self.sizes_into(&mut sizes);
+        sizes.sort_by(|left, right| right.1.cmp(&left.1).then_with(|| left.0.cmp(right.0)));
+        sizes.truncate(limit);
+        sizes
+    }
+
+    fn sizes_into<'a>(&'a self, sizes: &mut Vec<(&'a str, usize)>) {
+        sizes.extend(self.sizes.iter().map(|(path, size)| (path.as_str(), *size)));
+        match &self.details {
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                inner_arrays.iter().chain(inner_objects).for_each(|inner| inner.sizes_into(sizes));
+            },
+            ReportDetails::Object { members, .. } => members.iter().for_each(|(_, stats)| stats.sizes_into(sizes))
+        }
+    }
+
+    /// Paths of the keys missing from some of their objects, here and
+    /// inside, with the number of objects having them and the number of
+    /// objects; at most `limit` of them, the rarest first.
+    pub fn sparse_keys(&self, limit: usize) -> Vec<(String, usize, usize)> {
+        let mut keys = vec![];
+        self.sparse_into(&mut keys);
+        keys.sort_by(|left, right| (left.1 * right.2).cmp(&(right.1 * left.2)).then_with(|| left.0.cmp(&right.0)));
+        keys.truncate(limit);
+        keys
+    }
+
+    fn sparse_into(&self, keys: &mut Vec<(String, usize, usize)>) {
+        match &self.details {
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                inner_arrays.iter().chain(inner_objects).for_each(|inner| inner.sparse_into(keys));
+            },
+            ReportDetails::Object { objects, presence, members, .. } => {
+                keys.extend(presence.iter()
+                    .filter(|(_, count)| count < objects)
+                    .map(|(key, count)| (member_path(&self.path, key), *count, *objects)));
+                members.iter().for_each(|(_, stats)| stats.sparse_into(keys));
+            }
+        }
+    }
+
+    /// Keys whose values are of several types, here and inside.
+    pub fn all_anomalies(&self) -> Vec<&Anomaly> {
+        let mut anomalies = vec![];
+        self.anomalies_into(&mut anomalies);
+        anomalies
+    }
+
+    fn anomalies_into<'a>(&'a self, anomalies: &mut Vec<&'a Anomaly>) {
+        anomalies.extend(&self.anomalies);
+        match &self.details {
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                inner_arrays.iter().chain(inner_objects).for_each(|inner| inner.anomalies_into(anomalies));
+            },
+            ReportDetails::Object { members, .. } => members.iter().for_each(|(_, stats)| stats.anomalies_into(anomalies))
+        }
+    }
+
+    /// Heterogeneous arrays, these and the ones inside.
+    pub fn mixed_arrays(&self) -> Vec<&MixedArray> {
+        let mut mixed = vec![];
+        self.mixed_into(&mut mixed);
+        mixed
+    }
+
+    fn mixed_into<'a>(&'a self, mixed: &mut Vec<&'a MixedArray>) {
+        mixed.extend(&self.mixed);
+        match &self.details {
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                inner_arrays.iter().chain(inner_objects).for_each(|inner| inner.mixed_into(mixed));
+            },
+            ReportDetails::Object { members, .. } => members.iter().for_each(|(_, stats)| stats.mixed_into(mixed))
+        }
+    }
+
+    /// Duplicate items of arrays, these and the ones inside, for the arrays
+    /// having any.
+    pub fn duplicate_items(&self) -> Vec<&DuplicatesReport> {
+        let mut duplicates = vec![];
+        self.duplicates_into(&mut duplicates);
+        duplicates
+    }
+
+    fn duplicates_into<'a>(&'a self, duplicates: &mut Vec<&'a DuplicatesReport>) {
+        duplicates.extend(self.duplicates.iter().filter(|items| items.duplicates > 0));
+        match &self.details {
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                inner_arrays.iter().chain(inner_objects).for_each(|inner| inner.duplicates_into(duplicates));
+            },
+            ReportDetails::Object { members, .. } => members.iter().for_each(|(_, stats)| stats.duplicates_into(duplicates))
+        }
+    }
+
+    /// Number of objects having `key` set to `null` and number of objects
+    /// having `key`; `None` for arrays and keys never seen.
+    pub fn nulls(&self, key: &str) -> Option<(usize, usize)> {
+        let ReportDetails::Object { nulls, .. } = &self.details else {
+            return None;
+        };
+        let (present, _) = self.presence(key)?;
+        let count = nulls.iter().find(|(other, _)| other == key).map_or(0, |(_, count)| *count);
+        Some((count, present))
+    }
+}
+
+impl From<&NumbersReport> for JsonValue {
+    fn from(numbers: &NumbersReport) -> Self {
+        json!({
+            "count": numbers.count,
+            "sum": numbers.sum,
+            "avg": numbers.avg,
+            "maximums": numbers.maximums.clone(),
+            "minimums": numbers.minimums.clone(),
+            "kinds": JsonValue::from(&numbers.kinds),
+            "p50": numbers.p50,
+            "p95": numbers.p95,
+            "p99": numbers.p99,
+            "stddev": numbers.stddev,
+            "histogram": numbers.histogram.as_ref().map(|histogram| json!({
+                "edges": histogram.edges.clone(),
+                "counts": histogram.counts.clone()
+            }))
+        })
+    }
+}
+
+fn top_strings_json(top_strings: &[(String, usize)]) -> JsonValue {
+    JsonValue::array(top_strings.iter().map(|(line, count)| json!({"value": line.as_str(), "count": *count})))
+}
+
+impl From<&StringsReport> for JsonValue {
+    fn from(strings: &StringsReport) -> Self {
+        json!({
+            "strings": strings.strings.clone(),
+            "distinct_strings": strings.distinct_strings,
+            "is_distinct_estimated": strings.is_distinct_estimated,
+            "top_strings": top_strings_json(&strings.top_strings)
+        })
+    }
+}
+
+impl From<&JsonStatsReport> for JsonValue {
+    fn from(report: &JsonStatsReport) -> Self {
+        let mut json = json!({
+            "path": report.path.as_str(),
+            "type": report.kind(),
+            "types": report.types.clone(),
+            "numbers": report.numbers.as_ref().map(JsonValue::from),
+            "non_finite": report.non_finite,
+            "imprecise": report.imprecise,
+            "strings": report.strings.clone(),
+            "distinct_strings": report.distinct_strings,
+            "is_distinct_estimated": report.is_distinct_estimated,
+            "top_strings": top_strings_json(&report.top_strings),
+            "samples": JsonValue::object(report.samples.iter()
+                .map(|(path, values)| (path.as_str(), JsonValue::ARRAY(values.iter().map(sample_json).collect())))),
+            "formats": JsonValue::object(report.formats.iter()
+                .map(|(path, formats)| (path.as_str(), JsonValue::from(formats)))),
+            "depth": report.depth.map(|depth| json!({"max": depth.max, "avg": depth.avg})),
+            "nodes": report.nodes.as_ref().map(JsonValue::from),
+            "bytes": report.bytes,
+            "sizes": JsonValue::object(report.sizes.iter()
+                .map(|(path, size)| (path.as_str(), JsonValue::from(*size))))
+        });
+        json.insert("anomalies", JsonValue::array(report.anomalies.iter().map(JsonValue::from)));
+        json.insert("mixed", report.mixed.as_ref().map(JsonValue::from).into());
+        json.insert("duplicates", report.duplicates.as_ref().map(JsonValue::from).into());
+        match &report.details {
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                json.insert("inner_arrays", inner_arrays.as_deref().map(JsonValue::from).into());
+                json.insert("inner_objects", inner_objects.as_deref().map(JsonValue::from).into());
+            },
+            ReportDetails::Object { nonobligatory, mandatory, objects, presence, nulls, primitive_keys, number_kinds, booleans, key_numbers, key_strings, members } => {
+                json.insert("nonobligatory", nonobligatory.clone().into());
+                json.insert("mandatory", mandatory.clone().into());
+                json.insert("objects", (*objects).into());
+                json.insert("presence", JsonValue::object(presence.iter()
+                    .map(|(key, count)| (key.as_str(), JsonValue::from(*count)))));
+                json.insert("nulls", JsonValue::object(nulls.iter()
+                    .map(|(key, count)| (key.as_str(), JsonValue::from(*count)))));
+                json.insert("primitive_keys", JsonValue::object(primitive_keys.iter()
+                    .map(|(key, types)| (key.as_str(), types.clone().into()))));
+                json.insert("number_kinds", JsonValue::object(number_kinds.iter()
+                    .map(|(key, kinds)| (key.as_str(), JsonValue::from(kinds)))));
+                json.insert("booleans", JsonValue::object(booleans.iter()
+                    .map(|(key, booleans)| (key.as_str(), JsonValue::from(booleans)))));
+                json.insert("key_numbers", JsonValue::object(key_numbers.iter()
+                    .map(|(key, numbers)| (key.as_str(), JsonValue::from(numbers)))));
+                json.insert("key_strings", JsonValue::object(key_strings.iter()
+                    .map(|(key, strings)| (key.as_str(), JsonValue::from(strings)))));
+                json.insert("members", JsonValue::object(members.iter()
+                    .map(|(key, stats)| (key.as_str(), JsonValue::from(stats)))));
+            }
+        }
+        json
+    }
+}
+
+/// Row of the tabular export of a `JsonStatsReport`, describing the values
+/// at one path; cells are `None` where the stats are not known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsRow {
+    pub path: String,
+    pub types: Vec<String>,
+    /// Percentage of the enclosing objects having the key.
+    pub presence: Option<f64>,
+    /// Percentage of the objects having the key with it set to `null`.
+    pub nulls: Option<f64>,
+    /// Number of numbers found at the path.
+    pub count: Option<usize>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub avg: Option<f64>,
+    pub distinct_strings: Option<usize>
+}
+
+impl StatsRow {
+    fn new(path: String, types: Vec<String>) -> Self {
+        Self { path, types, presence: None, nulls: None, count: None, min: None, max: None, avg: None, distinct_strings: None }
+    }
+
+    fn set_numbers(&mut self, numbers: &NumbersReport) {
+        self.count = Some(numbers.count);
+        self.min = numbers.minimums.first().copied();
+        self.max = numbers.maximums.first().copied();
+        self.avg = Some(numbers.avg);
+    }
+}
+
+/// Writes a CSV field, quoted if it holds the delimiter, a quote or a line
+/// break.
+fn write_field(out: &mut String, field: &str, delimiter: char) {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        out.push('"');
+        out.push_str(&field.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(field);
+    }
+}
+
+impl JsonStatsReport {
+    fn rows_into(&self, rows: &mut Vec<StatsRow>) {
+        match &self.details {
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                let mut row = StatsRow::new(format!("{}[*]", self.path), self.types.clone());
+                if let Some(numbers) = &self.numbers {
+                    row.set_numbers(numbers);
+                }
+                row.distinct_strings = Some(self.distinct_strings);
+                rows.push(row);
+                inner_arrays.iter().chain(inner_objects).for_each(|inner| inner.rows_into(rows));
+            },
+            ReportDetails::Object { objects, presence, primitive_keys, key_numbers, key_strings, members, .. } => {
+                for (key, types) in primitive_keys {
+                    rows.push(StatsRow::new(member_path(&self.path, key), types.clone()));
+                }
+                for (key, stats) in members {
+                    match rows.iter_mut().find(|row| row.path == stats.path) {
+                        Some(row) => {
+                            row.types.push(stats.kind().to_string());
+                            row.types.sort();
+                        },
+                        None => rows.push(StatsRow::new(member_path(&self.path, key), vec![stats.kind().to_string()]))
+                    }
+                    stats.rows_into(rows);
+                }
+                for (key, count) in presence {
+                    let path = member_path(&self.path, key);
+                    if let Some(row) = rows.iter_mut().find(|row| row.path == path) {
+                        row.presence = Some(percentage(*count, *objects));
+                        row.nulls = self.nulls(key).map(|(nulls, present)| percentage(nulls, present));
+                    }
+                }
+                for (key, numbers) in key_numbers {
+                    let path = member_path(&self.path, key);
+                    if let Some(row) = rows.iter_mut().find(|row| row.path == path) {
+                        row.set_numbers(numbers);
+                    }
+                }
+                for (key, strings) in key_strings {
+                    let path = member_path(&self.path, key);
+                    if let Some(row) = rows.iter_mut().find(|row| row.path == path) {
+                        row.distinct_strings = Some(strings.distinct_strings);
+                    }
+                }
+            }
+        }
+    }
+
+    /// One row per path holding values, sorted by path: the keys of objects
+    /// and `[*]` for the items of arrays.
+    pub fn rows(&self) -> Vec<StatsRow> {
+        let mut rows = vec![];
+        self.rows_into(&mut rows);
+        rows.sort_by(|left, right| left.path.cmp(&right.path));
+        rows
+    }
+
+    /// The rows as CSV with a header line, or TSV if `delimiter` is a tab;
+    /// types are separated by `|`.
+    pub fn to_csv(&self, delimiter: char) -> String {
+        let header = ["path", "types", "presence %", "null %", "count", "min", "max", "avg", "distinct strings"];
+        let mut text = header.join(&delimiter.to_string());
+        text.push('\n');
+        let cell = |value: Option<String>| value.unwrap_or_default();
+        for row in self.rows() {
+            let fields = [
+                row.path.clone(),
+                row.types.join("|"),
+                cell(row.presence.map(|presence| format!("{:.1}", presence))),
+                cell(row.nulls.map(|nulls| format!("{:.1}", nulls))),
+                cell(row.count.map(|count| count.to_string())),
+                cell(row.min.map(|min| min.to_string())),
+                cell(row.max.map(|max| max.to_string())),
+                cell(row.avg.map(|avg| avg.to_string())),
+                cell(row.distinct_strings.map(|distinct| distinct.to_string()))
+            ];
+            for (index, field) in fields.iter().enumerate() {
+                if index > 0 {
+                    text.push(delimiter);
+                }
+                write_field(&mut text, field, delimiter);
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Node of the tree view of a `JsonStatsReport`.
+struct TreeNode {
+    label: String,
+    children: Vec<TreeNode>
+}
+
+/// Types as shown in the tree view, `true` and `false` being booleans.
+fn tree_types<'a>(types: impl IntoIterator<Item = &'a str>) -> String {
+    let types: BTreeSet<&str> = types.into_iter()
+        .map(|name| if name == "true" || name == "false" { "boolean" } else { name })
+        .collect();
+    types.into_iter().collect::<Vec<_>>().join("|")
+}
+
+fn write_tree(out: &mut String, nodes: &[TreeNode], prefix: &str) {
+    for (index, node) in nodes.iter().enumerate() {
+        let is_last = index + 1 == nodes.len();
+        out.push_str(prefix);
+        out.push_str(if is_last { "└── " } else { "├── " });
+        out.push_str(&node.label);
+        out.push('\n');
+        write_tree(out, &node.children, &format!("{}{}", prefix, if is_last { "    " } else { "│   " }));
+    }
+}
+
+impl JsonStatsReport {
+    /// Nodes of the values inside these containers.
+    fn tree_nodes(&self) -> Vec<TreeNode> {
+        match &self.details {
+            ReportDetails::Array { .. } if self.types.is_empty() => vec![],
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                let children = inner_objects.iter().chain(inner_arrays).flat_map(|inner| inner.tree_nodes()).collect();
+                vec![TreeNode { label: format!("[*]: {}", tree_types(self.types.iter().map(String::as_str))), children }]
+            },
+            ReportDetails::Object { nonobligatory, primitive_keys, members, .. } => {
+                let mut keys: Vec<&str> = primitive_keys.iter().map(|(key, _)| key.as_str()).collect();
+                keys.extend(members.iter()
+                    .map(|(key, _)| key.as_str())
+                    .filter(|key| ! primitive_keys.iter().any(|(other, _)| other == key)));
+                keys.into_iter().map(|key| {
+                    let primitive = primitive_keys.iter().find(|(other, _)| other == key).map_or(&[][..], |(_, types)| types.as_slice());
+                    let member = members.iter().find(|(other, _)| other == key).map(|(_, stats)| stats);
+                    let types = tree_types(primitive.iter().map(String::as_str).chain(member.map(JsonStatsReport::kind)));
+                    let optional = if nonobligatory.iter().any(|name| name == key) { "?" } else { "" };
+                    TreeNode {
+                        label: format!("{}{}: {}", key, optional, types),
+                        children: member.map(JsonStatsReport::tree_nodes).unwrap_or_default()
+                    }
+                }).collect()
+            }
+        }
+    }
+
+    /// Structure of the documents drawn like `tree(1)`, one line per key or
+    /// array items with the types of their values; keys missing from some
+    /// of their objects end with `?`.
+    pub fn to_tree(&self) -> String {
+        let mut tree = format!("(root): {}\n", self.kind());
+        write_tree(&mut tree, &self.tree_nodes(), "");
+        tree
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+details { margin-left: 1.5em; }
+summary { cursor: pointer; padding: 0.2em 0; }
+table { border-collapse: collapse; margin: 0.5em 0; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+.types { color: #666; }
+.bar { display: inline-block; height: 0.8em; background: #69c; }
+";
+
+/// Widest bar of the HTML histograms, in em.
+const HTML_BAR_WIDTH: f64 = 12f64;
+
+impl JsonStatsReport {
+    fn write_html(&self, out: &mut String) -> fmt::Result {
+        use fmt::Write;
+
+        let path = if self.path.is_empty() { "(root)" } else { &self.path };
+        writeln!(out, "<details open><summary><code>{}</code> {} <span class=\"types\">{}</span></summary>",
+            escape_html(path), self.kind(), escape_html(&self.types.join(", ")))?;
+        if let Some(depth) = self.depth {
+            writeln!(out, "<p>Nesting depth: at most {}, {:.1} on average</p>", depth.max, depth.avg)?;
+        }
+        if let Some(nodes) = &self.nodes {
+            writeln!(out, "<p>Nodes: {}</p>", nodes)?;
+        }
+        if let Some(bytes) = self.bytes.filter(|bytes| *bytes > 0) {
+            writeln!(out, "<table><tr><th>path</th><th>bytes</th><th>share of {} bytes</th></tr>", bytes)?;
+            for (path, size) in self.largest_paths(LARGEST_PATHS) {
+                writeln!(out, "<tr><td><code>{}</code></td><td>{}</td><td>{:.1}%</td></tr>", escape_html(path), size, percentage(size, bytes))?;
+            }
+            writeln!(out, "</table>")?;
+        }
+        let sparse = if self.path.is_empty() { self.sparse_keys(SPARSE_KEYS) } else { vec![] };
+        if ! sparse.is_empty() {
+            writeln!(out, "<table><tr><th>sparse key</th><th>presence</th></tr>")?;
+            for (path, count, objects) in sparse {
+                writeln!(out, "<tr><td><code>{}</code></td><td>{}/{} ({:.1}%)</td></tr>", escape_html(&path), count, objects, percentage(count, objects))?;
+            }
+            writeln!(out, "</table>")?;
+        }
+        let numbers = self.numbers.iter().map(|numbers| (String::new(), numbers));
+        let key_numbers: Vec<(String, &NumbersReport)> = match &self.details {
+            ReportDetails::Object { key_numbers, .. } => key_numbers.iter()
+                .map(|(key, numbers)| (format!(" of <code>{}</code>", escape_html(key)), numbers))
+                .collect(),
+            ReportDetails::Array { .. } => vec![]
+        };
+        for (of, numbers) in numbers.chain(key_numbers) {
+            writeln!(out, "<table><tr><th>numbers{}</th><th>sum</th><th>avg</th><th>min</th><th>max</th>\
+                <th>median</th><th>p95</th><th>p99</th><th>stddev</th></tr>", of)?;
+            let cell = |number: Option<&f64>| number.map(f64::to_string).unwrap_or_default();
+            writeln!(out, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr></table>",
+                numbers.count, numbers.sum, numbers.avg, cell(numbers.minimums.first()), cell(numbers.maximums.first()),
+                numbers.p50, numbers.p95, numbers.p99, numbers.stddev)?;
+            if let Some(histogram) = &numbers.histogram {
+                writeln!(out, "<table><tr><th>histogram{}</th><th>count</th><th></th></tr>", of)?;
+                let highest = histogram.counts.iter().copied().max().unwrap_or(0).max(1);
+                for (index, count) in histogram.counts.iter().enumerate() {
+                    writeln!(out, "<tr><td>{} to {}</td><td>{}</td><td><span class=\"bar\" style=\"width: {:.1}em\"></span></td></tr>",
+                        histogram.edges[index], histogram.edges[index + 1], count, HTML_BAR_WIDTH * *count as f64 / highest as f64)?;
+                }
+                writeln!(out, "</table>")?;
+            }
+        }
+        if self.non_finite > 0 || self.imprecise > 0 {
+            writeln!(out, "<p>{} NaN or Infinity values, {} integers beyond 2^53</p>", self.non_finite, self.imprecise)?;
+        }
+        let write_strings = |out: &mut String, of: &str, distinct: usize, is_estimated: bool, top: &[(String, usize)]| {
+            let about = if is_estimated { "about " } else { "" };
+            let shown: Vec<String> = top.iter()
+                .map(|(line, count)| format!("<code>{}</code> ({})", escape_html(line), count))
+                .collect();
+            writeln!(out, "<p>{}{} unique strings{}, the most frequent: {}</p>", about, distinct, of, shown.join(", "))
+        };
+        if self.distinct_strings > 0 {
+            write_strings(out, "", self.distinct_strings, self.is_distinct_estimated, &self.top_strings)?;
+        }
+        if let ReportDetails::Object { key_strings, .. } = &self.details {
+            for (key, strings) in key_strings {
+                let of = format!(" of <code>{}</code>", escape_html(key));
+                write_strings(out, &of, strings.distinct_strings, strings.is_distinct_estimated, &strings.top_strings)?;
+            }
+        }
+        for (path, values) in &self.samples {
+            let shown: Vec<String> = values.iter().map(|value| format!("<code>{}</code>", escape_html(&sample_text(value)))).collect();
+            writeln!(out, "<p>Samples of <code>{}</code>: {}</p>", escape_html(path), shown.join(", "))?;
+        }
+        for (path, formats) in &self.formats {
+            let personal = if formats.personal() > 0 { ", personal data" } else { "" };
+            let binary = if formats.blobs.is_some() { ", embedded binary" } else { "" };
+            writeln!(out, "<p>Formats of <code>{}</code>: {}{}{}</p>", escape_html(path), escape_html(&formats.to_string()), personal, binary)?;
+        }
+        match &self.details {
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                for inner in inner_arrays.iter().chain(inner_objects) {
+                    inner.write_html(out)?;
+                }
+            },
+            ReportDetails::Object { nonobligatory, primitive_keys, booleans, members, .. } => {
+                writeln!(out, "<table><tr><th>key</th><th>types</th><th>presence</th><th>examples</th></tr>")?;
+                let mut keys: Vec<(&str, Vec<&str>)> = primitive_keys.iter()
+                    .map(|(key, types)| (key.as_str(), types.iter().map(String::as_str).collect()))
+                    .collect();
+                for (key, stats) in members {
+                    match keys.iter_mut().find(|(name, _)| name == key) {
+                        Some((_, types)) => types.push(stats.kind()),
+                        None => keys.push((key, vec![stats.kind()]))
+                    }
+                }
+                for (key, types) in keys {
+                    let mut presence = match self.presence(key) {
+                        Some((count, objects)) => format!("{}/{} ({:.1}%)", count, objects, percentage(count, objects)),
+                        None => String::new()
+                    };
+                    if let Some((nulls, present)) = self.nulls(key).filter(|(nulls, _)| *nulls > 0) {
+                        presence.push_str(&format!(", null in {}/{} ({:.1}%)", nulls, present, percentage(nulls, present)));
+                    }
+                    let mut types = types.join(", ");
+                    if let Some((_, counts)) = booleans.iter().find(|(other, _)| other == key) {
+                        types.push_str(&format!(" ({})", counts));
+                    }
+                    let likeliness = if nonobligatory.iter().any(|name| name == key) { "likely nonobligatory" } else { "likely mandatory" };
+                    let samples = self.inline_samples(key).unwrap_or_default();
+                    writeln!(out, "<tr><td><code>{}</code></td><td>{}</td><td>{}, {}</td><td>{}</td></tr>",
+                        escape_html(key), escape_html(&types), presence, likeliness, escape_html(&samples))?;
+                }
+                writeln!(out, "</table>")?;
+                for (_, stats) in members {
+                    stats.write_html(out)?;
+                }
+            }
+        }
+        writeln!(out, "</details>")
+    }
+
+    /// Self-contained HTML page showing the report as a collapsible tree.
+    pub fn to_html(&self) -> String {
+        let mut html = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>json-stat report</title>\n\
+            <style>\n{}</style>\n</head>\n<body>\n<h1>json-stat report</h1>\n", HTML_STYLE);
+        let anomalies = self.all_anomalies();
+        if ! anomalies.is_empty() {
+            html.push_str("<h2>Type anomalies</h2>\n<ul>\n");
+            for anomaly in anomalies {
+                html.push_str(&format!("<li>{}</li>\n", escape_html(&anomaly.to_string())));
+            }
+            html.push_str("</ul>\n");
+        }
+        let mixed = self.mixed_arrays();
+        if ! mixed.is_empty() {
+            html.push_str("<h2>Heterogeneous arrays</h2>\n<ul>\n");
+            for array in mixed {
+                html.push_str(&format!("<li>{}</li>\n", escape_html(&array.to_string())));
+            }
+            html.push_str("</ul>\n");
+        }
+        let duplicates = self.duplicate_items();
+        if ! duplicates.is_empty() {
+            html.push_str("<h2>Duplicate items</h2>\n<ul>\n");
+            for items in duplicates {
+                html.push_str(&format!("<li>{}</li>\n", escape_html(&items.to_string())));
+            }
+            html.push_str("</ul>\n");
+        }
+        // writing into a String cannot fail
+        let _ = self.write_html(&mut html);
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T], quote: &str) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}{}{}", quote, item, quote)?;
+    }
+    Ok(())
+}
+
+fn write_strings(f: &mut fmt::Formatter<'_>, distinct: usize, is_estimated: bool, top: &[(String, usize)]) -> fmt::Result {
+    let about = if is_estimated { "about " } else { "" };
+    write!(f, "Encountered {}{} unique strings, the most frequent: ", about, distinct)?;
+    let shown: Vec<String> = top.iter().map(|(line, count)| format!("'{}' ({})", line, count)).collect();
+    write_list(f, &shown, "")
+}
+
+/// Lines of the numbers info of the text report.
+impl fmt::Display for NumbersReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Encountered {} numbers", self.count)?;
+        write!(f, ", sum = {}, avg = {}", self.sum, self.avg)?;
+        if ! self.maximums.is_empty() {
+            write!(f, "\n{} most maximum numbers: ", self.maximums.len())?;
+            write_list(f, &self.maximums, "")?;
+            write!(f, "\n{} most minimum numbers: ", self.minimums.len())?;
+            write_list(f, &self.minimums, "")?;
+        }
+        write!(f, "\nNumbers are {}", self.kinds)?;
+        write!(f, "\nMedian = {}, p95 = {}, p99 = {}, stddev = {}", self.p50, self.p95, self.p99, self.stddev)?;
+        if let Some(histogram) = &self.histogram {
+            write!(f, "\nHistogram of {} buckets from {} to {}: {}", histogram.counts.len(), histogram.edges[0],
+                histogram.edges[histogram.counts.len()], histogram.sparkline())?;
+        }
+        Ok(())
+    }
+}
+
+/// The text printed by the `stat` subcommand.
+impl fmt::Display for JsonStatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // conflicting types head the report of the whole documents
+        let anomalies = if self.path.is_empty() { self.all_anomalies() } else { vec![] };
+        if ! anomalies.is_empty() {
+            writeln!(f, "=== Type anomalies ===")?;
+            for anomaly in anomalies {
+                writeln!(f, "- {}", anomaly)?;
+            }
+        }
+        let mixed = if self.path.is_empty() { self.mixed_arrays() } else { vec![] };
+        if ! mixed.is_empty() {
+            writeln!(f, "=== Heterogeneous arrays ===")?;
+            for array in mixed {
+                writeln!(f, "- {}", array)?;
+            }
+        }
+        let duplicates = if self.path.is_empty() { self.duplicate_items() } else { vec![] };
+        if ! duplicates.is_empty() {
+            writeln!(f, "=== Duplicate items ===")?;
+            for items in duplicates {
+                writeln!(f, "- {}", items)?;
+            }
+        }
+        writeln!(f, "Type: {}", self.kind())?;
+        writeln!(f, "--- Common info ---")?;
+        f.write_str("Containing types: ")?;
+        write_list(f, &self.types, "")?;
+        if let Some(depth) = self.depth {
+            write!(f, "\nNesting depth: at most {}, {:.1} on average", depth.max, depth.avg)?;
+        }
+        if let Some(nodes) = &self.nodes {
+            write!(f, "\nNodes: {}", nodes)?;
+        }
+        if let Some(bytes) = self.bytes.filter(|bytes| *bytes > 0) {
+            write!(f, "\nSize: {} bytes of compact JSON, the largest paths:", bytes)?;
+            for (path, size) in self.largest_paths(LARGEST_PATHS) {
+                write!(f, "\n- {}: {} bytes ({:.1}%)", path, size, percentage(size, bytes))?;
+            }
+        }
+        let sparse = if self.path.is_empty() { self.sparse_keys(SPARSE_KEYS) } else { vec![] };
+        if ! sparse.is_empty() {
+            write!(f, "\nSparse keys, the rarest first:")?;
+            for (path, count, objects) in sparse {
+                write!(f, "\n- {}: present in {}/{} ({:.1}%)", path, count, objects, percentage(count, objects))?;
+            }
+        }
+        if let Some(numbers) = &self.numbers {
+            writeln!(f, "\n=== Numbers info ===")?;
+            write!(f, "{}", numbers)?;
+        }
+        if let ReportDetails::Object { key_numbers, .. } = &self.details {
+            for (key, numbers) in key_numbers {
+                writeln!(f, "\n=== Numbers info of key {} ===", key)?;
+                write!(f, "{}", numbers)?;
+            }
+        }
+        if self.non_finite > 0 {
+            writeln!(f, "\n=== Non-finite numbers info ===")?;
+            write!(f, "Encountered {} NaN or Infinity values", self.non_finite)?;
+        }
+        if self.imprecise > 0 {
+            writeln!(f, "\n=== Imprecise numbers info ===")?;
+            write!(f, "Encountered {} integers beyond 2^53 that may have lost precision", self.imprecise)?;
+        }
+        if self.distinct_strings > 0 {
+            writeln!(f, "\n=== Strings info ===")?;
+            write_strings(f, self.distinct_strings, self.is_distinct_estimated, &self.top_strings)?;
+        }
+        if let ReportDetails::Object { key_strings, .. } = &self.details {
+            for (key, strings) in key_strings {
+                writeln!(f, "\n=== Strings info of key {} ===", key)?;
+                write_strings(f, strings.distinct_strings, strings.is_distinct_estimated, &strings.top_strings)?;
+            }
+        }
+        if ! self.samples.is_empty() {
+            write!(f, "\n=== Sample values ===")?;
+            for (path, values) in &self.samples {
+                write!(f, "\n- {}: ", path)?;
+                write_list(f, &values.iter().map(sample_text).collect::<Vec<_>>(), "")?;
+            }
+        }
+        if ! self.formats.is_empty() {
+            write!(f, "\n=== String formats ===")?;
+            for (path, formats) in &self.formats {
+                write!(f, "\n- {}: {}", path, formats)?;
+                if formats.personal() > 0 {
+                    f.write_str(", personal data")?;
+                }
+                if formats.blobs.is_some() {
+                    f.write_str(", embedded binary")?;
+                }
+            }
+        }
+        match &self.details {
+            ReportDetails::Array { inner_arrays, inner_objects } => {
+                writeln!(f, "\n=== Array specific info ===")?;
+                if let Some(inner_arrays) = inner_arrays {
+                    writeln!(f, "*** Inner arrays info ***")?;
+                    write!(f, "{}", inner_arrays)?;
+                }
+                if let Some(inner_objects) = inner_objects {
+                    writeln!(f, "*** Inner objects info ***")?;
+                    write!(f, "{}", inner_objects)?;
+                }
+            },
+            ReportDetails::Object { nonobligatory, mandatory, objects, presence, primitive_keys, number_kinds, booleans, members, .. } => {
+                writeln!(f, "\n=== Object specific info ===")?;
+                write!(f, "{} keys are likely nonobligatory: ", nonobligatory.len())?;
+                write_list(f, nonobligatory, "'")?;
+                write!(f, "\n{} keys are likely mandatory: ", mandatory.len())?;
+                write_list(f, mandatory, "'")?;
+                write!(f, "\nPresence of keys in {} objects:", objects)?;
+                for (key, count) in presence {
+                    write!(f, "\n- {}: present in {}/{} ({:.1}%)", key, count, objects, percentage(*count, *objects))?;
+                    if let Some((nulls, present)) = self.nulls(key).filter(|(nulls, _)| *nulls > 0) {
+                        write!(f, ", null in {}/{} ({:.1}%)", nulls, present, percentage(nulls, present))?;
+                    }
+                }
+                writeln!(f, "\n{} keys have primitive values:", primitive_keys.len())?;
+                for (key, types) in primitive_keys {
+                    write!(f, "- {} is ", key)?;
+                    write_list(f, types, "")?;
+                    if let Some(format) = self.consistent_format(&member_path(&self.path, key)) {
+                        write!(f, ", {}", format)?;
+                    }
+                    if let Some((_, kinds)) = number_kinds.iter().find(|(other, _)| other == key) {
+                        write!(f, " ({})", kinds)?;
+                    }
+                    if let Some((_, counts)) = booleans.iter().find(|(other, _)| other == key) {
+                        write!(f, " ({})", counts)?;
+                    }
+                    if let Some(samples) = self.inline_samples(key) {
+                        write!(f, ", e.g. {}", samples)?;
+                    }
+                    writeln!(f)?;
+                }
+                for (key, stats) in members {
+                    writeln!(f, "*** Info for value at key {} ***", key)?;
+                    write!(f, "{}", stats)?;
+                }
+            }
+        }
+        writeln!(f)
+    }
+}
+
+pub fn print_complex_stats(stats: JsonComplexTypeStats) {
+    print!("{}", stats.report());
 }
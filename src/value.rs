@@ -0,0 +1,509 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Index;
+
+use crate::parser::{number_value, JsonKey, JsonMap, JsonValue};
+use crate::pointer::{self, PointerError};
+
+
+/// Result of indexing a value that lacks the key or item.
+static NULL: JsonValue = JsonValue::NULL;
+
+/// Reference counts stored before the text of a `JsonKey`.
+const KEY_HEADER: usize = 2 * std::mem::size_of::<usize>();
+
+
+macro_rules! impl_from_number {
+    ($($number:ty),*) => {$(
+        impl From<$number> for JsonValue {
+            fn from(number: $number) -> Self {
+                JsonValue::NUMBER(number as f64)
+            }
+        }
+    )*};
+}
+
+impl_from_number!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+impl From<bool> for JsonValue {
+    fn from(flag: bool) -> Self {
+        if flag { JsonValue::TRUE } else { JsonValue::FALSE }
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(line: &str) -> Self {
+        JsonValue::STRING(line.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(line: String) -> Self {
+        JsonValue::STRING(line)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(items: Vec<T>) -> Self {
+        JsonValue::ARRAY(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<JsonKey>, V: Into<JsonValue>> From<HashMap<K, V>> for JsonValue {
+    fn from(members: HashMap<K, V>) -> Self {
+        JsonValue::object(members.into_iter().map(|(key, value)| (key, value.into())))
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl<K: Into<JsonKey>, V: Into<JsonValue>> From<indexmap::IndexMap<K, V>> for JsonValue {
+    fn from(members: indexmap::IndexMap<K, V>) -> Self {
+        JsonValue::object(members.into_iter().map(|(key, value)| (key, value.into())))
+    }
+}
+
+/// `None` becomes `NULL`.
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(JsonValue::NULL, Into::into)
+    }
+}
+
+/// Bits of `number` with all zeros and all `NaN`s made the same.
+fn number_bits(number: f64) -> u64 {
+    if number.is_nan() {
+        return f64::NAN.to_bits();
+    }
+    if number == 0f64 {
+        return 0;
+    }
+    number.to_bits()
+}
+
+impl PartialEq for JsonValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (JsonValue::STRING(left), JsonValue::STRING(right)) => left == right,
+            (JsonValue::NUMBER(left), JsonValue::NUMBER(right)) => number_bits(*left) == number_bits(*right),
+            (JsonValue::RAWNUMBER(left), JsonValue::RAWNUMBER(right)) => left == right,
+            (JsonValue::OBJECT(left), JsonValue::OBJECT(right)) => left == right,
+            (JsonValue::ARRAY(left), JsonValue::ARRAY(right)) => left == right,
+            (JsonValue::TRUE, JsonValue::TRUE) | (JsonValue::FALSE, JsonValue::FALSE)
+                | (JsonValue::NULL, JsonValue::NULL) => true,
+            (JsonValue::RAW(left, left_text), JsonValue::RAW(right, right_text)) => left == right && left_text == right_text,
+            _ => false
+        }
+    }
+}
+
+/// Total equality: a number is equal to itself even if it is `NaN`. Number
+/// literals kept with `ParserOptions::preserve_number_literals` are compared
+/// as text and never equal a `NUMBER`.
+impl Eq for JsonValue {}
+
+/// Objects hash the same regardless of the order of their members, as they
+/// compare equal regardless of it.
+impl Hash for JsonValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            JsonValue::STRING(line) | JsonValue::RAWNUMBER(line) => line.hash(state),
+            JsonValue::NUMBER(number) => number_bits(*number).hash(state),
+            JsonValue::OBJECT(object) => {
+                object.len().hash(state);
+                let members = object.iter().fold(0u64, |sum, member| {
+                    let mut hasher = DefaultHasher::new();
+                    member.hash(&mut hasher);
+                    sum.wrapping_add(hasher.finish())
+                });
+                members.hash(state);
+            },
+            JsonValue::ARRAY(array) => array.hash(state),
+            JsonValue::RAW(span, text) => {
+                span.hash(state);
+                text.hash(state);
+            },
+            JsonValue::TRUE | JsonValue::FALSE | JsonValue::NULL => {}
+        }
+    }
+}
+
+/// Value of another type than a conversion expects.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeError {
+    pub expected: &'static str,
+    pub found: &'static str
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
+}
+
+impl Error for TypeError {}
+
+fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::STRING(_) => "string",
+        JsonValue::NUMBER(_) | JsonValue::RAWNUMBER(_) => "number",
+        JsonValue::OBJECT(_) => "object",
+        JsonValue::ARRAY(_) => "array",
+        JsonValue::TRUE | JsonValue::FALSE => "boolean",
+        JsonValue::NULL => "null",
+        JsonValue::RAW(..) => "raw"
+    }
+}
+
+impl TryFrom<JsonValue> for String {
+    type Error = TypeError;
+
+    fn try_from(value: JsonValue) -> Result<Self, TypeError> {
+        match value {
+            JsonValue::STRING(line) => Ok(line),
+            other => Err(TypeError { expected: "string", found: type_name(&other) })
+        }
+    }
+}
+
+impl TryFrom<JsonValue> for f64 {
+    type Error = TypeError;
+
+    fn try_from(value: JsonValue) -> Result<Self, TypeError> {
+        value.as_f64().ok_or(TypeError { expected: "number", found: type_name(&value) })
+    }
+}
+
+impl TryFrom<JsonValue> for bool {
+    type Error = TypeError;
+
+    fn try_from(value: JsonValue) -> Result<Self, TypeError> {
+        value.as_bool().ok_or(TypeError { expected: "boolean", found: type_name(&value) })
+    }
+}
+
+/// Integers convert only if they are exact and in range.
+macro_rules! impl_try_from_integer {
+    ($($integer:ty),*) => {$(
+        impl TryFrom<JsonValue> for $integer {
+            type Error = TypeError;
+
+            fn try_from(value: JsonValue) -> Result<Self, TypeError> {
+                let integer = match &value {
+                    JsonValue::RAWNUMBER(literal) => literal.parse().ok(),
+                    JsonValue::NUMBER(number) if number.fract() == 0f64
+                        && *number >= <$integer>::MIN as f64 && *number <= <$integer>::MAX as f64 => Some(*number as $integer),
+                    _ => None
+                };
+                integer.ok_or(TypeError { expected: "integer", found: type_name(&value) })
+            }
+        }
+    )*};
+}
+
+impl_try_from_integer!(i64, u64);
+
+impl TryFrom<JsonValue> for Vec<JsonValue> {
+    type Error = TypeError;
+
+    fn try_from(value: JsonValue) -> Result<Self, TypeError> {
+        match value {
+            JsonValue::ARRAY(array) => Ok(array),
+            other => Err(TypeError { expected: "array", found: type_name(&other) })
+        }
+    }
+}
+
+impl JsonValue {
+    pub fn string(line: impl Into<String>) -> Self {
+        JsonValue::STRING(line.into())
+    }
+
+    pub fn number(number: f64) -> Self {
+        JsonValue::NUMBER(number)
+    }
+
+    pub fn array(items: impl IntoIterator<Item = JsonValue>) -> Self {
+        JsonValue::ARRAY(items.into_iter().collect())
+    }
+
+    /// Object of `members`; of repeated keys the last one wins.
+    pub fn object<K: Into<JsonKey>>(members: impl IntoIterator<Item = (K, JsonValue)>) -> Self {
+        JsonValue::OBJECT(members.into_iter().map(|(key, value)| (key.into(), value)).collect())
+    }
+
+    /// Value of `key` if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object()?.get(key)
+    }
+
+    /// Item at `index` if this is an array long enough.
+    pub fn get_index(&self, index: usize) -> Option<&JsonValue> {
+        self.as_array()?.get(index)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::STRING(line) => Some(line),
+            _ => None
+        }
+    }
+
+    /// Number as `f64`, converting a preserved literal if needed.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::NUMBER(number) => Some(*number),
+            JsonValue::RAWNUMBER(literal) => number_value(literal),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::TRUE => Some(true),
+            JsonValue::FALSE => Some(false),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::ARRAY(array) => Some(array),
+            _ => None
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&JsonMap<JsonValue>> {
+        match self {
+            JsonValue::OBJECT(object) => Some(object),
+            _ => None
+        }
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, JsonValue::STRING(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, JsonValue::NUMBER(_) | JsonValue::RAWNUMBER(_))
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, JsonValue::TRUE | JsonValue::FALSE)
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, JsonValue::ARRAY(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, JsonValue::OBJECT(_))
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::NULL)
+    }
+
+    /// Value at the JSON Pointer (RFC 6901) `pointer`, e.g. `/users/0/name`.
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        pointer::tokens(pointer).ok()?.iter().try_fold(self, |value, token| match value {
+            JsonValue::OBJECT(object) => object.get(token.as_str()),
+            JsonValue::ARRAY(array) => pointer::index(token).and_then(|index| array.get(index)),
+            _ => None
+        })
+    }
+
+    /// Sets `key` of an object, returning its previous value. `NULL` is
+    /// turned into an empty object first.
+    ///
+    /// # Panics
+    ///
+    /// If the value is neither an object nor `NULL`.
+    pub fn insert(&mut self, key: impl Into<JsonKey>, value: JsonValue) -> Option<JsonValue> {
+        if self.is_null() {
+            *self = JsonValue::OBJECT(JsonMap::new());
+        }
+        let JsonValue::OBJECT(object) = self else {
+            panic!("cannot insert a key into a non-object value");
+        };
+        object.insert(key.into(), value)
+    }
+
+    /// Removes `key` from an object, returning its value; other values are
+    /// left as they are.
+    pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+        let JsonValue::OBJECT(object) = self else {
+            return None;
+        };
+        #[cfg(feature = "preserve_order")]
+        return object.shift_remove(key);
+        #[cfg(not(feature = "preserve_order"))]
+        return object.remove(key);
+    }
+
+    /// Appends an item to an array. `NULL` is turned into an empty array
+    /// first.
+    ///
+    /// # Panics
+    ///
+    /// If the value is neither an array nor `NULL`.
+    pub fn push(&mut self, value: JsonValue) {
+        if self.is_null() {
+            *self = JsonValue::ARRAY(vec![]);
+        }
+        let JsonValue::ARRAY(array) = self else {
+            panic!("cannot push an item into a non-array value");
+        };
+        array.push(value);
+    }
+
+    /// Replaces or adds the value at the JSON Pointer `pointer`, returning
+    /// the value replaced. The parent of the target must exist; in arrays
+    /// the index one past the end or `-` appends.
+    pub fn set_by_pointer(&mut self, pointer: &str, value: JsonValue) -> Result<Option<JsonValue>, PointerError> {
+        let tokens = pointer::tokens(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(Some(std::mem::replace(self, value)));
+        };
+        let mut parent = self;
+        for token in parents {
+            parent = match parent {
+                JsonValue::OBJECT(object) => object.get_mut(token.as_str()),
+                JsonValue::ARRAY(array) => pointer::index(token).and_then(|index| array.get_mut(index)),
+                _ => None
+            }.ok_or_else(|| PointerError::new(pointer, format!("no value at '{}'", token)))?;
+        }
+        match parent {
+            JsonValue::OBJECT(object) => Ok(object.insert(last.as_str().into(), value)),
+            JsonValue::ARRAY(array) => {
+                let index = if last == "-" { Some(array.len()) } else { pointer::index(last) };
+                match index {
+                    Some(index) if index < array.len() => Ok(Some(std::mem::replace(&mut array[index], value))),
+                    Some(index) if index == array.len() => {
+                        array.push(value);
+                        Ok(None)
+                    },
+                    _ => Err(PointerError::new(pointer, format!("invalid array index '{}'", last)))
+                }
+            },
+            _ => Err(PointerError::new(pointer, "parent is neither an object nor an array"))
+        }
+    }
+
+    /// Sorts the members of this and every nested object by key, so that
+    /// documents serialize the same regardless of the order of their keys.
+    #[cfg(feature = "preserve_order")]
+    pub fn sort_keys_recursively(&mut self) {
+        match self {
+            JsonValue::OBJECT(object) => {
+                object.sort_keys();
+                object.values_mut().for_each(|value| value.sort_keys_recursively());
+            },
+            JsonValue::ARRAY(array) => array.iter_mut().for_each(JsonValue::sort_keys_recursively),
+            _ => {}
+        }
+    }
+
+    /// Estimated number of bytes the value takes in memory, its own size
+    /// included: string buffers and the spare capacity of arrays and maps
+    /// are counted, as are the keys, even though equal keys may be shared.
+    pub fn approx_size_bytes(&self) -> usize {
+        std::mem::size_of::<JsonValue>() + match self {
+            JsonValue::STRING(line) | JsonValue::RAWNUMBER(line) | JsonValue::RAW(_, line) => line.capacity(),
+            JsonValue::OBJECT(object) => {
+                let entry = std::mem::size_of::<(JsonKey, JsonValue)>();
+                let members: usize = object.iter()
+                    .map(|(key, value)| KEY_HEADER + key.len() + std::mem::size_of::<JsonKey>() + value.approx_size_bytes())
+                    .sum();
+                members + (object.capacity() - object.len()) * entry
+            },
+            JsonValue::ARRAY(array) => {
+                let items: usize = array.iter().map(JsonValue::approx_size_bytes).sum();
+                items + (array.capacity() - array.len()) * std::mem::size_of::<JsonValue>()
+            },
+            JsonValue::NUMBER(_) | JsonValue::TRUE | JsonValue::FALSE | JsonValue::NULL => 0
+        }
+    }
+
+    /// Compares the values like `==`, except that numbers, whether kept as
+    /// literals or not, are equal if they differ by at most `epsilon`.
+    pub fn approx_eq(&self, other: &JsonValue, epsilon: f64) -> bool {
+        match (self, other) {
+            (JsonValue::OBJECT(left), JsonValue::OBJECT(right)) => left.len() == right.len()
+                && left.iter().all(|(key, value)| right.get(key).is_some_and(|other| value.approx_eq(other, epsilon))),
+            (JsonValue::ARRAY(left), JsonValue::ARRAY(right)) => left.len() == right.len()
+                && left.iter().zip(right).all(|(value, other)| value.approx_eq(other, epsilon)),
+            (JsonValue::NUMBER(_) | JsonValue::RAWNUMBER(_), JsonValue::NUMBER(_) | JsonValue::RAWNUMBER(_)) => {
+                match (self.as_f64(), other.as_f64()) {
+                    (Some(left), Some(right)) => left == right || (left - right).abs() <= epsilon
+                        || (left.is_nan() && right.is_nan()),
+                    _ => self == other
+                }
+            },
+            _ => self == other
+        }
+    }
+
+    /// Applies a JSON Merge Patch (RFC 7386): members of an object `patch`
+    /// are merged recursively with `null` removing them, any other `patch`
+    /// replaces the value.
+    pub fn merge_patch(&mut self, patch: JsonValue) {
+        let JsonValue::OBJECT(members) = patch else {
+            *self = patch;
+            return;
+        };
+        if ! self.is_object() {
+            *self = JsonValue::OBJECT(JsonMap::new());
+        }
+        for (key, value) in members {
+            if value.is_null() {
+                self.remove(&key);
+            } else if let JsonValue::OBJECT(object) = self {
+                object.entry(key).or_insert(JsonValue::NULL).merge_patch(value);
+            }
+        }
+    }
+}
+
+/// Combines objects of `base` and `other` recursively, any other value of
+/// `other` takes precedence, `null`s included. Fragments are merged with
+/// `fragments.into_iter().fold(JsonValue::NULL, merge)`.
+pub fn merge(base: JsonValue, other: JsonValue) -> JsonValue {
+    match (base, other) {
+        (JsonValue::OBJECT(mut object), JsonValue::OBJECT(members)) => {
+            for (key, value) in members {
+                match object.get_mut(&key) {
+                    Some(previous) => *previous = merge(std::mem::replace(previous, JsonValue::NULL), value),
+                    None => {
+                        object.insert(key, value);
+                    }
+                }
+            }
+            JsonValue::OBJECT(object)
+        },
+        (_, other) => other
+    }
+}
+
+/// Member of an object, or `JsonValue::NULL` if there is no such member or
+/// the value is not an object.
+impl Index<&str> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, key: &str) -> &JsonValue {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// Item of an array, or `JsonValue::NULL` if the index is out of bounds or
+/// the value is not an array.
+impl Index<usize> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, index: usize) -> &JsonValue {
+        self.get_index(index).unwrap_or(&NULL)
+    }
+}
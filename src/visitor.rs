@@ -0,0 +1,72 @@
+use crate::parser::{number_value, JsonMap, JsonValue, Span};
+use crate::pointer::JsonPointer;
+
+
+/// Hooks called by `visit` for every value of a tree, all of them doing
+/// nothing by default. Containers get a `leave_*` call after their content,
+/// so collectors can keep a stack of their own.
+pub trait JsonVisitor {
+    /// Returning `false` skips the members of the object and `leave_object`.
+    fn visit_object(&mut self, _pointer: &JsonPointer, _object: &JsonMap<JsonValue>) -> bool {
+        true
+    }
+
+    fn leave_object(&mut self, _pointer: &JsonPointer) {}
+
+    /// Returning `false` skips the items of the array and `leave_array`.
+    fn visit_array(&mut self, _pointer: &JsonPointer, _array: &[JsonValue]) -> bool {
+        true
+    }
+
+    fn leave_array(&mut self, _pointer: &JsonPointer) {}
+
+    fn visit_string(&mut self, _pointer: &JsonPointer, _line: &str) {}
+
+    fn visit_number(&mut self, _pointer: &JsonPointer, _number: f64) {}
+
+    /// Number kept as its literal, passed on to `visit_number` if it is
+    /// representable as `f64`.
+    fn visit_raw_number(&mut self, pointer: &JsonPointer, literal: &str) {
+        if let Some(number) = number_value(literal) {
+            self.visit_number(pointer, number);
+        }
+    }
+
+    fn visit_bool(&mut self, _pointer: &JsonPointer, _value: bool) {}
+
+    fn visit_null(&mut self, _pointer: &JsonPointer) {}
+
+    /// Container left unparsed as its source `text`, see
+    /// `ParserOptions::raw_depth`.
+    fn visit_raw(&mut self, _pointer: &JsonPointer, _span: &Span, _text: &str) {}
+}
+
+fn visit_at(value: &JsonValue, pointer: &JsonPointer, visitor: &mut impl JsonVisitor) {
+    match value {
+        JsonValue::OBJECT(object) => if visitor.visit_object(pointer, object) {
+            for (key, member) in object {
+                visit_at(member, &pointer.child(&**key), visitor);
+            }
+            visitor.leave_object(pointer);
+        },
+        JsonValue::ARRAY(array) => if visitor.visit_array(pointer, array) {
+            for (index, item) in array.iter().enumerate() {
+                visit_at(item, &pointer.child(index.to_string()), visitor);
+            }
+            visitor.leave_array(pointer);
+        },
+        JsonValue::STRING(line) => visitor.visit_string(pointer, line),
+        JsonValue::NUMBER(number) => visitor.visit_number(pointer, *number),
+        JsonValue::RAWNUMBER(literal) => visitor.visit_raw_number(pointer, literal),
+        JsonValue::TRUE => visitor.visit_bool(pointer, true),
+        JsonValue::FALSE => visitor.visit_bool(pointer, false),
+        JsonValue::NULL => visitor.visit_null(pointer),
+        JsonValue::RAW(span, text) => visitor.visit_raw(pointer, span, text)
+    }
+}
+
+/// Drives `visitor` through `value` depth-first, members of objects in the
+/// order of the map.
+pub fn visit(value: &JsonValue, visitor: &mut impl JsonVisitor) {
+    visit_at(value, &JsonPointer::default(), visitor);
+}
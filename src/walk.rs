@@ -0,0 +1,34 @@
+use crate::jsonpath::members;
+use crate::parser::JsonValue;
+use crate::pointer::JsonPointer;
+
+
+/// Depth-first, pre-order iterator over a value and all of its descendants
+/// together with their pointers. Object members come in document order with
+/// the `preserve_order` feature and sorted by key otherwise.
+pub struct Walk<'a> {
+    stack: Vec<(JsonPointer, &'a JsonValue)>
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (JsonPointer, &'a JsonValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (pointer, value) = self.stack.pop()?;
+        match value {
+            JsonValue::OBJECT(object) => self.stack.extend(members(object).into_iter().rev()
+                .map(|(key, member)| (pointer.child(key), member))),
+            JsonValue::ARRAY(array) => self.stack.extend(array.iter().enumerate().rev()
+                .map(|(index, item)| (pointer.child(index.to_string()), item))),
+            _ => {}
+        }
+        Some((pointer, value))
+    }
+}
+
+impl JsonValue {
+    /// Walks the value, starting with the value itself at the root pointer.
+    pub fn walk(&self) -> Walk<'_> {
+        Walk { stack: vec![(JsonPointer::default(), self)] }
+    }
+}